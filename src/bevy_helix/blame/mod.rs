@@ -0,0 +1,366 @@
+use bevy :: prelude :: *;
+use bevy :: tasks :: Task;
+use bevy :: utils :: HashMap;
+
+use helix_term :: ui :: menu :: Item;
+use helix_tui :: widgets :: Row;
+use helix_view :: DocumentId;
+
+use std :: {
+	io :: Write,
+	path :: { Path, PathBuf },
+	process :: Stdio,
+	sync :: { Arc, Mutex },
+	time :: { SystemTime, UNIX_EPOCH },
+};
+
+use super :: { folding :: FoldedView, git :: git_command };
+
+pub mod systems;
+
+pub const BLAME_DELAY_SECONDS		: f32 = 1.0; // blame is recalculated only after edits settle down
+pub const BLAME_GAP_COLUMNS			: usize = 4;
+pub const BLAME_GUTTER_COLUMNS		: usize = 40;
+pub const INLINE_BLAME_COLUMNS		: usize = 60;
+pub const MAX_HISTORY_COMMITS		: usize = 200;
+pub const COMMIT_POPUP_ID			: &str = "kodiki-commit";
+
+// lines that are not committed yet are blamed on a commit with all zeroes
+const UNCOMMITTED_HASH : &str = "0000000000000000000000000000000000000000";
+
+#[derive(Clone, Debug)]
+pub struct CommitInfo {
+	pub hash		: String,
+	pub author		: String,
+	pub time		: i64, // unix timestamp
+	pub summary		: String,
+}
+
+impl CommitInfo {
+	pub fn is_uncommitted(&self) -> bool {
+		self.hash == UNCOMMITTED_HASH
+	}
+
+	pub fn short_hash(&self) -> &str {
+		&self.hash[.. self.hash.len().min(8)]
+	}
+
+	// "author, 3 days ago • summary"
+	pub fn annotation(&self) -> String {
+		if self.is_uncommitted() {
+			return String::from("You, uncommitted changes");
+		}
+
+		format!("{}, {} • {}", self.author, relative_time(self.time), self.summary)
+	}
+
+	// everything that is known about the commit, one entry per row
+	pub fn details(&self) -> Vec<String> {
+		if self.is_uncommitted() {
+			return vec![self.annotation()];
+		}
+
+		vec![
+			format!("commit {}", self.hash),
+			format!("author {}", self.author),
+			format!("date   {}", relative_time(self.time)),
+			self.summary.clone(),
+		]
+	}
+}
+
+pub fn relative_time(time: i64) -> String {
+	let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs() as i64);
+	let seconds = (now - time).max(0);
+
+	let (value, unit) = match seconds {
+		s if s < 60					=> return String::from("just now"),
+		s if s < 60 * 60			=> (s / 60, "minute"),
+		s if s < 60 * 60 * 24		=> (s / (60 * 60), "hour"),
+		s if s < 60 * 60 * 24 * 30	=> (s / (60 * 60 * 24), "day"),
+		s if s < 60 * 60 * 24 * 365	=> (s / (60 * 60 * 24 * 30), "month"),
+		s							=> (s / (60 * 60 * 24 * 365), "year"),
+	};
+
+	format!("{} {}{} ago", value, unit, if value == 1 { "" } else { "s" })
+}
+
+#[derive(Default)]
+pub struct DocumentBlame {
+	pub version		: usize,
+	pub lines		: Vec<Arc<CommitInfo>>, // one per document line
+}
+
+impl DocumentBlame {
+	pub fn line(&self, line: usize) -> Option<&CommitInfo> {
+		self.lines.get(line).map(|commit| commit.as_ref())
+	}
+}
+
+pub struct BlameTask {
+	pub doc_id		: DocumentId,
+	pub version		: usize,
+	pub task		: Task<Result<Vec<Arc<CommitInfo>>, String>>,
+}
+
+#[derive(Resource)]
+pub struct Blame {
+	pub inline_enabled	: bool,
+	pub gutter_enabled	: bool,
+	pub hovered_line	: Option<usize>, // annotation of this line is hovered and commit details are shown
+
+	pub per_document	: HashMap<DocumentId, DocumentBlame>,
+	pub task			: Option<BlameTask>,
+	pub pending			: Option<(DocumentId, usize)>, // document version waiting for edits to settle down
+	pub pending_since	: f32,
+	pub failed			: Option<(DocumentId, usize)>, // documents outside of git repository are not blamed over and over again
+
+	pub history_task	: Option<Task<Result<Vec<LineCommit>, String>>>,
+	pub commit_request	: SharedCommitRequest,
+	pub commit_task		: Option<Task<String>>,
+
+	pub entities		: Vec<Entity>,
+	pub details_entities : Vec<Entity>, // hovering rebuilds only these so that annotation under mouse isn't respawned
+	pub cache			: Option<BlameCache>,
+	pub details_cache	: Option<Option<usize>>,
+}
+
+impl Default for Blame {
+	fn default() -> Self {
+		Self {
+			inline_enabled	: true,
+			gutter_enabled	: false,
+			hovered_line	: None,
+
+			per_document	: HashMap::default(),
+			task			: None,
+			pending			: None,
+			pending_since	: 0.0,
+			failed			: None,
+
+			history_task	: None,
+			commit_request	: SharedCommitRequest::default(),
+			commit_task		: None,
+
+			entities		: Vec::new(),
+			details_entities : Vec::new(),
+			cache			: None,
+			details_cache	: None,
+		}
+	}
+}
+
+// everything annotations placement and content depends on
+#[derive(Clone, PartialEq, Debug)]
+pub struct BlameCache {
+	pub view				: FoldedView,
+	pub cursor_line			: usize,
+	pub blame_version		: Option<usize>,
+	pub doc_version			: usize,
+	pub diagnostics_version	: usize,
+	pub folds_version		: usize,
+	pub horizontal_offset	: usize,
+	pub gutter_len			: usize,
+	pub theme				: String,
+	pub inline_enabled		: bool,
+	pub gutter_enabled		: bool,
+}
+
+// every annotation knows which line it belongs to so that hovering it shows commit details of that line
+#[derive(Component)]
+pub struct BlameAnnotation {
+	pub line : usize,
+}
+
+impl Blame {
+	// blame is only valid for the exact document version it was calculated for
+	pub fn get(&self, doc_id: DocumentId, version: usize) -> Option<&DocumentBlame> {
+		self.per_document.get(&doc_id).filter(|blame| blame.version == version)
+	}
+
+	pub fn toggle_inline(&mut self) {
+		self.inline_enabled = !self.inline_enabled;
+	}
+
+	pub fn toggle_gutter(&mut self) {
+		self.gutter_enabled = !self.gutter_enabled;
+	}
+}
+
+// buffer contents are passed to git so that blame matches unsaved changes too
+pub fn blame_file(path: &Path, contents: String) -> Result<Vec<Arc<CommitInfo>>, String> {
+	let Some((mut command, file_name)) = git_command(path) else { return Err(String::from("invalid path")) };
+
+	let mut child = command
+		.args(["blame", "--porcelain", "--contents", "-", "--", file_name.as_str()])
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.spawn()
+		.map_err(|e| e.to_string())?;
+
+	if let Some(mut stdin) = child.stdin.take() {
+		stdin.write_all(contents.as_bytes()).map_err(|e| e.to_string())?;
+	}
+
+	let output = child.wait_with_output().map_err(|e| e.to_string())?;
+
+	if !output.status.success() {
+		return Err(String::from_utf8_lossy(&output.stderr).to_string());
+	}
+
+	Ok(parse_porcelain(&String::from_utf8_lossy(&output.stdout)))
+}
+
+// porcelain format lists commit details only the first time commit is met, every line starts with a header "<hash> <orig line> <final line> [<group size>]"
+fn parse_porcelain(output: &str) -> Vec<Arc<CommitInfo>> {
+	let mut commits : HashMap<String, Arc<CommitInfo>> = HashMap::default();
+	let mut lines = Vec::new();
+
+	let mut current : Option<CommitInfo> = None;
+
+	for row in output.lines() {
+		if let Some(_content) = row.strip_prefix('\t') {
+			let Some(commit) = current.take() else { continue };
+
+			let commit = commits.entry(commit.hash.clone()).or_insert_with(|| Arc::new(commit)).clone();
+			lines.push(commit);
+
+			continue;
+		}
+
+		let Some(commit) = current.as_mut() else {
+			let hash = row.split(' ').next().unwrap_or_default().to_string();
+
+			current = Some(match commits.get(&hash) {
+				Some(known) => known.as_ref().clone(),
+				None => CommitInfo { hash, author: String::new(), time: 0, summary: String::new() },
+			});
+
+			continue;
+		};
+
+		if let Some(author) = row.strip_prefix("author ") {
+			commit.author = String::from(author);
+		} else if let Some(time) = row.strip_prefix("author-time ") {
+			commit.time = time.parse().unwrap_or(0);
+		} else if let Some(summary) = row.strip_prefix("summary ") {
+			commit.summary = String::from(summary);
+		}
+	}
+
+	lines
+}
+
+#[derive(Clone, Debug)]
+pub struct LineCommit {
+	pub hash		: String,
+	pub author		: String,
+	pub date		: String,
+	pub summary		: String,
+	pub path		: PathBuf,
+}
+
+impl Item for LineCommit {
+	type Data = ();
+
+	fn format(&self, _data: &Self::Data) -> Row {
+		format!("{} {} {} {}", &self.hash[.. self.hash.len().min(8)], self.date, self.author, self.summary).into()
+	}
+}
+
+impl LineCommit {
+	// changes this commit made to the file, runs in background
+	pub fn patch(&self) -> String {
+		let Some((mut command, file_name)) = git_command(&self.path) else { return String::from("invalid path") };
+
+		let output = command
+			.args(["show", "--stat", "--patch", self.hash.as_str(), "--", file_name.as_str()])
+			.output();
+
+		match output {
+			Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout).to_string(),
+			Ok(output) => String::from_utf8_lossy(&output.stderr).to_string(),
+			Err(e) => e.to_string(),
+		}
+	}
+}
+
+// line history picker puts selected commit here, its patch is then shown once background task is done
+pub type SharedCommitRequest = Arc<Mutex<Option<LineCommit>>>;
+
+// commits that touched given (0-based) line, newest first
+pub fn commits_touching_line(path: &Path, line: usize) -> Result<Vec<LineCommit>, String> {
+	let Some((mut command, file_name)) = git_command(path) else { return Err(String::from("invalid path")) };
+
+	let range = format!("-L{},{}:{}", line + 1, line + 1, file_name);
+	let max_count = format!("--max-count={}", MAX_HISTORY_COMMITS);
+
+	let output = command
+		.args(["log", "--no-patch", "--date=short", "--format=%H%x09%an%x09%ad%x09%s", max_count.as_str(), range.as_str()])
+		.output()
+		.map_err(|e| e.to_string())?;
+
+	if !output.status.success() {
+		return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+	}
+
+	let commits = String::from_utf8_lossy(&output.stdout)
+		.lines()
+		.filter_map(|row| {
+			let mut fields = row.splitn(4, '\t');
+
+			Some(LineCommit {
+				hash	: fields.next()?.to_string(),
+				author	: fields.next()?.to_string(),
+				date	: fields.next()?.to_string(),
+				summary	: fields.next().unwrap_or_default().to_string(),
+				path	: path.to_path_buf(),
+			})
+		})
+		.collect();
+
+	Ok(commits)
+}
+
+#[cfg(test)]
+mod tests {
+	use super :: *;
+
+	const HASH_A : &str = "1111111111111111111111111111111111111111";
+	const HASH_B : &str = "2222222222222222222222222222222222222222";
+
+	#[test]
+	fn parse_porcelain_repeated_commits() {
+		// details of a commit are only given the first time it shows up
+		let output = format!(
+			"{a} 1 1 2\nauthor Alice\nauthor-time 100\nsummary first\nfilename main.rs\n\tline one\n\
+			{a} 2 2\n\tline two\n\
+			{b} 3 3 1\nauthor Bob\nauthor-time 200\nsummary second\nfilename main.rs\n\tline three\n\
+			{u} 4 4 1\nauthor Not Committed Yet\nauthor-time 300\nsummary Version of main.rs from main.rs\nfilename main.rs\n\tline four\n",
+			a = HASH_A, b = HASH_B, u = UNCOMMITTED_HASH,
+		);
+
+		let lines = parse_porcelain(output.as_str());
+
+		assert_eq!(lines.len(), 4);
+
+		assert_eq!(lines[0].hash, HASH_A);
+		assert_eq!(lines[0].author, "Alice");
+		assert_eq!(lines[0].time, 100);
+		assert_eq!(lines[0].summary, "first");
+		assert!(Arc::ptr_eq(&lines[0], &lines[1]));
+
+		assert_eq!(lines[2].hash, HASH_B);
+		assert_eq!(lines[2].author, "Bob");
+		assert_eq!(lines[2].short_hash(), "22222222");
+
+		assert!(lines[3].is_uncommitted());
+		assert!(!lines[0].is_uncommitted());
+	}
+
+	#[test]
+	fn parse_porcelain_empty() {
+		assert!(parse_porcelain("").is_empty());
+	}
+}
@@ -0,0 +1,376 @@
+use bevy :: prelude :: *;
+use bevy :: tasks :: AsyncComputeTaskPool;
+
+#[cfg(feature = "tracing")]
+use bevy_puffin :: *;
+
+use futures_lite :: future;
+
+use helix_term :: ui :: EditorView;
+use helix_view :: graphics :: Color as HelixColor;
+
+use super :: *;
+
+use crate :: {
+	z_order,
+	kodiki_ui :: {
+		String3dSpawnRequest, CommonString3dSpawnParams,
+		color :: get_color_wmodified_lightness,
+		raypick :: RaypickHover,
+		spawn :: string_mesh_collision,
+	},
+	bevy_ab_glyph :: { ABGlyphFont, ABGlyphFonts, FontAssetHandles },
+	bevy_helix :: {
+		HelixApp,
+		diagnostics_lens :: short_message,
+		folding :: Folds,
+		surface :: SurfacesMapBevy,
+		systems_util :: popup_first_row,
+		utils :: color_from_helix,
+	},
+};
+
+// ctrl+alt+b toggles blame column, ctrl+alt+i toggles inline blame, ctrl+alt+g lists commits touching current line
+pub fn input_keyboard(
+		key			: Res<Input<KeyCode>>,
+	mut blame		: ResMut<Blame>,
+		app_option	: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	let ctrl_pressed	= key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl);
+	let alt_pressed		= key.pressed(KeyCode::LAlt) || key.pressed(KeyCode::RAlt);
+
+	if !(ctrl_pressed && alt_pressed) { return }
+
+	if key.just_pressed(KeyCode::B) {
+		blame.toggle_gutter();
+	} else if key.just_pressed(KeyCode::I) {
+		blame.toggle_inline();
+	} else if key.just_pressed(KeyCode::G) {
+		let (view, doc) = app.current_ref();
+
+		let Some(path) = doc.path().cloned() else {
+			app.editor.set_error("Line history is not available: document has no path");
+			return;
+		};
+
+		let text = doc.text().slice(..);
+		let line = text.char_to_line(doc.selection(view.id).primary().cursor(text));
+
+		let task = AsyncComputeTaskPool::get().spawn(async move {
+			commits_touching_line(&path, line)
+		});
+
+		blame.history_task = Some(task);
+	}
+}
+
+// git log and git show run in background, picker and commit popup are opened in Helix once they are done
+pub fn update_line_history(
+	mut blame		: ResMut<Blame>,
+		app_option	: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	if let Some(mut history_task) = blame.history_task.take() {
+		match future::block_on(future::poll_once(&mut history_task)) {
+			Some(Ok(commits)) => app.open_line_history(commits, blame.commit_request.clone()),
+			Some(Err(e)) => app.editor.set_error(format!("Failed to get line history: {}", e)),
+			None => blame.history_task = Some(history_task),
+		}
+	}
+
+	let selected_commit = blame.commit_request.lock().unwrap().take();
+
+	if let Some(commit) = selected_commit {
+		blame.commit_task = Some(AsyncComputeTaskPool::get().spawn(async move { commit.patch() }));
+	}
+
+	if let Some(mut commit_task) = blame.commit_task.take() {
+		match future::block_on(future::poll_once(&mut commit_task)) {
+			Some(patch) => app.show_commit_patch(patch),
+			None => blame.commit_task = Some(commit_task),
+		}
+	}
+}
+
+// blame of current document is recalculated in background once edits settle down
+pub fn update_blame(
+		time		: Res<Time>,
+	mut blame		: ResMut<Blame>,
+		app_option	: Option<NonSend<HelixApp>>,
+) {
+	let Some(app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	profile_function!();
+
+	blame.per_document.retain(|doc_id, _| app.editor.documents.contains_key(doc_id));
+
+	if let Some(mut blame_task) = blame.task.take() {
+		match future::block_on(future::poll_once(&mut blame_task.task)) {
+			Some(Ok(lines)) => {
+				blame.per_document.insert(blame_task.doc_id, DocumentBlame { version: blame_task.version, lines });
+			},
+			Some(Err(e)) => {
+				log::debug!("git blame failed: {}", e);
+				blame.failed = Some((blame_task.doc_id, blame_task.version));
+			},
+			None => {
+				blame.task = Some(blame_task);
+				return;
+			}
+		}
+	}
+
+	if !blame.inline_enabled && !blame.gutter_enabled { return }
+
+	let doc = app.current_document();
+	let key = (doc.id(), doc.version());
+
+	let Some(path) = doc.path() else { return };
+
+	if blame.get(key.0, key.1).is_some() || blame.failed == Some(key) { return }
+
+	if blame.pending != Some(key) {
+		blame.pending = Some(key);
+		blame.pending_since = 0.0;
+	}
+
+	blame.pending_since += time.delta_seconds();
+
+	// freshly opened documents are blamed right away, edited ones after a pause
+	let edited = blame.per_document.contains_key(&key.0);
+
+	if edited && blame.pending_since < BLAME_DELAY_SECONDS { return }
+
+	let path		= path.clone();
+	let contents	= doc.text().to_string();
+
+	let task = AsyncComputeTaskPool::get().spawn(async move {
+		blame_file(&path, contents)
+	});
+
+	blame.pending	= None;
+	blame.task		= Some(BlameTask { doc_id: key.0, version: key.1, task });
+}
+
+pub fn update_hover(
+		q_annotation	: Query<(&BlameAnnotation, &RaypickHover)>,
+	mut blame			: ResMut<Blame>,
+) {
+	let hovered_line = q_annotation.iter().find(|(_, hover)| hover.hovered()).map(|(annotation, _)| annotation.line);
+
+	if blame.hovered_line != hovered_line {
+		blame.hovered_line = hovered_line;
+	}
+}
+
+fn spawn_annotation_string(
+	string				: String,
+	line				: usize,
+	position			: Vec3,
+	color				: Color,
+	background_color	: Option<Color>,
+	font				: &ABGlyphFont,
+	commands			: &mut Commands,
+) -> Entity {
+	let collision_entity = string_mesh_collision(&string, font, commands);
+
+	let annotation_entity = commands.spawn((
+		BlameAnnotation { line },
+		RaypickHover::default(),
+		TransformBundle::from_transform(Transform::from_translation(position)),
+		VisibilityBundle::default(),
+		String3dSpawnRequest {
+			common : CommonString3dSpawnParams {
+				string,
+				color,
+				background_color,
+				..default()
+			},
+			..default()
+		},
+	)).id();
+
+	commands.entity(annotation_entity).add_child(collision_entity);
+
+	annotation_entity
+}
+
+// dimmed annotation after the end of the cursor line and optional blame column along the right edge of the view.
+// Hovering any of them lists commit details under the hovered line
+pub fn update_annotations(
+	mut blame			: ResMut<Blame>,
+		folds			: Res<Folds>,
+		surfaces_bevy	: Res<SurfacesMapBevy>,
+		font_assets		: Res<Assets<ABGlyphFont>>,
+		font_handles	: Res<FontAssetHandles>,
+	mut commands		: Commands,
+		app_option		: Option<NonSend<HelixApp>>,
+) {
+	let Some(app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	profile_function!();
+
+	let folded_view = FoldedView::new(&app);
+	let (view, doc) = app.current_ref();
+
+	let text		= doc.text().slice(..);
+	let cursor_line	= text.char_to_line(doc.selection(view.id).primary().cursor(text));
+
+	let cache = BlameCache {
+		view				: folded_view,
+		cursor_line,
+		blame_version		: blame.get(doc.id(), doc.version()).map(|doc_blame| doc_blame.version),
+		doc_version			: doc.version(),
+		diagnostics_version	: doc.diagnostics_version(),
+		folds_version		: folds.version,
+		horizontal_offset	: view.offset.horizontal_offset,
+		gutter_len			: app.gutter_len(),
+		theme				: app.editor.theme.name().into(),
+		inline_enabled		: blame.inline_enabled,
+		gutter_enabled		: blame.gutter_enabled,
+	};
+
+	let rebuild_annotations	= blame.cache.as_ref() != Some(&cache);
+	let rebuild_details		= rebuild_annotations || blame.details_cache != Some(blame.hovered_line);
+
+	if !rebuild_details {
+		return;
+	}
+
+	if rebuild_annotations {
+		for entity in blame.entities.drain(..) {
+			commands.entity(entity).despawn_recursive();
+		}
+	}
+
+	for entity in blame.details_entities.drain(..) {
+		commands.entity(entity).despawn_recursive();
+	}
+
+	blame.cache			= Some(cache);
+	blame.details_cache	= Some(blame.hovered_line);
+
+	let Some(doc_blame) = blame.get(doc.id(), doc.version()) else { return };
+	let Some(surface_editor) = surfaces_bevy.get(EditorView::ID) else { return };
+
+	let fonts			= ABGlyphFonts::new(&font_assets, &font_handles);
+	let row_height		= fonts.main.vertical_advance();
+	let column_width	= fonts.main.horizontal_advance_mono();
+
+	let theme			= &app.editor.theme;
+	let dark_theme		= app.dark_theme();
+	let color			= color_from_helix(theme.get("comment").fg.unwrap_or(HelixColor::Gray));
+	let details_background = get_color_wmodified_lightness(color, if dark_theme { -0.35 } else { 0.35 });
+
+	let tab				= " ".repeat(doc.tab_width());
+	let gutter_len		= app.gutter_len();
+	let top_row			= folded_view.top_row;
+	let view_height		= view.area.height as usize;
+	let view_right		= (view.area.x + view.area.width) as usize;
+	let text_column		= view.area.x as usize + gutter_len;
+	let horizontal_offset = view.offset.horizontal_offset;
+
+	let last_line = folds.document_row(folded_view.doc_id, top_row + view_height, top_row).min(text.len_lines());
+
+	let row_y = |visual_row: usize| -> f32 {
+		-((folded_view.surface_row(visual_row) + 1) as f32 * row_height)
+	};
+
+	// blame column covers the end of long lines so it gets a background to stay readable
+	let gutter_column	= view_right.saturating_sub(BLAME_GUTTER_COLUMNS).max(text_column);
+	let inline_right	= if blame.gutter_enabled { gutter_column.saturating_sub(BLAME_GAP_COLUMNS) } else { view_right };
+
+	let mut entities			= Vec::new();
+	let mut details_entities	= Vec::new();
+
+	if rebuild_annotations {
+		// diagnostics lens takes the end of the line if there is anything to report
+		let line_has_diagnostics = doc.diagnostics().iter().any(|diagnostic| diagnostic.line == cursor_line);
+
+		if blame.inline_enabled && !line_has_diagnostics && !folds.is_hidden(folded_view.doc_id, cursor_line)
+		&& (top_row .. last_line).contains(&cursor_line) {
+			if let Some(commit) = doc_blame.line(cursor_line) {
+				let line_len	= text.line(cursor_line).to_string().replace('\t', tab.as_str()).trim_end().chars().count();
+				let column		= text_column + line_len.saturating_sub(horizontal_offset) + BLAME_GAP_COLUMNS;
+				let free_columns = inline_right.saturating_sub(column).min(INLINE_BLAME_COLUMNS);
+
+				let string = short_message(commit.annotation().as_str(), free_columns);
+
+				if !string.is_empty() {
+					let visual_row	= folds.visual_row(folded_view.doc_id, cursor_line, top_row);
+					let position	= Vec3::new(column as f32 * column_width, row_y(visual_row), z_order::surface::text());
+
+					entities.push(spawn_annotation_string(string, cursor_line, position, color, None, fonts.main, &mut commands));
+				}
+			}
+		}
+
+		if blame.gutter_enabled {
+			let mut previous_hash : Option<&str> = None;
+
+			for line in top_row .. last_line {
+				if folds.is_hidden(folded_view.doc_id, line) { continue }
+
+				let Some(commit) = doc_blame.line(line) else { break };
+
+				// consecutive lines of the same commit are annotated once
+				if previous_hash == Some(commit.hash.as_str()) { continue }
+
+				previous_hash = Some(commit.hash.as_str());
+
+				let string = if commit.is_uncommitted() {
+					String::from("uncommitted")
+				} else {
+					format!("{} {} {}", commit.short_hash(), relative_time(commit.time), commit.author)
+				};
+
+				let visual_row	= folds.visual_row(folded_view.doc_id, line, top_row);
+				let position	= Vec3::new(gutter_column as f32 * column_width, row_y(visual_row), z_order::surface::child_surface());
+
+				entities.push(spawn_annotation_string(short_message(string.as_str(), BLAME_GUTTER_COLUMNS), line, position, color, Some(details_background), fonts.main, &mut commands));
+			}
+		}
+	}
+
+	if let Some(line) = blame.hovered_line {
+		if let Some(commit) = doc_blame.line(line) {
+			let visual_row	= folds.visual_row(folded_view.doc_id, line, top_row);
+			let max_columns	= view_right.saturating_sub(text_column);
+
+			let details : Vec<String> = commit.details().iter().map(|detail| short_message(detail.as_str(), max_columns)).collect();
+
+			// details are aligned to the right edge of the view
+			let width		= details.iter().map(|detail| detail.chars().count()).max().unwrap_or(0);
+			let column		= view_right.saturating_sub(width).max(text_column);
+
+			let first_row	= popup_first_row(visual_row, visual_row, details.len(), top_row, view_height);
+
+			for (index, detail) in details.into_iter().enumerate() {
+				let position = Vec3::new(column as f32 * column_width, row_y(first_row + index), z_order::surface::child_surface());
+
+				details_entities.push(spawn_annotation_string(detail, line, position, color, Some(details_background), fonts.main, &mut commands));
+			}
+		}
+	}
+
+	for entity in entities.iter().chain(details_entities.iter()) {
+		commands.entity(surface_editor.entity).add_child(*entity);
+	}
+
+	if rebuild_annotations {
+		blame.entities = entities;
+	}
+
+	blame.details_entities = details_entities;
+}
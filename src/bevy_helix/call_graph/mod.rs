@@ -0,0 +1,428 @@
+use bevy :: prelude :: *;
+use bevy :: utils :: HashMap;
+
+use helix_lsp :: { lsp, Client };
+
+use tokio :: task :: JoinHandle;
+
+use std :: {
+	path :: PathBuf,
+	sync :: Arc,
+};
+
+pub mod systems;
+
+pub const LEVEL_COLUMNS			: f32	= 56.0;	// horizontal distance between call levels
+pub const LEVEL_DEPTH_COLUMNS	: f32	= 6.0;	// every next level is pushed further away from camera
+pub const NODE_ROWS				: f32	= 3.0;	// name, file and a gap
+pub const LABEL_COLUMNS			: usize	= 48;	// marker, gap and name
+pub const VIEW_COLUMNS			: usize	= 160;	// what camera is centered on
+pub const EDGE_THICKNESS		: f32	= 0.1;	// fraction of column width
+pub const MAX_REFERENCES		: usize	= 200;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum CallDirection {
+	Incoming,
+	Outgoing,
+	References,
+}
+
+impl CallDirection {
+	// callers go to the left of the root, callees to the right, references are listed under it
+	pub fn side(self) -> f32 {
+		match self {
+			CallDirection::Incoming		=> -1.0,
+			CallDirection::Outgoing		=> 1.0,
+			CallDirection::References	=> 0.0,
+		}
+	}
+
+	pub fn header(self) -> &'static str {
+		match self {
+			CallDirection::Incoming		=> "callers",
+			CallDirection::Outgoing		=> "callees",
+			CallDirection::References	=> "references",
+		}
+	}
+
+	pub fn theme_scope(self) -> &'static str {
+		match self {
+			CallDirection::Incoming		=> "type",
+			CallDirection::Outgoing		=> "function",
+			CallDirection::References	=> "string",
+		}
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct CallNode {
+	pub name		: String,
+	pub path		: PathBuf,
+	pub range		: lsp::Range, // where the name of function is or where it is referenced
+	pub item		: Option<lsp::CallHierarchyItem>, // references are plain locations and can't be expanded
+	pub direction	: Option<CallDirection>, // none for the root
+	pub level		: usize,
+	pub parent		: Option<usize>,
+	pub call_sites	: usize,
+	pub expanded	: bool,
+}
+
+impl CallNode {
+	fn from_item(item: lsp::CallHierarchyItem, direction: Option<CallDirection>, parent: Option<usize>, level: usize, call_sites: usize) -> Self {
+		Self {
+			name		: item.name.clone(),
+			path		: item.uri.to_file_path().unwrap_or_default(),
+			range		: item.selection_range,
+			item		: Some(item),
+			direction,
+			level,
+			parent,
+			call_sites,
+			expanded	: false,
+		}
+	}
+
+	pub fn is_expandable(&self) -> bool {
+		self.item.is_some() && !self.expanded && self.direction != Some(CallDirection::References)
+	}
+
+	pub fn marker(&self) -> &'static str {
+		match (self.direction, self.is_expandable()) {
+			(None, _)								=> "●",
+			(Some(CallDirection::References), _)	=> "·",
+			(_, true)								=> "▸",
+			(_, false)								=> "▾",
+		}
+	}
+
+	pub fn label(&self) -> String {
+		if self.call_sites > 1 {
+			format!("{} ×{}", self.name, self.call_sites)
+		} else {
+			self.name.clone()
+		}
+	}
+
+	pub fn location(&self, workspace: &std::path::Path) -> String {
+		let path = self.path.strip_prefix(workspace).unwrap_or(&self.path);
+		format!("{}:{}", path.display(), self.range.start.line + 1)
+	}
+}
+
+pub type CallRequestTask = JoinHandle<Result<Vec<CallNode>, String>>;
+
+fn parse<T: serde::de::DeserializeOwned>(response: helix_lsp::Result<serde_json::Value>) -> Result<T, String> {
+	let value = response.map_err(|e| e.to_string())?;
+	serde_json::from_value(value).map_err(|e| e.to_string())
+}
+
+// Helix has no dedicated methods for call hierarchy so its requests are sent as generic ones
+fn supports_call_hierarchy(client: &Client) -> bool {
+	!matches!(client.capabilities().call_hierarchy_provider, None | Some(lsp::CallHierarchyServerCapability::Simple(false)))
+}
+
+// root is the function under cursor as language server sees it, or just the word under cursor if call hierarchy is not supported
+pub async fn resolve_root(
+	client			: Arc<Client>,
+	text_document	: lsp::TextDocumentIdentifier,
+	position		: lsp::Position,
+	fallback		: CallNode,
+) -> Result<Vec<CallNode>, String> {
+	let prepared : Vec<lsp::CallHierarchyItem> = if supports_call_hierarchy(&client) {
+		let params = lsp::CallHierarchyPrepareParams {
+			text_document_position_params	: lsp::TextDocumentPositionParams { text_document: text_document.clone(), position },
+			work_done_progress_params		: lsp::WorkDoneProgressParams::default(),
+		};
+
+		parse::<Option<Vec<lsp::CallHierarchyItem>>>(client.call::<lsp::request::CallHierarchyPrepare>(params).await)?.unwrap_or_default()
+	} else {
+		Vec::new()
+	};
+
+	let root = match prepared.into_iter().next() {
+		Some(item) => CallNode::from_item(item, None, None, 0, 0),
+		None => fallback,
+	};
+
+	let root_item = root.item.clone();
+
+	let mut nodes = vec![root];
+
+	if let Some(item) = root_item {
+		nodes.extend(expand(client.clone(), item.clone(), CallDirection::Incoming, 0, 1).await?);
+		nodes.extend(expand(client.clone(), item, CallDirection::Outgoing, 0, 1).await?);
+	}
+
+	nodes.extend(references(client, text_document, position, 0).await?);
+
+	nodes[0].expanded = true;
+
+	Ok(nodes)
+}
+
+// next level of callers or callees of a node
+pub async fn expand(
+	client		: Arc<Client>,
+	item		: lsp::CallHierarchyItem,
+	direction	: CallDirection,
+	parent		: usize,
+	level		: usize,
+) -> Result<Vec<CallNode>, String> {
+	if !supports_call_hierarchy(&client) { return Ok(Vec::new()) }
+
+	let nodes = match direction {
+		CallDirection::Incoming => {
+			let params = lsp::CallHierarchyIncomingCallsParams {
+				item,
+				work_done_progress_params	: lsp::WorkDoneProgressParams::default(),
+				partial_result_params		: lsp::PartialResultParams::default(),
+			};
+
+			parse::<Option<Vec<lsp::CallHierarchyIncomingCall>>>(client.call::<lsp::request::CallHierarchyIncomingCalls>(params).await)?.unwrap_or_default()
+				.into_iter()
+				.map(|call| CallNode::from_item(call.from, Some(direction), Some(parent), level, call.from_ranges.len()))
+				.collect()
+		},
+		CallDirection::Outgoing => {
+			let params = lsp::CallHierarchyOutgoingCallsParams {
+				item,
+				work_done_progress_params	: lsp::WorkDoneProgressParams::default(),
+				partial_result_params		: lsp::PartialResultParams::default(),
+			};
+
+			parse::<Option<Vec<lsp::CallHierarchyOutgoingCall>>>(client.call::<lsp::request::CallHierarchyOutgoingCalls>(params).await)?.unwrap_or_default()
+				.into_iter()
+				.map(|call| CallNode::from_item(call.to, Some(direction), Some(parent), level, call.from_ranges.len()))
+				.collect()
+		},
+		CallDirection::References => Vec::new(),
+	};
+
+	Ok(nodes)
+}
+
+// references are shown with the line they are on
+async fn references(
+	client			: Arc<Client>,
+	text_document	: lsp::TextDocumentIdentifier,
+	position		: lsp::Position,
+	parent			: usize,
+) -> Result<Vec<CallNode>, String> {
+	let Some(future) = client.goto_reference(text_document, position, false, None) else { return Ok(Vec::new()) };
+
+	let locations = parse::<Option<Vec<lsp::Location>>>(future.await)?.unwrap_or_default();
+
+	let mut files : HashMap<PathBuf, Vec<String>> = HashMap::new();
+
+	let nodes = locations.into_iter().take(MAX_REFERENCES).map(|location| {
+		let path = location.uri.to_file_path().unwrap_or_default();
+
+		let lines = files.entry(path.clone()).or_insert_with(|| {
+			std::fs::read_to_string(&path).map(|text| text.lines().map(String::from).collect()).unwrap_or_default()
+		});
+
+		let line = lines.get(location.range.start.line as usize).map_or("", |line| line.trim());
+
+		CallNode {
+			name		: String::from(line),
+			path,
+			range		: location.range,
+			item		: None,
+			direction	: Some(CallDirection::References),
+			level		: 1,
+			parent		: Some(parent),
+			call_sites	: 0,
+			expanded	: false,
+		}
+	}).collect();
+
+	Ok(nodes)
+}
+
+// word under cursor, used as the root when language server can't prepare call hierarchy
+pub fn word_at(text: helix_core::RopeSlice, pos: usize) -> String {
+	let is_word = |c: char| c.is_alphanumeric() || c == '_';
+
+	let mut start = pos;
+	while start > 0 && is_word(text.char(start - 1)) {
+		start -= 1;
+	}
+
+	let mut end = pos;
+	while end < text.len_chars() && is_word(text.char(end)) {
+		end += 1;
+	}
+
+	text.slice(start .. end).to_string()
+}
+
+#[derive(Component)]
+pub struct CallGraphNode {
+	pub index : usize,
+}
+
+#[derive(Component)]
+pub struct CallGraphExpander {
+	pub index : usize,
+}
+
+#[derive(Component)]
+pub struct CallGraphAnchor;
+
+// call hierarchy and references around symbol under cursor, shown over hidden editor while active
+#[derive(Resource, Default)]
+pub struct CallGraph {
+	pub active			: bool,
+	pub nodes			: Vec<CallNode>,
+	pub selected		: usize,
+	pub client			: Option<Arc<Client>>,
+	pub request_task	: Option<CallRequestTask>,
+	pub error			: Option<String>,
+	pub open_requested	: Option<usize>,
+
+	pub version			: usize, // bumped every time nodes change so graph is respawned
+	pub spawned_version	: Option<usize>,
+	pub root_entity		: Option<Entity>,
+	pub anchor_entity	: Option<Entity>,
+	pub marker_entity	: Option<Entity>, // highlights selected node
+	pub traveled_to		: Option<usize>, // node camera anchor was last sent to
+}
+
+pub fn call_graph_inactive(call_graph: Res<CallGraph>) -> bool {
+	!call_graph.active
+}
+
+impl CallGraph {
+	pub fn open(&mut self, client: Arc<Client>, task: CallRequestTask) {
+		self.clear();
+
+		self.active			= true;
+		self.client			= Some(client);
+		self.request_task	= Some(task);
+	}
+
+	pub fn close(&mut self) {
+		self.active = false;
+	}
+
+	pub fn is_loading(&self) -> bool {
+		self.request_task.is_some()
+	}
+
+	// requested nodes are added when language server responds
+	pub fn request_expand(&mut self, index: usize, runtime: &tokio::runtime::Runtime) {
+		if self.is_loading() { return }
+
+		let Some(client) = self.client.clone() else { return };
+		let Some(node) = self.nodes.get_mut(index) else { return };
+
+		if !node.is_expandable() { return }
+
+		let (Some(item), Some(direction)) = (node.item.clone(), node.direction) else { return };
+
+		node.expanded = true;
+
+		let level = node.level + 1;
+
+		self.request_task = Some(runtime.spawn(expand(client, item, direction, index, level)));
+		self.version += 1;
+	}
+
+	pub fn append(&mut self, nodes: Vec<CallNode>) {
+		if self.nodes.is_empty() && nodes.is_empty() {
+			self.error = Some(String::from("no symbol under cursor"));
+		}
+
+		self.nodes.extend(nodes);
+		self.version += 1;
+	}
+
+	// nodes are laid out in columns by level, children start next to their parent and go down.
+	// Returns column (signed level) and slot (row in units of NODE_ROWS) of every node
+	pub fn layout(&self) -> Vec<(f32, f32)> {
+		let mut children : Vec<Vec<usize>> = vec![Vec::new(); self.nodes.len()];
+
+		for (index, node) in self.nodes.iter().enumerate() {
+			if let Some(parent) = node.parent {
+				children[parent].push(index);
+			}
+		}
+
+		let mut slots		= vec![(0.0, 0.0); self.nodes.len()];
+		let mut next_free	: HashMap<(Option<CallDirection>, usize), f32> = HashMap::new();
+
+		// references go under the root leaving a row for their header
+		next_free.insert((Some(CallDirection::References), 1), 2.0);
+
+		let mut stack : Vec<(usize, f32)> = self.nodes.iter().enumerate()
+			.filter(|(_, node)| node.parent.is_none())
+			.map(|(index, _)| (index, 0.0))
+			.rev()
+			.collect();
+
+		while let Some((index, parent_slot)) = stack.pop() {
+			let node = &self.nodes[index];
+
+			let free = next_free.entry((node.direction, node.level)).or_insert(0.0);
+			let slot = parent_slot.max(*free);
+			*free = slot + 1.0;
+
+			let column = node.direction.map_or(0.0, |direction| direction.side() * node.level as f32);
+			slots[index] = (column, slot);
+
+			for child in children[index].iter().rev() {
+				stack.push((*child, slot));
+			}
+		}
+
+		slots
+	}
+
+	// arrows move between nodes of the same column and to the nearest node of neighbouring column
+	pub fn select_neighbour(&mut self, columns: f32, slots: f32) {
+		if self.nodes.is_empty() { return }
+
+		let layout = self.layout();
+		let (column, slot) = layout[self.selected];
+
+		let candidates = layout.iter().enumerate().filter(|(index, (other_column, other_slot))| {
+			if *index == self.selected { return false }
+
+			if columns != 0.0 {
+				*other_column == column + columns
+			} else {
+				*other_column == column && (*other_slot - slot).signum() == slots.signum()
+			}
+		});
+
+		let nearest = candidates.min_by(|(_, (_, a)), (_, (_, b))| (a - slot).abs().total_cmp(&(b - slot).abs()));
+
+		if let Some((index, _)) = nearest {
+			self.selected = index;
+		}
+	}
+
+	// everything spawned for graph except the camera anchor is a child of the root
+	pub fn take_graph_entities(&mut self) -> Option<Entity> {
+		self.marker_entity		= None;
+		self.traveled_to		= None;
+		self.spawned_version	= None;
+
+		self.root_entity.take()
+	}
+
+	pub fn take_entities(&mut self) -> Vec<Entity> {
+		self.take_graph_entities().into_iter().chain(self.anchor_entity.take()).collect()
+	}
+
+	pub fn clear(&mut self) {
+		self.active			= false;
+		self.nodes.clear();
+		self.selected		= 0;
+		self.client			= None;
+		self.request_task	= None;
+		self.error			= None;
+		self.open_requested	= None;
+		self.version		+= 1;
+	}
+}
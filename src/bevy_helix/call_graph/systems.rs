@@ -0,0 +1,540 @@
+use bevy :: prelude :: *;
+use bevy_tweening :: *;
+use bevy_reader_camera :: { ReaderCamera, TextDescriptor };
+
+#[cfg(feature = "tracing")]
+use bevy_puffin :: *;
+
+use futures_lite :: future;
+
+use helix_lsp :: util :: pos_to_lsp_pos;
+use helix_term :: ui :: EditorView;
+use helix_view :: graphics :: Color as HelixColor;
+
+use std :: time :: Duration;
+
+use super :: *;
+
+use crate :: {
+	z_order,
+	kodiki :: DespawnResource,
+	kodiki_ui :: {
+		String3dSpawnRequest, CommonString3dSpawnParams, ColorMaterialsCache,
+		color :: get_color_material_handle,
+		raypick :: RaypickHover,
+		spawn :: string_mesh_collision,
+		tween_lens :: TransformLens,
+	},
+	bevy_ab_glyph :: { ABGlyphFont, ABGlyphFonts, FontAssetHandles },
+	bevy_framerate_manager :: FramerateManager,
+	bevy_helix :: {
+		HelixApp, TokioRuntime,
+		diagnostics_lens :: short_message,
+		surface :: SurfacesMapBevy,
+		utils :: color_from_helix,
+	},
+};
+
+// where nodes go in the graph, levels grow away from the root in both directions and away from camera
+struct GraphMetrics {
+	column_width	: f32,
+	row_height		: f32,
+}
+
+impl GraphMetrics {
+	fn new(font: &ABGlyphFont) -> Self {
+		Self {
+			column_width	: font.horizontal_advance_mono(),
+			row_height		: font.vertical_advance(),
+		}
+	}
+
+	fn node_position(&self, (column, slot): (f32, f32)) -> Vec3 {
+		Vec3::new(
+			column * LEVEL_COLUMNS * self.column_width,
+			-slot * NODE_ROWS * self.row_height,
+			-column.abs() * LEVEL_DEPTH_COLUMNS * self.column_width + z_order::surface::text()
+		)
+	}
+
+	fn label_width(&self) -> f32 {
+		LABEL_COLUMNS as f32 * self.column_width
+	}
+
+	// strings grow right and up from their position so edges are attached to the middle of the name row
+	fn edge_points(&self, parent: Vec3, child: Vec3, direction: CallDirection) -> (Vec3, Vec3) {
+		let middle = Vec3::new(0.0, self.row_height / 2.0, z_order::surface::coloring() - z_order::surface::text());
+
+		match direction {
+			CallDirection::Incoming		=> (parent + middle, child + middle + Vec3::X * self.label_width()),
+			CallDirection::Outgoing		=> (parent + middle + Vec3::X * self.label_width(), child + middle),
+			CallDirection::References	=> (parent + middle - Vec3::X * self.column_width, child + middle - Vec3::X * self.column_width),
+		}
+	}
+
+	// selected node stays in the middle of the screen
+	fn anchor_position(&self, node: Vec3, visible_rows: f32) -> Vec3 {
+		Vec3::new(
+			node.x + self.label_width() / 2.0 - (VIEW_COLUMNS / 2) as f32 * self.column_width,
+			node.y + (visible_rows / 2.0).floor() * self.row_height,
+			0.0
+		)
+	}
+}
+
+fn spawn_string(
+	string				: String,
+	position			: Vec3,
+	color				: Color,
+	commands			: &mut Commands,
+) -> Entity {
+	commands.spawn((
+		TransformBundle::from_transform(Transform::from_translation(position)),
+		VisibilityBundle::default(),
+		String3dSpawnRequest {
+			common : CommonString3dSpawnParams {
+				string,
+				color,
+				..default()
+			},
+			..default()
+		},
+	)).id()
+}
+
+fn make_clickable(entity: Entity, string: &String, font: &ABGlyphFont, commands: &mut Commands) {
+	let collision_entity = string_mesh_collision(string, font, commands);
+
+	commands.entity(entity)
+		.insert(RaypickHover::default())
+		.add_child(collision_entity);
+}
+
+// unit quad stretched between two points
+fn spawn_segment(
+	from		: Vec3,
+	to			: Vec3,
+	thickness	: f32,
+	mesh		: &Handle<Mesh>,
+	material	: &Handle<StandardMaterial>,
+	commands	: &mut Commands,
+) -> Option<Entity> {
+	let delta	= to - from;
+	let length	= delta.length();
+
+	if length < f32::EPSILON { return None }
+
+	Some(commands.spawn(PbrBundle {
+		mesh		: mesh.clone(),
+		material	: material.clone(),
+		transform	: Transform {
+			translation	: (from + to) / 2.0,
+			rotation	: Quat::from_rotation_arc(Vec3::Y, delta / length),
+			scale		: Vec3::new(thickness, length, 1.0),
+		},
+		..default()
+	}).id())
+}
+
+// requests call hierarchy and references of symbol under cursor from its language server
+fn open_call_graph(app: &mut HelixApp, call_graph: &mut CallGraph, tokio_runtime: &TokioRuntime) {
+	let (view, doc) = app.current_ref();
+
+	let Some(server_id) = doc.language_server().map(|server| server.id()) else {
+		app.editor.set_error("Call graph needs a language server");
+		return;
+	};
+
+	let Some(client) = app.editor.language_servers.iter_clients().find(|client| client.id() == server_id).cloned() else { return };
+
+	let text		= doc.text();
+	let cursor		= doc.selection(view.id).primary().cursor(text.slice(..));
+	let position	= pos_to_lsp_pos(text, cursor, client.offset_encoding());
+
+	let fallback = CallNode {
+		name		: word_at(text.slice(..), cursor),
+		path		: doc.path().cloned().unwrap_or_default(),
+		range		: lsp::Range::new(position, position),
+		item		: None,
+		direction	: None,
+		level		: 0,
+		parent		: None,
+		call_sites	: 0,
+		expanded	: true,
+	};
+
+	let task = tokio_runtime.spawn(resolve_root(client.clone(), doc.identifier(), position, fallback));
+
+	call_graph.open(client, task);
+}
+
+// ctrl+alt+c opens graph for symbol under cursor. While graph is shown: esc closes it, arrows (hjkl) move selection,
+// space expands selected node, enter opens its location
+pub fn input_keyboard(
+		key				: Res<Input<KeyCode>>,
+	mut call_graph		: ResMut<CallGraph>,
+		tokio_runtime	: Res<TokioRuntime>,
+		app_option		: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	let ctrl_pressed	= key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl);
+	let alt_pressed		= key.pressed(KeyCode::LAlt) || key.pressed(KeyCode::RAlt);
+
+	if ctrl_pressed && alt_pressed && key.just_pressed(KeyCode::C) {
+		if call_graph.active {
+			call_graph.close();
+		} else if app.editor_focused() {
+			open_call_graph(&mut app, &mut call_graph, &tokio_runtime);
+			app.request_render();
+		}
+
+		return;
+	}
+
+	if !call_graph.active { return }
+
+	if key.just_pressed(KeyCode::Escape) {
+		call_graph.close();
+	} else if key.just_pressed(KeyCode::Up) || key.just_pressed(KeyCode::K) {
+		call_graph.select_neighbour(0.0, -1.0);
+	} else if key.just_pressed(KeyCode::Down) || key.just_pressed(KeyCode::J) {
+		call_graph.select_neighbour(0.0, 1.0);
+	} else if key.just_pressed(KeyCode::Left) || key.just_pressed(KeyCode::H) {
+		call_graph.select_neighbour(-1.0, 0.0);
+	} else if key.just_pressed(KeyCode::Right) || key.just_pressed(KeyCode::L) {
+		call_graph.select_neighbour(1.0, 0.0);
+	} else if key.just_pressed(KeyCode::Space) {
+		let selected = call_graph.selected;
+		call_graph.request_expand(selected, &tokio_runtime);
+	} else if key.just_pressed(KeyCode::Return) {
+		call_graph.open_requested = Some(call_graph.selected);
+	}
+}
+
+// clicking a node opens its location, clicking the arrow in front of it expands it
+pub fn input_mouse(
+		mouse_button	: Res<Input<MouseButton>>,
+		q_node			: Query<(&CallGraphNode, &RaypickHover)>,
+		q_expander		: Query<(&CallGraphExpander, &RaypickHover)>,
+	mut call_graph		: ResMut<CallGraph>,
+		tokio_runtime	: Res<TokioRuntime>,
+) {
+	if !call_graph.active || !mouse_button.just_pressed(MouseButton::Left) { return }
+
+	if let Some((expander, _)) = q_expander.iter().find(|(_, hover)| hover.hovered()) {
+		call_graph.selected = expander.index;
+		call_graph.request_expand(expander.index, &tokio_runtime);
+		return;
+	}
+
+	let Some((node, _)) = q_node.iter().find(|(_, hover)| hover.hovered()) else { return };
+
+	call_graph.selected = node.index;
+	call_graph.open_requested = Some(node.index);
+}
+
+// nodes are added once language server responds
+pub fn poll_requests(
+	mut call_graph	: ResMut<CallGraph>,
+) {
+	let Some(mut request_task) = call_graph.request_task.take() else { return };
+
+	let result = match future::block_on(future::poll_once(&mut request_task)) {
+		Some(result) => result,
+		None => {
+			call_graph.request_task = Some(request_task);
+			return;
+		}
+	};
+
+	match result.map_err(|e| e.to_string()).and_then(|result| result) {
+		Ok(nodes) => call_graph.append(nodes),
+		Err(e) => {
+			call_graph.error = Some(e);
+			call_graph.version += 1;
+		},
+	}
+}
+
+// location is opened in editor and graph is closed
+pub fn open_requested_location(
+	mut call_graph		: ResMut<CallGraph>,
+		tokio_runtime	: Res<TokioRuntime>,
+		app_option		: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(index) = call_graph.open_requested.take() else { return };
+	let Some(mut app) = app_option else { return };
+	let Some(node) = call_graph.nodes.get(index) else { return };
+
+	if node.path.as_os_str().is_empty() { return }
+
+	let path = node.path.clone();
+	let (line, column) = (node.range.start.line as usize, node.range.start.character as usize);
+
+	tokio_runtime.block_on(app.jump_to_path(&path, Some(line), Some(column)));
+	app.request_render();
+
+	call_graph.close();
+}
+
+fn travel(
+	anchor_entity	: Entity,
+	start			: Transform,
+	end				: Transform,
+	commands		: &mut Commands,
+) {
+	let distance = start.translation.distance(end.translation);
+	let travel_duration = (distance * 100.0).clamp(150.0, 500.0) as u64;
+
+	let tween = Tween::new(
+		EaseFunction::QuadraticInOut,
+		Duration::from_millis(travel_duration),
+		TransformLens {
+			start,
+			end,
+		}
+	);
+
+	commands.entity(anchor_entity).insert(Animator::new(tween));
+}
+
+// editor is hidden while graph is shown, graph is respawned every time nodes change
+pub fn spawn_graph(
+	mut call_graph		: ResMut<CallGraph>,
+		surfaces_bevy	: Res<SurfacesMapBevy>,
+	mut q_camera		: Query<(&mut ReaderCamera, &Transform)>,
+	mut q_visibility	: Query<&mut Visibility>,
+		font_assets		: Res<Assets<ABGlyphFont>>,
+		font_handles	: Res<FontAssetHandles>,
+	mut color_materials_cache : ResMut<ColorMaterialsCache>,
+	mut mesh_assets		: ResMut<Assets<Mesh>>,
+	mut material_assets	: ResMut<Assets<StandardMaterial>>,
+	mut despawn			: ResMut<DespawnResource>,
+	mut commands		: Commands,
+		app_option		: Option<NonSend<HelixApp>>,
+) {
+	let Some(app) = app_option else { return };
+	let Ok((mut reader_camera, camera_transform)) = q_camera.get_single_mut() else { return };
+
+	let fonts	= ABGlyphFonts::new(&font_assets, &font_handles);
+	let metrics	= GraphMetrics::new(fonts.main);
+
+	// closing
+
+	if !call_graph.active {
+		if call_graph.anchor_entity.is_none() { return }
+
+		for entity in call_graph.take_entities() {
+			despawn.recursive.push(entity);
+		}
+
+		call_graph.clear();
+
+		for (_surface_name, surface_bevy) in surfaces_bevy.iter() {
+			let Ok(mut visibility) = q_visibility.get_mut(surface_bevy.entity) else { continue };
+			*visibility.as_mut() = Visibility::Visible;
+
+			if surface_bevy.name == EditorView::ID {
+				reader_camera.target_entity = Some(surface_bevy.entity);
+				reader_camera.column = (surface_bevy.area.width / 2) as usize;
+			}
+		}
+
+		return;
+	}
+
+	// opening
+
+	if call_graph.anchor_entity.is_none() {
+		for (_surface_name, surface_bevy) in surfaces_bevy.iter() {
+			let Ok(mut visibility) = q_visibility.get_mut(surface_bevy.entity) else { continue };
+			*visibility.as_mut() = Visibility::Hidden;
+		}
+
+		// camera starts from where it's looking now and travels to the root once graph is there
+		let anchor_entity = commands.spawn((
+			CallGraphAnchor,
+			TransformBundle::from_transform(Transform::from_translation(Vec3::new(
+				camera_transform.translation.x - (VIEW_COLUMNS / 2) as f32 * metrics.column_width,
+				camera_transform.translation.y + (reader_camera.visible_rows / 2.0).floor() * metrics.row_height,
+				0.0
+			))),
+			TextDescriptor {
+				rows			: reader_camera.visible_rows as usize,
+				columns			: VIEW_COLUMNS,
+				glyph_width		: metrics.column_width,
+				glyph_height	: metrics.row_height,
+			},
+		)).id();
+
+		reader_camera.target_entity = Some(anchor_entity);
+		reader_camera.column = VIEW_COLUMNS / 2;
+		reader_camera.set_row_offset_in(0);
+
+		call_graph.anchor_entity = Some(anchor_entity);
+	}
+
+	if call_graph.spawned_version == Some(call_graph.version) { return }
+
+	profile_function!();
+
+	if let Some(root_entity) = call_graph.take_graph_entities() {
+		despawn.recursive.push(root_entity);
+	}
+
+	let theme			= &app.editor.theme;
+	let text_color		= color_from_helix(theme.get("ui.text").fg.unwrap_or(HelixColor::White));
+	let comment_color	= color_from_helix(theme.get("comment").fg.unwrap_or(HelixColor::Gray));
+	let error_color		= color_from_helix(theme.get("error").fg.unwrap_or(HelixColor::Red));
+	let root_color		= color_from_helix(theme.get("ui.statusline").bg.unwrap_or(HelixColor::Cyan));
+	let marker_color	= color_from_helix(theme.get("ui.selection").bg.unwrap_or(HelixColor::Gray));
+
+	let direction_color = |direction: CallDirection| color_from_helix(theme.get(direction.theme_scope()).fg.unwrap_or(HelixColor::White));
+
+	let root_entity = commands.spawn((
+		TransformBundle::default(),
+		VisibilityBundle::default(),
+	)).id();
+
+	let mut children = Vec::new();
+
+	let layout		= call_graph.layout();
+	let positions	: Vec<Vec3> = layout.iter().map(|slot| metrics.node_position(*slot)).collect();
+
+	let quad_mesh		= mesh_assets.add(Mesh::from(shape::Quad::new(Vec2::ONE)));
+	let edge_thickness	= EDGE_THICKNESS * metrics.column_width;
+
+	let workspace = std::env::current_dir().unwrap_or_default();
+
+	for (index, node) in call_graph.nodes.iter().enumerate() {
+		let position = positions[index];
+
+		let color = node.direction.map_or(root_color, direction_color);
+
+		let marker = String::from(node.marker());
+		let marker_entity = spawn_string(marker.clone(), position, color, &mut commands);
+		children.push(marker_entity);
+
+		// arrow in front of expandable node is clickable on its own
+		if node.is_expandable() {
+			make_clickable(marker_entity, &marker, fonts.main, &mut commands);
+			commands.entity(marker_entity).insert(CallGraphExpander { index });
+		}
+
+		let label = short_message(node.label().as_str(), LABEL_COLUMNS - 2);
+		let label_entity = spawn_string(label.clone(), position + Vec3::X * 2.0 * metrics.column_width, color, &mut commands);
+		make_clickable(label_entity, &label, fonts.main, &mut commands);
+
+		commands.entity(label_entity).insert(CallGraphNode { index });
+		children.push(label_entity);
+
+		let location = short_message(node.location(&workspace).as_str(), LABEL_COLUMNS - 2);
+		children.push(spawn_string(location, position + Vec3::new(2.0 * metrics.column_width, -metrics.row_height, 0.0), comment_color, &mut commands));
+
+		let (Some(parent), Some(direction)) = (node.parent, node.direction) else { continue };
+
+		let (from, to) = metrics.edge_points(positions[parent], position, direction);
+		let material = get_color_material_handle(color, &mut color_materials_cache, &mut material_assets);
+
+		if let Some(entity) = spawn_segment(from, to, edge_thickness, &quad_mesh, &material, &mut commands) {
+			children.push(entity);
+		}
+	}
+
+	// column headers above first level of every direction that has nodes
+	for direction in [CallDirection::Incoming, CallDirection::Outgoing, CallDirection::References] {
+		let Some(index) = call_graph.nodes.iter().position(|node| node.direction == Some(direction) && node.level == 1) else { continue };
+
+		let (column, slot) = layout[index];
+		let slot = slot - 2.0 / NODE_ROWS; // two rows above
+
+		children.push(spawn_string(String::from(direction.header()), metrics.node_position((column, slot)), comment_color, &mut commands));
+	}
+
+	// hint and state of requests above the root
+	let status = match (call_graph.error.as_ref(), call_graph.is_loading()) {
+		(Some(error), _)	=> (short_message(error.as_str(), LABEL_COLUMNS * 2), error_color),
+		(None, true)		=> (String::from("waiting for language server..."), comment_color),
+		(None, false)		=> (String::from("enter: open, space: expand, arrows: select, esc: back to editor"), text_color),
+	};
+
+	children.push(spawn_string(status.0, metrics.node_position((0.0, -1.0)), status.1, &mut commands));
+
+	// marker is moved under selected label in update_selection
+	let marker_material = get_color_material_handle(marker_color, &mut color_materials_cache, &mut material_assets);
+	let marker_entity = commands.spawn(PbrBundle {
+		mesh		: quad_mesh,
+		material	: marker_material,
+		transform	: Transform::from_scale(Vec3::new(metrics.label_width(), metrics.row_height, 1.0)),
+		visibility	: if call_graph.nodes.is_empty() { Visibility::Hidden } else { Visibility::Inherited },
+		..default()
+	}).id();
+
+	children.push(marker_entity);
+
+	commands.entity(root_entity).push_children(children.as_slice());
+
+	call_graph.root_entity		= Some(root_entity);
+	call_graph.marker_entity	= Some(marker_entity);
+	call_graph.spawned_version	= Some(call_graph.version);
+}
+
+// camera travels to selected node which is highlighted with marker
+pub fn update_selection(
+	mut call_graph			: ResMut<CallGraph>,
+		q_camera			: Query<&ReaderCamera>,
+	mut q_transform			: Query<&mut Transform, Without<ReaderCamera>>,
+		q_animator			: Query<&Animator<Transform>, With<CallGraphAnchor>>,
+	mut framerate_manager	: ResMut<FramerateManager>,
+		font_assets			: Res<Assets<ABGlyphFont>>,
+		font_handles		: Res<FontAssetHandles>,
+	mut commands			: Commands,
+) {
+	if !call_graph.active || call_graph.nodes.is_empty() { return }
+
+	let Some(anchor_entity) = call_graph.anchor_entity else { return };
+	let Ok(reader_camera) = q_camera.get_single() else { return };
+
+	if let Ok(animator) = q_animator.get(anchor_entity) {
+		let progress = animator.tweenable().progress();
+		if progress < 1.0 {
+			framerate_manager.request_active_framerate(format!("call graph camera travel {:.2}%", progress * 100.));
+		}
+	}
+
+	let selected = call_graph.selected.min(call_graph.nodes.len() - 1);
+
+	if call_graph.traveled_to == Some(selected) { return }
+
+	// anchor spawned this frame is not there until commands are applied
+	let Ok(anchor_transform) = q_transform.get(anchor_entity).map(|transform| *transform) else { return };
+
+	profile_function!();
+
+	let fonts		= ABGlyphFonts::new(&font_assets, &font_handles);
+	let metrics		= GraphMetrics::new(fonts.main);
+	let position	= metrics.node_position(call_graph.layout()[selected]);
+
+	let end = Transform::from_translation(metrics.anchor_position(position, reader_camera.visible_rows));
+	travel(anchor_entity, anchor_transform, end, &mut commands);
+
+	if let Some(mut marker_transform) = call_graph.marker_entity.and_then(|entity| q_transform.get_mut(entity).ok()) {
+		let label_size = marker_transform.scale.truncate();
+		marker_transform.translation = position + Vec3::new(label_size.x / 2.0, label_size.y / 2.0, z_order::surface::highlight_selection() - z_order::surface::text());
+	}
+
+	call_graph.traveled_to = Some(selected);
+}
+
+// graph is tied to the document it was opened from so it is closed when leaving code editor
+pub fn on_context_switch_out(
+	mut call_graph	: ResMut<CallGraph>,
+	mut despawn		: ResMut<DespawnResource>,
+) {
+	for entity in call_graph.take_entities() {
+		despawn.recursive.push(entity);
+	}
+
+	call_graph.clear();
+}
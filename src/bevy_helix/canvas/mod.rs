@@ -0,0 +1,194 @@
+use bevy :: prelude :: *;
+use bevy :: utils :: HashMap;
+
+use helix_core :: syntax :: HighlightEvent;
+use helix_tui :: buffer :: Buffer as SurfaceHelix;
+use helix_view :: { Document, DocumentId, Theme };
+
+use std :: path :: { Path, PathBuf };
+
+use super :: VersionType;
+
+pub mod systems;
+
+// every open document is shown as a card on a plane, cards are either auto-laid-out by directory or placed manually by dragging.
+// Focused card is edited in place: editor surface takes its spot until another card is focused or canvas goes back to overview
+#[derive(Resource, Default)]
+pub struct DocumentCanvas {
+	pub active			: bool,
+	pub anchor_entity	: Option<Entity>, // camera follows this entity while canvas is active
+	pub focus_requested	: Option<PathBuf>, // document is opened once camera arrives at its card
+	pub editing			: Option<Entity>, // card which document is being edited by editor surface
+	pub cards			: Vec<Entity>,
+	pub manual_positions: HashMap<PathBuf, Vec3>,
+	pub layout_loaded	: bool,
+	pub hidden_surfaces	: Vec<(Entity, Visibility)>, // surfaces hidden by canvas along with visibility they had before
+	pub camera_restore	: Option<(Option<Entity>, usize)>, // camera target and column to get back to once canvas is closed
+}
+
+#[derive(Component)]
+pub struct CanvasCard {
+	pub path			: PathBuf,
+	pub doc_id			: DocumentId,
+	pub doc_version		: Option<VersionType>, // version shown on card surface
+	pub surface_name	: String,
+	pub drag_offset		: Option<Vec3>,
+	pub drag_distance	: f32,
+}
+
+#[derive(Component)]
+pub struct CanvasAnchor;
+
+pub const CARD_COLUMNS			: usize	= 60;
+pub const CARD_PREVIEW_ROWS		: usize	= 24;
+pub const CARD_GAP_COLUMNS		: f32	= 8.0;
+pub const CARD_CLICK_THRESHOLD	: f32	= 0.05; // less than that is considered a click, not a drag
+
+// Helix gets input while canvas is closed or while one of the cards is edited
+pub fn canvas_inactive(canvas: Res<DocumentCanvas>) -> bool {
+	!canvas.overview()
+}
+
+impl DocumentCanvas {
+	// cards are shown and camera is driven by canvas
+	pub fn overview(&self) -> bool {
+		self.active && self.editing.is_none()
+	}
+
+	// title row followed by the beginning of document highlighted the same way as in editor
+	pub fn fill_card_surface(
+		surface	: &mut SurfaceHelix,
+		title	: &str,
+		doc		: &Document,
+		theme	: &Theme,
+	) {
+		let area = surface.area;
+
+		surface.reset();
+
+		let background	= theme.get("ui.background");
+		let text_style	= background.patch(theme.get("ui.text"));
+		let title_style	= background.patch(theme.get("ui.statusline"));
+
+		surface.set_style(area, background);
+		surface.set_stringn(area.x, area.y, format!(" {}", title), area.width as usize, title_style);
+
+		let text		= doc.text().slice(..);
+		let rows		= (area.height as usize).saturating_sub(1).min(text.len_lines());
+		let end			= text.line_to_byte(rows);
+		let tab			= " ".repeat(doc.tab_width());
+
+		let highlights : Vec<HighlightEvent> = match doc.syntax() {
+			Some(syntax) => syntax.highlight_iter(text, Some(0 .. end), None).filter_map(|event| event.ok()).collect(),
+			None => vec![HighlightEvent::Source { start: 0, end }],
+		};
+
+		let mut spans	= Vec::new();
+		let mut row		= 0usize;
+		let mut x		= area.x;
+
+		for event in highlights {
+			match event {
+				HighlightEvent::HighlightStart(span) => spans.push(span),
+				HighlightEvent::HighlightEnd => { spans.pop(); },
+				HighlightEvent::Source { start, end } => {
+					let style = spans.iter().fold(text_style, |acc, span| acc.patch(theme.highlight(span.0)));
+					let source = text.byte_slice(start .. end).to_string();
+
+					for (index, piece) in source.split('\n').enumerate() {
+						if index > 0 {
+							row += 1;
+							x = area.x;
+						}
+
+						if row >= rows {
+							break;
+						}
+
+						let piece = piece.trim_end_matches('\r').replace('\t', tab.as_str());
+						let max_width = area.right().saturating_sub(x) as usize;
+
+						if !piece.is_empty() && max_width > 0 {
+							x = surface.set_stringn(x, area.y + 1 + row as u16, piece, max_width, style).0;
+						}
+					}
+				}
+			}
+		}
+	}
+
+	// columns are directories, rows are files in them, both sorted alphabetically
+	pub fn auto_layout(
+		&self,
+		paths		: &Vec<PathBuf>,
+		card_size	: Vec2,
+		gap			: f32,
+	) -> Vec<Vec3> {
+		let mut directories : Vec<&Path> = paths.iter().filter_map(|path| path.parent()).collect();
+		directories.sort();
+		directories.dedup();
+
+		let mut rows_per_directory = vec![0usize; directories.len()];
+
+		paths.iter().map(|path| {
+			if let Some(position) = self.manual_positions.get(path) {
+				return *position;
+			}
+
+			let directory_index = path.parent().and_then(|dir| directories.iter().position(|d| *d == dir)).unwrap_or(0);
+			let row_index = rows_per_directory.get(directory_index).copied().unwrap_or(0);
+
+			if let Some(rows) = rows_per_directory.get_mut(directory_index) {
+				*rows += 1;
+			}
+
+			Vec3::new(
+				directory_index as f32 * (card_size.x + gap),
+				-(row_index as f32) * (card_size.y + gap),
+				0.0
+			)
+		}).collect()
+	}
+
+	// layout is stored per workspace in helix cache directory
+	fn layout_file_path() -> Option<PathBuf> {
+		let workspace = std::env::current_dir().ok()?;
+		let workspace_name : String = workspace.to_string_lossy().chars().map(|c| if c.is_alphanumeric() { c } else { '_' }).collect();
+
+		Some(helix_loader::cache_dir().join("kodiki_canvas").join(format!("{}.json", workspace_name)))
+	}
+
+	pub fn load_layout(&mut self) {
+		self.layout_loaded = true;
+
+		let Some(file_path) = Self::layout_file_path() else { return };
+		let Ok(contents) = std::fs::read_to_string(&file_path) else { return };
+
+		let Ok(positions) = serde_json::from_str::<std::collections::HashMap<String, [f32; 3]>>(&contents) else {
+			warn!("failed to parse canvas layout from {:?}", file_path);
+			return
+		};
+
+		self.manual_positions = positions.into_iter().map(|(path, pos)| (PathBuf::from(path), Vec3::from(pos))).collect();
+	}
+
+	pub fn save_layout(&self) {
+		let Some(file_path) = Self::layout_file_path() else { return };
+
+		let positions : std::collections::HashMap<String, [f32; 3]> = self.manual_positions.iter()
+			.map(|(path, pos)| (path.to_string_lossy().to_string(), pos.to_array()))
+			.collect();
+
+		let Ok(contents) = serde_json::to_string_pretty(&positions) else { return };
+
+		if let Some(dir) = file_path.parent() {
+			if std::fs::create_dir_all(dir).is_err() {
+				return;
+			}
+		}
+
+		if let Err(err) = std::fs::write(&file_path, contents) {
+			warn!("failed to save canvas layout to {:?}: {:?}", file_path, err);
+		}
+	}
+}
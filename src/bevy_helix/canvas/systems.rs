@@ -0,0 +1,566 @@
+use bevy :: prelude :: *;
+use bevy_tweening :: *;
+use bevy_reader_camera :: { ReaderCamera, TextDescriptor };
+
+#[cfg(feature = "tracing")]
+use bevy_puffin :: *;
+
+use helix_tui :: buffer :: { Buffer as SurfaceHelix, SurfaceFlags };
+use helix_view :: graphics :: { Color as HelixColor, Rect };
+
+use super :: *;
+
+use crate :: {
+	z_order,
+	kodiki :: DespawnResource,
+	kodiki_ui :: {
+		*,
+		color		:: *,
+		tween_lens	:: *,
+		raypick		:: *,
+		spawn		:: background_quad,
+	},
+	bevy_ab_glyph :: { ABGlyphFont, ABGlyphFonts, FontAssetHandles },
+	bevy_framerate_manager :: FramerateManager,
+	bevy_helix :: {
+		HelixApp, TokioRuntime, KeyboardInputGuard,
+		surface :: { SurfacesMapBevy, SurfacesMapHelix, WordDescription, canvas_card_surface_name, is_canvas_card_surface_name },
+		systems_util :: hovered_surface_name,
+		utils :: color_from_helix,
+	},
+};
+
+use helix_term :: ui :: EditorView;
+
+use std :: time :: Duration;
+
+// ctrl+shift+home opens canvas, goes back to overview from edited card and closes canvas from overview. Escape closes it from overview too
+pub fn input_keyboard(
+	mut key				: ResMut<Input<KeyCode>>,
+	mut input_guard		: ResMut<KeyboardInputGuard>,
+	mut canvas			: ResMut<DocumentCanvas>,
+) {
+	let ctrl_pressed	= key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl);
+	let shift_pressed	= key.pressed(KeyCode::LShift) || key.pressed(KeyCode::RShift);
+
+	if ctrl_pressed && shift_pressed && key.just_pressed(KeyCode::Home) {
+		if !canvas.active {
+			canvas.active = true;
+		} else if canvas.editing.is_some() {
+			canvas.editing = None;
+		} else {
+			canvas.active = false;
+		}
+	} else if canvas.overview() && key.just_pressed(KeyCode::Escape) {
+		canvas.active = false;
+	} else {
+		return;
+	}
+
+	// keys handled by canvas must not make it to Helix once it gets input back
+	key.clear_just_pressed(KeyCode::Escape);
+	key.clear_just_pressed(KeyCode::Home);
+	input_guard.consume();
+}
+
+fn card_size(fonts: &ABGlyphFonts) -> Vec2 {
+	let column_width	= fonts.main.horizontal_advance_mono();
+	let row_height		= fonts.main.vertical_advance();
+
+	Vec2::new(
+		CARD_COLUMNS as f32 * column_width,
+		(CARD_PREVIEW_ROWS + 2) as f32 * row_height // + title and a margin
+	)
+}
+
+// card surface is inset by a column on the sides and by half a row on top and bottom
+fn card_surface_position(card_center: Vec3, card_size: Vec2, fonts: &ABGlyphFonts) -> Vec3 {
+	let column_width	= fonts.main.horizontal_advance_mono();
+	let row_height		= fonts.main.vertical_advance();
+
+	card_top_left(card_center, card_size) + Vec3::new(column_width, -row_height / 2.0, card_center.z + z_order::thickness())
+}
+
+// everything but cards is hidden in overview, surfaces get their own visibility back afterwards
+fn hide_surfaces(
+	canvas			: &mut DocumentCanvas,
+	surfaces_bevy	: &SurfacesMapBevy,
+	q_visibility	: &mut Query<&mut Visibility>,
+) {
+	for (surface_name, surface_bevy) in surfaces_bevy.iter() {
+		if is_canvas_card_surface_name(surface_name) {
+			continue;
+		}
+
+		let Ok(mut visibility) = q_visibility.get_mut(surface_bevy.entity) else { continue };
+
+		canvas.hidden_surfaces.push((surface_bevy.entity, *visibility));
+		*visibility = Visibility::Hidden;
+	}
+}
+
+fn restore_surfaces(
+	canvas			: &mut DocumentCanvas,
+	q_visibility	: &mut Query<&mut Visibility>,
+) {
+	for (entity, visibility_prev) in canvas.hidden_surfaces.drain(..) {
+		let Ok(mut visibility) = q_visibility.get_mut(entity) else { continue };
+		*visibility = visibility_prev;
+	}
+}
+
+pub fn spawn_cards(
+	mut canvas			: ResMut<DocumentCanvas>,
+	mut surfaces_helix	: ResMut<SurfacesMapHelix>,
+		surfaces_bevy	: Res<SurfacesMapBevy>,
+	mut q_camera		: Query<(&mut ReaderCamera, &Transform)>,
+	mut q_visibility	: Query<&mut Visibility>,
+		font_assets		: Res<Assets<ABGlyphFont>>,
+		font_handles	: Res<FontAssetHandles>,
+	mut color_materials_cache : ResMut<ColorMaterialsCache>,
+	mut mesh_assets		: ResMut<Assets<Mesh>>,
+	mut material_assets	: ResMut<Assets<StandardMaterial>>,
+	mut commands		: Commands,
+		app_option		: Option<NonSend<HelixApp>>,
+) {
+	if !canvas.active || !canvas.cards.is_empty() {
+		return;
+	}
+
+	let Some(app) = app_option else { return };
+	let Ok((mut reader_camera, camera_transform)) = q_camera.get_single_mut() else { return };
+
+	if !canvas.layout_loaded {
+		canvas.load_layout();
+	}
+
+	let fonts = ABGlyphFonts::new(&font_assets, &font_handles);
+
+	let column_width	= fonts.main.horizontal_advance_mono();
+	let row_height		= fonts.main.vertical_advance();
+
+	let card_size		= card_size(&fonts);
+	let gap				= CARD_GAP_COLUMNS * column_width;
+
+	let documents : Vec<(PathBuf, DocumentId)> = app.editor.documents().filter_map(|doc| {
+		Some((doc.path()?.clone(), doc.id()))
+	}).collect();
+
+	if documents.is_empty() {
+		canvas.active = false;
+		return;
+	}
+
+	let paths : Vec<PathBuf> = documents.iter().map(|(path, _)| path.clone()).collect();
+	let positions = canvas.auto_layout(&paths, card_size, gap);
+
+	let theme				= &app.editor.theme;
+	let background_color	= color_from_helix(theme.get("ui.background").bg.unwrap_or(HelixColor::Black));
+	let card_color			= get_color_wmodified_lightness(background_color, 0.05);
+
+	let card_material_handle = get_color_material_handle(
+		card_color,
+		&mut color_materials_cache,
+		&mut material_assets
+	);
+
+	// each card shows its document on a surface of its own, it's filled and placed in update_card_surfaces
+	let surface_area = Rect::new(0, 0, (CARD_COLUMNS - 2) as u16, (CARD_PREVIEW_ROWS + 1) as u16);
+
+	for (index, ((path, doc_id), position)) in documents.iter().zip(positions.iter()).enumerate() {
+		let card_entity = background_quad(
+			*position,
+			card_size,
+			true, /* with_collision */
+			Some(&card_material_handle),
+			&mut mesh_assets,
+			&mut commands
+		);
+
+		let surface_name = canvas_card_surface_name(index);
+
+		surfaces_helix.insert(surface_name.clone(), SurfaceHelix::empty_with_spatial(surface_area, SurfaceFlags::default()));
+
+		commands.entity(card_entity).insert((
+			CanvasCard {
+				path			: path.clone(),
+				doc_id			: *doc_id,
+				doc_version		: None,
+				surface_name,
+				drag_offset		: None,
+				drag_distance	: 0.0,
+			},
+			RaypickHover::default(),
+		));
+
+		canvas.cards.push(card_entity);
+	}
+
+	// editor is hidden while canvas is shown
+	hide_surfaces(&mut canvas, &surfaces_bevy, &mut q_visibility);
+
+	canvas.camera_restore = Some((reader_camera.target_entity, reader_camera.column));
+
+	// camera follows anchor that travels between cards. Start from where camera is looking now
+	let anchor_entity = commands.spawn((
+		CanvasAnchor,
+		TransformBundle::from_transform(Transform::from_translation(Vec3::new(camera_transform.translation.x - card_size.x / 2.0, camera_transform.translation.y + card_size.y / 2.0, 0.0))),
+		TextDescriptor {
+			rows			: CARD_PREVIEW_ROWS + 2,
+			columns			: CARD_COLUMNS,
+			glyph_width		: column_width,
+			glyph_height	: row_height,
+		},
+	)).id();
+
+	canvas.anchor_entity = Some(anchor_entity);
+
+	reader_camera.target_entity = Some(anchor_entity);
+	reader_camera.column = CARD_COLUMNS / 2;
+	reader_camera.set_row_offset_in(0);
+
+	// fly to the card of current document
+	let current_path = app.current_document().path().cloned();
+	if let Some(index) = paths.iter().position(|path| Some(path) == current_path.as_ref()) {
+		let start = Transform::from_translation(Vec3::new(camera_transform.translation.x - card_size.x / 2.0, camera_transform.translation.y + card_size.y / 2.0, 0.0));
+		let end = Transform::from_translation(card_top_left(positions[index], card_size));
+		travel(anchor_entity, start, end, &mut commands);
+	}
+}
+
+fn card_top_left(card_center: Vec3, card_size: Vec2) -> Vec3 {
+	Vec3::new(card_center.x - card_size.x / 2.0, card_center.y + card_size.y / 2.0, 0.0)
+}
+
+fn travel(
+	anchor_entity	: Entity,
+	start			: Transform,
+	end				: Transform,
+	commands		: &mut Commands,
+) {
+	let distance = start.translation.distance(end.translation);
+	let travel_duration = (distance * 100.0).clamp(300.0, 900.0) as u64;
+
+	let tween = Tween::new(
+		EaseFunction::QuadraticInOut,
+		Duration::from_millis(travel_duration),
+		TransformLens {
+			start,
+			end,
+		}
+	);
+
+	commands.entity(anchor_entity).insert(Animator::new(tween));
+}
+
+pub fn despawn_cards(
+	mut canvas			: ResMut<DocumentCanvas>,
+	mut surfaces_helix	: ResMut<SurfacesMapHelix>,
+		surfaces_bevy	: Res<SurfacesMapBevy>,
+		q_card			: Query<&CanvasCard>,
+	mut q_camera		: Query<&mut ReaderCamera>,
+	mut q_visibility	: Query<&mut Visibility>,
+	mut q_transform		: Query<&mut Transform, Without<ReaderCamera>>,
+	mut despawn			: ResMut<DespawnResource>,
+) {
+	if canvas.active || canvas.cards.is_empty() {
+		return;
+	}
+
+	// card surfaces on bevy side are despawned along with the rest of unused surfaces
+	for card_entity in canvas.cards.drain(..) {
+		if let Ok(card) = q_card.get(card_entity) {
+			surfaces_helix.remove(&card.surface_name);
+		}
+
+		despawn.recursive.push(card_entity);
+	}
+
+	if let Some(anchor_entity) = canvas.anchor_entity.take() {
+		despawn.recursive.push(anchor_entity);
+	}
+
+	canvas.focus_requested = None;
+
+	// editor surface goes back from the card it was editing
+	if canvas.editing.take().is_some() {
+		if let Some(surface_editor) = surfaces_bevy.get(&String::from(EditorView::ID)) {
+			if let Ok(mut transform) = q_transform.get_mut(surface_editor.entity) {
+				transform.translation = Vec3::ZERO;
+			}
+		}
+	}
+
+	restore_surfaces(&mut canvas, &mut q_visibility);
+
+	canvas.save_layout();
+
+	let Ok(mut reader_camera) = q_camera.get_single_mut() else { return };
+
+	if let Some((target_entity, column)) = canvas.camera_restore.take() {
+		reader_camera.target_entity = target_entity;
+		reader_camera.column = column;
+	}
+}
+
+// card under mouse either directly or through its surface
+fn hovered_card(
+	hovered_entity	: Entity,
+	q_card			: &Query<(Entity, &mut CanvasCard, &mut Transform)>,
+	q_word			: &Query<&WordDescription>,
+	surfaces_bevy	: &SurfacesMapBevy,
+) -> Option<Entity> {
+	if q_card.contains(hovered_entity) {
+		return Some(hovered_entity);
+	}
+
+	let surface_name = hovered_surface_name(hovered_entity, q_word, surfaces_bevy)?;
+
+	q_card.iter().find(|(_, card, _)| card.surface_name == *surface_name).map(|(entity, _, _)| entity)
+}
+
+pub fn input_mouse(
+	mut q_card			: Query<(Entity, &mut CanvasCard, &mut Transform)>,
+		q_anchor		: Query<&Transform, (With<CanvasAnchor>, Without<CanvasCard>)>,
+		q_word			: Query<&WordDescription>,
+		surfaces_bevy	: Res<SurfacesMapBevy>,
+		mouse_button	: Res<Input<MouseButton>>,
+		raypick			: Res<Raypick>,
+	mut canvas			: ResMut<DocumentCanvas>,
+		font_assets		: Res<Assets<ABGlyphFont>>,
+		font_handles	: Res<FontAssetHandles>,
+	mut commands		: Commands,
+) {
+	if !canvas.active || canvas.focus_requested.is_some() {
+		return;
+	}
+
+	profile_function!();
+
+	// intersection of mouse ray with canvas plane
+	if raypick.ray_dir.z.abs() < f32::EPSILON {
+		return;
+	}
+	let t = -raypick.ray_pos.z / raypick.ray_dir.z;
+	let mouse_on_canvas = raypick.ray_pos + raypick.ray_dir * t;
+
+	if mouse_button.just_pressed(MouseButton::Left) {
+		let Some(hovered_entity) = raypick.last_hover else { return };
+		let Some(card_entity) = hovered_card(hovered_entity, &q_card, &q_word, &surfaces_bevy) else { return };
+
+		// edited card is covered by editor surface and clicks on it go to Helix
+		if canvas.editing == Some(card_entity) {
+			return;
+		}
+
+		let Ok((_, mut card, transform)) = q_card.get_mut(card_entity) else { return };
+
+		card.drag_offset	= Some(transform.translation - mouse_on_canvas);
+		card.drag_distance	= 0.0;
+	} else if mouse_button.pressed(MouseButton::Left) {
+		for (_, mut card, mut transform) in q_card.iter_mut() {
+			let Some(drag_offset) = card.drag_offset else { continue };
+
+			let new_position = Vec3::new(mouse_on_canvas.x + drag_offset.x, mouse_on_canvas.y + drag_offset.y, transform.translation.z);
+			card.drag_distance += transform.translation.distance(new_position);
+			transform.translation = new_position;
+		}
+	} else if mouse_button.just_released(MouseButton::Left) {
+		let fonts = ABGlyphFonts::new(&font_assets, &font_handles);
+		let card_size = card_size(&fonts);
+
+		for (_, mut card, transform) in q_card.iter_mut() {
+			if card.drag_offset.take().is_none() {
+				continue;
+			}
+
+			if card.drag_distance > CARD_CLICK_THRESHOLD {
+				// manually placed cards keep their position between sessions
+				canvas.manual_positions.insert(card.path.clone(), transform.translation);
+				canvas.save_layout();
+				continue;
+			}
+
+			// click focuses document: travel to its card first, document is opened in update_camera_travel.
+			// Card that was edited before goes back to overview, anchor picks camera up from there
+			canvas.editing = None;
+
+			let Some(anchor_entity) = canvas.anchor_entity else { continue };
+			let Ok(anchor_transform) = q_anchor.get(anchor_entity) else { continue };
+
+			travel(
+				anchor_entity,
+				*anchor_transform,
+				Transform::from_translation(card_top_left(transform.translation, card_size)),
+				&mut commands
+			);
+
+			canvas.focus_requested = Some(card.path.clone());
+		}
+	}
+}
+
+pub fn update_camera_travel(
+		q_animator		: Query<&Animator<Transform>, With<CanvasAnchor>>,
+		q_card			: Query<(Entity, &CanvasCard)>,
+	mut canvas			: ResMut<DocumentCanvas>,
+	mut framerate_manager : ResMut<FramerateManager>,
+		tokio_runtime	: Res<TokioRuntime>,
+		app_option		: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(anchor_entity) = canvas.anchor_entity else { return };
+
+	let travel_finished = match q_animator.get(anchor_entity) {
+		Ok(animator) => {
+			let progress = animator.tweenable().progress();
+			if progress < 1.0 {
+				framerate_manager.request_active_framerate(format!("canvas camera travel {:.2}%", progress * 100.));
+			}
+			progress >= 1.0
+		},
+		Err(_) => true,
+	};
+
+	if !travel_finished {
+		return;
+	}
+
+	let Some(path) = canvas.focus_requested.take() else { return };
+	let Some(mut app) = app_option else { return };
+
+	tokio_runtime.block_on(
+		app.jump_to_path(&path, None, None)
+	);
+
+	// document is edited right on its card
+	canvas.editing = q_card.iter().find(|(_, card)| card.path == path).map(|(entity, _)| entity);
+}
+
+// editor surface takes place of edited card, going back to overview hides it again and hands camera back to anchor
+pub fn update_editing(
+	mut canvas			: ResMut<DocumentCanvas>,
+	mut editing_prev	: Local<Option<Entity>>,
+		surfaces_bevy	: Res<SurfacesMapBevy>,
+		q_card			: Query<&CanvasCard>,
+	mut q_camera		: Query<&mut ReaderCamera>,
+	mut q_visibility	: Query<&mut Visibility>,
+	mut q_transform		: Query<&mut Transform, Without<ReaderCamera>>,
+		font_assets		: Res<Assets<ABGlyphFont>>,
+		font_handles	: Res<FontAssetHandles>,
+) {
+	if !canvas.active {
+		*editing_prev = None;
+		return;
+	}
+
+	if canvas.editing == *editing_prev {
+		return;
+	}
+
+	let Some(surface_editor) = surfaces_bevy.get(&String::from(EditorView::ID)) else { return };
+	let Ok(mut reader_camera) = q_camera.get_single_mut() else { return };
+
+	// card and its surface are hidden while editor surface covers them
+	let set_card_visibility = |card_entity: Entity, visibility_new: Visibility, q_visibility: &mut Query<&mut Visibility>| {
+		let Ok(card) = q_card.get(card_entity) else { return };
+
+		let surface_entity = surfaces_bevy.get(&card.surface_name).map(|surface| surface.entity);
+
+		for entity in std::iter::once(card_entity).chain(surface_entity) {
+			if let Ok(mut visibility) = q_visibility.get_mut(entity) {
+				*visibility = visibility_new;
+			}
+		}
+	};
+
+	if let Some(card_entity) = *editing_prev {
+		set_card_visibility(card_entity, Visibility::Inherited, &mut q_visibility);
+	}
+
+	if let Some(card_entity) = canvas.editing {
+		set_card_visibility(card_entity, Visibility::Hidden, &mut q_visibility);
+	}
+
+	match (*editing_prev, canvas.editing) {
+		(None, Some(_)) => {
+			restore_surfaces(&mut canvas, &mut q_visibility);
+
+			reader_camera.target_entity = Some(surface_editor.entity);
+			reader_camera.column = (surface_editor.area.width / 2) as usize;
+		},
+		(Some(card_entity), None) => {
+			hide_surfaces(&mut canvas, &surfaces_bevy, &mut q_visibility);
+
+			if let Ok(mut transform) = q_transform.get_mut(surface_editor.entity) {
+				transform.translation = Vec3::ZERO;
+			}
+
+			// anchor picks camera up right where editor was
+			let fonts = ABGlyphFonts::new(&font_assets, &font_handles);
+			let card_translation = q_transform.get(card_entity).map_or(Vec3::ZERO, |transform| transform.translation);
+
+			if let Some(anchor_entity) = canvas.anchor_entity {
+				if let Ok(mut anchor_transform) = q_transform.get_mut(anchor_entity) {
+					anchor_transform.translation = card_top_left(card_translation, card_size(&fonts));
+				}
+			}
+
+			reader_camera.target_entity = canvas.anchor_entity;
+			reader_camera.column = CARD_COLUMNS / 2;
+			reader_camera.set_row_offset_in(0);
+		},
+		_ => (),
+	}
+
+	*editing_prev = canvas.editing;
+}
+
+// card surfaces follow their cards and get refilled whenever their document changes, editor surface sits on top of edited card
+pub fn update_card_surfaces(
+	mut q_card			: Query<(Entity, &mut CanvasCard)>,
+	mut q_transform		: Query<&mut Transform>,
+	mut surfaces_helix	: ResMut<SurfacesMapHelix>,
+		surfaces_bevy	: Res<SurfacesMapBevy>,
+		canvas			: Res<DocumentCanvas>,
+		font_assets		: Res<Assets<ABGlyphFont>>,
+		font_handles	: Res<FontAssetHandles>,
+		app_option		: Option<NonSend<HelixApp>>,
+) {
+	if !canvas.active {
+		return;
+	}
+
+	let Some(app) = app_option else { return };
+
+	profile_function!();
+
+	let fonts		= ABGlyphFonts::new(&font_assets, &font_handles);
+	let card_size	= card_size(&fonts);
+	let workspace	= std::env::current_dir().unwrap_or_default();
+
+	for (card_entity, mut card) in q_card.iter_mut() {
+		if let Some(doc) = app.editor.document(card.doc_id) {
+			if card.doc_version != Some(doc.version()) {
+				if let Some(surface_helix) = surfaces_helix.get_mut(&card.surface_name) {
+					let title = card.path.strip_prefix(&workspace).unwrap_or(&card.path).to_string_lossy().to_string();
+
+					DocumentCanvas::fill_card_surface(surface_helix, title.as_str(), doc, &app.editor.theme);
+
+					card.doc_version = Some(doc.version());
+				}
+			}
+		}
+
+		let Ok(card_translation) = q_transform.get(card_entity).map(|transform| transform.translation) else { continue };
+		let surface_position = card_surface_position(card_translation, card_size, &fonts);
+
+		let surface_name = if canvas.editing == Some(card_entity) { String::from(EditorView::ID) } else { card.surface_name.clone() };
+		let Some(surface_bevy) = surfaces_bevy.get(&surface_name) else { continue };
+
+		if let Ok(mut surface_transform) = q_transform.get_mut(surface_bevy.entity) {
+			if surface_transform.translation != surface_position {
+				surface_transform.translation = surface_position;
+			}
+		}
+	}
+}
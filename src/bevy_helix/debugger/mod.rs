@@ -0,0 +1,537 @@
+use bevy :: prelude :: *;
+use bevy :: utils :: { HashMap, HashSet };
+
+use futures_lite :: future;
+
+use helix_dap :: { Client as DebuggerClient, Request, Scope, StackFrame, Thread, ThreadId, Variable, requests };
+use helix_term :: ui :: EditorView;
+use helix_tui :: buffer :: { Buffer as SurfaceHelix, SurfaceFlags, SurfacePlacement };
+use helix_view :: { Editor, graphics :: Rect, handlers :: dap :: jump_to_stack_frame };
+
+use tokio :: task :: JoinHandle;
+
+use std :: {
+	cmp :: Ordering,
+	path :: PathBuf,
+	time :: Duration,
+};
+
+use super :: {
+	TokioRuntime,
+	helix_app :: HelixApp,
+	surface :: { SurfacesMapHelix, DEBUG_PANEL_SURFACE_NAME },
+};
+
+pub mod systems;
+
+pub const PANEL_COLUMNS			: u16 = 48;
+pub const MAX_VARIABLES_DEPTH	: usize = 8;
+// requests that adapter never answered are dropped so that panel doesn't wait for them forever
+pub const REQUEST_TIMEOUT_MS	: u64 = 500;
+pub const WHEEL_ROWS			: i32 = 3;
+
+// f9 toggles breakpoint at any time, f5/f6/f10/f11 drive debug session while there is one. Handled in systems::input_keyboard
+// and kept away from Helix. Shift is part of f5 and f11 bindings so only ctrl and alt make them regular keys
+pub fn is_debugger_hotkey(keycode: KeyCode, key: &Input<KeyCode>, debug_session_active: bool) -> bool {
+	let ctrl_pressed	= key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl);
+	let alt_pressed		= key.pressed(KeyCode::LAlt) || key.pressed(KeyCode::RAlt);
+
+	if ctrl_pressed || alt_pressed {
+		return false;
+	}
+
+	match keycode {
+		KeyCode::F9 => true,
+		KeyCode::F5 | KeyCode::F6 | KeyCode::F10 | KeyCode::F11 => debug_session_active,
+		_ => false,
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PanelRowKind {
+	Header,
+	Frame(usize), // index in stack frames of current thread
+	Thread(ThreadId),
+	Variable(usize), // variables reference, 0 if variable has no children
+	Watch(usize), // index in watch expressions
+}
+
+#[derive(Clone, Debug)]
+pub struct PanelRow {
+	pub text		: String,
+	pub kind		: PanelRowKind,
+	pub depth		: usize,
+	pub active		: bool,
+}
+
+impl PanelRow {
+	fn header(text: &str) -> Self {
+		Self { text: String::from(text), kind: PanelRowKind::Header, depth: 0, active: false }
+	}
+}
+
+// state of debug session that panel contents were requested for. Adapter is queried again only when it changes
+#[derive(Clone, PartialEq, Debug)]
+pub struct DebugStateKey {
+	pub thread_id		: Option<ThreadId>,
+	pub active_frame	: Option<usize>,
+	pub frames			: Vec<(usize, usize)>, // frame id and line
+	pub thread_states	: Vec<(ThreadId, String)>,
+}
+
+// request to debug adapter running on tokio runtime, finished ones are picked up in refresh_panels
+pub enum PendingRequest {
+	Threads(JoinHandle<Option<requests::ThreadsResponse>>),
+	Scopes(JoinHandle<Option<requests::ScopesResponse>>),
+	Variables(usize, JoinHandle<Option<requests::VariablesResponse>>),
+	Watch(String, JoinHandle<Option<requests::EvaluateResponse>>),
+	StackTrace(ThreadId, JoinHandle<Option<requests::StackTraceResponse>>), // thread clicked in panel
+}
+
+impl PendingRequest {
+	fn abort(&self) {
+		match self {
+			PendingRequest::Threads(handle)		=> handle.abort(),
+			PendingRequest::Scopes(handle)		=> handle.abort(),
+			PendingRequest::Variables(_, handle) => handle.abort(),
+			PendingRequest::Watch(_, handle)	=> handle.abort(),
+			PendingRequest::StackTrace(_, handle) => handle.abort(),
+		}
+	}
+}
+
+// answers of debug adapter for current state key
+#[derive(Default)]
+pub struct DebugResponses {
+	pub threads		: Vec<Thread>,
+	pub scopes		: Vec<Scope>,
+	pub variables	: HashMap<usize, Vec<Variable>>, // by variables reference
+	pub watches		: HashMap<String, String>, // expression and its value
+	pub selected_thread	: Option<(ThreadId, Vec<StackFrame>)>, // focused in refresh_panels right after it arrives
+}
+
+#[derive(Resource, Default)]
+pub struct DebugPanels {
+	pub rows				: Vec<PanelRow>,
+	pub scroll				: usize,
+	pub visible_rows		: usize,
+	pub expanded_variables	: HashSet<usize>,
+	pub watches				: Vec<String>,
+	pub version				: usize, // bumped on every change made from Kodiki side: expanding variables, adding watches etc
+	pub rows_version		: Option<usize>, // panels version rows were built for
+	pub state_key			: Option<DebugStateKey>,
+
+	pub responses			: DebugResponses,
+	pub pending				: Vec<PendingRequest>,
+
+	pub execution_highlight	: Option<Entity>,
+	pub execution_cache		: Option<(PathBuf, usize, usize, String)>, // path, surface row, gutter length, theme
+}
+
+impl DebugPanels {
+	pub fn clear(&mut self) {
+		self.rows.clear();
+		self.scroll = 0;
+		self.state_key = None;
+		self.rows_version = None;
+		self.abort_requests();
+		self.responses = DebugResponses::default();
+	}
+
+	fn abort_requests(&mut self) {
+		for request in self.pending.drain(..) {
+			request.abort();
+		}
+	}
+
+	// thread selection is not a part of panel contents so it survives debug state changes
+	fn abort_panel_requests(&mut self) {
+		self.pending.retain(|request| {
+			let keep = matches!(request, PendingRequest::StackTrace(..));
+			if !keep {
+				request.abort();
+			}
+
+			keep
+		});
+	}
+
+	// stack trace of clicked thread is fetched in background, thread gets focused once it arrives
+	pub fn select_thread(&mut self, debugger: &DebuggerClient, tokio_runtime: &TokioRuntime, thread_id: ThreadId) {
+		self.pending.retain(|request| {
+			let previous_selection = matches!(request, PendingRequest::StackTrace(..));
+			if previous_selection {
+				request.abort();
+			}
+
+			!previous_selection
+		});
+
+		let arguments = requests::StackTraceArguments {
+			thread_id,
+			start_frame	: None,
+			levels		: None,
+			format		: None,
+		};
+
+		self.pending.push(PendingRequest::StackTrace(thread_id, spawn_request::<requests::StackTrace>(debugger, tokio_runtime, arguments)));
+	}
+
+	// same as select_thread_id of Helix does after fetching stack trace: focus thread, its top frame and jump there
+	pub fn focus_selected_thread(&mut self, editor: &mut Editor) {
+		let Some((thread_id, frames)) = self.responses.selected_thread.take() else { return };
+		let Some(debugger) = editor.debugger.as_mut() else { return };
+
+		let frame = frames.first().cloned();
+
+		debugger.thread_id		= Some(thread_id);
+		debugger.active_frame	= Some(0);
+		debugger.stack_frames.insert(thread_id, frames);
+
+		if let Some(frame) = frame {
+			jump_to_stack_frame(editor, &frame);
+		}
+	}
+
+	pub fn scroll_by(&mut self, delta: i32) {
+		let max_scroll = self.rows.len().saturating_sub(self.visible_rows);
+
+		self.scroll = (self.scroll as i64 + delta as i64).clamp(0, max_scroll as i64) as usize;
+	}
+
+	pub fn toggle_variable(&mut self, reference: usize) {
+		if !self.expanded_variables.remove(&reference) {
+			self.expanded_variables.insert(reference);
+		}
+
+		self.version += 1;
+	}
+
+	// adds expression to watch list or removes it if it's there already
+	pub fn toggle_watch(&mut self, expression: String) {
+		if let Some(index) = self.watches.iter().position(|watch| *watch == expression) {
+			self.watches.remove(index);
+		} else {
+			self.watches.push(expression);
+		}
+
+		self.version += 1;
+	}
+
+	pub fn remove_watch(&mut self, index: usize) {
+		if index < self.watches.len() {
+			self.watches.remove(index);
+			self.version += 1;
+		}
+	}
+
+	// row on screen to panel row
+	pub fn row_at(&self, row: usize) -> Option<&PanelRow> {
+		self.rows.get(row + self.scroll)
+	}
+
+	// stack frames of thread that debugger is currently focused on
+	pub fn current_frames(debugger: &DebuggerClient) -> Vec<StackFrame> {
+		debugger.thread_id
+			.and_then(|thread_id| debugger.stack_frames.get(&thread_id))
+			.cloned()
+			.unwrap_or_default()
+	}
+
+	pub fn state_key(&self, debugger: &DebuggerClient) -> DebugStateKey {
+		let mut thread_states : Vec<(ThreadId, String)> = debugger.thread_states.iter().map(|(id, state)| (*id, state.clone())).collect();
+		thread_states.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+		DebugStateKey {
+			thread_id		: debugger.thread_id,
+			active_frame	: debugger.active_frame,
+			frames			: Self::current_frames(debugger).iter().map(|frame| (frame.id, frame.line)).collect(),
+			thread_states,
+		}
+	}
+
+	fn frame_id(debugger: &DebuggerClient) -> Option<usize> {
+		let frames = Self::current_frames(debugger);
+
+		debugger.active_frame.and_then(|index| frames.get(index)).map(|frame| frame.id)
+	}
+
+	// debug session moved on: everything shown in panels is requested again
+	pub fn request_all(&mut self, debugger: &DebuggerClient, tokio_runtime: &TokioRuntime) {
+		self.abort_panel_requests();
+		self.responses = DebugResponses::default();
+
+		self.pending.push(PendingRequest::Threads(spawn_request::<requests::Threads>(debugger, tokio_runtime, ())));
+
+		if let Some(frame_id) = Self::frame_id(debugger) {
+			self.pending.push(PendingRequest::Scopes(spawn_request::<requests::Scopes>(debugger, tokio_runtime, requests::ScopesArguments { frame_id })));
+		}
+
+		self.request_missing(debugger, tokio_runtime);
+	}
+
+	fn is_pending_variables(&self, reference: usize) -> bool {
+		self.pending.iter().any(|request| matches!(request, PendingRequest::Variables(pending, _) if *pending == reference))
+	}
+
+	fn is_pending_watch(&self, expression: &str) -> bool {
+		self.pending.iter().any(|request| matches!(request, PendingRequest::Watch(pending, _) if pending == expression))
+	}
+
+	// variables of scopes and expanded variables and watch values that were neither received nor requested yet
+	pub fn request_missing(&mut self, debugger: &DebuggerClient, tokio_runtime: &TokioRuntime) {
+		let mut references : Vec<usize> = self.responses.scopes.iter().map(|scope| scope.variables_reference).collect();
+
+		references.extend(
+			self.responses.variables.values()
+				.flatten()
+				.map(|variable| variable.variables_reference)
+				.filter(|reference| *reference != 0 && self.expanded_variables.contains(reference))
+		);
+
+		for reference in references {
+			if self.responses.variables.contains_key(&reference) || self.is_pending_variables(reference) {
+				continue;
+			}
+
+			let arguments = requests::VariablesArguments {
+				variables_reference	: reference,
+				filter				: None,
+				start				: None,
+				count				: None,
+				format				: None,
+			};
+
+			self.pending.push(PendingRequest::Variables(reference, spawn_request::<requests::Variables>(debugger, tokio_runtime, arguments)));
+		}
+
+		let Some(frame_id) = Self::frame_id(debugger) else { return };
+
+		for expression in self.watches.clone() {
+			if self.responses.watches.contains_key(&expression) || self.is_pending_watch(&expression) {
+				continue;
+			}
+
+			let arguments = requests::EvaluateArguments {
+				expression	: expression.clone(),
+				frame_id	: Some(frame_id),
+				context		: Some(String::from("watch")),
+				format		: None,
+			};
+
+			self.pending.push(PendingRequest::Watch(expression, spawn_request::<requests::Evaluate>(debugger, tokio_runtime, arguments)));
+		}
+	}
+
+	// picks up answers that arrived since last frame. Returns true if anything arrived
+	pub fn poll_requests(&mut self) -> bool {
+		let mut received = false;
+
+		for mut request in std::mem::take(&mut self.pending) {
+			let finished = match &mut request {
+				PendingRequest::Threads(handle) => poll_request(handle).map(|response| {
+					if let Some(response) = response { self.responses.threads = response.threads }
+				}),
+				PendingRequest::Scopes(handle) => poll_request(handle).map(|response| {
+					if let Some(response) = response { self.responses.scopes = response.scopes }
+				}),
+				PendingRequest::Variables(reference, handle) => poll_request(handle).map(|response| {
+					// failed requests are remembered as empty so that they are not sent over and over again
+					self.responses.variables.insert(*reference, response.map_or_else(Vec::new, |response| response.variables));
+				}),
+				PendingRequest::Watch(expression, handle) => poll_request(handle).map(|response| {
+					let value = response.map_or_else(|| String::from("<not available>"), |response| response.result);
+					self.responses.watches.insert(expression.clone(), value);
+				}),
+				PendingRequest::StackTrace(thread_id, handle) => poll_request(handle).map(|response| {
+					if let Some(response) = response { self.responses.selected_thread = Some((*thread_id, response.stack_frames)) }
+				}),
+			};
+
+			if finished.is_some() {
+				received = true;
+			} else {
+				self.pending.push(request);
+			}
+		}
+
+		received
+	}
+
+	// panel rows out of everything adapter answered so far
+	pub fn rebuild_rows(&mut self, debugger: &DebuggerClient) {
+		self.rows.clear();
+
+		let frames = Self::current_frames(debugger);
+		let active_frame = debugger.active_frame;
+
+		self.rows.push(PanelRow::header("CALL STACK"));
+
+		for (index, frame) in frames.iter().enumerate() {
+			let source_name = frame.source.as_ref()
+				.and_then(|source| source.name.clone().or_else(|| source.path.as_ref().map(|path| path.to_string_lossy().to_string())))
+				.unwrap_or_default();
+
+			self.rows.push(PanelRow {
+				text	: format!("{} {}:{}", frame.name, source_name, frame.line),
+				kind	: PanelRowKind::Frame(index),
+				depth	: 1,
+				active	: active_frame == Some(index),
+			});
+		}
+
+		self.rows.push(PanelRow::header("THREADS"));
+
+		for thread in self.responses.threads.iter() {
+			let state = debugger.thread_states.get(&thread.id).map_or("running", |state| state.as_str());
+
+			self.rows.push(PanelRow {
+				text	: format!("{} {} ({})", thread.id, thread.name, state),
+				kind	: PanelRowKind::Thread(thread.id),
+				depth	: 1,
+				active	: debugger.thread_id == Some(thread.id),
+			});
+		}
+
+		let frame_id = Self::frame_id(debugger);
+
+		self.rows.push(PanelRow::header("VARIABLES"));
+
+		let mut variable_rows = Vec::new();
+
+		for scope in self.responses.scopes.iter() {
+			variable_rows.push(PanelRow {
+				text	: scope.name.clone(),
+				kind	: PanelRowKind::Header,
+				depth	: 1,
+				active	: false,
+			});
+
+			self.push_variables(&mut variable_rows, scope.variables_reference, 2);
+		}
+
+		self.rows.append(&mut variable_rows);
+
+		self.rows.push(PanelRow::header("WATCH"));
+
+		for (index, expression) in self.watches.iter().enumerate() {
+			let value = match frame_id {
+				Some(_) => self.responses.watches.get(expression).map_or("…", |value| value.as_str()),
+				None => "<not stopped>",
+			};
+
+			self.rows.push(PanelRow {
+				text	: format!("{} = {}", expression, value),
+				kind	: PanelRowKind::Watch(index),
+				depth	: 1,
+				active	: false,
+			});
+		}
+
+		self.scroll_by(0);
+	}
+
+	fn push_variables(&self, rows: &mut Vec<PanelRow>, reference: usize, depth: usize) {
+		if depth > MAX_VARIABLES_DEPTH {
+			return;
+		}
+
+		// not received yet
+		let Some(variables) = self.responses.variables.get(&reference) else { return };
+
+		for variable in variables.iter() {
+			let expandable	= variable.variables_reference != 0;
+			let expanded	= expandable && self.expanded_variables.contains(&variable.variables_reference);
+
+			let marker = match (expandable, expanded) {
+				(true, true)	=> "▾ ",
+				(true, false)	=> "▸ ",
+				_				=> "  ",
+			};
+
+			rows.push(PanelRow {
+				text	: format!("{}{} = {}", marker, variable.name, variable.value),
+				kind	: PanelRowKind::Variable(variable.variables_reference),
+				depth,
+				active	: false,
+			});
+
+			if expanded {
+				self.push_variables(rows, variable.variables_reference, depth + 1);
+			}
+		}
+	}
+
+	// panel is placed over the right side of focused view while debug session is running
+	pub fn update_surface(
+		&mut self,
+		surfaces_helix	: &mut SurfacesMapHelix,
+		app				: &HelixApp,
+	) {
+		let Some(surface_editor) = surfaces_helix.get(EditorView::ID) else { return };
+		let view_area = app.views().iter().find(|view_desc| view_desc.focused).map_or(surface_editor.area, |view_desc| view_desc.area);
+
+		if app.editor.debugger.is_none() || self.rows.is_empty() || view_area.width < PANEL_COLUMNS * 2 {
+			surfaces_helix.remove(DEBUG_PANEL_SURFACE_NAME);
+			return;
+		}
+
+		let height	= (self.rows.len() as u16).min(view_area.height);
+		let area	= Rect::new(view_area.right() - PANEL_COLUMNS, view_area.y, PANEL_COLUMNS, height);
+
+		// panel is cut off at view height, the rest is reachable with mouse wheel
+		self.visible_rows = height as usize;
+		self.scroll_by(0);
+
+		let surface = surfaces_helix.entry(String::from(DEBUG_PANEL_SURFACE_NAME)).or_insert_with(|| {
+			SurfaceHelix::empty_with_spatial(area, SurfaceFlags::default())
+		});
+
+		if surface.area != area {
+			surface.resize(area);
+		}
+
+		surface.placement = SurfacePlacement::AreaCoordinates;
+		surface.reset();
+
+		let theme			= &app.editor.theme;
+		let background		= theme.get("ui.background").patch(theme.get("ui.popup"));
+		let text_style		= background.patch(theme.get("ui.text"));
+		let header_style	= background.patch(theme.get("ui.text.focus"));
+		let active_style	= text_style.patch(theme.get("ui.debug.active"));
+
+		surface.set_style(area, background);
+
+		for (index, row) in self.rows.iter().skip(self.scroll).take(height as usize).enumerate() {
+			let style = match (row.kind, row.active) {
+				(_, true)					=> active_style,
+				(PanelRowKind::Header, _)	=> header_style,
+				_							=> text_style,
+			};
+
+			let indent = " ".repeat(row.depth * 2);
+
+			surface.set_stringn(area.x, area.y + index as u16, format!("{}{}", indent, row.text), PANEL_COLUMNS as usize, style);
+		}
+	}
+}
+
+// request is created on main thread since it needs debugger client, but the answer is awaited on tokio runtime.
+// Timeouts and errors are treated the same way: there is just nothing to show
+fn spawn_request<R: Request>(debugger: &DebuggerClient, tokio_runtime: &TokioRuntime, arguments: R::Arguments) -> JoinHandle<Option<R::Result>>
+where
+	R::Result: Send + 'static,
+{
+	let call = debugger.call::<R>(arguments);
+
+	tokio_runtime.spawn(async move {
+		let value = tokio::time::timeout(Duration::from_millis(REQUEST_TIMEOUT_MS), call).await.ok()?.ok()?;
+
+		serde_json::from_value(value).ok()
+	})
+}
+
+// None while request is still running, Some(None) if it failed
+fn poll_request<T>(handle: &mut JoinHandle<Option<T>>) -> Option<Option<T>> {
+	future::block_on(future::poll_once(handle)).map(|result| result.ok().flatten())
+}
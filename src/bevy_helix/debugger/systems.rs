@@ -0,0 +1,357 @@
+use bevy :: prelude :: *;
+use bevy :: input :: mouse :: { MouseWheel, MouseScrollUnit };
+
+#[cfg(feature = "tracing")]
+use bevy_puffin :: *;
+
+use helix_term :: { commands, ui :: EditorView };
+use helix_view :: { editor :: GutterType, graphics :: Color as HelixColor, handlers :: dap :: jump_to_stack_frame };
+
+use super :: *;
+
+use crate :: {
+	kodiki_ui :: { DraggingState, ColorMaterialsCache, color :: get_color_material_walpha_handle, raypick :: Raypick },
+	bevy_ab_glyph :: { ABGlyphFont, ABGlyphFonts, FontAssetHandles },
+	bevy_helix :: {
+		HighlightKind, MousePosState,
+		folding :: { Folds, FoldedView },
+		surface :: { SurfacesMapBevy, WordDescription },
+		systems_util :: hovered_surface_name,
+		utils :: color_from_helix,
+	},
+};
+
+// f5 continue (shift: terminate), f6 pause, f9 breakpoint at cursor line, f10 step over, f11 step in (shift: step out).
+// ctrl+alt+w adds primary selection to watch expressions or removes it from there
+pub fn input_keyboard(
+		key				: Res<Input<KeyCode>>,
+	mut debug_panels	: ResMut<DebugPanels>,
+		app_option		: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() || !app.editor_focused() { return }
+
+	let ctrl_pressed	= key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl);
+	let shift_pressed	= key.pressed(KeyCode::LShift) || key.pressed(KeyCode::RShift);
+	let alt_pressed		= key.pressed(KeyCode::LAlt) || key.pressed(KeyCode::RAlt);
+
+	if ctrl_pressed && alt_pressed && key.just_pressed(KeyCode::W) {
+		let expression = {
+			let (view, doc) = app.current_ref();
+			doc.selection(view.id).primary().fragment(doc.text().slice(..)).trim().to_string()
+		};
+
+		if !expression.is_empty() {
+			debug_panels.toggle_watch(expression);
+		}
+
+		return;
+	}
+
+	if ctrl_pressed || alt_pressed { return }
+
+	let debug_session_active = app.editor.debugger.is_some();
+
+	if key.just_pressed(KeyCode::F9) {
+		let line = {
+			let (view, doc) = app.current_ref();
+			let text = doc.text().slice(..);
+			text.char_to_line(doc.selection(view.id).primary().cursor(text))
+		};
+
+		app.toggle_breakpoint(line);
+		return;
+	}
+
+	// without debug session these keys go to Helix as usual
+	if !debug_session_active { return }
+
+	let command : fn(&mut commands::Context) =
+	if key.just_pressed(KeyCode::F5) {
+		if shift_pressed { commands::dap_terminate } else { commands::dap_continue }
+	} else if key.just_pressed(KeyCode::F6) {
+		commands::dap_pause
+	} else if key.just_pressed(KeyCode::F10) {
+		commands::dap_next
+	} else if key.just_pressed(KeyCode::F11) {
+		if shift_pressed { commands::dap_step_out } else { commands::dap_step_in }
+	} else {
+		return
+	};
+
+	profile_function!();
+
+	app.run_command(command);
+}
+
+// clicking line numbers toggles breakpoint. Runs after main input_mouse so mouse position is already in document rows
+pub fn input_mouse_gutter(
+		mouse_button	: Res<Input<MouseButton>>,
+		mouse_pos_state	: Res<MousePosState>,
+		dragging_state	: Res<DraggingState>,
+		app_option		: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() || dragging_state.is_active() { return }
+
+	if !mouse_button.just_pressed(MouseButton::Left) || mouse_pos_state.surface_name != EditorView::ID { return }
+
+	let folded_view = FoldedView::new(&app);
+	let (view, doc) = app.current_ref();
+
+	// fold markers and diff column have clicks of their own
+	let Some(breakpoint_columns) = app.gutter_columns(GutterType::LineNumbers) else { return };
+	if !breakpoint_columns.contains(&mouse_pos_state.col) { return }
+
+	let Some(line) = folded_view.document_row(folded_view.top_row + mouse_pos_state.row as usize) else { return };
+
+	if line >= doc.text().len_lines() { return }
+
+	profile_function!();
+
+	app.toggle_breakpoint(line);
+}
+
+// panel is cut off at view height so it scrolls on its own
+pub fn input_scroll(
+	mut scroll_events	: EventReader<MouseWheel>,
+		raypick			: Res<Raypick>,
+		surfaces		: Res<SurfacesMapBevy>,
+		q_word			: Query<&WordDescription>,
+	mut debug_panels	: ResMut<DebugPanels>,
+		app_option		: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	let hovered = raypick.last_hover
+		.and_then(|entity| hovered_surface_name(entity, &q_word, &surfaces))
+		.map_or(false, |name| name == DEBUG_PANEL_SURFACE_NAME);
+
+	if !hovered {
+		scroll_events.clear();
+		return;
+	}
+
+	let mut delta = 0;
+
+	for scroll_event in scroll_events.iter() {
+		let steps = match scroll_event.unit {
+			MouseScrollUnit::Line	=> scroll_event.y.signum() as i32,
+			MouseScrollUnit::Pixel	=> (scroll_event.y / 50.0) as i32,
+		};
+
+		delta -= steps * WHEEL_ROWS;
+	}
+
+	if delta == 0 { return }
+
+	debug_panels.scroll_by(delta);
+
+	app.request_render();
+}
+
+// clicking a frame or a thread focuses it, clicking a variable expands it and clicking a watch expression removes it
+pub fn input_mouse(
+		mouse_button	: Res<Input<MouseButton>>,
+		raypick			: Res<Raypick>,
+		surfaces		: Res<SurfacesMapBevy>,
+		q_word			: Query<&WordDescription>,
+		q_transform		: Query<&GlobalTransform>,
+		font_assets		: Res<Assets<ABGlyphFont>>,
+		font_handles	: Res<FontAssetHandles>,
+	mut debug_panels	: ResMut<DebugPanels>,
+		dragging_state	: Res<DraggingState>,
+		tokio_runtime	: Res<TokioRuntime>,
+		app_option		: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() || dragging_state.is_active() { return }
+
+	if !mouse_button.just_pressed(MouseButton::Left) { return }
+
+	let Some(hovered_entity) = raypick.last_hover else { return };
+	let Some(surface_name) = hovered_surface_name(hovered_entity, &q_word, &surfaces) else { return };
+
+	if surface_name != DEBUG_PANEL_SURFACE_NAME { return }
+
+	profile_function!();
+
+	let Some(surface_panel) = surfaces.get(surface_name) else { return };
+	let Ok(surface_transform) = q_transform.get(surface_panel.entity) else { return };
+
+	let font = font_assets.get(&font_handles.main).unwrap();
+	let row_height = font.vertical_advance();
+
+	// world space to surface space
+	let cursor_position_world	= raypick.ray_pos + raypick.ray_dir * raypick.ray_dist;
+	let cursor_position_surface	= surface_transform.compute_matrix().inverse().transform_point3(cursor_position_world);
+
+	let row = (cursor_position_surface.y.abs() / row_height) - surface_panel.scroll_info.offset as f32;
+	if row < 0.0 {
+		return;
+	}
+
+	let Some(panel_row) = debug_panels.row_at(row as usize) else { return };
+
+	match panel_row.kind {
+		PanelRowKind::Header => (),
+		PanelRowKind::Frame(index) => {
+			let Some(debugger) = app.editor.debugger.as_mut() else { return };
+			let Some(frame) = DebugPanels::current_frames(debugger).get(index).cloned() else { return };
+
+			debugger.active_frame = Some(index);
+
+			jump_to_stack_frame(&mut app.editor, &frame);
+		},
+		PanelRowKind::Thread(thread_id) => {
+			let Some(debugger) = app.editor.debugger.as_ref() else { return };
+			debug_panels.select_thread(debugger, &tokio_runtime, thread_id);
+		},
+		PanelRowKind::Variable(reference) => {
+			if reference != 0 {
+				debug_panels.toggle_variable(reference);
+			}
+		},
+		PanelRowKind::Watch(index) => {
+			debug_panels.remove_watch(index);
+		},
+	}
+
+	app.request_render();
+}
+
+// adapter is queried in background only when debug session state changes, panel rows are rebuilt every time answers arrive
+pub fn refresh_panels(
+	mut debug_panels	: ResMut<DebugPanels>,
+		tokio_runtime	: Res<TokioRuntime>,
+		app_option		: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	if app.editor.debugger.is_none() {
+		if !debug_panels.rows.is_empty() || !debug_panels.pending.is_empty() {
+			debug_panels.clear();
+		}
+		return;
+	}
+
+	// thread clicked in panel is focused as soon as its stack trace arrives, panels follow it through state key then
+	if debug_panels.responses.selected_thread.is_some() {
+		debug_panels.focus_selected_thread(&mut app.editor);
+	}
+
+	let Some(debugger) = app.editor.debugger.as_ref() else { return };
+
+	let state_key		= debug_panels.state_key(debugger);
+	let state_changed	= debug_panels.state_key.as_ref() != Some(&state_key);
+	let panels_changed	= debug_panels.rows_version != Some(debug_panels.version);
+
+	if !state_changed && !panels_changed && debug_panels.pending.is_empty() {
+		return;
+	}
+
+	profile_function!();
+
+	if state_changed {
+		debug_panels.request_all(debugger, &tokio_runtime);
+		debug_panels.state_key = Some(state_key);
+	}
+
+	let received = debug_panels.poll_requests();
+
+	// expanded variables and new watches are requested as soon as there is something to request them for
+	if received || panels_changed {
+		debug_panels.request_missing(debugger, &tokio_runtime);
+	}
+
+	if !(state_changed || panels_changed || received) {
+		return;
+	}
+
+	debug_panels.rebuild_rows(debugger);
+	debug_panels.rows_version = Some(debug_panels.version);
+
+	app.request_render();
+}
+
+// whole line highlight on the line where execution is paused in active frame
+pub fn update_execution_line(
+	mut debug_panels		: ResMut<DebugPanels>,
+		folds				: Res<Folds>,
+		surfaces_bevy		: Res<SurfacesMapBevy>,
+		font_assets			: Res<Assets<ABGlyphFont>>,
+		font_handles		: Res<FontAssetHandles>,
+	mut mesh_assets			: ResMut<Assets<Mesh>>,
+	mut material_assets		: ResMut<Assets<StandardMaterial>>,
+	mut color_materials_cache : ResMut<ColorMaterialsCache>,
+	mut commands			: Commands,
+		app_option			: Option<NonSend<HelixApp>>,
+) {
+	let Some(app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	profile_function!();
+
+	let folded_view = FoldedView::new(&app);
+	let doc = app.current_document();
+
+	// frame lines are 1-based
+	let execution_line = app.editor.debugger.as_ref().and_then(|debugger| {
+		let frame = debugger.active_frame.and_then(|index| DebugPanels::current_frames(debugger).get(index).cloned())?;
+		let path = frame.source.and_then(|source| source.path)?;
+
+		(doc.path() == Some(&path) && frame.line > 0).then(|| (path, frame.line - 1))
+	});
+
+	let cache = execution_line.map(|(path, line)| {
+		let visual_row = folds.visual_row(folded_view.doc_id, line, folded_view.top_row);
+
+		(path, folded_view.surface_row(visual_row), app.gutter_len(), app.editor.theme.name().to_string())
+	});
+
+	if debug_panels.execution_cache == cache {
+		return;
+	}
+
+	if let Some(entity) = debug_panels.execution_highlight.take() {
+		commands.entity(entity).despawn_recursive();
+	}
+
+	debug_panels.execution_cache = cache;
+
+	let Some((_, surface_row, gutter_len, _)) = debug_panels.execution_cache.clone() else { return };
+	let Some(surface_editor) = surfaces_bevy.get(EditorView::ID) else { return };
+
+	let theme = &app.editor.theme;
+	let style = theme.get("ui.debug.active");
+
+	let mut base_color = color_from_helix(style.bg.or(style.fg).unwrap_or(HelixColor::Yellow));
+	base_color.set_a(0.25);
+
+	let material_handle = get_color_material_walpha_handle(
+		base_color,
+		AlphaMode::Blend,
+		&mut color_materials_cache,
+		&mut material_assets
+	);
+
+	let fonts = ABGlyphFonts::new(&font_assets, &font_handles);
+
+	let highlight_entity = surface_editor.spawn_highlight_whole_line_untracked(
+		surface_row,
+		HighlightKind::Cursor,
+		&material_handle,
+		gutter_len,
+		&fonts,
+		&mut mesh_assets,
+		&mut commands
+	);
+
+	debug_panels.execution_highlight = Some(highlight_entity);
+}
@@ -0,0 +1,132 @@
+use bevy :: prelude :: *;
+
+use helix_core :: diagnostic :: { Diagnostic, Severity, NumberOrString };
+use helix_view :: { Document, Theme, graphics :: Color as HelixColor };
+
+use std :: ops :: Range;
+
+use crate :: kodiki_ui :: color :: get_color_wmodified_lightness;
+
+use super :: { folding :: FoldedView, utils :: color_from_helix };
+
+pub mod systems;
+
+pub const LENS_GAP_COLUMNS		: usize = 4;
+pub const LENS_MIN_COLUMNS		: usize = 12; // with less free space after the line only a counter is shown
+pub const LENS_EXPANDED_COLUMNS	: usize = 100;
+
+#[derive(Resource)]
+pub struct DiagnosticsLens {
+	pub enabled				: bool,
+	pub show_inactive_code	: bool, // rust-analyzer reports every cfg-disabled block which is mostly noise
+	pub expanded_line		: Option<usize>,
+
+	pub entities			: Vec<Entity>,
+	pub expanded_entities	: Vec<Entity>, // rebuilt apart from lens strings, the lens under mouse stays as is and keeps its hover
+	pub cache				: Option<DiagnosticsLensCache>,
+	pub expanded_cache		: Option<Option<usize>>,
+}
+
+impl Default for DiagnosticsLens {
+	fn default() -> Self {
+		Self {
+			enabled				: true,
+			show_inactive_code	: false,
+			expanded_line		: None,
+
+			entities			: Vec::new(),
+			expanded_entities	: Vec::new(),
+			cache				: None,
+			expanded_cache		: None,
+		}
+	}
+}
+
+// everything lens placement and content depends on
+#[derive(Clone, PartialEq, Debug)]
+pub struct DiagnosticsLensCache {
+	pub view				: FoldedView,
+	pub theme				: String,
+	pub doc_version			: usize,
+	pub diagnostics_version	: usize,
+	pub folds_version		: usize,
+	pub horizontal_offset	: usize,
+	pub gutter_len			: usize,
+	pub enabled				: bool,
+	pub show_inactive_code	: bool,
+}
+
+// every lens entity knows which line it belongs to so that hovering collapsed counter or any of expanded messages keeps them expanded
+#[derive(Component)]
+pub struct DiagnosticsLensRow {
+	pub line : usize,
+}
+
+pub fn is_inactive_code(diagnostic: &Diagnostic) -> bool {
+	matches!(&diagnostic.code, Some(NumberOrString::String(code)) if code == "inactive-code")
+}
+
+pub fn severity_scope(severity: Option<Severity>) -> &'static str {
+	match severity {
+		Some(Severity::Error)	=> "error",
+		Some(Severity::Warning)	=> "warning",
+		Some(Severity::Info)	=> "info",
+		Some(Severity::Hint) | None => "hint",
+	}
+}
+
+// text color and a darker (lighter for light themes) version of it for background quad
+pub fn severity_colors(severity: Option<Severity>, theme: &Theme, dark_theme: bool) -> (Color, Color) {
+	let style = theme.get(severity_scope(severity));
+	let color = color_from_helix(style.fg.unwrap_or(HelixColor::Cyan));
+
+	let background_color = get_color_wmodified_lightness(color, if dark_theme { -0.35 } else { 0.35 });
+
+	(color, background_color)
+}
+
+impl DiagnosticsLens {
+	// diagnostics on given lines grouped per line, most severe first
+	pub fn diagnostics_by_line<'a>(&self, doc: &'a Document, lines: Range<usize>) -> Vec<(usize, Vec<&'a Diagnostic>)> {
+		let mut grouped : Vec<(usize, Vec<&Diagnostic>)> = Vec::new();
+
+		for diagnostic in doc.diagnostics() {
+			if !lines.contains(&diagnostic.line) || (!self.show_inactive_code && is_inactive_code(diagnostic)) {
+				continue;
+			}
+
+			match grouped.iter_mut().find(|(line, _)| *line == diagnostic.line) {
+				Some((_, diagnostics)) => diagnostics.push(diagnostic),
+				None => grouped.push((diagnostic.line, vec![diagnostic])),
+			}
+		}
+
+		for (_, diagnostics) in grouped.iter_mut() {
+			diagnostics.sort_by(|a, b| b.severity.cmp(&a.severity));
+		}
+
+		grouped
+	}
+
+	pub fn toggle(&mut self) {
+		self.enabled = !self.enabled;
+	}
+
+	pub fn toggle_inactive_code(&mut self) {
+		self.show_inactive_code = !self.show_inactive_code;
+	}
+}
+
+// first line of the message cut to max_len characters
+pub fn short_message(message: &str, max_len: usize) -> String {
+	let first_line = message.lines().next().unwrap_or_default().trim();
+
+	if first_line.chars().count() <= max_len {
+		return String::from(first_line);
+	}
+
+	let mut short : String = first_line.chars().take(max_len.saturating_sub(1)).collect();
+	short.push('…');
+
+	short
+}
@@ -0,0 +1,230 @@
+use bevy :: prelude :: *;
+
+#[cfg(feature = "tracing")]
+use bevy_puffin :: *;
+
+use helix_term :: ui :: EditorView;
+
+use super :: *;
+
+use crate :: {
+	z_order,
+	kodiki_ui :: {
+		String3dSpawnRequest, CommonString3dSpawnParams,
+		raypick :: RaypickHover,
+		spawn :: string_mesh_collision,
+	},
+	bevy_ab_glyph :: { ABGlyphFont, ABGlyphFonts, FontAssetHandles },
+	bevy_helix :: {
+		HelixApp,
+		folding :: Folds,
+		surface :: SurfacesMapBevy,
+		systems_util :: popup_first_row,
+	},
+};
+
+// ctrl+alt+d toggles lens, ctrl+alt+h toggles cfg-inactive code hints
+pub fn input_keyboard(
+		key			: Res<Input<KeyCode>>,
+	mut lens		: ResMut<DiagnosticsLens>,
+) {
+	let ctrl_pressed	= key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl);
+	let alt_pressed		= key.pressed(KeyCode::LAlt) || key.pressed(KeyCode::RAlt);
+
+	if !(ctrl_pressed && alt_pressed) { return }
+
+	if key.just_pressed(KeyCode::D) {
+		lens.toggle();
+	} else if key.just_pressed(KeyCode::H) {
+		lens.toggle_inactive_code();
+	}
+}
+
+pub fn update_hover(
+		q_lens_row	: Query<(&DiagnosticsLensRow, &RaypickHover)>,
+	mut lens		: ResMut<DiagnosticsLens>,
+) {
+	let hovered_line = q_lens_row.iter().find(|(_, hover)| hover.hovered()).map(|(lens_row, _)| lens_row.line);
+
+	if lens.expanded_line != hovered_line {
+		lens.expanded_line = hovered_line;
+	}
+}
+
+fn spawn_lens_string(
+	string				: String,
+	line				: usize,
+	position			: Vec3,
+	color				: Color,
+	background_color	: Color,
+	font				: &ABGlyphFont,
+	commands			: &mut Commands,
+) -> Entity {
+	let collision_entity = string_mesh_collision(&string, font, commands);
+
+	let lens_entity = commands.spawn((
+		DiagnosticsLensRow { line },
+		RaypickHover::default(),
+		TransformBundle::from_transform(Transform::from_translation(position)),
+		VisibilityBundle::default(),
+		String3dSpawnRequest {
+			common : CommonString3dSpawnParams {
+				string,
+				color,
+				background_color : Some(background_color),
+				..default()
+			},
+			..default()
+		},
+	)).id();
+
+	commands.entity(lens_entity).add_child(collision_entity);
+
+	lens_entity
+}
+
+// end-of-line diagnostics for visible rows. Messages never go over the right edge of the view,
+// several diagnostics on one line are collapsed into a counter and listed under the line while hovered
+pub fn update_lens(
+	mut lens			: ResMut<DiagnosticsLens>,
+		folds			: Res<Folds>,
+		surfaces_bevy	: Res<SurfacesMapBevy>,
+		font_assets		: Res<Assets<ABGlyphFont>>,
+		font_handles	: Res<FontAssetHandles>,
+	mut commands		: Commands,
+		app_option		: Option<NonSend<HelixApp>>,
+) {
+	let Some(app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	profile_function!();
+
+	let folded_view = FoldedView::new(&app);
+	let (view, doc) = app.current_ref();
+
+	let cache = DiagnosticsLensCache {
+		view				: folded_view,
+		theme				: app.editor.theme.name().into(),
+		doc_version			: doc.version(),
+		diagnostics_version	: doc.diagnostics_version(),
+		folds_version		: folds.version,
+		horizontal_offset	: view.offset.horizontal_offset,
+		gutter_len			: app.gutter_len(),
+		enabled				: lens.enabled,
+		show_inactive_code	: lens.show_inactive_code,
+	};
+
+	let rebuild_lens		= lens.cache.as_ref() != Some(&cache);
+	let rebuild_expanded	= rebuild_lens || lens.expanded_cache != Some(lens.expanded_line);
+
+	if !rebuild_expanded {
+		return;
+	}
+
+	if rebuild_lens {
+		for entity in lens.entities.drain(..) {
+			commands.entity(entity).despawn_recursive();
+		}
+	}
+
+	for entity in lens.expanded_entities.drain(..) {
+		commands.entity(entity).despawn_recursive();
+	}
+
+	lens.cache			= Some(cache);
+	lens.expanded_cache	= Some(lens.expanded_line);
+
+	if !lens.enabled { return }
+
+	let Some(surface_editor) = surfaces_bevy.get(EditorView::ID) else { return };
+
+	let fonts			= ABGlyphFonts::new(&font_assets, &font_handles);
+	let row_height		= fonts.main.vertical_advance();
+	let column_width	= fonts.main.horizontal_advance_mono();
+
+	let theme			= &app.editor.theme;
+	let dark_theme		= app.dark_theme();
+	let text			= doc.text().slice(..);
+	let tab				= " ".repeat(doc.tab_width());
+	let gutter_len		= app.gutter_len();
+	let top_row			= folded_view.top_row;
+	let view_height		= view.area.height as usize;
+	let view_right		= (view.area.x + view.area.width) as usize;
+	let text_column		= view.area.x as usize + gutter_len;
+	let horizontal_offset = view.offset.horizontal_offset;
+
+	// rows below folds are moved up so lines further down can be visible
+	let last_line = folds.document_row(folded_view.doc_id, top_row + view_height, top_row).min(text.len_lines());
+
+	let row_y = |visual_row: usize| -> f32 {
+		-((folded_view.surface_row(visual_row) + 1) as f32 * row_height)
+	};
+
+	let mut entities			= Vec::new();
+	let mut expanded_entities	= Vec::new();
+
+	for (line, diagnostics) in lens.diagnostics_by_line(doc, top_row .. last_line) {
+		if folds.is_hidden(folded_view.doc_id, line) {
+			continue;
+		}
+
+		let visual_row = folds.visual_row(folded_view.doc_id, line, top_row);
+
+		let line_len	= text.line(line).to_string().replace('\t', tab.as_str()).trim_end().chars().count();
+		let column		= text_column + line_len.saturating_sub(horizontal_offset) + LENS_GAP_COLUMNS;
+		let free_columns = view_right.saturating_sub(column);
+
+		let (color, background_color) = severity_colors(diagnostics[0].severity, theme, dark_theme);
+
+		let string = if diagnostics.len() == 1 && free_columns >= LENS_MIN_COLUMNS {
+			short_message(diagnostics[0].message.as_str(), free_columns)
+		} else {
+			format!("■ {}", diagnostics.len())
+		};
+
+		// line is too long to fit even a counter
+		if string.chars().count() > free_columns {
+			continue;
+		}
+
+		let expandable = diagnostics.len() > 1 || string.ends_with('…') || free_columns < LENS_MIN_COLUMNS;
+
+		if rebuild_lens {
+			let position = Vec3::new(column as f32 * column_width, row_y(visual_row), z_order::surface::text());
+
+			entities.push(spawn_lens_string(string, line, position, color, background_color, fonts.main, &mut commands));
+		}
+
+		if !expandable || lens.expanded_line != Some(line) {
+			continue;
+		}
+
+		// full list is aligned to the right edge of the view and covers code while hovered
+		let max_columns	= LENS_EXPANDED_COLUMNS.min(view_right.saturating_sub(text_column));
+		let messages : Vec<String> = diagnostics.iter().map(|diagnostic| short_message(diagnostic.message.as_str(), max_columns)).collect();
+
+		let width		= messages.iter().map(|message| message.chars().count()).max().unwrap_or(0);
+		let column		= view_right.saturating_sub(width).max(text_column);
+
+		let first_row	= popup_first_row(visual_row, visual_row, messages.len(), top_row, view_height);
+
+		for ((index, diagnostic), message) in diagnostics.iter().enumerate().zip(messages.into_iter()) {
+			let (color, background_color) = severity_colors(diagnostic.severity, theme, dark_theme);
+
+			let position = Vec3::new(column as f32 * column_width, row_y(first_row + index), z_order::surface::child_surface());
+
+			expanded_entities.push(spawn_lens_string(message, line, position, color, background_color, fonts.main, &mut commands));
+		}
+	}
+
+	for entity in entities.iter().chain(expanded_entities.iter()) {
+		commands.entity(surface_editor.entity).add_child(*entity);
+	}
+
+	if rebuild_lens {
+		lens.entities = entities;
+	}
+
+	lens.expanded_entities = expanded_entities;
+}
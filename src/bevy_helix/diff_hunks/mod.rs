@@ -0,0 +1,442 @@
+use bevy :: prelude :: *;
+use bevy :: tasks :: Task;
+use bevy :: utils :: HashMap;
+
+use helix_core :: { Rope, diff :: compare_ropes };
+use helix_view :: { Editor, Document, DocumentId };
+
+use std :: {
+	io :: Write,
+	ops :: Range,
+	path :: { Path, PathBuf },
+	process :: { Command, Stdio },
+	time :: SystemTime,
+};
+
+use super :: { file_sync :: replace_document_text, folding :: FoldedView, git :: git_command };
+
+pub mod systems;
+
+pub const INDEX_CHECK_SECONDS	: f32 = 3.0; // index can be changed from outside so modification time of its file is polled
+pub const PREVIEW_COLUMNS		: usize = 100;
+pub const PREVIEW_MAX_LINES		: usize = 30;
+
+// diff hunk as seen by the diff gutter: lines of diff base (HEAD) it replaces and lines of the document it takes
+#[derive(Clone, PartialEq, Debug)]
+pub struct HunkSnapshot {
+	pub before		: Range<usize>,
+	pub after		: Range<usize>,
+	pub original	: String,
+	pub current		: String,
+}
+
+impl HunkSnapshot {
+	pub fn is_pure_insertion(&self) -> bool {
+		self.before.is_empty()
+	}
+
+	// none of the document lines hunk spans differ from the index, index being diffed against the document directly
+	// so that edits made after staging make the hunk unstaged again
+	pub fn is_staged(&self, index: &Rope, text: &Rope) -> bool {
+		!changed_lines(index, text).iter().any(|(_, after)| ranges_touch(after, &self.after))
+	}
+}
+
+// empty ranges are insertions between lines so they touch ranges that start or end right there
+pub fn ranges_touch(a: &Range<usize>, b: &Range<usize>) -> bool {
+	if a.is_empty() || b.is_empty() {
+		a.start <= b.end && b.start <= a.end
+	} else {
+		a.start < b.end && b.start < a.end
+	}
+}
+
+fn rope_lines(rope: &Rope, lines: &Range<usize>) -> String {
+	let end = lines.end.min(rope.len_lines());
+	let start = lines.start.min(end);
+
+	rope.slice(rope.line_to_char(start) .. rope.line_to_char(end)).to_string()
+}
+
+// hunk at given document line, removals count for the line they were removed before
+pub fn hunk_at_line(doc: &Document, line: usize) -> Option<HunkSnapshot> {
+	let diff_handle = doc.diff_handle()?;
+	let diff = diff_handle.load();
+
+	let hunk = diff.nth_hunk(diff.hunk_at(line as u32, true)?);
+
+	let before	= hunk.before.start as usize .. hunk.before.end as usize;
+	let after	= hunk.after.start as usize .. hunk.after.end as usize;
+
+	Some(HunkSnapshot {
+		original	: rope_lines(diff.diff_base(), &before),
+		current		: rope_lines(diff.doc(), &after),
+		before,
+		after,
+	})
+}
+
+pub fn diff_base(doc: &Document) -> Option<Rope> {
+	Some(doc.diff_handle()?.load().diff_base().clone())
+}
+
+// lines of the document hunk spans are replaced with the original ones as a regular edit so it can be undone
+pub fn revert_hunk(editor: &mut Editor, doc_id: DocumentId, hunk: &HunkSnapshot) -> bool {
+	let Some(doc) = editor.documents.get(&doc_id) else { return false };
+
+	let text = doc.text();
+	let mut new_text = rope_lines(text, &(0 .. hunk.after.start));
+	new_text.push_str(hunk.original.as_str());
+	new_text.push_str(rope_lines(text, &(hunk.after.end .. text.len_lines())).as_str());
+
+	replace_document_text(editor, doc_id, new_text.as_str())
+}
+
+// patches are applied from repository root and name files relative to it
+fn repository_location(path: &Path) -> Result<(PathBuf, String), String> {
+	let Some((mut command, file_name)) = git_command(path) else { return Err(String::from("invalid path")) };
+
+	let output = command.args(["rev-parse", "--show-toplevel", "--show-prefix"]).output().map_err(|e| e.to_string())?;
+
+	if !output.status.success() {
+		return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+	}
+
+	let stdout		= String::from_utf8_lossy(&output.stdout);
+	let mut lines	= stdout.lines();
+
+	let root	= PathBuf::from(lines.next().unwrap_or_default());
+	let prefix	= lines.next().unwrap_or_default();
+
+	Ok((root, format!("{}{}", prefix, file_name)))
+}
+
+fn push_patch_lines(patch: &mut String, prefix: char, lines: &str) {
+	for line in lines.split_inclusive('\n') {
+		patch.push(prefix);
+		patch.push_str(line);
+
+		if !line.ends_with('\n') {
+			patch.push_str("\n\\ No newline at end of file\n");
+		}
+	}
+}
+
+// lines that differ between two versions of a text as pairs of line ranges in the old and the new one.
+// Character level changes within the same lines are merged into one pair
+pub fn changed_lines(old: &Rope, new: &Rope) -> Vec<(Range<usize>, Range<usize>)> {
+	let line_range = |rope: &Rope, from: usize, to: usize| -> Range<usize> {
+		let start	= rope.char_to_line(from);
+		let end_line = rope.char_to_line(to);
+		let end		= if to > rope.line_to_char(end_line) { end_line + 1 } else { end_line };
+
+		start .. end.max(start)
+	};
+
+	let mut changed : Vec<(Range<usize>, Range<usize>)> = Vec::new();
+	let mut offset : isize = 0; // how far the new text is shifted relative to the old one before current change
+
+	for (from, to, insertion) in compare_ropes(old, new).changes().changes_iter() {
+		let inserted	= insertion.map_or(0, |insertion| insertion.chars().count());
+		let new_from	= (from as isize + offset) as usize;
+
+		offset += inserted as isize - (to - from) as isize;
+
+		let before	= line_range(old, from, to);
+		let after	= line_range(new, new_from, new_from + inserted);
+
+		match changed.last_mut() {
+			Some((last_before, last_after)) if ranges_touch(last_before, &before) || ranges_touch(last_after, &after) => {
+				last_before.end	= last_before.end.max(before.end);
+				last_after.end	= last_after.end.max(after.end);
+			},
+			_ => changed.push((before, after)),
+		}
+	}
+
+	changed
+}
+
+// zero-context patch turning old lines of every hunk into new ones. Hunks are sorted and new line numbers
+// only account for hunks in the patch since the rest of the changes are not applied
+pub fn hunks_patch(repository_path: &str, old: &Rope, new: &Rope, hunks: &[(Range<usize>, Range<usize>)]) -> String {
+	// unified diff counts lines from 1, empty ranges point at the line they follow
+	let start = |range: &Range<usize>| if range.is_empty() { range.start } else { range.start + 1 };
+
+	let mut patch = format!("diff --git a/{path} b/{path}\n--- a/{path}\n+++ b/{path}\n", path = repository_path);
+	let mut offset : isize = 0;
+
+	for (before, after) in hunks.iter() {
+		let after_start = (before.start as isize + offset) as usize;
+		let shifted_after = after_start .. after_start + after.len();
+
+		patch.push_str(format!("@@ -{},{} +{},{} @@\n", start(before), before.len(), start(&shifted_after), after.len()).as_str());
+
+		push_patch_lines(&mut patch, '-', rope_lines(old, before).as_str());
+		push_patch_lines(&mut patch, '+', rope_lines(new, after).as_str());
+
+		offset += after.len() as isize - before.len() as isize;
+	}
+
+	patch
+}
+
+// contents of the file in the index, None for files git does not track
+pub fn index_text(path: &Path) -> Option<Rope> {
+	let (mut command, file_name) = git_command(path)?;
+
+	let output = command.args(["show", format!(":./{}", file_name).as_str()]).output().ok()?;
+
+	if !output.status.success() {
+		return None;
+	}
+
+	Some(Rope::from(String::from_utf8_lossy(&output.stdout).as_ref()))
+}
+
+// staging takes lines of the document that differ from the index where the hunk is, unstaging brings back lines
+// of HEAD where the index differs from it. Both patches go from the index read right now so that they apply
+// no matter what was staged before or edited after
+pub fn apply_to_index(path: &Path, text: &Rope, head: &Rope, hunk: &HunkSnapshot, unstage: bool) -> Result<(), String> {
+	let (root, repository_path) = repository_location(path)?;
+
+	let Some(index) = index_text(path) else { return Err(String::from("file is not tracked")) };
+
+	let patch = if unstage {
+		let hunks : Vec<_> = changed_lines(head, &index)
+			.into_iter()
+			.filter(|(before, _)| ranges_touch(before, &hunk.before))
+			.map(|(before, after)| (after, before))
+			.collect();
+
+		if hunks.is_empty() { return Err(String::from("nothing staged there")) }
+
+		hunks_patch(repository_path.as_str(), &index, head, &hunks)
+	} else {
+		let hunks : Vec<_> = changed_lines(&index, text).into_iter().filter(|(_, after)| ranges_touch(after, &hunk.after)).collect();
+
+		if hunks.is_empty() { return Err(String::from("nothing to stage there")) }
+
+		hunks_patch(repository_path.as_str(), &index, text, &hunks)
+	};
+
+	let mut child = Command::new("git")
+		.current_dir(root)
+		.args(["apply", "--cached", "--unidiff-zero", "--whitespace=nowarn", "-"])
+		.stdin(Stdio::piped())
+		.stdout(Stdio::null())
+		.stderr(Stdio::piped())
+		.spawn()
+		.map_err(|e| e.to_string())?;
+
+	if let Some(mut stdin) = child.stdin.take() {
+		stdin.write_all(patch.as_bytes()).map_err(|e| e.to_string())?;
+	}
+
+	let output = child.wait_with_output().map_err(|e| e.to_string())?;
+
+	if !output.status.success() {
+		return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+	}
+
+	Ok(())
+}
+
+// index contents and lines of HEAD version that differ in it
+#[derive(Clone, PartialEq, Debug)]
+pub struct IndexSnapshot {
+	pub text		: Rope,
+	pub staged		: Vec<Range<usize>>,
+}
+
+impl IndexSnapshot {
+	pub fn new(path: &Path, head: &Rope) -> Option<Self> {
+		let text = index_text(path)?;
+		let staged = changed_lines(head, &text).into_iter().map(|(before, _)| before).collect();
+
+		Some(Self { text, staged })
+	}
+}
+
+// git index file of the repository document is in. Index is only reread once this file changes
+#[derive(Clone, PartialEq, Debug)]
+pub struct IndexFile {
+	pub path		: PathBuf,
+	pub modified	: Option<SystemTime>,
+}
+
+impl IndexFile {
+	pub fn new(path: &Path) -> Option<Self> {
+		let (mut command, _) = git_command(path)?;
+
+		let output = command.args(["rev-parse", "--git-path", "index"]).output().ok()?;
+
+		if !output.status.success() {
+			return None;
+		}
+
+		// git path is relative to the directory git was run from unless repository is elsewhere
+		let index_path = path.parent()?.join(String::from_utf8_lossy(&output.stdout).trim());
+
+		Some(Self { modified: Self::modified_time(&index_path), path: index_path })
+	}
+
+	pub fn modified_time(path: &Path) -> Option<SystemTime> {
+		std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+	}
+
+	pub fn changed(&self) -> bool {
+		Self::modified_time(&self.path) != self.modified
+	}
+}
+
+// index file is looked at before reading index contents so that changes made in between are not missed
+pub fn refresh_index(path: &Path, head: &Rope) -> (Option<IndexSnapshot>, Option<IndexFile>) {
+	let index_file = IndexFile::new(path);
+
+	(IndexSnapshot::new(path, head), index_file)
+}
+
+pub struct IndexTask {
+	pub doc_id		: DocumentId,
+	pub task		: Task<(Option<IndexSnapshot>, Option<IndexFile>)>,
+}
+
+pub struct ApplyTask {
+	pub unstage		: bool,
+	pub task		: Task<Result<(), String>>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum HunkActionKind {
+	Revert,
+	Stage,
+	Unstage,
+	Close,
+}
+
+impl HunkActionKind {
+	pub fn label(&self) -> &'static str {
+		match self {
+			HunkActionKind::Revert	=> "[revert]",
+			HunkActionKind::Stage	=> "[stage]",
+			HunkActionKind::Unstage	=> "[unstage]",
+			HunkActionKind::Close	=> "[close]",
+		}
+	}
+}
+
+#[derive(Component)]
+pub struct HunkButton {
+	pub action : HunkActionKind,
+}
+
+#[derive(Resource, Default)]
+pub struct DiffHunks {
+	// hunk with original text shown, remembered by document and the first line of the hunk
+	pub preview				: Option<(DocumentId, usize)>,
+
+	pub index				: HashMap<DocumentId, IndexSnapshot>,
+	pub staged_version		: usize,
+	pub index_task			: Option<IndexTask>,
+	pub index_file			: Option<IndexFile>,
+	pub indexed_doc			: Option<DocumentId>,
+	pub since_last_check	: f32,
+	pub refresh_requested	: bool,
+	pub apply_task			: Option<ApplyTask>,
+
+	pub entities			: Vec<Entity>,
+	pub cache				: Option<HunkPreviewCache>,
+}
+
+// everything preview placement and content depends on
+#[derive(Clone, PartialEq, Debug)]
+pub struct HunkPreviewCache {
+	pub view				: FoldedView,
+	pub hunk				: Option<HunkSnapshot>,
+	pub folds_version		: usize,
+	pub staged_version		: usize,
+	pub theme				: String,
+	pub view_area			: (u16, u16, u16), // left column, width and height
+	pub gutter_len			: usize,
+}
+
+impl DiffHunks {
+	pub fn staged_ranges(&self, doc_id: DocumentId) -> &[Range<usize>] {
+		self.index.get(&doc_id).map_or(&[], |index| index.staged.as_slice())
+	}
+
+	pub fn index_text(&self, doc_id: DocumentId) -> Option<&Rope> {
+		self.index.get(&doc_id).map(|index| &index.text)
+	}
+
+	pub fn toggle_preview(&mut self, doc_id: DocumentId, hunk: &HunkSnapshot) {
+		let preview = Some((doc_id, hunk.after.start));
+
+		self.preview = if self.preview == preview { None } else { preview };
+	}
+
+	pub fn request_refresh(&mut self) {
+		self.refresh_requested = true;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super :: *;
+
+	const HEADER : &str = "diff --git a/src/main.rs b/src/main.rs\n--- a/src/main.rs\n+++ b/src/main.rs\n";
+
+	#[test]
+	fn changed_lines_replacement_and_insertion() {
+		let old = Rope::from("a\nb\nc\n");
+
+		assert_eq!(changed_lines(&old, &Rope::from("a\nB\nc\n")), vec![(1 .. 2, 1 .. 2)]);
+		assert_eq!(changed_lines(&Rope::from("a\nc\n"), &old), vec![(1 .. 1, 1 .. 2)]);
+		assert!(changed_lines(&old, &old.clone()).is_empty());
+	}
+
+	#[test]
+	fn ranges_touch_insertions() {
+		assert!(ranges_touch(&(1 .. 1), &(1 .. 3)));
+		assert!(ranges_touch(&(3 .. 3), &(1 .. 3)));
+		assert!(!ranges_touch(&(0 .. 0), &(1 .. 2)));
+		assert!(!ranges_touch(&(0 .. 1), &(1 .. 2)));
+	}
+
+	#[test]
+	fn hunks_patch_stage() {
+		let index	= Rope::from("a\nb\nc\n");
+		let text	= Rope::from("a\nB\nc\nd\n");
+
+		// only the first of two changes is staged
+		let hunks : Vec<_> = changed_lines(&index, &text).into_iter().take(1).collect();
+
+		assert_eq!(hunks_patch("src/main.rs", &index, &text, &hunks), format!("{}@@ -2,1 +2,1 @@\n-b\n+B\n", HEADER));
+	}
+
+	#[test]
+	fn hunks_patch_unstage_counts_lines_of_index() {
+		let head	= Rope::from("a\nb\nc\n");
+		let index	= Rope::from("x\na\nB\nc\n");
+
+		// line inserted above stays staged so the unstaged line is further down in the index than in HEAD
+		let hunks : Vec<_> = changed_lines(&head, &index)
+			.into_iter()
+			.filter(|(before, _)| ranges_touch(before, &(1 .. 2)))
+			.map(|(before, after)| (after, before))
+			.collect();
+
+		assert_eq!(hunks_patch("src/main.rs", &index, &head, &hunks), format!("{}@@ -3,1 +3,1 @@\n-B\n+b\n", HEADER));
+	}
+
+	#[test]
+	fn hunks_patch_pure_insertion() {
+		let old = Rope::from("a\nc\n");
+		let new = Rope::from("a\nb\nc\n");
+
+		let hunks = changed_lines(&old, &new);
+
+		assert_eq!(hunks_patch("src/main.rs", &old, &new, &hunks), format!("{}@@ -1,0 +2,1 @@\n+b\n", HEADER));
+	}
+}
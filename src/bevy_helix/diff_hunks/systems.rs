@@ -0,0 +1,347 @@
+use bevy :: prelude :: *;
+use bevy :: tasks :: AsyncComputeTaskPool;
+
+#[cfg(feature = "tracing")]
+use bevy_puffin :: *;
+
+use futures_lite :: future;
+
+use helix_term :: ui :: EditorView;
+use helix_view :: { editor :: GutterType, graphics :: Color as HelixColor };
+
+use super :: *;
+
+use crate :: {
+	z_order,
+	kodiki_ui :: {
+		DraggingState, String3dSpawnRequest, CommonString3dSpawnParams,
+		color :: get_color_wmodified_lightness,
+		raypick :: RaypickHover,
+		spawn :: string_mesh_collision,
+	},
+	bevy_ab_glyph :: { ABGlyphFont, ABGlyphFonts, FontAssetHandles },
+	bevy_helix :: {
+		HelixApp, MousePosState,
+		folding :: Folds,
+		surface :: SurfacesMapBevy,
+		systems_util :: popup_first_row,
+		utils :: color_from_helix,
+	},
+};
+
+// clicking diff gutter next to a hunk shows its original text with actions for it
+pub fn input_mouse_gutter(
+		mouse_button	: Res<Input<MouseButton>>,
+		mouse_pos_state	: Res<MousePosState>,
+		dragging_state	: Res<DraggingState>,
+	mut diff_hunks		: ResMut<DiffHunks>,
+		app_option		: Option<NonSend<HelixApp>>,
+) {
+	let Some(app) = app_option else { return };
+
+	if app.should_close() || dragging_state.is_active() { return }
+
+	if !mouse_button.just_pressed(MouseButton::Left) || mouse_pos_state.surface_name != EditorView::ID { return }
+
+	let Some(diff_column) = app.gutter_offset(GutterType::Diff) else { return };
+
+	let folded_view = FoldedView::new(&app);
+	let (view, doc) = app.current_ref();
+
+	if mouse_pos_state.col != view.area.x + diff_column as u16 { return }
+
+	let Some(line) = folded_view.document_row(folded_view.top_row + mouse_pos_state.row as usize) else { return };
+
+	profile_function!();
+
+	let Some(hunk) = hunk_at_line(doc, line) else { return };
+
+	diff_hunks.toggle_preview(doc.id(), &hunk);
+}
+
+pub fn input_mouse_buttons(
+		mouse_button	: Res<Input<MouseButton>>,
+		q_button		: Query<(&HunkButton, &RaypickHover)>,
+	mut diff_hunks		: ResMut<DiffHunks>,
+		app_option		: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	if !mouse_button.just_pressed(MouseButton::Left) { return }
+
+	let Some((button, _)) = q_button.iter().find(|(_, hover)| hover.hovered()) else { return };
+	let Some((doc_id, line)) = diff_hunks.preview else { return };
+
+	profile_function!();
+
+	let Some(doc) = app.editor.documents.get(&doc_id) else { return };
+	let Some(hunk) = hunk_at_line(doc, line) else { return };
+	let path = doc.path().cloned();
+	let text = doc.text().clone();
+	let head = diff_base(doc);
+
+	match button.action {
+		HunkActionKind::Revert => {
+			if revert_hunk(&mut app.editor, doc_id, &hunk) {
+				diff_hunks.preview = None;
+			}
+		},
+		HunkActionKind::Stage | HunkActionKind::Unstage => {
+			let (Some(path), Some(head)) = (path, head) else { return };
+
+			// one patch at a time since each one is made against the index as it is before applying
+			if diff_hunks.apply_task.is_some() { return }
+
+			let unstage = button.action == HunkActionKind::Unstage;
+
+			let task = AsyncComputeTaskPool::get().spawn(async move {
+				apply_to_index(&path, &text, &head, &hunk, unstage)
+			});
+
+			diff_hunks.apply_task = Some(ApplyTask { unstage, task });
+		},
+		HunkActionKind::Close => diff_hunks.preview = None,
+	}
+
+	app.request_render();
+}
+
+// index contents of the current document are reread in background when document is switched, after staging
+// or unstaging and when index file changes
+pub fn update_index(
+		time		: Res<Time>,
+	mut diff_hunks	: ResMut<DiffHunks>,
+		app_option	: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	profile_function!();
+
+	diff_hunks.index.retain(|doc_id, _| app.editor.documents.contains_key(doc_id));
+
+	if let Some(mut apply_task) = diff_hunks.apply_task.take() {
+		match future::block_on(future::poll_once(&mut apply_task.task)) {
+			Some(result) => {
+				let unstage = apply_task.unstage;
+
+				match result {
+					Ok(_) => app.editor.set_status(if unstage { "Hunk unstaged" } else { "Hunk staged" }),
+					Err(e) => app.editor.set_error(format!("Failed to {} hunk: {}", if unstage { "unstage" } else { "stage" }, e)),
+				}
+
+				diff_hunks.request_refresh();
+				app.request_render();
+			},
+			None => diff_hunks.apply_task = Some(apply_task),
+		}
+	}
+
+	if let Some(mut index_task) = diff_hunks.index_task.take() {
+		match future::block_on(future::poll_once(&mut index_task.task)) {
+			Some((index, index_file)) => {
+				diff_hunks.index_file = index_file;
+
+				if diff_hunks.index.get(&index_task.doc_id) != index.as_ref() {
+					match index {
+						Some(index) => diff_hunks.index.insert(index_task.doc_id, index),
+						None => diff_hunks.index.remove(&index_task.doc_id),
+					};
+
+					diff_hunks.staged_version += 1;
+				}
+			},
+			None => {
+				diff_hunks.index_task = Some(index_task);
+				return;
+			}
+		}
+	}
+
+	let doc = app.current_document();
+
+	let doc_switched = diff_hunks.indexed_doc != Some(doc.id());
+
+	diff_hunks.since_last_check += time.delta_seconds();
+
+	let check_index_file = diff_hunks.since_last_check >= INDEX_CHECK_SECONDS;
+
+	if check_index_file {
+		diff_hunks.since_last_check = 0.0;
+	}
+
+	let index_file_changed = check_index_file && diff_hunks.index_file.as_ref().map_or(false, |index_file| index_file.changed());
+
+	if !doc_switched && !diff_hunks.refresh_requested && !index_file_changed { return }
+
+	let Some(path) = doc.path().cloned() else { return };
+	let Some(head) = diff_base(doc) else { return };
+
+	let task = AsyncComputeTaskPool::get().spawn(async move {
+		refresh_index(&path, &head)
+	});
+
+	diff_hunks.index_task			= Some(IndexTask { doc_id: doc.id(), task });
+	diff_hunks.indexed_doc			= Some(doc.id());
+	diff_hunks.refresh_requested	= false;
+}
+
+fn spawn_preview_string(
+	string				: String,
+	position			: Vec3,
+	color				: Color,
+	background_color	: Color,
+	button				: Option<HunkActionKind>,
+	font				: &ABGlyphFont,
+	commands			: &mut Commands,
+) -> Entity {
+	let preview_entity = commands.spawn((
+		TransformBundle::from_transform(Transform::from_translation(position)),
+		VisibilityBundle::default(),
+		String3dSpawnRequest {
+			common : CommonString3dSpawnParams {
+				string : string.clone(),
+				color,
+				background_color : Some(background_color),
+				..default()
+			},
+			..default()
+		},
+	)).id();
+
+	if let Some(action) = button {
+		let collision_entity = string_mesh_collision(&string, font, commands);
+
+		commands.entity(preview_entity)
+			.insert((HunkButton { action }, RaypickHover::default()))
+			.add_child(collision_entity);
+	}
+
+	preview_entity
+}
+
+// actions and original text of previewed hunk go under the hunk inside of the view
+pub fn update_preview(
+	mut diff_hunks		: ResMut<DiffHunks>,
+		folds			: Res<Folds>,
+		surfaces_bevy	: Res<SurfacesMapBevy>,
+		font_assets		: Res<Assets<ABGlyphFont>>,
+		font_handles	: Res<FontAssetHandles>,
+	mut commands		: Commands,
+		app_option		: Option<NonSend<HelixApp>>,
+) {
+	let Some(app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	profile_function!();
+
+	let folded_view = FoldedView::new(&app);
+	let (view, doc) = app.current_ref();
+
+	let hunk = match diff_hunks.preview {
+		Some((doc_id, line)) if doc_id == doc.id() => hunk_at_line(doc, line),
+		_ => None,
+	};
+
+	// hunk is gone after edits, revert or switching documents
+	if hunk.is_none() && diff_hunks.preview.map_or(false, |(doc_id, _)| doc_id == doc.id()) {
+		diff_hunks.preview = None;
+	}
+
+	let cache = HunkPreviewCache {
+		view				: folded_view,
+		hunk				: hunk.clone(),
+		folds_version		: folds.version,
+		staged_version		: diff_hunks.staged_version,
+		theme				: app.editor.theme.name().into(),
+		view_area			: (view.area.x, view.area.width, view.area.height),
+		gutter_len			: app.gutter_len(),
+	};
+
+	if diff_hunks.cache.as_ref() == Some(&cache) {
+		return;
+	}
+
+	for entity in diff_hunks.entities.drain(..) {
+		commands.entity(entity).despawn_recursive();
+	}
+
+	diff_hunks.cache = Some(cache);
+
+	let Some(hunk) = hunk else { return };
+	let Some(surface_editor) = surfaces_bevy.get(EditorView::ID) else { return };
+
+	let fonts			= ABGlyphFonts::new(&font_assets, &font_handles);
+	let row_height		= fonts.main.vertical_advance();
+	let column_width	= fonts.main.horizontal_advance_mono();
+
+	let theme			= &app.editor.theme;
+	let dark_theme		= app.dark_theme();
+	let removed_color	= color_from_helix(theme.get("diff.minus").fg.unwrap_or(HelixColor::Red));
+	let button_color	= color_from_helix(theme.get("ui.text").fg.unwrap_or(HelixColor::White));
+	let background_color = get_color_wmodified_lightness(removed_color, if dark_theme { -0.4 } else { 0.4 });
+
+	let tab				= " ".repeat(doc.tab_width());
+	let top_row			= folded_view.top_row;
+	let view_height		= view.area.height as usize;
+	let view_right		= (view.area.x + view.area.width) as usize;
+	let column			= view.area.x as usize + app.gutter_len();
+	let max_columns		= PREVIEW_COLUMNS.min(view_right.saturating_sub(column));
+
+	let start_row		= folds.visual_row(folded_view.doc_id, hunk.after.start, top_row);
+	let end_row			= folds.visual_row(folded_view.doc_id, hunk.after.end.saturating_sub(1).max(hunk.after.start), top_row);
+
+	let position_at = |column: usize, visual_row: usize| -> Vec3 {
+		Vec3::new(
+			column as f32 * column_width,
+			-((folded_view.surface_row(visual_row) + 1) as f32 * row_height),
+			z_order::surface::child_surface()
+		)
+	};
+
+	let staged = diff_hunks.index_text(doc.id()).map_or(false, |index| hunk.is_staged(index, doc.text()));
+
+	let mut actions = vec![HunkActionKind::Revert];
+	actions.push(if staged { HunkActionKind::Unstage } else { HunkActionKind::Stage });
+	actions.push(HunkActionKind::Close);
+
+	let original_rows : Vec<String> = if hunk.is_pure_insertion() {
+		vec![format!("{} line(s) added", hunk.after.len())]
+	} else {
+		hunk.original.lines()
+			.take(PREVIEW_MAX_LINES)
+			.map(|line| line.replace('\t', tab.as_str()).chars().take(max_columns).collect())
+			.collect()
+	};
+
+	// actions row goes first and original lines follow it
+	let actions_row = popup_first_row(start_row, end_row, original_rows.len() + 1, top_row, view_height);
+
+	let mut entities = Vec::new();
+	let mut action_column = column;
+
+	for action in actions {
+		let label = action.label();
+
+		entities.push(spawn_preview_string(String::from(label), position_at(action_column, actions_row), button_color, background_color, Some(action), fonts.main, &mut commands));
+
+		action_column += label.chars().count() + 1;
+	}
+
+	for (index, row) in original_rows.into_iter().enumerate() {
+		// empty strings have nothing to render but the row still has to be marked as removed
+		let row = if row.is_empty() { String::from(" ") } else { row };
+
+		entities.push(spawn_preview_string(row, position_at(column, actions_row + 1 + index), removed_color, background_color, None, fonts.main, &mut commands));
+	}
+
+	for entity in entities.iter() {
+		commands.entity(surface_editor.entity).add_child(*entity);
+	}
+
+	diff_hunks.entities = entities;
+}
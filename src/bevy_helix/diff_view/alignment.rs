@@ -0,0 +1,173 @@
+use helix_core :: Rope;
+
+use std :: ops :: Range;
+
+use crate :: bevy_helix :: diff_hunks :: changed_lines;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RowKind {
+	Equal,
+	Removed,	// only left side has a line
+	Added,		// only right side has a line
+	Modified,	// both sides have different lines
+}
+
+// one row of side by side view, side without a line is drawn as filler
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct AlignedRow {
+	pub left	: Option<usize>,
+	pub right	: Option<usize>,
+	pub kind	: RowKind,
+}
+
+#[derive(Default)]
+pub struct Alignment {
+	pub rows	: Vec<AlignedRow>,
+	pub hunks	: Vec<usize>, // first row of every group of changed rows
+}
+
+// trailing line break does not start another line
+fn line_count(text: &Rope) -> usize {
+	let lines = text.len_lines();
+
+	if text.line(lines - 1).len_chars() == 0 { lines - 1 } else { lines }
+}
+
+impl Alignment {
+	// removed and added lines of the same hunk are paired row by row, the rest goes against filler
+	pub fn new(left: &Rope, right: &Rope) -> Self {
+		let mut alignment = Alignment::default();
+
+		let left_len	= line_count(left);
+		let right_len	= line_count(right);
+
+		let (mut x, mut y) = (0, 0);
+
+		for (before, after) in changed_lines(left, right) {
+			let before	= before.start.min(left_len) .. before.end.min(left_len);
+			let after	= after.start.min(right_len) .. after.end.min(right_len);
+
+			while x < before.start && y < after.start {
+				alignment.rows.push(AlignedRow { left: Some(x), right: Some(y), kind: RowKind::Equal });
+				x += 1;
+				y += 1;
+			}
+
+			if !before.is_empty() || !after.is_empty() {
+				alignment.push_hunk(before.clone(), after.clone());
+			}
+
+			x = before.end;
+			y = after.end;
+		}
+
+		while x < left_len && y < right_len {
+			alignment.rows.push(AlignedRow { left: Some(x), right: Some(y), kind: RowKind::Equal });
+			x += 1;
+			y += 1;
+		}
+
+		alignment
+	}
+
+	fn push_hunk(&mut self, removed: Range<usize>, added: Range<usize>) {
+		self.hunks.push(self.rows.len());
+
+		let rows = removed.len().max(added.len());
+
+		for i in 0 .. rows {
+			let left	= (i < removed.len()).then(|| removed.start + i);
+			let right	= (i < added.len()).then(|| added.start + i);
+
+			let kind = match (left, right) {
+				(Some(_), Some(_))	=> RowKind::Modified,
+				(Some(_), None)		=> RowKind::Removed,
+				_					=> RowKind::Added,
+			};
+
+			self.rows.push(AlignedRow { left, right, kind });
+		}
+	}
+
+	pub fn next_hunk(&self, row: usize) -> Option<usize> {
+		self.hunks.iter().copied().find(|hunk_row| *hunk_row > row)
+	}
+
+	pub fn prev_hunk(&self, row: usize) -> Option<usize> {
+		self.hunks.iter().copied().rev().find(|hunk_row| *hunk_row < row)
+	}
+}
+
+// characters that differ between two versions of a line after trimming common head and tail
+pub fn intraline_ranges(left: &str, right: &str) -> (Range<usize>, Range<usize>) {
+	let left_chars	: Vec<char> = left.chars().collect();
+	let right_chars	: Vec<char> = right.chars().collect();
+
+	let head = left_chars.iter().zip(right_chars.iter()).take_while(|(a, b)| a == b).count();
+	let tail = left_chars[head ..].iter().rev().zip(right_chars[head ..].iter().rev()).take_while(|(a, b)| a == b).count();
+
+	(head .. left_chars.len() - tail, head .. right_chars.len() - tail)
+}
+
+#[cfg(test)]
+mod tests {
+	use super :: *;
+
+	fn row(left: Option<usize>, right: Option<usize>, kind: RowKind) -> AlignedRow {
+		AlignedRow { left, right, kind }
+	}
+
+	#[test]
+	fn alignment_pairs_modified_and_fills_added() {
+		let left	= Rope::from("a\nb\nc\nd\n");
+		let right	= Rope::from("a\nB\nc\nx\nd\n");
+
+		let alignment = Alignment::new(&left, &right);
+
+		assert_eq!(alignment.rows, vec![
+			row(Some(0), Some(0), RowKind::Equal),
+			row(Some(1), Some(1), RowKind::Modified),
+			row(Some(2), Some(2), RowKind::Equal),
+			row(None, Some(3), RowKind::Added),
+			row(Some(3), Some(4), RowKind::Equal),
+		]);
+
+		assert_eq!(alignment.hunks, vec![1, 3]);
+		assert_eq!(alignment.next_hunk(1), Some(3));
+		assert_eq!(alignment.prev_hunk(3), Some(1));
+		assert_eq!(alignment.next_hunk(3), None);
+	}
+
+	#[test]
+	fn alignment_removed_at_the_end() {
+		let left	= Rope::from("a\nb\nc\n");
+		let right	= Rope::from("a\n");
+
+		let alignment = Alignment::new(&left, &right);
+
+		assert_eq!(alignment.rows, vec![
+			row(Some(0), Some(0), RowKind::Equal),
+			row(Some(1), None, RowKind::Removed),
+			row(Some(2), None, RowKind::Removed),
+		]);
+
+		assert_eq!(alignment.hunks, vec![1]);
+	}
+
+	#[test]
+	fn alignment_of_equal_texts() {
+		let text = Rope::from("a\nb");
+
+		let alignment = Alignment::new(&text, &text.clone());
+
+		assert_eq!(alignment.rows.len(), 2);
+		assert!(alignment.hunks.is_empty());
+	}
+
+	#[test]
+	fn intraline_ranges_trim_common_head_and_tail() {
+		assert_eq!(intraline_ranges("let a = 1;", "let b = 1;"), (4 .. 5, 4 .. 5));
+		assert_eq!(intraline_ranges("foo()", "foo(bar)"), (4 .. 4, 4 .. 7));
+		assert_eq!(intraline_ranges("same", "same"), (4 .. 4, 4 .. 4));
+	}
+}
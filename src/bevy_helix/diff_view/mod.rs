@@ -0,0 +1,394 @@
+use bevy :: prelude :: *;
+use bevy :: tasks :: { AsyncComputeTaskPool, Task };
+
+use futures_lite :: future;
+
+use helix_core :: Rope;
+use helix_term :: ui :: EditorView;
+use helix_tui :: buffer :: { Buffer as SurfaceHelix, SurfaceFlags, SurfacePlacement };
+use helix_view :: {
+	Editor, DocumentId, Theme,
+	graphics :: { Color as HelixColor, Rect, Style },
+};
+
+use std :: {
+	path :: { Path, PathBuf },
+	process :: Command,
+	sync :: { Arc, Mutex },
+};
+
+use super :: {
+	HelixApp,
+	surface :: { SurfacesMapHelix, DIFF_LEFT_SURFACE_NAME, DIFF_RIGHT_SURFACE_NAME },
+};
+
+pub mod alignment;
+pub mod systems;
+
+use alignment :: { Alignment, AlignedRow, RowKind, intraline_ranges };
+
+pub const HUNK_CONTEXT_ROWS	: usize = 3; // rows shown above a hunk when jumping to it
+pub const MIN_PANE_COLUMNS	: u16 = 20;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum DiffSource {
+	Document(DocumentId), // working copy, unsaved changes included
+	Revision { revision: String, path: PathBuf },
+	File(PathBuf),
+	Empty(String), // side of a file that was added or deleted, titled with what happened to it
+}
+
+impl DiffSource {
+	pub fn title(&self, editor: &Editor) -> String {
+		match self {
+			DiffSource::Document(doc_id) => {
+				let name = editor.documents.get(doc_id).map_or_else(String::new, |doc| doc.display_name().to_string());
+				format!("{} (working copy)", name)
+			},
+			DiffSource::Revision { revision, path } => format!("{}:{}", revision, path.file_name().unwrap_or_default().to_string_lossy()),
+			DiffSource::File(path) => path.to_string_lossy().to_string(),
+			DiffSource::Empty(title) => title.clone(),
+		}
+	}
+
+	pub fn load(&self, editor: &Editor) -> Result<Rope, String> {
+		match self {
+			DiffSource::Document(doc_id) => editor.documents.get(doc_id)
+				.map(|doc| doc.text().clone())
+				.ok_or_else(|| String::from("document is closed")),
+			DiffSource::Revision { revision, path } => show_revision(revision, path).map(|text| Rope::from(text.as_str())),
+			DiffSource::File(path) => std::fs::read_to_string(path).map(|text| Rope::from(text.as_str())).map_err(|e| format!("{}: {}", path.display(), e)),
+			DiffSource::Empty(_) => Ok(Rope::new()),
+		}
+	}
+
+	// working copy changes all the time and the view follows it
+	pub fn version(&self, editor: &Editor) -> Option<usize> {
+		match self {
+			DiffSource::Document(doc_id) => editor.documents.get(doc_id).map(|doc| doc.version()),
+			_ => None,
+		}
+	}
+}
+
+fn show_revision(revision: &str, path: &Path) -> Result<String, String> {
+	let dir			= path.parent().ok_or_else(|| String::from("invalid path"))?;
+	let file_name	= path.file_name().ok_or_else(|| String::from("invalid path"))?.to_string_lossy();
+
+	let output = Command::new("git")
+		.current_dir(dir)
+		.args(["show", format!("{}:./{}", revision, file_name).as_str()])
+		.output()
+		.map_err(|e| e.to_string())?;
+
+	if !output.status.success() {
+		return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+	}
+
+	Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct DiffRequest {
+	pub left	: DiffSource,
+	pub right	: DiffSource,
+}
+
+// filled from Helix prompt callback which has no access to Bevy resources
+pub type SharedDiffRequest = Arc<Mutex<Option<DiffRequest>>>;
+
+// empty input compares working copy with HEAD, one argument compares working copy with a revision or a file,
+// two arguments compare revisions or files with each other
+pub fn parse_request(input: &str, editor: &Editor, doc_id: DocumentId) -> Result<DiffRequest, String> {
+	let doc_path = editor.documents.get(&doc_id).and_then(|doc| doc.path().cloned());
+
+	let source = |argument: &str| -> Result<DiffSource, String> {
+		let path = PathBuf::from(argument);
+
+		if path.is_file() {
+			return Ok(DiffSource::File(path));
+		}
+
+		let Some(doc_path) = doc_path.clone() else { return Err(format!("'{}' is not a file and document has no path to look up revision for", argument)) };
+
+		Ok(DiffSource::Revision { revision: String::from(argument), path: doc_path })
+	};
+
+	let arguments : Vec<&str> = input.split_whitespace().collect();
+
+	match arguments.as_slice() {
+		[] => Ok(DiffRequest { left: source("HEAD")?, right: DiffSource::Document(doc_id) }),
+		[left] => Ok(DiffRequest { left: source(left)?, right: DiffSource::Document(doc_id) }),
+		[left, right] => Ok(DiffRequest { left: source(left)?, right: source(right)? }),
+		_ => Err(String::from("Diff takes at most two revisions or files")),
+	}
+}
+
+#[derive(Default)]
+pub struct DiffSide {
+	pub title	: String,
+	pub text	: Rope,
+	pub lines	: Vec<String>,
+}
+
+impl DiffSide {
+	fn new(title: String, text: Rope) -> Self {
+		Self {
+			lines : text.to_string().lines().map(String::from).collect(),
+			title,
+			text,
+		}
+	}
+}
+
+// both sides with their alignment are rebuilt in background after working copy changes
+pub struct AlignTask {
+	pub versions	: (Option<usize>, Option<usize>),
+	pub task		: Task<(DiffSide, DiffSide, Alignment)>,
+}
+
+// two panes side by side with rows aligned so that unchanged lines are always on the same row
+#[derive(Resource, Default)]
+pub struct DiffView {
+	pub active			: bool,
+	pub request			: Option<DiffRequest>,
+	pub pending			: SharedDiffRequest,
+
+	pub left			: DiffSide,
+	pub right			: DiffSide,
+	pub alignment		: Alignment,
+	pub loaded_versions	: (Option<usize>, Option<usize>),
+	pub align_task		: Option<AlignTask>,
+
+	pub scroll_row		: usize, // where diff view wants to be, camera scrolls towards it
+	pub top_row			: usize, // what camera shows right now
+	pub visible_rows	: usize,
+}
+
+pub fn diff_view_inactive(diff_view: Res<DiffView>) -> bool {
+	!diff_view.active
+}
+
+impl DiffView {
+	pub fn open(&mut self, request: DiffRequest, editor: &Editor) -> Result<(), String> {
+		self.left	= DiffSide::new(request.left.title(editor), request.left.load(editor)?);
+		self.right	= DiffSide::new(request.right.title(editor), request.right.load(editor)?);
+
+		self.loaded_versions	= (request.left.version(editor), request.right.version(editor));
+		self.align_task			= None;
+		self.alignment			= Alignment::new(&self.left.text, &self.right.text);
+
+		self.active		= true;
+		self.request	= Some(request);
+		self.scroll_row	= self.alignment.hunks.first().map_or(0, |row| row.saturating_sub(HUNK_CONTEXT_ROWS));
+
+		Ok(())
+	}
+
+	pub fn close(&mut self) {
+		self.active		= false;
+		self.request	= None;
+		self.left		= DiffSide::default();
+		self.right		= DiffSide::default();
+		self.alignment	= Alignment::default();
+		self.align_task	= None;
+	}
+
+	// working copy side is realigned in background after edits, one alignment at a time so that
+	// fast typing only realigns the latest version. Scroll position is kept
+	pub fn reload_if_changed(&mut self, editor: &Editor) -> Result<(), String> {
+		let Some(request) = self.request.as_ref() else { return Ok(()) };
+
+		if let Some(mut align_task) = self.align_task.take() {
+			match future::block_on(future::poll_once(&mut align_task.task)) {
+				Some((left, right, alignment)) => {
+					self.left				= left;
+					self.right				= right;
+					self.alignment			= alignment;
+					self.loaded_versions	= align_task.versions;
+
+					self.scroll_row = self.scroll_row.min(self.last_row());
+				},
+				None => {
+					self.align_task = Some(align_task);
+					return Ok(());
+				}
+			}
+		}
+
+		let versions = (request.left.version(editor), request.right.version(editor));
+
+		if versions == self.loaded_versions { return Ok(()) }
+
+		// only working copy sides change, revisions and files are kept as loaded
+		let left_text	= if versions.0 != self.loaded_versions.0 { request.left.load(editor)? } else { self.left.text.clone() };
+		let right_text	= if versions.1 != self.loaded_versions.1 { request.right.load(editor)? } else { self.right.text.clone() };
+
+		let left_title	= request.left.title(editor);
+		let right_title	= request.right.title(editor);
+
+		let task = AsyncComputeTaskPool::get().spawn(async move {
+			let alignment = Alignment::new(&left_text, &right_text);
+
+			(DiffSide::new(left_title, left_text), DiffSide::new(right_title, right_text), alignment)
+		});
+
+		self.align_task = Some(AlignTask { versions, task });
+
+		Ok(())
+	}
+
+	pub fn last_row(&self) -> usize {
+		self.alignment.rows.len().saturating_sub(1)
+	}
+
+	pub fn scroll(&mut self, delta: i32) {
+		self.scroll_row = (self.scroll_row as i64 + delta as i64).clamp(0, self.last_row() as i64) as usize;
+	}
+
+	// current hunk is the one a few rows below the top, where jumping to it puts it
+	pub fn goto_next_hunk(&mut self) {
+		if let Some(row) = self.alignment.next_hunk(self.scroll_row + HUNK_CONTEXT_ROWS) {
+			self.scroll_row = row.saturating_sub(HUNK_CONTEXT_ROWS);
+		}
+	}
+
+	pub fn goto_prev_hunk(&mut self) {
+		if let Some(row) = self.alignment.prev_hunk(self.scroll_row + HUNK_CONTEXT_ROWS) {
+			self.scroll_row = row.saturating_sub(HUNK_CONTEXT_ROWS);
+		}
+	}
+
+	pub fn update_surface(
+		&mut self,
+		surfaces_helix	: &mut SurfacesMapHelix,
+		app				: &HelixApp,
+	) {
+		let Some(surface_editor) = surfaces_helix.get(EditorView::ID) else { return };
+		let view_area = app.views().iter().find(|view_desc| view_desc.focused).map_or(surface_editor.area, |view_desc| view_desc.area);
+
+		if !self.active || view_area.width < MIN_PANE_COLUMNS * 2 || view_area.height < 2 {
+			surfaces_helix.remove(DIFF_LEFT_SURFACE_NAME);
+			surfaces_helix.remove(DIFF_RIGHT_SURFACE_NAME);
+			return;
+		}
+
+		let left_width	= view_area.width / 2;
+		let left_area	= Rect::new(view_area.x, view_area.y, left_width, view_area.height);
+		let right_area	= Rect::new(view_area.x + left_width, view_area.y, view_area.width - left_width, view_area.height);
+
+		// first row is taken by titles
+		self.visible_rows = view_area.height as usize - 1;
+
+		let theme = &app.editor.theme;
+		let number_width = self.left.lines.len().max(self.right.lines.len()).to_string().len();
+
+		for (name, area, side, is_left) in [
+			(DIFF_LEFT_SURFACE_NAME, left_area, &self.left, true),
+			(DIFF_RIGHT_SURFACE_NAME, right_area, &self.right, false),
+		] {
+			let surface = surfaces_helix.entry(String::from(name)).or_insert_with(|| {
+				SurfaceHelix::empty_with_spatial(area, SurfaceFlags::default())
+			});
+
+			if surface.area != area {
+				surface.resize(area);
+			}
+
+			surface.placement = SurfacePlacement::AreaCoordinates;
+			surface.reset();
+
+			let other_side = if is_left { &self.right } else { &self.left };
+
+			render_pane(surface, area, side, other_side, is_left, &self.alignment.rows, self.top_row, number_width, app.current_document().tab_width(), theme);
+		}
+	}
+}
+
+// mixes diff color into background so that text stays readable. Themes without rgb colors get colored text instead
+pub fn tinted_style(theme: &Theme, scope: &str, amount: f32) -> Style {
+	let background	= theme.get("ui.background").bg;
+	let color		= theme.get(scope).fg;
+
+	match (color, background) {
+		(Some(HelixColor::Rgb(r, g, b)), Some(HelixColor::Rgb(br, bg, bb))) => {
+			let mix = |c: u8, bc: u8| (bc as f32 + (c as f32 - bc as f32) * amount) as u8;
+			Style::default().bg(HelixColor::Rgb(mix(r, br), mix(g, bg), mix(b, bb)))
+		},
+		(Some(color), _) => Style::default().fg(color),
+		_ => Style::default(),
+	}
+}
+
+#[allow(clippy::too_many_arguments)]
+fn render_pane(
+	surface			: &mut SurfaceHelix,
+	area			: Rect,
+	side			: &DiffSide,
+	other_side		: &DiffSide,
+	is_left			: bool,
+	rows			: &[AlignedRow],
+	top_row			: usize,
+	number_width	: usize,
+	tab_width		: usize,
+	theme			: &Theme,
+) {
+	let background		= theme.get("ui.background");
+	let text_style		= background.patch(theme.get("ui.text"));
+	let title_style		= background.patch(theme.get("ui.statusline"));
+	let number_style	= background.patch(theme.get("ui.linenr"));
+	let filler_style	= background.patch(theme.get("ui.virtual.whitespace"));
+
+	let changed_scope	= if is_left { "diff.minus" } else { "diff.plus" };
+	let changed_style	= text_style.patch(tinted_style(theme, changed_scope, 0.2));
+	let modified_style	= text_style.patch(tinted_style(theme, "diff.delta", 0.2));
+	let intraline_style	= text_style.patch(tinted_style(theme, changed_scope, 0.5));
+
+	surface.set_style(area, background);
+
+	surface.set_style(Rect::new(area.x, area.y, area.width, 1), title_style);
+	surface.set_stringn(area.x, area.y, format!(" {}", side.title), area.width as usize, title_style);
+
+	let tab			= " ".repeat(tab_width);
+	let text_x		= area.x + number_width as u16 + 1;
+	let text_width	= area.right().saturating_sub(text_x) as usize;
+
+	let expand = |line: &str| -> String { line.replace('\t', tab.as_str()) };
+
+	for (index, row) in rows.iter().skip(top_row).take(area.height as usize - 1).enumerate() {
+		let y = area.y + 1 + index as u16;
+		let row_area = Rect::new(area.x, y, area.width, 1);
+
+		let (line, other_line) = if is_left { (row.left, row.right) } else { (row.right, row.left) };
+
+		let Some(line) = line else {
+			surface.set_stringn(area.x, y, "╱".repeat(area.width as usize), area.width as usize, filler_style);
+			continue;
+		};
+
+		let row_style = match row.kind {
+			RowKind::Equal						=> text_style,
+			RowKind::Modified					=> modified_style,
+			RowKind::Removed | RowKind::Added	=> changed_style,
+		};
+
+		surface.set_style(row_area, row_style);
+		surface.set_stringn(area.x, y, format!("{:>width$} ", line + 1, width = number_width), number_width + 1, number_style);
+
+		let text = expand(side.lines.get(line).map_or("", |line| line.as_str()));
+		surface.set_stringn(text_x, y, text.as_str(), text_width, row_style);
+
+		if row.kind != RowKind::Modified { continue }
+
+		let Some(other_line) = other_line else { continue };
+		let other_text = expand(other_side.lines.get(other_line).map_or("", |line| line.as_str()));
+
+		let (left_range, right_range) = if is_left { intraline_ranges(&text, &other_text) } else { intraline_ranges(&other_text, &text) };
+		let range = if is_left { left_range } else { right_range };
+
+		if range.start >= text_width || range.is_empty() { continue }
+
+		let changed : String = text.chars().skip(range.start).take(range.len()).collect();
+		surface.set_stringn(text_x + range.start as u16, y, changed.as_str(), text_width - range.start, intraline_style);
+	}
+}
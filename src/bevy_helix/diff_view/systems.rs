@@ -0,0 +1,107 @@
+use bevy :: prelude :: *;
+
+#[cfg(feature = "tracing")]
+use bevy_puffin :: *;
+
+use super :: *;
+
+use crate :: bevy_helix :: KeyboardInputGuard;
+
+// ctrl+alt+v toggles diff of working copy against HEAD, ctrl+alt+shift+v asks what to compare.
+// While diff is shown Helix gets no input: escape or q closes it, n/p or ]/[ jump between hunks
+pub fn input_keyboard(
+		key			: Res<Input<KeyCode>>,
+	mut input_guard	: ResMut<KeyboardInputGuard>,
+	mut diff_view	: ResMut<DiffView>,
+		app_option	: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	let ctrl_pressed	= key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl);
+	let alt_pressed		= key.pressed(KeyCode::LAlt) || key.pressed(KeyCode::RAlt);
+	let shift_pressed	= key.pressed(KeyCode::LShift) || key.pressed(KeyCode::RShift);
+
+	if ctrl_pressed && alt_pressed && key.just_pressed(KeyCode::V) {
+		if diff_view.active {
+			diff_view.close();
+		} else if shift_pressed {
+			app.prompt_diff_sources(diff_view.pending.clone());
+		} else {
+			let doc_id = app.current_document().id();
+
+			match parse_request("", &app.editor, doc_id) {
+				Ok(request) => *diff_view.pending.lock().unwrap() = Some(request),
+				Err(e) => app.editor.set_error(e),
+			}
+		}
+
+		app.request_render();
+		return;
+	}
+
+	if !diff_view.active { return }
+
+	let page = diff_view.visible_rows.max(1) as i32;
+
+	if key.just_pressed(KeyCode::Escape) || key.just_pressed(KeyCode::Q) {
+		diff_view.close();
+
+		// closing key is still buffered and Helix would get it next frame
+		input_guard.consume();
+	} else if (key.just_pressed(KeyCode::N) && !shift_pressed) || key.just_pressed(KeyCode::BracketRight) {
+		diff_view.goto_next_hunk();
+	} else if key.just_pressed(KeyCode::N) || key.just_pressed(KeyCode::P) || key.just_pressed(KeyCode::BracketLeft) {
+		diff_view.goto_prev_hunk();
+	} else if key.just_pressed(KeyCode::J) || key.just_pressed(KeyCode::Down) {
+		diff_view.scroll(1);
+	} else if key.just_pressed(KeyCode::K) || key.just_pressed(KeyCode::Up) {
+		diff_view.scroll(-1);
+	} else if key.just_pressed(KeyCode::PageDown) {
+		diff_view.scroll(page);
+	} else if key.just_pressed(KeyCode::PageUp) {
+		diff_view.scroll(-page);
+	} else if key.just_pressed(KeyCode::Home) {
+		diff_view.scroll_row = 0;
+	} else if key.just_pressed(KeyCode::End) {
+		diff_view.scroll_row = diff_view.last_row();
+	} else {
+		return;
+	}
+
+	app.request_render();
+}
+
+// opens requested diff and keeps working copy side in sync with edits
+pub fn update(
+	mut diff_view	: ResMut<DiffView>,
+		app_option	: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	profile_function!();
+
+	let request = diff_view.pending.lock().unwrap().take();
+
+	if let Some(request) = request {
+		match diff_view.open(request, &app.editor) {
+			Ok(_) if diff_view.alignment.hunks.is_empty() => app.editor.set_status("No differences"),
+			Ok(_) => (),
+			Err(e) => app.editor.set_error(format!("Failed to open diff: {}", e)),
+		}
+
+		app.request_render();
+		return;
+	}
+
+	if !diff_view.active { return }
+
+	if let Err(e) = diff_view.reload_if_changed(&app.editor) {
+		app.editor.set_error(format!("Diff closed: {}", e));
+		diff_view.close();
+		app.request_render();
+	}
+}
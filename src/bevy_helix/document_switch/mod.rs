@@ -0,0 +1,49 @@
+use bevy :: prelude :: *;
+use bevy :: utils :: HashMap;
+
+use helix_view :: { DocumentId, ViewId };
+
+pub mod systems;
+
+pub const TRANSITION_DURATION_MS : u64 = 250;
+
+// camera and Helix viewport state of a document in a view at the moment it was last visible
+#[derive(Clone, Copy, Debug)]
+pub struct DocumentViewport {
+	pub row_offset			: usize,
+	pub camera_row_offset	: u32,
+	pub column				: usize,
+	pub zoom				: f32,
+}
+
+#[derive(Resource, Default)]
+pub struct DocumentViewports {
+	viewports	: HashMap<(DocumentId, ViewId), DocumentViewport>,
+	active		: Option<(DocumentId, ViewId)>,
+}
+
+impl DocumentViewports {
+	pub fn remember(&mut self, doc_id: DocumentId, view_id: ViewId, viewport: DocumentViewport) {
+		self.viewports.insert((doc_id, view_id), viewport);
+	}
+
+	pub fn get(&self, doc_id: DocumentId, view_id: ViewId) -> Option<&DocumentViewport> {
+		self.viewports.get(&(doc_id, view_id))
+	}
+
+	// returns true if active document or view is different from the one seen last time
+	pub fn set_active(&mut self, doc_id: DocumentId, view_id: ViewId) -> bool {
+		let changed = self.active.is_some() && self.active != Some((doc_id, view_id));
+		self.active = Some((doc_id, view_id));
+		changed
+	}
+
+	// forget documents and views that were closed
+	pub fn retain(&mut self, mut is_open: impl FnMut(&DocumentId, &ViewId) -> bool) {
+		self.viewports.retain(|(doc_id, view_id), _| is_open(doc_id, view_id));
+	}
+}
+
+// quad in front of editor text that fades out revealing freshly switched document
+#[derive(Component)]
+pub struct DocumentTransitionCurtain;
@@ -0,0 +1,140 @@
+use bevy :: prelude :: *;
+use bevy_tweening :: *;
+use bevy_reader_camera :: ReaderCamera;
+
+#[cfg(feature = "tracing")]
+use bevy_puffin :: *;
+
+use helix_term :: ui :: EditorView;
+use helix_view :: graphics :: Color as HelixColor;
+
+use std :: time :: Duration;
+
+use super :: *;
+
+use crate :: {
+	z_order,
+	kodiki_ui :: tween_lens :: StandardMaterialAlphaLens,
+	bevy_ab_glyph :: { ABGlyphFont, ABGlyphFonts, FontAssetHandles },
+	bevy_helix :: {
+		HelixApp,
+		surface :: SurfacesMapBevy,
+		utils :: color_from_helix,
+	},
+};
+
+// runs before camera_update and render so switched document is rendered right away with its remembered viewport
+pub fn switch_document_viewport(
+	mut q_camera			: Query<&mut ReaderCamera>,
+	mut viewports			: ResMut<DocumentViewports>,
+		surfaces_bevy		: Res<SurfacesMapBevy>,
+		font_assets			: Res<Assets<ABGlyphFont>>,
+		font_handles		: Res<FontAssetHandles>,
+	mut mesh_assets			: ResMut<Assets<Mesh>>,
+	mut material_assets		: ResMut<Assets<StandardMaterial>>,
+	mut commands			: Commands,
+		app_option			: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	profile_function!();
+
+	let Ok(mut reader_camera) = q_camera.get_single_mut() else { return };
+
+	let (doc_id, view_id) = {
+		let (view, doc) = app.current_ref();
+		(doc.id(), view.id)
+	};
+
+	if !viewports.set_active(doc_id, view_id) {
+		// same document: keep its viewport up to date to restore it later
+		viewports.remember(doc_id, view_id, DocumentViewport {
+			row_offset			: app.row_offset_internal(),
+			camera_row_offset	: reader_camera.row_offset_out(),
+			column				: reader_camera.column,
+			zoom				: reader_camera.zoom,
+		});
+
+		return;
+	}
+
+	viewports.retain(|doc_id, view_id| app.editor.documents.contains_key(doc_id) && app.editor.tree.contains(*view_id));
+
+	if let Some(viewport) = viewports.get(doc_id, view_id).copied() {
+		app.set_row_offset_internal(viewport.row_offset);
+		app.set_row_offset_external(viewport.camera_row_offset as usize);
+
+		reader_camera.set_row_offset_in(viewport.camera_row_offset);
+		reader_camera.column	= viewport.column;
+		reader_camera.zoom		= viewport.zoom;
+
+		// document was opened at a specific location (goto definition, picker with preview position etc):
+		// Helix brings cursor back into view and jump animation travels there from the restored viewport
+		app.ensure_cursor_in_view();
+	}
+
+	// cover abrupt respawn of all words with a curtain fading out
+	let Some(surface_editor) = surfaces_bevy.get(EditorView::ID) else { return };
+
+	let fonts			= ABGlyphFonts::new(&font_assets, &font_handles);
+	let row_height		= fonts.main.vertical_advance();
+	let column_width	= fonts.main.horizontal_advance_mono();
+
+	let curtain_size	= Vec2::new(
+		surface_editor.area.width as f32 * column_width,
+		surface_editor.area.height as f32 * row_height
+	);
+
+	let curtain_position = Vec3::new(
+		curtain_size.x / 2.0,
+		-((app.row_offset_internal() as f32 * row_height) + curtain_size.y / 2.0),
+		z_order::surface::transition()
+	);
+
+	let background_style = app.editor.theme.get("ui.background");
+	let background_color = color_from_helix(background_style.bg.unwrap_or(HelixColor::Black));
+
+	// material is not cached because its alpha is animated
+	let material_handle = material_assets.add(
+		StandardMaterial {
+			base_color	: background_color,
+			alpha_mode	: AlphaMode::Blend,
+			unlit		: true,
+			..default()
+		}
+	);
+
+	let tween_fade = Tween::new(
+		EaseFunction::QuadraticOut,
+		Duration::from_millis(TRANSITION_DURATION_MS),
+		StandardMaterialAlphaLens {
+			start	: 1.0,
+			end		: 0.0
+		}
+	);
+
+	let curtain_entity = commands.spawn((
+		PbrBundle {
+			mesh		: mesh_assets.add(shape::Quad::new(curtain_size).into()),
+			material	: material_handle.clone_weak(),
+			transform	: Transform::from_translation(curtain_position),
+			..default()
+		},
+		AssetAnimator::new(material_handle, tween_fade),
+		DocumentTransitionCurtain,
+	)).id();
+
+	commands.entity(surface_editor.entity).add_child(curtain_entity);
+}
+
+pub fn despawn_transition_curtain(
+		q_curtain	: Query<Entity, (With<DocumentTransitionCurtain>, Without<AssetAnimator<StandardMaterial>>)>,
+	mut commands	: Commands,
+) {
+	// finished material animators are removed in animations_cleanup_components so curtain without one has faded out
+	for entity in q_curtain.iter() {
+		commands.entity(entity).despawn_recursive();
+	}
+}
@@ -0,0 +1,909 @@
+use bevy :: prelude :: *;
+use bevy :: tasks :: Task;
+use bevy :: utils :: { HashMap, HashSet };
+
+use helix_lsp :: lsp;
+use helix_view :: { Editor, Theme, graphics :: Style };
+
+use std :: {
+	fs,
+	path :: { Component, Path, PathBuf },
+	process :: Command,
+	sync :: { Arc, Mutex },
+};
+
+use crate :: {
+	bevy_ab_glyph :: ABGlyphFont,
+	kodiki_ui :: {
+		text_surface			:: { TextSurface, TextSurfaceAnchor, TextSurfacePlacement, TextSurfaceCellCluster },
+		text_background_quad	:: TextBackgroundQuad,
+		raypick					:: RaypickHover,
+	},
+};
+
+use super :: {
+	git_history :: repository_root,
+	surface :: FILE_EXPLORER_SURFACE_NAME,
+	utils :: color_from_helix,
+};
+
+pub mod systems;
+
+pub const EXPLORER_COLUMNS	: usize	= 48;
+pub const GAP_COLUMNS		: usize	= 2; // between explorer and editor
+pub const HEADER_ROWS		: usize	= 2; // workspace name and buttons stay on top while tree scrolls
+pub const INDENT_COLUMNS	: usize	= 2;
+pub const MAX_ENTRIES		: usize	= 20000;
+pub const REFRESH_SECONDS	: f32	= 2.0; // tree is rescanned periodically to pick up changes made outside of editor
+pub const WHEEL_ROWS		: i32	= 3;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ExplorerOperation {
+	NewFile,
+	NewDirectory,
+	Rename,
+	Move,
+	Delete,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ExplorerButton {
+	Operation(ExplorerOperation),
+	Reveal,
+}
+
+const BUTTONS : [(&str, ExplorerButton); 6] = [
+	("[new]",		ExplorerButton::Operation(ExplorerOperation::NewFile)),
+	("[dir]",		ExplorerButton::Operation(ExplorerOperation::NewDirectory)),
+	("[rename]",	ExplorerButton::Operation(ExplorerOperation::Rename)),
+	("[move]",		ExplorerButton::Operation(ExplorerOperation::Move)),
+	("[delete]",	ExplorerButton::Operation(ExplorerOperation::Delete)),
+	("[reveal]",	ExplorerButton::Reveal),
+];
+
+// text entered in Helix prompt along with operation and path it was asked for
+pub type SharedExplorerInput = Arc<Mutex<Option<(ExplorerOperation, PathBuf, String)>>>;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GitMark {
+	Modified,
+	Added,
+	Untracked,
+	Deleted,
+	Conflict,
+	Ignored,
+}
+
+#[derive(Default)]
+pub struct GitStatus {
+	files	: HashMap<PathBuf, GitMark>,
+	dirs	: Vec<(PathBuf, GitMark)>, // untracked and ignored directories are reported as a whole
+}
+
+impl GitStatus {
+	pub fn load(dir: &Path) -> Self {
+		let mut status = Self::default();
+
+		let Ok(root) = repository_root(dir) else { return status };
+
+		let Ok(output) = Command::new("git")
+			.args(["status", "--porcelain=v1", "-z", "--ignored", "--untracked-files=normal"])
+			.current_dir(&root)
+			.output()
+		else {
+			return status
+		};
+
+		if !output.status.success() { return status }
+
+		let stdout = String::from_utf8_lossy(&output.stdout);
+		let mut records = stdout.split('\0');
+
+		while let Some(record) = records.next() {
+			if record.len() < 4 { continue }
+
+			let code = &record[..2];
+			let path = record[3..].trim_end_matches('/');
+
+			// renamed and copied entries are followed by the original path
+			if code.starts_with('R') || code.starts_with('C') {
+				records.next();
+			}
+
+			let mark = match code {
+				"!!" => GitMark::Ignored,
+				"??" => GitMark::Untracked,
+				"DD" | "AA" | "UU" | "AU" | "UA" | "DU" | "UD" => GitMark::Conflict,
+				_ if code.contains('D') => GitMark::Deleted,
+				_ if code.starts_with('A') => GitMark::Added,
+				_ => GitMark::Modified,
+			};
+
+			let path = root.join(path);
+
+			if record.ends_with('/') {
+				status.dirs.push((path, mark));
+			} else {
+				status.files.insert(path, mark);
+			}
+		}
+
+		status
+	}
+
+	pub fn mark(&self, path: &Path, is_dir: bool) -> Option<GitMark> {
+		if let Some(mark) = self.files.get(path) {
+			return Some(*mark);
+		}
+
+		if let Some((_, mark)) = self.dirs.iter().find(|(dir, _)| path.starts_with(dir)) {
+			return Some(*mark);
+		}
+
+		if !is_dir {
+			return None;
+		}
+
+		// directory is as changed as its most important descendant
+		let mut changed = false;
+
+		let descendants = self.files.iter()
+			.chain(self.dirs.iter().map(|(dir, mark)| (dir, mark)))
+			.filter(|(descendant, mark)| **mark != GitMark::Ignored && descendant.starts_with(path))
+		;
+
+		for (_, mark) in descendants {
+			if *mark == GitMark::Conflict {
+				return Some(GitMark::Conflict);
+			}
+
+			changed = true;
+		}
+
+		changed.then_some(GitMark::Modified)
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct ExplorerEntry {
+	pub path	: PathBuf,
+	pub name	: String,
+	pub depth	: usize,
+	pub is_dir	: bool,
+	pub git		: Option<GitMark>,
+}
+
+// directories first, both sorted by name. Only expanded directories are listed
+pub fn scan_tree(root: &Path, expanded: &HashSet<PathBuf>) -> Vec<ExplorerEntry> {
+	let git = GitStatus::load(root);
+
+	let mut entries = Vec::new();
+
+	scan_directory(root, 0, expanded, &git, &mut entries);
+
+	entries
+}
+
+fn scan_directory(dir: &Path, depth: usize, expanded: &HashSet<PathBuf>, git: &GitStatus, entries: &mut Vec<ExplorerEntry>) {
+	let Ok(read_dir) = fs::read_dir(dir) else { return };
+
+	let mut children : Vec<(String, PathBuf, bool)> = read_dir
+		.filter_map(|entry| entry.ok())
+		.map(|entry| (entry.file_name().to_string_lossy().to_string(), entry.path()))
+		.filter(|(name, _)| name != ".git")
+		.map(|(name, path)| { let is_dir = path.is_dir(); (name, path, is_dir) })
+		.collect();
+
+	children.sort_by(|(name_a, _, dir_a), (name_b, _, dir_b)| {
+		dir_b.cmp(dir_a).then_with(|| name_a.to_lowercase().cmp(&name_b.to_lowercase()))
+	});
+
+	for (name, path, is_dir) in children {
+		if entries.len() >= MAX_ENTRIES { return }
+
+		let expand = is_dir && expanded.contains(&path);
+
+		entries.push(ExplorerEntry {
+			git		: git.mark(&path, is_dir),
+			path	: path.clone(),
+			name,
+			depth,
+			is_dir,
+		});
+
+		if expand {
+			scan_directory(&path, depth + 1, expanded, git, entries);
+		}
+	}
+}
+
+// errors and warnings per file and summed up for every directory above it
+fn diagnostics_counts(editor: &Editor, root: &Path) -> HashMap<PathBuf, (usize, usize)> {
+	let mut counts : HashMap<PathBuf, (usize, usize)> = HashMap::default();
+
+	for (url, diagnostics) in editor.diagnostics.iter() {
+		let Ok(path) = url.to_file_path() else { continue };
+
+		let errors		= diagnostics.iter().filter(|diagnostic| diagnostic.severity == Some(lsp::DiagnosticSeverity::ERROR)).count();
+		let warnings	= diagnostics.iter().filter(|diagnostic| diagnostic.severity == Some(lsp::DiagnosticSeverity::WARNING)).count();
+
+		if errors + warnings == 0 { continue }
+
+		for ancestor in path.ancestors() {
+			if ancestor == root || !ancestor.starts_with(root) { break }
+
+			let count = counts.entry(ancestor.to_path_buf()).or_default();
+			count.0 += errors;
+			count.1 += warnings;
+		}
+	}
+
+	counts
+}
+
+fn diagnostics_fingerprint(editor: &Editor) -> (usize, usize) {
+	(editor.diagnostics.len(), editor.diagnostics.values().map(|diagnostics| diagnostics.len()).sum())
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ExplorerCell {
+	text		: String,
+	row			: usize,
+	col			: usize,
+	foreground	: Color,
+	background	: Color,
+}
+
+impl TextSurfaceCellCluster for ExplorerCell {
+	fn text(&self) -> &str {
+		self.text.as_str()
+	}
+
+	fn row(&self) -> usize {
+		self.row
+	}
+
+	fn col(&self) -> usize {
+		self.col
+	}
+
+	fn foreground(&self) -> Color {
+		self.foreground
+	}
+
+	fn background(&self) -> Color {
+		self.background
+	}
+}
+
+#[derive(Clone, Copy)]
+struct ExplorerColors {
+	background	: Color,
+	text		: Color,
+	dim			: Color,
+	header		: Color,
+	button		: Color,
+	selected	: Color,
+	modified	: Color,
+	added		: Color,
+	deleted		: Color,
+	error		: Color,
+	warning		: Color,
+}
+
+impl ExplorerColors {
+	fn new(theme: &Theme) -> Self {
+		let background	= theme.get("ui.background").patch(theme.get("ui.popup"));
+		let text		= background.patch(theme.get("ui.text"));
+
+		let fg = |style: Style| style.fg.map_or(Color::WHITE, color_from_helix);
+		let bg = |style: Style| style.bg.map_or(Color::BLACK, color_from_helix);
+
+		Self {
+			background	: bg(background),
+			text		: fg(text),
+			dim			: fg(text.patch(theme.get("ui.linenr"))),
+			header		: fg(text.patch(theme.get("ui.text.focus"))),
+			button		: fg(text.patch(theme.get("ui.menu"))),
+			selected	: bg(background.patch(theme.get("ui.menu.selected"))),
+			modified	: fg(text.patch(theme.get("diff.delta"))),
+			added		: fg(text.patch(theme.get("diff.plus"))),
+			deleted		: fg(text.patch(theme.get("diff.minus"))),
+			error		: fg(text.patch(theme.get("error"))),
+			warning		: fg(text.patch(theme.get("warning"))),
+		}
+	}
+
+	fn git(&self, mark: Option<GitMark>) -> Color {
+		match mark {
+			None						=> self.text,
+			Some(GitMark::Modified)		=> self.modified,
+			Some(GitMark::Added)		=> self.added,
+			Some(GitMark::Untracked)	=> self.added,
+			Some(GitMark::Deleted)		=> self.deleted,
+			Some(GitMark::Conflict)		=> self.error,
+			Some(GitMark::Ignored)		=> self.dim,
+		}
+	}
+}
+
+struct RowBuilder {
+	cells		: Vec<ExplorerCell>,
+	row			: usize,
+	background	: Color,
+}
+
+impl RowBuilder {
+	fn new(row: usize, background: Color) -> Self {
+		Self { cells: Vec::with_capacity(EXPLORER_COLUMNS), row, background }
+	}
+
+	fn push(&mut self, text: &str, foreground: Color) {
+		for symbol in text.chars() {
+			if self.cells.len() >= EXPLORER_COLUMNS { return }
+
+			self.cells.push(ExplorerCell {
+				text		: symbol.to_string(),
+				row			: self.row,
+				col			: self.cells.len(),
+				foreground,
+				background	: self.background,
+			});
+		}
+	}
+
+	// every column is filled so that the last word in row gets flushed and background spans the whole row
+	fn finish(mut self, foreground: Color) -> Vec<ExplorerCell> {
+		while self.cells.len() < EXPLORER_COLUMNS {
+			self.push(" ", foreground);
+		}
+
+		self.cells
+	}
+}
+
+fn truncate(text: &str, columns: usize) -> String {
+	if text.chars().count() <= columns {
+		return text.to_string();
+	}
+
+	let mut truncated : String = text.chars().take(columns.saturating_sub(1)).collect();
+	truncated.push('…');
+	truncated
+}
+
+#[derive(Clone, PartialEq)]
+struct ContentKey {
+	scan_version	: usize,
+	scroll			: usize,
+	rows			: usize,
+	selected		: Option<PathBuf>,
+	diagnostics		: (usize, usize),
+	theme			: String,
+}
+
+#[derive(Resource)]
+pub struct FileExplorer {
+	pub visible			: bool,
+	pub root			: PathBuf,
+	pub expanded		: HashSet<PathBuf>,
+	pub entries			: Vec<ExplorerEntry>,
+	pub selected		: Option<PathBuf>,
+	pub scroll			: usize,
+	pub visible_rows	: usize, // tree rows below header
+
+	pub scan_task		: Option<Task<Vec<ExplorerEntry>>>,
+	pub scan_requested	: bool,
+	pub scan_version	: usize,
+	pub since_scan		: f32,
+	pub reveal_requested: bool, // scroll to selected entry once the next scan is done
+
+	pub pending_input	: SharedExplorerInput,
+
+	pub entity			: Option<Entity>,
+	pub background_entity : Option<Entity>,
+	pub camera_column	: Option<usize>, // camera column set while explorer is shown
+
+		rendered		: Vec<Vec<ExplorerCell>>,
+		content_key		: Option<ContentKey>,
+}
+
+impl Default for FileExplorer {
+	fn default() -> Self {
+		let root = std::env::current_dir().unwrap_or_default();
+		let root = root.canonicalize().unwrap_or(root);
+
+		Self {
+			visible			: false,
+			root,
+			expanded		: HashSet::default(),
+			entries			: Vec::new(),
+			selected		: None,
+			scroll			: 0,
+			visible_rows	: 0,
+			scan_task		: None,
+			scan_requested	: true,
+			scan_version	: 0,
+			since_scan		: 0.0,
+			reveal_requested: false,
+			pending_input	: SharedExplorerInput::default(),
+			entity			: None,
+			background_entity : None,
+			camera_column	: None,
+			rendered		: Vec::new(),
+			content_key		: None,
+		}
+	}
+}
+
+impl FileExplorer {
+	pub fn spawn(&mut self, font: &ABGlyphFont, mesh_assets: &mut Assets<Mesh>, commands: &mut Commands) {
+		let camera_space	= false;
+		let fill_vertically	= false;
+		let top_anchor		= true;
+		let side_gap		= 0.05;
+
+		let background_entity = TextBackgroundQuad::spawn(
+			camera_space,
+			fill_vertically,
+			top_anchor,
+			side_gap,
+			font,
+			mesh_assets,
+			commands
+		);
+
+		let entity = commands.spawn((
+			TextSurface::new(
+				FILE_EXPLORER_SURFACE_NAME,
+				EXPLORER_COLUMNS,
+				TextSurfaceAnchor::Top,
+				TextSurfacePlacement::Center,
+				Some(background_entity),
+			),
+			TransformBundle::default(),
+			VisibilityBundle::default(),
+			RaypickHover::default(),
+		))
+		.id();
+
+		commands.entity(entity).push_children(&[background_entity]);
+
+		self.entity				= Some(entity);
+		self.background_entity	= Some(background_entity);
+		self.rendered.clear();
+		self.content_key		= None;
+	}
+
+	// returns entity to despawn recursively
+	pub fn take_entity(&mut self) -> Option<Entity> {
+		self.background_entity = None;
+		self.rendered.clear();
+		self.content_key = None;
+
+		self.entity.take()
+	}
+
+	pub fn is_shown(&self) -> bool {
+		self.visible && self.entity.is_some()
+	}
+
+	// columns taken from Helix editor while explorer is shown
+	pub fn reserved_columns(&self) -> usize {
+		if self.is_shown() { EXPLORER_COLUMNS + GAP_COLUMNS } else { 0 }
+	}
+
+	pub fn owns(&self, entity: Entity) -> bool {
+		Some(entity) == self.entity || Some(entity) == self.background_entity
+	}
+
+	pub fn request_scan(&mut self) {
+		self.scan_requested = true;
+	}
+
+	pub fn finish_scan(&mut self, entries: Vec<ExplorerEntry>) {
+		self.entries = entries;
+		self.scan_version += 1;
+		self.since_scan = 0.0;
+
+		if self.reveal_requested {
+			self.reveal_requested = false;
+			self.scroll_to_selected();
+		}
+
+		self.clamp_scroll();
+	}
+
+	// expands all directories down to the given path and selects it
+	pub fn reveal(&mut self, path: &Path) {
+		if !path.starts_with(&self.root) { return }
+
+		for ancestor in path.ancestors().skip(1) {
+			if ancestor == self.root || !ancestor.starts_with(&self.root) { break }
+
+			self.expanded.insert(ancestor.to_path_buf());
+		}
+
+		self.selected = Some(path.to_path_buf());
+		self.reveal_requested = true;
+		self.request_scan();
+	}
+
+	pub fn toggle_expanded(&mut self, path: &Path) {
+		if !self.expanded.remove(path) {
+			self.expanded.insert(path.to_path_buf());
+		}
+
+		self.request_scan();
+	}
+
+	fn scroll_to_selected(&mut self) {
+		let Some(selected) = self.selected.as_ref() else { return };
+		let Some(index) = self.entries.iter().position(|entry| &entry.path == selected) else { return };
+
+		if index < self.scroll || index >= self.scroll + self.visible_rows {
+			self.scroll = index.saturating_sub(self.visible_rows / 2);
+		}
+	}
+
+	fn clamp_scroll(&mut self) {
+		self.scroll = self.scroll.min(self.entries.len().saturating_sub(self.visible_rows.max(1)));
+	}
+
+	pub fn scroll_by(&mut self, delta: i32) {
+		self.scroll = (self.scroll as i32 + delta).max(0) as usize;
+		self.clamp_scroll();
+	}
+
+	pub fn set_visible_rows(&mut self, rows: usize) {
+		self.visible_rows = rows;
+		self.clamp_scroll();
+	}
+
+	pub fn entry_at_row(&self, row: usize) -> Option<&ExplorerEntry> {
+		if row < HEADER_ROWS { return None }
+
+		self.entries.get(self.scroll + row - HEADER_ROWS)
+	}
+
+	pub fn button_at(&self, row: usize, column: usize) -> Option<ExplorerButton> {
+		if row != 1 { return None }
+
+		// buttons are laid out the same way as in header row
+		let mut start = 1;
+
+		for (label, button) in BUTTONS {
+			let end = start + label.chars().count();
+			if (start..end).contains(&column) {
+				return Some(button);
+			}
+
+			start = end + 1;
+		}
+
+		None
+	}
+
+	pub fn selected_entry(&self) -> Option<&ExplorerEntry> {
+		let selected = self.selected.as_ref()?;
+		self.entries.iter().find(|entry| &entry.path == selected)
+	}
+
+	// new files and directories go into selected directory or next to selected file
+	pub fn target_directory(&self) -> PathBuf {
+		match self.selected_entry() {
+			Some(entry) if entry.is_dir => entry.path.clone(),
+			Some(entry) => entry.path.parent().map_or(self.root.clone(), |parent| parent.to_path_buf()),
+			None => self.root.clone(),
+		}
+	}
+
+	pub fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+		path.strip_prefix(&self.root).unwrap_or(path)
+	}
+
+	// new entries are created relative to target and can't end up outside of the workspace
+	pub fn new_entry_path(&self, target: &Path, input: &str) -> Result<PathBuf, String> {
+		let relative = Path::new(input);
+
+		if relative.components().any(|component| !matches!(component, Component::Normal(_) | Component::CurDir)) {
+			return Err(format!("{} has to be a path relative to selected directory without '..'", input));
+		}
+
+		let path = target.join(relative);
+
+		if !path.starts_with(&self.root) {
+			return Err(format!("{} is outside of workspace", path.display()));
+		}
+
+		Ok(path)
+	}
+
+	// returns path that should be selected after operation is done
+	pub fn apply_operation(&mut self, operation: ExplorerOperation, target: &Path, input: &str, editor: &mut Editor) -> Result<Option<PathBuf>, String> {
+		let input = input.trim();
+
+		if input.is_empty() {
+			return Ok(None);
+		}
+
+		let result = match operation {
+			ExplorerOperation::NewFile => {
+				let path = self.new_entry_path(target, input)?;
+
+				if let Some(parent) = path.parent() {
+					fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+				}
+
+				fs::OpenOptions::new().write(true).create_new(true).open(&path).map_err(|e| e.to_string())?;
+
+				Some(path)
+			},
+			ExplorerOperation::NewDirectory => {
+				let path = self.new_entry_path(target, input)?;
+
+				if path.exists() {
+					return Err(format!("{} already exists", self.relative(&path).display()));
+				}
+
+				fs::create_dir_all(&path).map_err(|e| e.to_string())?;
+
+				Some(path)
+			},
+			ExplorerOperation::Rename => {
+				if input.contains(std::path::MAIN_SEPARATOR) {
+					return Err("new name can't contain path separators, use move instead".into());
+				}
+
+				let Some(parent) = target.parent() else { return Err("can't rename workspace root".into()) };
+				let path = parent.join(input);
+
+				self.rename(target, &path, editor)?;
+
+				Some(path)
+			},
+			ExplorerOperation::Move => {
+				let mut path = PathBuf::from(input);
+				if path.is_relative() {
+					path = self.root.join(path);
+				}
+
+				// moving into existing directory keeps the name
+				if path.is_dir() {
+					let Some(name) = target.file_name() else { return Err("can't move workspace root".into()) };
+					path = path.join(name);
+				}
+
+				if path.starts_with(target) {
+					return Err("can't move directory into itself".into());
+				}
+
+				if let Some(parent) = path.parent() {
+					fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+				}
+
+				self.rename(target, &path, editor)?;
+
+				Some(path)
+			},
+			ExplorerOperation::Delete => {
+				if !input.eq_ignore_ascii_case("y") && !input.eq_ignore_ascii_case("yes") {
+					return Ok(None);
+				}
+
+				if target == self.root {
+					return Err("can't delete workspace root".into());
+				}
+
+				if target.is_dir() {
+					fs::remove_dir_all(target).map_err(|e| e.to_string())?;
+				} else {
+					fs::remove_file(target).map_err(|e| e.to_string())?;
+				}
+
+				close_documents_under(editor, target);
+
+				self.expanded.retain(|dir| !dir.starts_with(target));
+
+				target.parent().map(|parent| parent.to_path_buf())
+			},
+		};
+
+		self.request_scan();
+
+		Ok(result)
+	}
+
+	fn rename(&mut self, from: &Path, to: &Path, editor: &mut Editor) -> Result<(), String> {
+		if to.exists() {
+			return Err(format!("{} already exists", self.relative(to).display()));
+		}
+
+		fs::rename(from, to).map_err(|e| e.to_string())?;
+
+		retarget_documents(editor, from, to);
+
+		// expanded directories keep being expanded at the new place
+		let moved : Vec<PathBuf> = self.expanded.iter().filter(|dir| dir.starts_with(from)).cloned().collect();
+		for dir in moved {
+			self.expanded.remove(&dir);
+
+			let rest = dir.strip_prefix(from).unwrap_or(Path::new(""));
+			self.expanded.insert(to.join(rest));
+		}
+
+		Ok(())
+	}
+
+	pub fn update_surface(
+		&mut self,
+		editor		: &Editor,
+		rows		: usize,
+	) -> Option<Vec<(usize, Vec<ExplorerCell>)>> {
+		let content_key = ContentKey {
+			scan_version	: self.scan_version,
+			scroll			: self.scroll,
+			rows,
+			selected		: self.selected.clone(),
+			diagnostics		: diagnostics_fingerprint(editor),
+			theme			: editor.theme.name().to_string(),
+		};
+
+		if self.content_key.as_ref() == Some(&content_key) {
+			return None;
+		}
+
+		self.content_key = Some(content_key);
+
+		let new_rows = self.build_rows(editor, rows);
+
+		self.rendered.resize_with(rows, Vec::new);
+
+		let mut changed = Vec::new();
+
+		for (row_index, row) in new_rows.into_iter().enumerate() {
+			if self.rendered[row_index] == row { continue }
+
+			self.rendered[row_index] = row.clone();
+			changed.push((row_index, row));
+		}
+
+		Some(changed)
+	}
+
+	// row cache has to be rebuilt after text surface got resized
+	pub fn invalidate(&mut self) {
+		self.rendered.clear();
+		self.content_key = None;
+	}
+
+	pub fn background_color(&self, theme: &Theme) -> Color {
+		ExplorerColors::new(theme).background
+	}
+
+	fn build_rows(&self, editor: &Editor, rows: usize) -> Vec<Vec<ExplorerCell>> {
+		let colors = ExplorerColors::new(&editor.theme);
+		let diagnostics = diagnostics_counts(editor, &self.root);
+
+		let mut result = Vec::with_capacity(rows);
+
+		// workspace name
+		let mut title = RowBuilder::new(0, colors.background);
+		let root_name = self.root.file_name().map_or(self.root.to_string_lossy(), |name| name.to_string_lossy());
+		title.push(" ", colors.header);
+		title.push(&truncate(&root_name.to_uppercase(), EXPLORER_COLUMNS - 2), colors.header);
+		result.push(title.finish(colors.text));
+
+		// buttons
+		let mut buttons = RowBuilder::new(1, colors.background);
+		for (label, _) in BUTTONS {
+			buttons.push(" ", colors.button);
+			buttons.push(label, colors.button);
+		}
+		result.push(buttons.finish(colors.text));
+
+		for row_index in HEADER_ROWS..rows {
+			let Some(entry) = self.entry_at_row(row_index) else {
+				result.push(RowBuilder::new(row_index, colors.background).finish(colors.text));
+				continue;
+			};
+
+			let selected = self.selected.as_ref() == Some(&entry.path);
+			let background = if selected { colors.selected } else { colors.background };
+
+			let mut row = RowBuilder::new(row_index, background);
+
+			let indent = (entry.depth * INDENT_COLUMNS).min(EXPLORER_COLUMNS / 2);
+			row.push(&" ".repeat(indent + 1), colors.text);
+
+			let marker = match (entry.is_dir, self.expanded.contains(&entry.path)) {
+				(true, true)	=> "▾ ",
+				(true, false)	=> "▸ ",
+				(false, _)		=> "  ",
+			};
+			row.push(marker, colors.dim);
+
+			// diagnostics are right aligned and shorten the name when there is not enough room
+			let (errors, warnings) = diagnostics.get(&entry.path).copied().unwrap_or_default();
+
+			let mut counts = Vec::new();
+			if errors > 0 {
+				counts.push((format!("E{}", errors), colors.error));
+			}
+			if warnings > 0 {
+				counts.push((format!("W{}", warnings), colors.warning));
+			}
+
+			let counts_columns : usize = counts.iter().map(|(text, _)| text.chars().count() + 1).sum();
+			let name_columns = EXPLORER_COLUMNS.saturating_sub(row.cells.len() + counts_columns + 1);
+
+			row.push(&truncate(&entry.name, name_columns), colors.git(entry.git));
+
+			let padding = EXPLORER_COLUMNS.saturating_sub(row.cells.len() + counts_columns + 1);
+			row.push(&" ".repeat(padding), colors.text);
+
+			for (text, color) in counts.iter() {
+				row.push(" ", colors.text);
+				row.push(text, *color);
+			}
+
+			result.push(row.finish(colors.text));
+		}
+
+		result
+	}
+}
+
+fn retarget_documents(editor: &mut Editor, from: &Path, to: &Path) {
+	for doc in editor.documents_mut() {
+		let Some(path) = doc.path().cloned() else { continue };
+		let Ok(rest) = path.strip_prefix(from) else { continue };
+
+		let new_path = if rest.as_os_str().is_empty() { to.to_path_buf() } else { to.join(rest) };
+
+		if let Err(e) = doc.set_path(Some(&new_path)) {
+			log::warn!("failed to update document path {:?} -> {:?}: {}", path, new_path, e);
+		}
+	}
+}
+
+// modified documents stay open so that their content can still be saved somewhere
+fn close_documents_under(editor: &mut Editor, path: &Path) {
+	let doc_ids : Vec<_> = editor.documents()
+		.filter(|doc| !doc.is_modified() && doc.path().map_or(false, |doc_path| doc_path.starts_with(path)))
+		.map(|doc| doc.id())
+		.collect();
+
+	for doc_id in doc_ids {
+		let _ = editor.close_document(doc_id, false);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super :: *;
+
+	fn explorer() -> FileExplorer {
+		FileExplorer { root: PathBuf::from("/workspace"), ..default() }
+	}
+
+	#[test]
+	fn new_entry_goes_under_target() {
+		let explorer = explorer();
+		let target = Path::new("/workspace/src");
+
+		assert_eq!(explorer.new_entry_path(target, "main.rs"), Ok(PathBuf::from("/workspace/src/main.rs")));
+		assert_eq!(explorer.new_entry_path(target, "./a/b.rs"), Ok(PathBuf::from("/workspace/src/a/b.rs")));
+	}
+
+	#[test]
+	fn new_entry_can_not_leave_workspace() {
+		let explorer = explorer();
+		let target = Path::new("/workspace/src");
+
+		assert!(explorer.new_entry_path(target, "/etc/passwd").is_err());
+		assert!(explorer.new_entry_path(target, "../../outside.rs").is_err());
+		assert!(explorer.new_entry_path(target, "a/../../b.rs").is_err());
+		assert!(explorer.new_entry_path(Path::new("/elsewhere"), "a.rs").is_err());
+	}
+}
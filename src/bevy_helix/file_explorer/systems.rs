@@ -0,0 +1,407 @@
+use bevy :: prelude :: *;
+use bevy :: input :: mouse :: { MouseWheel, MouseScrollUnit };
+use bevy :: tasks :: AsyncComputeTaskPool;
+
+use bevy_reader_camera :: ReaderCamera;
+
+#[cfg(feature = "tracing")]
+use bevy_puffin :: *;
+
+use futures_lite :: future;
+
+use helix_term :: ui :: EditorView;
+
+use super :: *;
+
+use crate :: {
+	z_order,
+	kodiki :: { DespawnResource, DockLayout, DockSide },
+	kodiki_ui :: {
+		DraggingState,
+		raypick :: Raypick,
+		text_surface :: {
+			WordDescription,
+			WordsRow, ClusterRowState, WordSpawnInfo,
+			ColoringLineRow, ColoringLineRowState, ColoringLinesToSpawn,
+		},
+	},
+	bevy_ab_glyph :: { ABGlyphFont, ABGlyphFonts, FontAssetHandles },
+	bevy_helix :: {
+		HelixApp, TokioRuntime,
+		git_history :: GitHistory,
+		surface :: SurfacesMapBevy,
+	},
+};
+
+fn hovers_explorer(raypick: &Raypick, q_word: &Query<&WordDescription>, explorer: &FileExplorer) -> bool {
+	let Some(hovered_entity) = raypick.last_hover else { return false };
+
+	if let Ok(word) = q_word.get(hovered_entity) {
+		return word.surface_name == FILE_EXPLORER_SURFACE_NAME;
+	}
+
+	explorer.owns(hovered_entity)
+}
+
+// ctrl+shift+e shows explorer with current file revealed in it or hides it
+pub fn input_keyboard(
+		key			: Res<Input<KeyCode>>,
+	mut explorer	: ResMut<FileExplorer>,
+		app_option	: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() || !app.editor_focused() { return }
+
+	let ctrl_pressed	= key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl);
+	let shift_pressed	= key.pressed(KeyCode::LShift) || key.pressed(KeyCode::RShift);
+
+	if !ctrl_pressed || !shift_pressed || !key.just_pressed(KeyCode::E) { return }
+
+	explorer.visible = !explorer.visible;
+
+	if explorer.visible {
+		if let Some(path) = app.current_document().path().cloned() {
+			explorer.reveal(&path);
+		}
+	}
+
+	app.request_render();
+}
+
+// clicks on directories expand and collapse them, clicks on files open them. Header buttons ask for operation details in Helix prompt
+pub fn input_mouse(
+		mouse_button	: Res<Input<MouseButton>>,
+		raypick			: Res<Raypick>,
+		q_word			: Query<&WordDescription>,
+		q_transform		: Query<&GlobalTransform>,
+		font_assets		: Res<Assets<ABGlyphFont>>,
+		font_handles	: Res<FontAssetHandles>,
+	mut explorer		: ResMut<FileExplorer>,
+		dragging_state	: Res<DraggingState>,
+		tokio_runtime	: Res<TokioRuntime>,
+		app_option		: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() || dragging_state.is_active() || !explorer.is_shown() { return }
+
+	if !mouse_button.just_pressed(MouseButton::Left) { return }
+
+	if !hovers_explorer(&raypick, &q_word, &explorer) { return }
+
+	profile_function!();
+
+	let Some(explorer_entity) = explorer.entity else { return };
+	let Ok(explorer_transform) = q_transform.get(explorer_entity) else { return };
+
+	let font = font_assets.get(&font_handles.main).unwrap();
+	let row_height		= font.vertical_advance();
+	let column_width	= font.horizontal_advance_mono();
+
+	// world space to surface space, rows go down from the top anchor
+	let cursor_position_world	= raypick.ray_pos + raypick.ray_dir * raypick.ray_dist;
+	let cursor_position_surface	= explorer_transform.compute_matrix().inverse().transform_point3(cursor_position_world);
+
+	if cursor_position_surface.y > 0.0 || cursor_position_surface.x < 0.0 {
+		return;
+	}
+
+	let row		= (-cursor_position_surface.y / row_height) as usize;
+	let column	= (cursor_position_surface.x / column_width) as usize;
+
+	if let Some(button) = explorer.button_at(row, column) {
+		match button {
+			ExplorerButton::Reveal => {
+				if let Some(path) = app.current_document().path().cloned() {
+					explorer.reveal(&path);
+				}
+			},
+			ExplorerButton::Operation(operation) => {
+				let target = match operation {
+					ExplorerOperation::NewFile | ExplorerOperation::NewDirectory => Some(explorer.target_directory()),
+					_ => explorer.selected.clone(),
+				};
+
+				let Some(target) = target else {
+					app.editor.set_error("Select a file or directory in explorer first");
+					app.request_render();
+					return;
+				};
+
+				let relative = explorer.relative(&target).to_path_buf();
+				app.prompt_explorer_operation(operation, target, relative, explorer.pending_input.clone());
+			},
+		}
+
+		app.request_render();
+		return;
+	}
+
+	let Some(entry) = explorer.entry_at_row(row).cloned() else { return };
+
+	explorer.selected = Some(entry.path.clone());
+
+	if entry.is_dir {
+		explorer.toggle_expanded(&entry.path);
+	} else {
+		tokio_runtime.block_on(app.jump_to_path(&entry.path, None, None));
+	}
+
+	app.request_render();
+}
+
+// wheel over explorer scrolls the tree, Helix doesn't get those events
+pub fn input_scroll(
+	mut scroll_events	: EventReader<MouseWheel>,
+		raypick			: Res<Raypick>,
+		q_word			: Query<&WordDescription>,
+	mut explorer		: ResMut<FileExplorer>,
+) {
+	if !explorer.is_shown() || !hovers_explorer(&raypick, &q_word, &explorer) {
+		scroll_events.clear();
+		return;
+	}
+
+	let mut delta = 0;
+
+	for scroll_event in scroll_events.iter() {
+		let steps = match scroll_event.unit {
+			MouseScrollUnit::Line	=> scroll_event.y.signum() as i32,
+			MouseScrollUnit::Pixel	=> (scroll_event.y / 50.0) as i32,
+		};
+
+		delta -= steps * WHEEL_ROWS;
+	}
+
+	if delta == 0 { return }
+
+	explorer.scroll_by(delta);
+}
+
+// applies entered operations, keeps tree rescanned and spawns or despawns the panel. Panel makes room for git history while it's shown
+pub fn update(
+	mut explorer		: ResMut<FileExplorer>,
+		history			: Res<GitHistory>,
+		time			: Res<Time>,
+	mut mesh_assets		: ResMut<Assets<Mesh>>,
+		font_assets		: Res<Assets<ABGlyphFont>>,
+		font_handles	: Res<FontAssetHandles>,
+	mut despawn			: ResMut<DespawnResource>,
+	mut commands		: Commands,
+		tokio_runtime	: Res<TokioRuntime>,
+		app_option		: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	let should_be_shown = explorer.visible && !history.active;
+
+	if should_be_shown && explorer.entity.is_none() {
+		let font = font_assets.get(&font_handles.main).unwrap();
+		explorer.spawn(font, &mut mesh_assets, &mut commands);
+	} else if !should_be_shown {
+		if let Some(entity) = explorer.take_entity() {
+			despawn.recursive.push(entity);
+
+			// git history takes over camera so there is nothing to restore afterwards
+			if history.active {
+				explorer.camera_column = None;
+			}
+		}
+	}
+
+	let input = explorer.pending_input.lock().unwrap().take();
+
+	if let Some((operation, target, input)) = input {
+		match explorer.apply_operation(operation, &target, &input, &mut app.editor) {
+			Ok(Some(path)) => {
+				if operation == ExplorerOperation::NewFile {
+					tokio_runtime.block_on(app.jump_to_path(&path, None, None));
+				}
+
+				explorer.reveal(&path);
+			},
+			Ok(None) => (),
+			Err(e) => app.editor.set_error(format!("File explorer: {}", e)),
+		}
+
+		app.request_render();
+	}
+
+	if !explorer.visible { return }
+
+	explorer.since_scan += time.delta_seconds();
+
+	if explorer.scan_task.is_none() && (explorer.scan_requested || explorer.since_scan >= REFRESH_SECONDS) {
+		explorer.scan_requested = false;
+		explorer.since_scan = 0.0;
+
+		let root		= explorer.root.clone();
+		let expanded	= explorer.expanded.clone();
+
+		let task_pool = AsyncComputeTaskPool::get();
+		explorer.scan_task = Some(task_pool.spawn(async move { scan_tree(&root, &expanded) }));
+	}
+
+	let Some(mut task) = explorer.scan_task.take() else { return };
+
+	let Some(entries) = future::block_on(future::poll_once(&mut task)) else {
+		explorer.scan_task = Some(task);
+		return;
+	};
+
+	profile_function!();
+
+	explorer.finish_scan(entries);
+}
+
+// explorer is attached to the left side of editor and camera looks at the middle of both
+pub fn update_transform(
+	mut explorer		: ResMut<FileExplorer>,
+	mut q_camera		: Query<(&mut ReaderCamera, &Transform)>,
+	mut q_transform		: Query<&mut Transform, Without<ReaderCamera>>,
+		surfaces_bevy	: Res<SurfacesMapBevy>,
+		dock_layout		: Res<DockLayout>,
+		font_assets		: Res<Assets<ABGlyphFont>>,
+		font_handles	: Res<FontAssetHandles>,
+		app_option		: Option<NonSend<HelixApp>>,
+) {
+	let Some(app) = app_option else { return };
+
+	let Ok((mut reader_camera, camera_transform)) = q_camera.get_single_mut() else { return };
+	let Some(surface_editor) = surfaces_bevy.get(EditorView::ID) else { return };
+
+	let editor_columns = surface_editor.area.width as usize;
+
+	// split views and terminal docked to the right take care of camera column on their own
+	let camera_owned = app.views().len() > 1 || dock_layout.side == DockSide::Right;
+
+	let Some(explorer_entity) = explorer.entity else {
+		// editor was rendered narrower this frame and gets its columns back in the next one
+		if explorer.camera_column.take().is_some() && !camera_owned {
+			reader_camera.column = (editor_columns + EXPLORER_COLUMNS + GAP_COLUMNS) / 2;
+		}
+
+		return;
+	};
+
+	let Ok(editor_transform) = q_transform.get(surface_editor.entity).map(|transform| *transform) else { return };
+	let Ok(mut transform) = q_transform.get_mut(explorer_entity) else { return };
+
+	profile_function!();
+
+	let font = font_assets.get(&font_handles.main).unwrap();
+	let column_width = font.horizontal_advance_mono();
+
+	let x = editor_transform.translation.x - (EXPLORER_COLUMNS + GAP_COLUMNS) as f32 * column_width;
+	let y = camera_transform.translation.y + reader_camera.y_top;
+
+	// explorer is drawn on top of code editor background quad that fills the whole screen
+	transform.translation = Vec3::new(x, y, z_order::surface::child_surface());
+
+	if camera_owned { return }
+
+	let camera_column = editor_columns.saturating_sub(EXPLORER_COLUMNS + GAP_COLUMNS) / 2;
+
+	if explorer.camera_column != Some(camera_column) || reader_camera.column != camera_column {
+		reader_camera.column = camera_column;
+		explorer.camera_column = Some(camera_column);
+	}
+}
+
+// only rows that changed since the last frame are respawned
+pub fn update_text_surface(
+	mut explorer		: ResMut<FileExplorer>,
+	mut q_surface		: Query<&mut TextSurface>,
+	mut q_background	: Query<&mut TextBackgroundQuad>,
+		q_camera		: Query<&ReaderCamera>,
+		font_assets		: Res<Assets<ABGlyphFont>>,
+		font_handles	: Res<FontAssetHandles>,
+	mut despawn			: ResMut<DespawnResource>,
+	mut commands		: Commands,
+		app_option		: Option<NonSend<HelixApp>>,
+) {
+	let Some(app) = app_option else { return };
+
+	let Some(explorer_entity) = explorer.entity else { return };
+	let Ok(mut text_surface) = q_surface.get_mut(explorer_entity) else { return };
+	let Ok(reader_camera) = q_camera.get_single() else { return };
+
+	profile_function!();
+
+	let rows = (reader_camera.visible_rows.floor() as usize).max(HEADER_ROWS + 1);
+
+	if text_surface.rows.len() != rows {
+		text_surface.on_resize(rows, EXPLORER_COLUMNS, &mut despawn);
+		explorer.invalidate();
+	}
+
+	explorer.set_visible_rows(rows - HEADER_ROWS);
+
+	let background_color = explorer.background_color(&app.editor.theme);
+
+	if let Some(mut background) = explorer.background_entity.and_then(|entity| q_background.get_mut(entity).ok()) {
+		if background.columns != EXPLORER_COLUMNS || background.rows != rows {
+			background.columns	= EXPLORER_COLUMNS;
+			background.rows		= rows;
+		}
+
+		if background.color != Some(background_color) {
+			background.color = Some(background_color);
+		}
+	}
+
+	let Some(changed_rows) = explorer.update_surface(&app.editor, rows) else { return };
+
+	if changed_rows.is_empty() { return }
+
+	let fonts = ABGlyphFonts::new(&font_assets, &font_handles);
+
+	let mut words_to_spawn = WordSpawnInfo::default();
+	let mut lines_to_spawn = ColoringLinesToSpawn::default();
+
+	for (row_index, cells) in changed_rows.iter() {
+		let mut row_colors_state = ColoringLineRowState::default();
+		let mut row_colors = ColoringLineRow::new();
+
+		let mut row_state = ClusterRowState::default();
+		let mut row	= WordsRow::new();
+
+		for cell in cells.iter() {
+			text_surface.process_cluster_into_row(
+				cell,
+				&background_color,
+				&mut row_state,
+				&mut row,
+				&mut row_colors_state,
+				&mut row_colors,
+				&fonts
+			);
+		}
+
+		text_surface.update_cached_row(
+			*row_index,
+			&row,
+			&row_colors,
+			&mut words_to_spawn,
+			&mut lines_to_spawn,
+			&mut despawn
+		);
+	}
+
+	commands.entity(explorer_entity).insert((words_to_spawn, lines_to_spawn));
+}
+
+pub fn on_context_switch_out(
+	mut explorer	: ResMut<FileExplorer>,
+	mut despawn		: ResMut<DespawnResource>,
+) {
+	if let Some(entity) = explorer.take_entity() {
+		despawn.recursive.push(entity);
+	}
+
+	// camera is placed anew once explorer is spawned again
+	explorer.camera_column = None;
+}
@@ -0,0 +1,166 @@
+use bevy :: prelude :: *;
+use bevy :: utils :: HashMap;
+
+use helix_core :: { Rope, Selection, diff :: compare_ropes, path :: get_relative_path };
+use helix_view :: { Editor, DocumentId };
+
+use std :: {
+	path :: PathBuf,
+	time :: SystemTime,
+};
+
+pub mod systems;
+
+pub const WATCH_INTERVAL_SECONDS	: f32 = 1.0;
+pub const CONFLICT_DIFF_POPUP_ID	: &str = "kodiki-external-change-diff";
+pub const MAX_DIFF_LINES			: usize = 100;
+
+// kodiki_autosave.json in helix config directory, omitted fields keep their defaults:
+// { "on_idle": true, "on_context_switch": false }
+#[derive(Resource, serde::Deserialize)]
+#[serde(default)]
+pub struct AutosaveSettings {
+	pub on_idle				: bool, // Helix idle timeout, fires shortly after typing stops
+	pub on_focus_lost		: bool,
+	pub on_context_switch	: bool,
+}
+
+impl Default for AutosaveSettings {
+	fn default() -> Self {
+		Self {
+			on_idle				: false,
+			on_focus_lost		: true,
+			on_context_switch	: true,
+		}
+	}
+}
+
+impl AutosaveSettings {
+	pub fn load() -> Self {
+		let file_path = helix_loader::config_dir().join("kodiki_autosave.json");
+		let Ok(contents) = std::fs::read_to_string(&file_path) else { return Self::default() };
+
+		serde_json::from_str(&contents).unwrap_or_else(|err| {
+			warn!("failed to parse autosave settings from {:?}: {:?}", file_path, err);
+			Self::default()
+		})
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct WatchedFile {
+	pub path		: PathBuf,
+	pub modified	: SystemTime,
+	pub written		: Option<Rope>, // contents of our last save
+}
+
+// document saved by Helix, recorded right when the write is done so that the watcher doesn't take it for an external change
+#[derive(Clone, Debug)]
+pub struct WrittenFile {
+	pub doc_id		: DocumentId,
+	pub path		: PathBuf,
+	pub modified	: SystemTime,
+	pub text		: Rope,
+}
+
+// modification time of every open document as it was last seen. Files are polled since documents come and go all the time
+#[derive(Resource, Default)]
+pub struct FileWatcher {
+	pub files			: HashMap<DocumentId, WatchedFile>,
+	pub since_last_poll	: f32,
+}
+
+impl FileWatcher {
+	// returns true if file was modified since the last time it was seen or written by us. Newly opened documents are only remembered
+	pub fn update(&mut self, doc_id: DocumentId, path: &PathBuf, modified: SystemTime) -> bool {
+		match self.files.get_mut(&doc_id) {
+			Some(watched) if watched.path == *path => {
+				let changed = watched.modified != modified;
+				watched.modified = modified;
+
+				changed
+			},
+			_ => {
+				self.files.insert(doc_id, WatchedFile { path: path.clone(), modified, written: None });
+
+				false
+			}
+		}
+	}
+
+	pub fn written(&mut self, file: WrittenFile) {
+		self.files.insert(file.doc_id, WatchedFile { path: file.path, modified: file.modified, written: Some(file.text) });
+	}
+
+	pub fn written_text(&self, doc_id: DocumentId) -> Option<&Rope> {
+		self.files.get(&doc_id).and_then(|watched| watched.written.as_ref())
+	}
+}
+
+// replaces document contents as a regular edit so that it can be undone. Returns false if document is not open
+pub fn replace_document_text(editor: &mut Editor, doc_id: DocumentId, new_text: &str) -> bool {
+	// any view that shows the document works for applying changes
+	let view_id = editor.tree.views().find(|(view, _)| view.doc == doc_id).map(|(view, _)| view.id);
+
+	let Some(doc) = editor.documents.get_mut(&doc_id) else { return false };
+
+	let view_id = match view_id.or_else(|| doc.selections().keys().next().copied()) {
+		Some(view_id) => view_id,
+		// documents loaded in background have no selections yet, they are edited on behalf of focused view
+		None => {
+			doc.set_selection(editor.tree.focus, Selection::point(0));
+			editor.tree.focus
+		},
+	};
+
+	let transaction = compare_ropes(doc.text(), &Rope::from(new_text));
+	doc.apply(&transaction, view_id);
+
+	if editor.tree.contains(view_id) {
+		doc.append_changes_to_history(editor.tree.get_mut(view_id));
+	}
+
+	true
+}
+
+pub fn reload_document(editor: &mut Editor, doc_id: DocumentId, disk_text: &str) {
+	if !replace_document_text(editor, doc_id, disk_text) { return }
+
+	let Some(doc) = editor.documents.get_mut(&doc_id) else { return };
+
+	doc.reset_modified();
+
+	let path = doc.path().map(|path| get_relative_path(path).to_string_lossy().to_string()).unwrap_or_default();
+
+	editor.set_status(format!("'{}' reloaded, it was changed on disk", path));
+}
+
+// lines that differ between two versions of a document after trimming common head and tail
+pub fn diff_markdown(header: &str, old_text: &str, new_text: &str) -> String {
+	let old_lines	: Vec<&str> = old_text.lines().collect();
+	let new_lines		: Vec<&str> = new_text.lines().collect();
+
+	let head = old_lines.iter().zip(new_lines.iter()).take_while(|(a, b)| a == b).count();
+	let tail = old_lines[head..].iter().rev().zip(new_lines[head..].iter().rev()).take_while(|(a, b)| a == b).count();
+
+	let old_changed	= &old_lines[head .. old_lines.len() - tail];
+	let new_changed	= &new_lines[head .. new_lines.len() - tail];
+
+	let mut diff = format!("{}\n```diff\n@@ line {} @@\n", header, head + 1);
+
+	for line in old_changed.iter().take(MAX_DIFF_LINES) {
+		diff.push_str(format!("-{}\n", line).as_str());
+	}
+
+	for line in new_changed.iter().take(MAX_DIFF_LINES) {
+		diff.push_str(format!("+{}\n", line).as_str());
+	}
+
+	if old_changed.len() > MAX_DIFF_LINES || new_changed.len() > MAX_DIFF_LINES {
+		diff.push_str("…\n");
+	}
+
+	diff.push_str("```");
+
+	diff
+}
@@ -0,0 +1,108 @@
+use bevy :: prelude :: *;
+use bevy :: window :: WindowFocused;
+
+#[cfg(feature = "tracing")]
+use bevy_puffin :: *;
+
+use super :: *;
+
+use crate :: bevy_helix :: HelixApp;
+
+pub fn load_settings(
+	mut settings : ResMut<AutosaveSettings>,
+) {
+	*settings = AutosaveSettings::load();
+}
+
+// idle timeout is handled by Helix side so the setting is just passed along
+pub fn sync_settings(
+		settings	: Res<AutosaveSettings>,
+		app_option	: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.autosave_on_idle != settings.on_idle {
+		app.autosave_on_idle = settings.on_idle;
+	}
+}
+
+pub fn autosave_on_focus_lost(
+	mut focused_events	: EventReader<WindowFocused>,
+		settings		: Res<AutosaveSettings>,
+		app_option		: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	let focus_lost = focused_events.iter().any(|event| !event.focused);
+
+	if !focus_lost || !settings.on_focus_lost || app.should_close() { return }
+
+	profile_function!();
+
+	app.save_modified_documents();
+}
+
+pub fn autosave_on_context_switch(
+		settings	: Res<AutosaveSettings>,
+		app_option	: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if !settings.on_context_switch || app.should_close() { return }
+
+	profile_function!();
+
+	app.save_modified_documents();
+}
+
+// clean documents are reloaded right away, documents with unsaved changes get a conflict prompt
+pub fn watch_files(
+		time		: Res<Time>,
+	mut watcher		: ResMut<FileWatcher>,
+		app_option	: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	// modification time of our own saves is the one to compare against from now on
+	for written_file in app.written_files.drain(..) {
+		watcher.written(written_file);
+	}
+
+	watcher.since_last_poll += time.delta_seconds();
+
+	if watcher.since_last_poll < WATCH_INTERVAL_SECONDS { return }
+
+	watcher.since_last_poll = 0.0;
+
+	profile_function!();
+
+	watcher.files.retain(|doc_id, _| app.editor.documents.contains_key(doc_id));
+
+	let mut changed = Vec::new();
+
+	for doc in app.editor.documents() {
+		let Some(path) = doc.path() else { continue };
+		let Ok(modified) = std::fs::metadata(path).and_then(|metadata| metadata.modified()) else { continue };
+
+		if !watcher.update(doc.id(), path, modified) { continue }
+
+		let Ok(disk_text) = std::fs::read_to_string(path) else { continue };
+
+		// touched files and files written with the same contents as ours
+		if *doc.text() == disk_text.as_str() || watcher.written_text(doc.id()).map_or(false, |written| *written == disk_text.as_str()) { continue }
+
+		changed.push((doc.id(), doc.is_modified(), disk_text));
+	}
+
+	for (doc_id, is_modified, disk_text) in changed {
+		if is_modified {
+			app.prompt_external_change(doc_id, disk_text);
+		} else {
+			reload_document(&mut app.editor, doc_id, &disk_text);
+		}
+
+		app.request_render();
+	}
+}
@@ -0,0 +1,458 @@
+use bevy :: prelude :: *;
+use bevy :: utils :: HashMap;
+
+use helix_core :: { Assoc, syntax :: Syntax, tree_sitter :: Node };
+use helix_view :: { Document, DocumentId, View };
+
+use super :: { VersionType, helix_app :: HelixApp };
+
+pub mod systems;
+
+pub const FOLD_ANIMATION_MS : u64 = 200;
+
+// tree-sitter node kinds that can be collapsed
+const FOLDABLE_KINDS : &[&str] = &[
+	// rust
+	"mod_item",
+	"impl_item",
+	"trait_item",
+	"function_item",
+	"struct_item",
+	"enum_item",
+	"match_arm",
+	"line_comment",
+	"block_comment",
+	// common across other grammars
+	"function_definition",
+	"function_declaration",
+	"method_definition",
+	"method_declaration",
+	"class_definition",
+	"class_declaration",
+	"comment",
+];
+
+// first line stays visible as a placeholder, the rest is hidden
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Fold {
+	pub start_line	: usize,
+	pub end_line	: usize,
+}
+
+impl Fold {
+	pub fn hidden_rows(&self) -> usize {
+		self.end_line - self.start_line
+	}
+
+	pub fn hides(&self, row: usize) -> bool {
+		row > self.start_line && row <= self.end_line
+	}
+
+	// hidden rows that are below top_row, rows above the viewport are not rendered anyway
+	fn hidden_range(&self, top_row: usize) -> Option<(usize, usize)> {
+		let first_hidden = (self.start_line + 1).max(top_row);
+
+		if first_hidden > self.end_line {
+			None
+		} else {
+			Some((first_hidden, self.end_line))
+		}
+	}
+}
+
+// fold lines as characters of document text at history revision folds were synced with
+#[derive(Clone, Copy, Debug)]
+struct FoldAnchor {
+	start	: usize,
+	end		: usize,
+}
+
+#[derive(Default)]
+pub struct DocumentFolds {
+	pub folds		: Vec<Fold>, // sorted by start_line, never overlapping
+	pub version		: VersionType,
+	anchors			: Vec<FoldAnchor>, // one per fold, changes not committed to history yet are not applied to them
+	revision		: usize,
+}
+
+impl DocumentFolds {
+	// folds were changed when there were no pending changes so they are anchored in current text
+	fn anchor(&mut self, doc: &Document) {
+		let text = doc.text();
+
+		self.anchors = self.folds.iter().map(|fold| FoldAnchor {
+			start	: text.line_to_char(fold.start_line),
+			end		: text.line_to_char(fold.end_line),
+		}).collect();
+
+		let history = doc.history.take();
+		self.revision = history.current_revision();
+		doc.history.set(history);
+
+		self.version = doc.version();
+	}
+
+	// moves folds along with the lines they were made for: anchors follow history revisions and pending changes
+	// of insert mode are applied on top of them. Folds that lost their body are dropped
+	fn map_through_changes(&mut self, doc: &Document) {
+		let history = doc.history.take();
+		let revision = history.current_revision();
+		let transaction = if revision != self.revision && !self.anchors.is_empty() { history.changes_since(self.revision) } else { None };
+		doc.history.set(history);
+
+		if let Some(transaction) = transaction {
+			let changes = transaction.changes();
+
+			for anchor in self.anchors.iter_mut() {
+				anchor.start	= changes.map_pos(anchor.start.min(changes.len()), Assoc::After);
+				anchor.end		= changes.map_pos(anchor.end.min(changes.len()), Assoc::After);
+			}
+		}
+
+		self.revision = revision;
+		self.version = doc.version();
+
+		let text	= doc.text();
+		let pending	= doc.changes();
+
+		let map_line = |pos: usize| {
+			let pos = if pending.is_empty() { pos } else { pending.map_pos(pos.min(pending.len()), Assoc::After) };
+			text.char_to_line(pos.min(text.len_chars()))
+		};
+
+		let mut folds	: Vec<Fold>			= Vec::with_capacity(self.anchors.len());
+		let mut anchors	: Vec<FoldAnchor>	= Vec::with_capacity(self.anchors.len());
+
+		for anchor in self.anchors.iter() {
+			let fold = Fold { start_line: map_line(anchor.start), end_line: map_line(anchor.end) };
+
+			if fold.end_line <= fold.start_line { continue }
+
+			// ranges that collapsed onto each other are merged into the outer one
+			if folds.last().map_or(false, |last| last.end_line >= fold.start_line) { continue }
+
+			folds.push(fold);
+			anchors.push(*anchor);
+		}
+
+		self.folds = folds;
+		self.anchors = anchors;
+	}
+
+	fn retain(&mut self, keep: impl Fn(&Fold) -> bool) {
+		let (folds, anchors) = self.folds.iter()
+			.zip(self.anchors.iter())
+			.filter(|(fold, _)| keep(fold))
+			.map(|(fold, anchor)| (*fold, *anchor))
+			.unzip();
+
+		self.folds = folds;
+		self.anchors = anchors;
+	}
+}
+
+#[derive(Resource, Default)]
+pub struct Folds {
+	pub per_document	: HashMap<DocumentId, DocumentFolds>,
+	pub version			: usize, // incremented on every fold or unfold to animate rows
+
+	pub marker_entities	: Vec<Entity>,
+	pub markers_cache	: Option<(FoldedView, usize, Option<usize>)>, // view, folds version, foldable line under cursor
+}
+
+impl Folds {
+	pub fn get(&self, doc_id: DocumentId) -> &[Fold] {
+		self.per_document.get(&doc_id).map_or(&[], |doc_folds| doc_folds.folds.as_slice())
+	}
+
+	// returns true if range was folded, false if existing fold starting at the same line was unfolded.
+	// Pending changes of insert mode are committed to history first so that folds can be anchored in current text
+	pub fn toggle(&mut self, doc: &mut Document, view: &mut View, fold: Fold) -> bool {
+		doc.append_changes_to_history(view);
+
+		let doc_folds = self.per_document.entry(doc.id()).or_default();
+		doc_folds.map_through_changes(doc);
+
+		self.version += 1;
+
+		let folded = if let Some(index) = doc_folds.folds.iter().position(|f| f.start_line == fold.start_line) {
+			doc_folds.folds.remove(index);
+			false
+		} else {
+			// nested folds are swallowed by the outer one
+			doc_folds.folds.retain(|f| f.end_line < fold.start_line || f.start_line > fold.end_line);
+
+			let index = doc_folds.folds.partition_point(|f| f.start_line < fold.start_line);
+			doc_folds.folds.insert(index, fold);
+
+			true
+		};
+
+		doc_folds.anchor(doc);
+
+		folded
+	}
+
+	// unfolds everything that hides given row. Returns true if something was unfolded
+	pub fn reveal(&mut self, doc_id: DocumentId, row: usize) -> bool {
+		let Some(doc_folds) = self.per_document.get_mut(&doc_id) else { return false };
+
+		let folds_cnt = doc_folds.folds.len();
+		doc_folds.retain(|f| !f.hides(row));
+
+		if doc_folds.folds.len() == folds_cnt {
+			return false;
+		}
+
+		self.version += 1;
+
+		true
+	}
+
+	// drop folds of closed documents and follow edits in the rest of them
+	pub fn retain_valid<'a>(&mut self, document: impl Fn(&DocumentId) -> Option<&'a Document>) {
+		let mut changed = false;
+
+		self.per_document.retain(|doc_id, doc_folds| {
+			let Some(doc) = document(doc_id) else {
+				changed = true;
+				return false;
+			};
+
+			if doc.version() != doc_folds.version {
+				let folds_prev = doc_folds.folds.clone();
+				doc_folds.map_through_changes(doc);
+
+				changed |= doc_folds.folds != folds_prev;
+			}
+
+			!doc_folds.folds.is_empty()
+		});
+
+		if changed {
+			self.version += 1;
+		}
+	}
+
+	pub fn fold_at(&self, doc_id: DocumentId, start_line: usize) -> Option<&Fold> {
+		self.get(doc_id).iter().find(|f| f.start_line == start_line)
+	}
+
+	pub fn is_hidden(&self, doc_id: DocumentId, row: usize) -> bool {
+		self.get(doc_id).iter().any(|f| f.hides(row))
+	}
+
+	// how many rows between top_row and given row are hidden
+	pub fn row_shift(&self, doc_id: DocumentId, row: usize, top_row: usize) -> usize {
+		self.get(doc_id).iter()
+			.take_while(|f| f.end_line < row)
+			.filter_map(|f| f.hidden_range(top_row))
+			.map(|(first, last)| last - first + 1)
+			.sum()
+	}
+
+	// rows hidden in the viewport. Editor is rendered that many rows taller so that rows below folds fill the space they free up
+	pub fn hidden_rows(&self, doc_id: DocumentId, top_row: usize, visible_rows: usize) -> usize {
+		let mut hidden_rows = 0;
+
+		for fold in self.get(doc_id).iter() {
+			if fold.start_line >= top_row + visible_rows + hidden_rows { break }
+
+			let Some((first, last)) = fold.hidden_range(top_row) else { continue };
+
+			hidden_rows += last - first + 1;
+		}
+
+		hidden_rows
+	}
+
+	// document row to the row it's displayed at. Hidden rows are displayed at their placeholder row
+	pub fn visual_row(&self, doc_id: DocumentId, row: usize, top_row: usize) -> usize {
+		let row = self.get(doc_id).iter().find(|f| f.hides(row)).map_or(row, |f| f.start_line);
+
+		row - self.row_shift(doc_id, row, top_row)
+	}
+
+	// displayed row back to document row, used to translate mouse position
+	pub fn document_row(&self, doc_id: DocumentId, visual_row: usize, top_row: usize) -> usize {
+		let mut row = visual_row;
+
+		for fold in self.get(doc_id).iter() {
+			let Some((first, last)) = fold.hidden_range(top_row) else { continue };
+
+			if first <= row {
+				row += last - first + 1;
+			}
+		}
+
+		row
+	}
+
+	// innermost foldable node that encloses given line and spans several lines
+	pub fn foldable_range(syntax: &Syntax, doc: &Document, line: usize) -> Option<Fold> {
+		let text = doc.text().slice(..);
+
+		if line >= text.len_lines() {
+			return None;
+		}
+
+		// start from the first non-whitespace character so that line itself is enclosed by the node we find
+		let line_start	= text.line_to_char(line);
+		let indent		= text.line(line).chars().take_while(|c| c.is_whitespace() && *c != '\n').count();
+		let byte		= text.char_to_byte(line_start + indent);
+
+		let node_lines = |node: &Node| {
+			let start	= text.byte_to_line(node.start_byte());
+			// nodes like line comments include trailing newline
+			let end		= text.byte_to_line(node.end_byte().saturating_sub(1).max(node.start_byte()));
+			(start, end)
+		};
+
+		let mut node = syntax.tree().root_node().descendant_for_byte_range(byte, byte);
+
+		while let Some(foldable_node) = node {
+			let kind = foldable_node.kind();
+
+			if FOLDABLE_KINDS.contains(&kind) {
+				let (mut start_line, mut end_line) = node_lines(&foldable_node);
+
+				// consecutive line comments are folded as a single block
+				if kind.ends_with("comment") {
+					let mut prev = foldable_node.prev_sibling();
+					while let Some(sibling) = prev {
+						let (sibling_start, sibling_end) = node_lines(&sibling);
+						if sibling.kind() != kind || sibling_end + 1 != start_line { break }
+						start_line = sibling_start;
+						prev = sibling.prev_sibling();
+					}
+
+					let mut next = foldable_node.next_sibling();
+					while let Some(sibling) = next {
+						let (sibling_start, sibling_end) = node_lines(&sibling);
+						if sibling.kind() != kind || sibling_start != end_line + 1 { break }
+						end_line = sibling_end;
+						next = sibling.next_sibling();
+					}
+				}
+
+				if end_line > start_line {
+					return Some(Fold { start_line, end_line });
+				}
+			}
+
+			node = foldable_node.parent();
+		}
+
+		None
+	}
+}
+
+// focused view as seen by folding: rows of editor surface are offset by view position
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FoldedView {
+	pub doc_id		: DocumentId,
+	pub top_row		: usize,
+	pub view_y		: usize,
+}
+
+impl FoldedView {
+	pub fn new(app: &HelixApp) -> Self {
+		let (view, doc) = app.current_ref();
+
+		Self {
+			doc_id	: doc.id(),
+			top_row	: app.row_offset_external(),
+			view_y	: view.area.y as usize,
+		}
+	}
+
+	// global row of editor surface (scroll offset included) to document row
+	pub fn document_row(&self, surface_row: usize) -> Option<usize> {
+		surface_row.checked_sub(self.view_y)
+	}
+
+	pub fn surface_row(&self, document_row: usize) -> usize {
+		document_row + self.view_y
+	}
+}
+
+// gutter markers and "N lines" placeholders are not words of Helix surface and are respawned when folds change
+#[derive(Component)]
+pub struct FoldMarker;
+
+#[cfg(test)]
+mod tests {
+	use super :: *;
+
+	fn folds(ranges: &[(usize, usize)]) -> (Folds, DocumentId) {
+		let doc_id = DocumentId::default();
+
+		let doc_folds = DocumentFolds {
+			folds	: ranges.iter().map(|(start_line, end_line)| Fold { start_line: *start_line, end_line: *end_line }).collect(),
+			anchors	: ranges.iter().map(|(start, end)| FoldAnchor { start: *start, end: *end }).collect(),
+			..default()
+		};
+
+		let mut folds = Folds::default();
+		folds.per_document.insert(doc_id, doc_folds);
+
+		(folds, doc_id)
+	}
+
+	#[test]
+	fn fold_keeps_first_line_visible() {
+		let fold = Fold { start_line: 2, end_line: 5 };
+
+		assert_eq!(fold.hidden_rows(), 3);
+		assert!(!fold.hides(2));
+		assert!(fold.hides(3));
+		assert!(fold.hides(5));
+		assert!(!fold.hides(6));
+	}
+
+	#[test]
+	fn rows_below_folds_move_up() {
+		let (folds, doc_id) = folds(&[(2, 5), (10, 12)]);
+
+		assert_eq!(folds.visual_row(doc_id, 1, 0), 1);
+		assert_eq!(folds.visual_row(doc_id, 4, 0), 2); // hidden row is shown at placeholder
+		assert_eq!(folds.visual_row(doc_id, 7, 0), 4);
+		assert_eq!(folds.visual_row(doc_id, 13, 0), 8);
+
+		assert_eq!(folds.document_row(doc_id, 2, 0), 2);
+		assert_eq!(folds.document_row(doc_id, 4, 0), 7);
+		assert_eq!(folds.document_row(doc_id, 8, 0), 13);
+	}
+
+	#[test]
+	fn rows_above_viewport_are_not_counted() {
+		let (folds, doc_id) = folds(&[(2, 5), (10, 12)]);
+
+		assert_eq!(folds.row_shift(doc_id, 7, 4), 2);
+		assert_eq!(folds.visual_row(doc_id, 7, 4), 5);
+		assert_eq!(folds.document_row(doc_id, 5, 4), 7);
+	}
+
+	#[test]
+	fn hidden_rows_in_viewport() {
+		let (folds, doc_id) = folds(&[(2, 5), (10, 12)]);
+
+		// second fold starts below the viewport even after rows of the first one are hidden
+		assert_eq!(folds.hidden_rows(doc_id, 0, 5), 3);
+		assert_eq!(folds.hidden_rows(doc_id, 0, 8), 5);
+		assert_eq!(folds.hidden_rows(doc_id, 6, 8), 2);
+	}
+
+	#[test]
+	fn reveal_unfolds_hiding_fold_only() {
+		let (mut folds, doc_id) = folds(&[(2, 5), (10, 12)]);
+
+		assert!(folds.reveal(doc_id, 4));
+		assert_eq!(folds.get(doc_id), &[Fold { start_line: 10, end_line: 12 }]);
+		assert_eq!(folds.version, 1);
+
+		assert!(!folds.reveal(doc_id, 4));
+		assert!(!folds.reveal(doc_id, 10));
+		assert!(folds.is_hidden(doc_id, 11));
+	}
+}
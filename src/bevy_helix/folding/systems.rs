@@ -0,0 +1,314 @@
+use bevy :: prelude :: *;
+use bevy :: ecs :: change_detection :: Ref;
+use bevy_tweening :: { *, lens :: TransformPositionLens };
+
+#[cfg(feature = "tracing")]
+use bevy_puffin :: *;
+
+use helix_term :: ui :: EditorView;
+use helix_view :: { Document, graphics :: Color as HelixColor };
+
+use std :: time :: Duration;
+
+use super :: *;
+
+use crate :: {
+	z_order,
+	kodiki_ui :: { DraggingState, String3dSpawnRequest, CommonString3dSpawnParams },
+	bevy_ab_glyph :: { ABGlyphFont, ABGlyphFonts, FontAssetHandles },
+	bevy_helix :: {
+		MousePosState,
+		surface :: { SurfacesMapBevy, WordDescription, ColoringLineDescription },
+		utils :: color_from_helix,
+	},
+};
+
+fn cursor_line(app: &HelixApp) -> usize {
+	let (view, doc) = app.current_ref();
+	let text = doc.text().slice(..);
+
+	text.char_to_line(doc.selection(view.id).primary().cursor(text))
+}
+
+// existing fold starting at given line or the innermost foldable node around it
+fn fold_for_line(folds: &Folds, doc: &Document, line: usize) -> Option<Fold> {
+	folds.fold_at(doc.id(), line).copied().or_else(|| {
+		doc.syntax().and_then(|syntax| Folds::foldable_range(syntax, doc, line))
+	})
+}
+
+// ctrl+shift+[ folds scope around cursor or unfolds the one starting at cursor line
+pub fn input_keyboard(
+		key			: Res<Input<KeyCode>>,
+	mut folds		: ResMut<Folds>,
+		app_option	: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() || !app.editor_focused() { return }
+
+	let ctrl_pressed	= key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl);
+	let shift_pressed	= key.pressed(KeyCode::LShift) || key.pressed(KeyCode::RShift);
+
+	if !(ctrl_pressed && shift_pressed && key.just_pressed(KeyCode::BracketLeft)) { return }
+
+	profile_function!();
+
+	let line = cursor_line(&app);
+
+	let folded = {
+		let (view, doc) = app.current_mut();
+		let Some(fold) = fold_for_line(&folds, doc, line) else { return };
+
+		folds.toggle(doc, view, fold).then_some(fold)
+	};
+
+	// cursor left inside of folded range would unfold it right away in reveal_cursor
+	if let Some(fold) = folded.filter(|fold| line != fold.start_line) {
+		app.set_cursor(fold.start_line, 0);
+	}
+}
+
+// clicking gutter marker toggles fold. Runs after main input_mouse so mouse position is already in document rows
+pub fn input_mouse(
+		mouse_button	: Res<Input<MouseButton>>,
+		mouse_pos_state	: Res<MousePosState>,
+		dragging_state	: Res<DraggingState>,
+	mut folds			: ResMut<Folds>,
+		app_option		: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() || dragging_state.is_active() { return }
+
+	if !mouse_button.just_pressed(MouseButton::Left) || mouse_pos_state.surface_name != EditorView::ID { return }
+
+	let folded_view = FoldedView::new(&app);
+	let (view, doc) = app.current_mut();
+
+	if mouse_pos_state.col != view.area.x { return }
+
+	profile_function!();
+
+	let Some(line) = folded_view.document_row(folded_view.top_row + mouse_pos_state.row as usize) else { return };
+	let Some(fold) = fold_for_line(&folds, doc, line) else { return };
+
+	// markers are shown only on the first line of foldable range
+	if fold.start_line != line { return }
+
+	folds.toggle(doc, view, fold);
+}
+
+// runs before camera_update so that cursor is never left in hidden rows and stale folds are not rendered
+pub fn reveal_cursor(
+	mut folds		: ResMut<Folds>,
+		app_option	: Option<NonSend<HelixApp>>,
+) {
+	let Some(app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	profile_function!();
+
+	folds.retain_valid(|doc_id| app.editor.documents.get(doc_id));
+
+	let doc_id = app.current_document().id();
+
+	folds.reveal(doc_id, cursor_line(&app));
+}
+
+fn apply_row_offset(
+	entity			: Entity,
+	target			: Vec3,
+	hidden			: bool,
+	animate			: bool,
+	transform		: &mut Transform,
+	visibility		: &mut Visibility,
+	commands		: &mut Commands,
+) {
+	let target_visibility = if hidden { Visibility::Hidden } else { Visibility::Inherited };
+	if *visibility != target_visibility {
+		*visibility = target_visibility;
+	}
+
+	if transform.translation == target {
+		return;
+	}
+
+	if animate && !hidden {
+		let tween = Tween::new(
+			EaseFunction::QuadraticInOut,
+			Duration::from_millis(FOLD_ANIMATION_MS),
+			TransformPositionLens {
+				start	: transform.translation,
+				end		: target,
+			}
+		);
+
+		commands.entity(entity).insert(Animator::new(tween));
+	} else {
+		transform.translation = target;
+
+		commands.entity(entity).remove::<Animator<Transform>>();
+	}
+}
+
+// moves words and coloring lines of editor surface up by the amount of hidden rows above them.
+// Runs in PostUpdate so that words spawned during this frame are placed before they are rendered
+pub fn update_rows_offset(
+	mut q_words			: Query<(Entity, Ref<WordDescription>, &mut Transform, &mut Visibility), Without<ColoringLineDescription>>,
+	mut q_lines			: Query<(Entity, Ref<ColoringLineDescription>, &mut Transform, &mut Visibility), Without<WordDescription>>,
+		folds			: Res<Folds>,
+		font_assets		: Res<Assets<ABGlyphFont>>,
+		font_handles	: Res<FontAssetHandles>,
+	mut applied			: Local<Option<(FoldedView, usize)>>,
+	mut commands		: Commands,
+		app_option		: Option<NonSend<HelixApp>>,
+) {
+	let Some(app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	let folded_view = FoldedView::new(&app);
+	let state = Some((folded_view, folds.version));
+
+	let full_update = *applied != state;
+
+	// rows slide only when something was folded or unfolded, scrolling and switching documents move them right away
+	let animate = full_update && applied.map_or(false, |(view, version)| view == folded_view && version != folds.version);
+
+	*applied = state;
+
+	// newly spawned words are already in place if there is nothing folded
+	if !full_update && folds.get(folded_view.doc_id).is_empty() { return }
+
+	profile_function!();
+
+	let row_height = font_assets.get(&font_handles.main).unwrap().vertical_advance();
+
+	let row_offset = |row: usize| -> (f32, bool) {
+		let Some(doc_row) = folded_view.document_row(row) else { return (0.0, false) };
+
+		let shift = folds.row_shift(folded_view.doc_id, doc_row, folded_view.top_row);
+
+		(shift as f32 * row_height, folds.is_hidden(folded_view.doc_id, doc_row))
+	};
+
+	for (entity, word, mut transform, mut visibility) in q_words.iter_mut() {
+		if !word.is_on_editor || word.surface_name != EditorView::ID { continue }
+		if !full_update && !word.is_added() { continue }
+
+		let (offset_y, hidden) = row_offset(word.row);
+
+		apply_row_offset(entity, word.position() + Vec3::Y * offset_y, hidden, animate, &mut transform, &mut visibility, &mut commands);
+	}
+
+	for (entity, line, mut transform, mut visibility) in q_lines.iter_mut() {
+		if !line.is_editor || line.surface_name != EditorView::ID { continue }
+		if !full_update && !line.is_added() { continue }
+
+		let (offset_y, hidden) = row_offset(line.row);
+
+		apply_row_offset(entity, line.position() + Vec3::Y * offset_y, hidden, animate, &mut transform, &mut visibility, &mut commands);
+	}
+}
+
+// gutter markers for folded ranges and for the scope around cursor plus a placeholder after each folded line
+pub fn update_markers(
+	mut folds			: ResMut<Folds>,
+		surfaces_bevy	: Res<SurfacesMapBevy>,
+		font_assets		: Res<Assets<ABGlyphFont>>,
+		font_handles	: Res<FontAssetHandles>,
+	mut commands		: Commands,
+		app_option		: Option<NonSend<HelixApp>>,
+) {
+	let Some(app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	profile_function!();
+
+	let folded_view = FoldedView::new(&app);
+	let (view, doc) = app.current_ref();
+
+	let cursor_foldable = doc.syntax()
+		.and_then(|syntax| Folds::foldable_range(syntax, doc, cursor_line(&app)))
+		.map(|fold| fold.start_line);
+
+	let cache = Some((folded_view, folds.version, cursor_foldable));
+	if folds.markers_cache == cache {
+		return;
+	}
+
+	folds.markers_cache = cache;
+
+	for entity in folds.marker_entities.drain(..) {
+		commands.entity(entity).despawn_recursive();
+	}
+
+	let Some(surface_editor) = surfaces_bevy.get(EditorView::ID) else { return };
+
+	let fonts			= ABGlyphFonts::new(&font_assets, &font_handles);
+	let row_height		= fonts.main.vertical_advance();
+	let column_width	= fonts.main.horizontal_advance_mono();
+
+	let theme				= &app.editor.theme;
+	let marker_color		= color_from_helix(theme.get("ui.linenr").fg.unwrap_or(HelixColor::Gray));
+	let placeholder_style	= theme.get("ui.virtual");
+	let placeholder_color	= color_from_helix(placeholder_style.fg.or(theme.get("comment").fg).unwrap_or(HelixColor::Gray));
+
+	let text		= doc.text().slice(..);
+	let tab			= " ".repeat(doc.tab_width());
+	let gutter_len	= app.gutter_len();
+	let top_row		= folded_view.top_row;
+	let horizontal_offset = view.offset.horizontal_offset;
+
+	let mut markers : Vec<(usize, usize, String, Color)> = Vec::new();
+
+	for fold in folds.get(doc.id()).iter().filter(|fold| fold.start_line >= top_row) {
+		markers.push((fold.start_line, view.area.x as usize, String::from("▸"), marker_color));
+
+		let line_len = text.line(fold.start_line).to_string().replace('\t', tab.as_str()).trim_end().chars().count();
+		let placeholder_column = view.area.x as usize + gutter_len + line_len.saturating_sub(horizontal_offset) + 1;
+
+		markers.push((fold.start_line, placeholder_column, format!("⋯ {} lines", fold.hidden_rows()), placeholder_color));
+	}
+
+	if let Some(line) = cursor_foldable {
+		if line >= top_row && folds.fold_at(doc.id(), line).is_none() {
+			markers.push((line, view.area.x as usize, String::from("▾"), marker_color));
+		}
+	}
+
+	for (line, column, string, color) in markers {
+		let visual_row = folds.visual_row(doc.id(), line, top_row);
+
+		if visual_row - top_row >= view.area.height as usize {
+			continue;
+		}
+
+		let position = Vec3::new(
+			column as f32 * column_width,
+			-((folded_view.surface_row(visual_row) + 1) as f32 * row_height),
+			z_order::surface::text()
+		);
+
+		let marker_entity = commands.spawn((
+			FoldMarker,
+			TransformBundle::from_transform(Transform::from_translation(position)),
+			VisibilityBundle::default(),
+			String3dSpawnRequest {
+				common : CommonString3dSpawnParams {
+					string,
+					color,
+					..default()
+				},
+				..default()
+			},
+		)).id();
+
+		commands.entity(surface_editor.entity).add_child(marker_entity);
+
+		folds.marker_entities.push(marker_entity);
+	}
+}
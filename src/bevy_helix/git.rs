@@ -0,0 +1,15 @@
+use std :: {
+	path :: Path,
+	process :: Command,
+};
+
+// git is run from the directory of given file, file itself is passed to it by name
+pub fn git_command(path: &Path) -> Option<(Command, String)> {
+	let dir			= path.parent()?;
+	let file_name	= path.file_name()?.to_string_lossy().to_string();
+
+	let mut command = Command::new("git");
+	command.current_dir(dir);
+
+	Some((command, file_name))
+}
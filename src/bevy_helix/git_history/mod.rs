@@ -0,0 +1,391 @@
+use bevy :: prelude :: *;
+use bevy :: tasks :: Task;
+use bevy :: utils :: HashMap;
+
+use std :: {
+	path :: { Path, PathBuf },
+	process :: Command,
+};
+
+use super :: diff_view :: { DiffRequest, DiffSource };
+
+pub mod systems;
+
+// name of the history entry in ContextRegistry, history is shown while it is the active context
+pub const HISTORY_CONTEXT_NAME		: &str = "git_history";
+
+pub const MAX_COMMITS				: usize	= 500;
+pub const NODE_ROWS					: f32	= 2.0;	// vertical distance between commits
+pub const LANE_COLUMNS				: f32	= 3.0;	// horizontal distance between branch lanes
+pub const LANE_DEPTH_COLUMNS		: f32	= 2.0;	// every next lane is pushed further away from camera
+pub const LABEL_COLUMNS				: usize	= 80;
+pub const FILES_GAP_COLUMNS			: usize	= 4;
+pub const FILE_COLUMNS				: usize	= 60;
+pub const VIEW_COLUMNS				: usize	= 160;	// what camera is centered on
+pub const EDGE_THICKNESS			: f32	= 0.1;	// fraction of column width
+pub const PAGE_COMMITS				: usize	= 10;
+
+#[derive(Clone, Debug)]
+pub struct GraphCommit {
+	pub hash		: String,
+	pub parents		: Vec<String>,
+	pub author		: String,
+	pub time		: i64,
+	pub refs		: String,
+	pub subject		: String,
+}
+
+impl GraphCommit {
+	pub fn short_hash(&self) -> &str {
+		&self.hash[.. self.hash.len().min(8)]
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct ChangedFile {
+	pub status		: char, // A, M, D, R etc. as reported by git
+	pub path		: PathBuf, // relative to repository root
+	pub old_path	: Option<PathBuf>, // renamed and copied files are taken from here in parent revision
+}
+
+impl ChangedFile {
+	// added files have nothing on the left, deleted ones on the right
+	pub fn diff_request(&self, root: &Path, commit: &GraphCommit) -> DiffRequest {
+		let path		= root.join(&self.path);
+		let old_path	= root.join(self.old_path.as_ref().unwrap_or(&self.path));
+
+		let left = match (self.status, commit.parents.is_empty()) {
+			('A', _) | (_, true) => DiffSource::Empty(format!("(added in {})", commit.short_hash())),
+			_ => DiffSource::Revision { revision: format!("{}^", commit.short_hash()), path: old_path },
+		};
+
+		let right = match self.status {
+			'D' => DiffSource::Empty(format!("(deleted in {})", commit.short_hash())),
+			_ => DiffSource::Revision { revision: String::from(commit.short_hash()), path },
+		};
+
+		DiffRequest { left, right }
+	}
+}
+
+// commits of the repository together with lanes they are drawn in
+#[derive(Default)]
+pub struct CommitGraph {
+	pub root		: PathBuf,
+	pub commits		: Vec<GraphCommit>,
+	pub lanes		: Vec<usize>,
+	pub lane_count	: usize,
+	pub edges		: Vec<(usize, usize)>, // child and parent indices, parents that didn't fit into MAX_COMMITS are left out
+}
+
+impl CommitGraph {
+	// newest commits come first. Every commit takes the lane its first child reserved for it, other parents get free lanes
+	pub fn new(root: PathBuf, commits: Vec<GraphCommit>) -> Self {
+		let index_of : HashMap<&str, usize> = commits.iter().enumerate().map(|(index, commit)| (commit.hash.as_str(), index)).collect();
+
+		let mut reserved : Vec<Option<&str>> = Vec::new();
+		let mut lanes = Vec::with_capacity(commits.len());
+		let mut edges = Vec::new();
+
+		for (index, commit) in commits.iter().enumerate() {
+			let hash = commit.hash.as_str();
+
+			let lane = match reserved.iter().position(|reserved_hash| *reserved_hash == Some(hash)) {
+				Some(lane) => lane,
+				None => free_lane(&mut reserved),
+			};
+
+			// branches that meet here end in this lane
+			for reserved_hash in reserved.iter_mut() {
+				if *reserved_hash == Some(hash) {
+					*reserved_hash = None;
+				}
+			}
+
+			for (parent_index, parent) in commit.parents.iter().enumerate() {
+				if let Some(parent_row) = index_of.get(parent.as_str()) {
+					edges.push((index, *parent_row));
+				}
+
+				if reserved.iter().any(|reserved_hash| *reserved_hash == Some(parent.as_str())) {
+					continue;
+				}
+
+				let parent_lane = if parent_index == 0 { lane } else { free_lane(&mut reserved) };
+				reserved[parent_lane] = Some(parent.as_str());
+			}
+
+			lanes.push(lane);
+		}
+
+		let lane_count = lanes.iter().max().map_or(0, |lane| lane + 1);
+
+		Self { root, commits, lanes, lane_count, edges }
+	}
+}
+
+fn free_lane(reserved: &mut Vec<Option<&str>>) -> usize {
+	match reserved.iter().position(|reserved_hash| reserved_hash.is_none()) {
+		Some(lane) => lane,
+		None => {
+			reserved.push(None);
+			reserved.len() - 1
+		}
+	}
+}
+
+pub fn repository_root(dir: &Path) -> Result<PathBuf, String> {
+	let output = Command::new("git")
+		.current_dir(dir)
+		.args(["rev-parse", "--show-toplevel"])
+		.output()
+		.map_err(|e| e.to_string())?;
+
+	if !output.status.success() {
+		return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+	}
+
+	Ok(PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()))
+}
+
+// all branches of repository the workspace is in, newest first
+pub fn load_graph(workspace: &Path) -> Result<CommitGraph, String> {
+	let root = repository_root(workspace)?;
+
+	let max_count = format!("--max-count={}", MAX_COMMITS);
+
+	let output = Command::new("git")
+		.current_dir(&root)
+		.args(["log", "--all", "--date-order", "--format=%H%x1f%P%x1f%an%x1f%at%x1f%D%x1f%s", max_count.as_str()])
+		.output()
+		.map_err(|e| e.to_string())?;
+
+	if !output.status.success() {
+		return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+	}
+
+	let commits = String::from_utf8_lossy(&output.stdout)
+		.lines()
+		.filter_map(|row| {
+			let mut fields = row.splitn(6, '\x1f');
+
+			Some(GraphCommit {
+				hash	: fields.next()?.to_string(),
+				parents	: fields.next()?.split_whitespace().map(String::from).collect(),
+				author	: fields.next()?.to_string(),
+				time	: fields.next()?.parse().unwrap_or_default(),
+				refs	: fields.next()?.to_string(),
+				subject	: fields.next().unwrap_or_default().to_string(),
+			})
+		})
+		.collect();
+
+	Ok(CommitGraph::new(root, commits))
+}
+
+// merges are compared with their first parent
+pub fn changed_files(root: &Path, hash: &str) -> Result<Vec<ChangedFile>, String> {
+	let output = Command::new("git")
+		.current_dir(root)
+		.args(["diff-tree", "-r", "--root", "-m", "--first-parent", "--no-commit-id", "--name-status", hash])
+		.output()
+		.map_err(|e| e.to_string())?;
+
+	if !output.status.success() {
+		return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+	}
+
+	let files = String::from_utf8_lossy(&output.stdout)
+		.lines()
+		.filter_map(|row| {
+			let mut fields = row.split('\t');
+
+			let status = fields.next()?.chars().next()?;
+
+			// renames and copies list old path first
+			let first	= PathBuf::from(fields.next()?);
+			let second	= fields.next().map(PathBuf::from);
+
+			Some(match second {
+				Some(path) => ChangedFile { status, path, old_path: Some(first) },
+				None => ChangedFile { status, path: first, old_path: None },
+			})
+		})
+		.collect();
+
+	Ok(files)
+}
+
+#[derive(Component)]
+pub struct HistoryCommitNode {
+	pub index : usize,
+}
+
+#[derive(Component)]
+pub struct HistoryFileEntry {
+	pub index : usize,
+}
+
+#[derive(Component)]
+pub struct HistoryAnchor;
+
+// commit graph shown in its own context, camera travels along it following selected commit
+#[derive(Resource, Default)]
+pub struct GitHistory {
+	pub active			: bool,
+	pub graph			: Option<CommitGraph>,
+	pub load_task		: Option<Task<Result<CommitGraph, String>>>,
+	pub error			: Option<String>,
+
+	pub selected		: usize,
+	pub files			: Option<Vec<ChangedFile>>, // changed files of selected commit once it is expanded
+	pub selected_file	: Option<usize>,
+	pub diff_requested	: Option<DiffRequest>,
+
+	pub root_entity		: Option<Entity>,
+	pub anchor_entity	: Option<Entity>,
+	pub marker_entity	: Option<Entity>, // highlights selected commit
+	pub traveled_to		: Option<usize>, // commit camera anchor was last sent to
+	pub details			: Vec<Entity>,
+	pub details_cache	: Option<(usize, Option<usize>, bool, bool)>, // selected commit, selected file, whether files and error are shown
+}
+
+pub fn history_inactive(history: Res<GitHistory>) -> bool {
+	!history.active
+}
+
+impl GitHistory {
+	pub fn select(&mut self, index: usize) {
+		let Some(graph) = self.graph.as_ref() else { return };
+
+		let index = index.min(graph.commits.len().saturating_sub(1));
+
+		if index != self.selected {
+			self.selected		= index;
+			self.files			= None;
+			self.selected_file	= None;
+			self.error			= None;
+		}
+	}
+
+	pub fn select_relative(&mut self, delta: i64) {
+		self.select((self.selected as i64 + delta).max(0) as usize);
+	}
+
+	pub fn selected_commit(&self) -> Option<&GraphCommit> {
+		self.graph.as_ref()?.commits.get(self.selected)
+	}
+
+	pub fn toggle_files(&mut self) -> Result<(), String> {
+		if self.files.take().is_some() {
+			self.selected_file = None;
+			return Ok(());
+		}
+
+		let Some(graph) = self.graph.as_ref() else { return Ok(()) };
+		let Some(commit) = graph.commits.get(self.selected) else { return Ok(()) };
+
+		self.files = Some(changed_files(&graph.root, &commit.hash)?);
+
+		Ok(())
+	}
+
+	pub fn select_file_relative(&mut self, delta: i64) {
+		let Some(files) = self.files.as_ref() else { return };
+		if files.is_empty() { return }
+
+		let index = self.selected_file.map_or(0, |index| (index as i64 + delta).clamp(0, files.len() as i64 - 1) as usize);
+
+		self.selected_file = Some(index);
+	}
+
+	// diff is opened by diff view once code editor is back
+	pub fn request_diff(&mut self, file_index: usize) {
+		let Some(graph) = self.graph.as_ref() else { return };
+		let Some(commit) = graph.commits.get(self.selected) else { return };
+		let Some(file) = self.files.as_ref().and_then(|files| files.get(file_index)) else { return };
+
+		self.diff_requested = Some(file.diff_request(&graph.root, commit));
+	}
+
+	// everything spawned for history, details and marker are children of the root
+	pub fn take_entities(&mut self) -> Vec<Entity> {
+		self.marker_entity	= None;
+		self.traveled_to	= None;
+		self.details_cache	= None;
+		self.details.clear();
+
+		self.root_entity.take().into_iter().chain(self.anchor_entity.take()).collect()
+	}
+
+	pub fn clear(&mut self) {
+		self.active			= false;
+		self.graph			= None;
+		self.load_task		= None;
+		self.error			= None;
+		self.selected		= 0;
+		self.files			= None;
+		self.selected_file	= None;
+		self.diff_requested	= None;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super :: *;
+
+	fn commit(hash: &str, parents: &[&str]) -> GraphCommit {
+		GraphCommit {
+			hash	: String::from(hash),
+			parents	: parents.iter().map(|parent| String::from(*parent)).collect(),
+			author	: String::new(),
+			time	: 0,
+			refs	: String::new(),
+			subject	: String::new(),
+		}
+	}
+
+	#[test]
+	fn commit_graph_merge_lanes() {
+		// merge of a branch that forked off the root
+		let commits = vec![
+			commit("m", &["a", "b"]),
+			commit("a", &["c"]),
+			commit("b", &["c"]),
+			commit("c", &[]),
+		];
+
+		let graph = CommitGraph::new(PathBuf::new(), commits);
+
+		assert_eq!(graph.lanes, vec![0, 0, 1, 0]);
+		assert_eq!(graph.lane_count, 2);
+		assert_eq!(graph.edges, vec![(0, 1), (0, 2), (1, 3), (2, 3)]);
+	}
+
+	#[test]
+	fn commit_graph_lane_is_reused_after_branches_meet() {
+		let commits = vec![
+			commit("m", &["a", "b"]),
+			commit("b", &["c"]),
+			commit("a", &["c"]),
+			commit("x", &["c"]), // separate branch that is not merged anywhere
+			commit("c", &["outside"]), // parent that didn't fit into the log
+		];
+
+		let graph = CommitGraph::new(PathBuf::new(), commits);
+
+		// lane of "a" is free once it joins "c" so "x" takes it
+		assert_eq!(graph.lanes, vec![0, 1, 0, 0, 1]);
+		assert_eq!(graph.lane_count, 2);
+		assert_eq!(graph.edges, vec![(0, 2), (0, 1), (1, 4), (2, 4), (3, 4)]);
+	}
+
+	#[test]
+	fn renamed_file_is_diffed_against_old_path() {
+		let file = ChangedFile { status: 'R', path: PathBuf::from("new.rs"), old_path: Some(PathBuf::from("old.rs")) };
+		let request = file.diff_request(Path::new("/repo"), &commit("0123456789", &["parent"]));
+
+		assert_eq!(request.left, DiffSource::Revision { revision: String::from("01234567^"), path: PathBuf::from("/repo/old.rs") });
+		assert_eq!(request.right, DiffSource::Revision { revision: String::from("01234567"), path: PathBuf::from("/repo/new.rs") });
+	}
+}
@@ -0,0 +1,569 @@
+use bevy :: prelude :: *;
+use bevy :: tasks :: AsyncComputeTaskPool;
+use bevy_tweening :: *;
+use bevy_reader_camera :: { ReaderCamera, TextDescriptor };
+
+#[cfg(feature = "tracing")]
+use bevy_puffin :: *;
+
+use futures_lite :: future;
+
+use helix_term :: ui :: EditorView;
+use helix_view :: { Theme, graphics :: Color as HelixColor };
+
+use std :: time :: Duration;
+
+use super :: *;
+
+use crate :: {
+	z_order,
+	kodiki :: { CODE_EDITOR_CONTEXT_NAME, ContextRegistry, DespawnResource },
+	kodiki_ui :: {
+		String3dSpawnRequest, CommonString3dSpawnParams, ColorMaterialsCache,
+		color :: get_color_material_handle,
+		raypick :: RaypickHover,
+		spawn :: string_mesh_collision,
+		tween_lens :: TransformLens,
+	},
+	bevy_ab_glyph :: { ABGlyphFont, ABGlyphFonts, FontAssetHandles },
+	bevy_framerate_manager :: FramerateManager,
+	bevy_helix :: {
+		HelixApp, KeyboardInputGuard,
+		blame :: relative_time,
+		diagnostics_lens :: short_message,
+		diff_view :: DiffView,
+		surface :: SurfacesMapBevy,
+		utils :: color_from_helix,
+	},
+};
+
+// where things go on the graph plane, rows grow downwards from the newest commit
+struct GraphMetrics {
+	column_width	: f32,
+	row_height		: f32,
+	label_column	: f32,
+}
+
+impl GraphMetrics {
+	fn new(font: &ABGlyphFont, lane_count: usize) -> Self {
+		Self {
+			column_width	: font.horizontal_advance_mono(),
+			row_height		: font.vertical_advance(),
+			label_column	: lane_count as f32 * LANE_COLUMNS + 1.0,
+		}
+	}
+
+	fn row_y(&self, row: f32) -> f32 {
+		-(row * NODE_ROWS + 1.0) * self.row_height
+	}
+
+	fn node_position(&self, row: f32, lane: usize) -> Vec3 {
+		Vec3::new(
+			lane as f32 * LANE_COLUMNS * self.column_width,
+			self.row_y(row),
+			-(lane as f32) * LANE_DEPTH_COLUMNS * self.column_width + z_order::surface::text()
+		)
+	}
+
+	// strings grow right and up from their position so edges are attached to the middle of the node glyph
+	fn node_center(&self, row: f32, lane: usize) -> Vec3 {
+		self.node_position(row, lane) + Vec3::new(self.column_width / 2.0, self.row_height / 2.0, z_order::surface::coloring() - z_order::surface::text())
+	}
+
+	fn label_position(&self, row: usize) -> Vec3 {
+		Vec3::new(self.label_column * self.column_width, self.row_y(row as f32), z_order::surface::text())
+	}
+
+	fn details_position(&self, row: usize, line: usize) -> Vec3 {
+		let column = self.label_column + (LABEL_COLUMNS + FILES_GAP_COLUMNS) as f32;
+
+		Vec3::new(column * self.column_width, self.row_y(row as f32) - line as f32 * self.row_height, z_order::surface::text())
+	}
+
+	// selected commit stays in the middle of the screen
+	fn anchor_position(&self, row: usize, visible_rows: f32) -> Vec3 {
+		Vec3::new(0.0, self.row_y(row as f32) + (visible_rows / 2.0).floor() * self.row_height, 0.0)
+	}
+}
+
+fn lane_color(theme: &Theme, lane: usize) -> Color {
+	const SCOPES : [&str; 6] = ["function", "keyword", "string", "type", "constant", "diff.delta"];
+
+	color_from_helix(theme.get(SCOPES[lane % SCOPES.len()]).fg.unwrap_or(HelixColor::Cyan))
+}
+
+fn spawn_string(
+	string				: String,
+	position			: Vec3,
+	color				: Color,
+	background_color	: Option<Color>,
+	commands			: &mut Commands,
+) -> Entity {
+	commands.spawn((
+		TransformBundle::from_transform(Transform::from_translation(position)),
+		VisibilityBundle::default(),
+		String3dSpawnRequest {
+			common : CommonString3dSpawnParams {
+				string,
+				color,
+				background_color,
+				..default()
+			},
+			..default()
+		},
+	)).id()
+}
+
+fn make_clickable(entity: Entity, string: &String, font: &ABGlyphFont, commands: &mut Commands) {
+	let collision_entity = string_mesh_collision(string, font, commands);
+
+	commands.entity(entity)
+		.insert(RaypickHover::default())
+		.add_child(collision_entity);
+}
+
+// unit quad stretched between two points
+fn spawn_segment(
+	from		: Vec3,
+	to			: Vec3,
+	thickness	: f32,
+	mesh		: &Handle<Mesh>,
+	material	: &Handle<StandardMaterial>,
+	commands	: &mut Commands,
+) -> Option<Entity> {
+	let delta	= to - from;
+	let length	= delta.length();
+
+	if length < f32::EPSILON { return None }
+
+	Some(commands.spawn(PbrBundle {
+		mesh		: mesh.clone(),
+		material	: material.clone(),
+		transform	: Transform {
+			translation	: (from + to) / 2.0,
+			rotation	: Quat::from_rotation_arc(Vec3::Y, delta / length),
+			scale		: Vec3::new(thickness, length, 1.0),
+		},
+		..default()
+	}).id())
+}
+
+// esc goes back to code editor, up/down (j/k) select commits, enter or right (l) lists changed files, enter on a file opens its diff
+pub fn input_keyboard(
+		key					: Res<Input<KeyCode>>,
+	mut input_guard			: ResMut<KeyboardInputGuard>,
+	mut history				: ResMut<GitHistory>,
+	mut context_registry	: ResMut<ContextRegistry>,
+) {
+	if !history.active { return }
+
+	let files_selected = history.selected_file.is_some();
+
+	if key.just_pressed(KeyCode::Escape) {
+		if history.files.is_some() {
+			history.files = None;
+			history.selected_file = None;
+		} else {
+			context_registry.request_switch_by_name(CODE_EDITOR_CONTEXT_NAME);
+
+			// escape is still buffered and Helix would get it once code editor is back
+			input_guard.consume();
+		}
+	} else if key.just_pressed(KeyCode::Up) || key.just_pressed(KeyCode::K) {
+		if files_selected { history.select_file_relative(-1) } else { history.select_relative(-1) }
+	} else if key.just_pressed(KeyCode::Down) || key.just_pressed(KeyCode::J) {
+		if files_selected { history.select_file_relative(1) } else { history.select_relative(1) }
+	} else if key.just_pressed(KeyCode::PageUp) {
+		history.select_relative(-(PAGE_COMMITS as i64));
+	} else if key.just_pressed(KeyCode::PageDown) {
+		history.select_relative(PAGE_COMMITS as i64);
+	} else if key.just_pressed(KeyCode::Home) {
+		history.select(0);
+	} else if key.just_pressed(KeyCode::End) {
+		history.select(usize::MAX);
+	} else if key.just_pressed(KeyCode::Right) || key.just_pressed(KeyCode::L) {
+		if history.files.is_none() {
+			if let Err(e) = history.toggle_files() {
+				history.error = Some(e);
+			}
+		}
+
+		history.select_file_relative(0);
+	} else if key.just_pressed(KeyCode::Left) || key.just_pressed(KeyCode::H) {
+		history.selected_file = None;
+	} else if key.just_pressed(KeyCode::Return) {
+		match history.selected_file {
+			Some(file_index) => history.request_diff(file_index),
+			None => if let Err(e) = history.toggle_files() {
+				history.error = Some(e);
+			},
+		}
+	}
+}
+
+// clicking a commit selects it, clicking selected commit lists its files, clicking a file opens its diff
+pub fn input_mouse(
+		mouse_button	: Res<Input<MouseButton>>,
+		q_node			: Query<(&HistoryCommitNode, &RaypickHover)>,
+		q_file			: Query<(&HistoryFileEntry, &RaypickHover)>,
+	mut history			: ResMut<GitHistory>,
+) {
+	if !history.active || !mouse_button.just_pressed(MouseButton::Left) { return }
+
+	if let Some((entry, _)) = q_file.iter().find(|(_, hover)| hover.hovered()) {
+		history.selected_file = Some(entry.index);
+		history.request_diff(entry.index);
+		return;
+	}
+
+	let Some((node, _)) = q_node.iter().find(|(_, hover)| hover.hovered()) else { return };
+
+	if node.index != history.selected {
+		history.select(node.index);
+	} else if let Err(e) = history.toggle_files() {
+		history.error = Some(e);
+	}
+}
+
+// history is shown while its entry is active in context switcher, editor is hidden meanwhile
+pub fn update_activation(
+		context_registry	: Res<ContextRegistry>,
+	mut history				: ResMut<GitHistory>,
+		surfaces_bevy		: Res<SurfacesMapBevy>,
+	mut q_camera			: Query<&mut ReaderCamera>,
+	mut q_visibility		: Query<&mut Visibility>,
+	mut despawn				: ResMut<DespawnResource>,
+) {
+	let should_be_active = context_registry.active().map_or(false, |desc| desc.name == HISTORY_CONTEXT_NAME);
+
+	if should_be_active == history.active { return }
+
+	if should_be_active {
+		history.active = true;
+
+		let workspace = std::env::current_dir().unwrap_or_default();
+
+		history.load_task = Some(AsyncComputeTaskPool::get().spawn(async move {
+			load_graph(&workspace)
+		}));
+	} else {
+		for entity in history.take_entities() {
+			despawn.recursive.push(entity);
+		}
+
+		history.clear();
+	}
+
+	let Ok(mut reader_camera) = q_camera.get_single_mut() else { return };
+
+	for (_surface_name, surface_bevy) in surfaces_bevy.iter() {
+		let Ok(mut visibility) = q_visibility.get_mut(surface_bevy.entity) else { continue };
+		*visibility.as_mut() = if should_be_active { Visibility::Hidden } else { Visibility::Visible };
+
+		if !should_be_active && surface_bevy.name == EditorView::ID {
+			reader_camera.target_entity = Some(surface_bevy.entity);
+			reader_camera.column = (surface_bevy.area.width / 2) as usize;
+		}
+	}
+}
+
+// once git log is there every commit gets a node in its lane, a label and edges to its parents
+pub fn spawn_graph(
+	mut history			: ResMut<GitHistory>,
+	mut q_camera		: Query<&mut ReaderCamera>,
+		font_assets		: Res<Assets<ABGlyphFont>>,
+		font_handles	: Res<FontAssetHandles>,
+	mut color_materials_cache : ResMut<ColorMaterialsCache>,
+	mut mesh_assets		: ResMut<Assets<Mesh>>,
+	mut material_assets	: ResMut<Assets<StandardMaterial>>,
+	mut commands		: Commands,
+		app_option		: Option<NonSend<HelixApp>>,
+) {
+	let Some(mut load_task) = history.load_task.take() else { return };
+
+	let result = match future::block_on(future::poll_once(&mut load_task)) {
+		Some(result) => result,
+		None => {
+			history.load_task = Some(load_task);
+			return;
+		}
+	};
+
+	let Some(app) = app_option else { return };
+	let Ok(mut reader_camera) = q_camera.get_single_mut() else { return };
+
+	profile_function!();
+
+	let graph = result.unwrap_or_else(|e| {
+		history.error = Some(e);
+		CommitGraph::default()
+	});
+
+	let fonts	= ABGlyphFonts::new(&font_assets, &font_handles);
+	let metrics	= GraphMetrics::new(fonts.main, graph.lane_count);
+
+	let theme		= &app.editor.theme;
+	let text_color	= color_from_helix(theme.get("ui.text").fg.unwrap_or(HelixColor::White));
+	let refs_color	= color_from_helix(theme.get("ui.statusline").bg.unwrap_or(HelixColor::Cyan));
+	let marker_color = color_from_helix(theme.get("ui.selection").bg.unwrap_or(HelixColor::Gray));
+
+	let root_entity = commands.spawn((
+		TransformBundle::default(),
+		VisibilityBundle::default(),
+	)).id();
+
+	let mut children = Vec::new();
+
+	let quad_mesh = mesh_assets.add(Mesh::from(shape::Quad::new(Vec2::ONE)));
+	let edge_thickness = EDGE_THICKNESS * metrics.column_width;
+
+	for (child, parent) in graph.edges.iter().copied() {
+		let child_lane	= graph.lanes[child];
+		let parent_lane	= graph.lanes[parent];
+
+		let from	= metrics.node_center(child as f32, child_lane);
+		let to		= metrics.node_center(parent as f32, parent_lane);
+
+		// branches leave their lane right away and join another one right before the parent
+		let bend = if parent_lane > child_lane {
+			metrics.node_center(child as f32 + 1.0, parent_lane)
+		} else {
+			metrics.node_center(parent as f32 - 1.0, child_lane)
+		};
+
+		let material = get_color_material_handle(lane_color(theme, child_lane.max(parent_lane)), &mut color_materials_cache, &mut material_assets);
+
+		let points = if child_lane == parent_lane { vec![from, to] } else { vec![from, bend, to] };
+
+		for segment in points.windows(2) {
+			if let Some(entity) = spawn_segment(segment[0], segment[1], edge_thickness, &quad_mesh, &material, &mut commands) {
+				children.push(entity);
+			}
+		}
+	}
+
+	for (index, commit) in graph.commits.iter().enumerate() {
+		let lane = graph.lanes[index];
+
+		let node = String::from("●");
+		let node_entity = spawn_string(node.clone(), metrics.node_position(index as f32, lane), lane_color(theme, lane), None, &mut commands);
+		make_clickable(node_entity, &node, fonts.main, &mut commands);
+
+		let label = if commit.refs.is_empty() {
+			format!("{} {}", commit.short_hash(), commit.subject)
+		} else {
+			format!("{} ({}) {}", commit.short_hash(), commit.refs, commit.subject)
+		};
+
+		let label = short_message(label.as_str(), LABEL_COLUMNS);
+		let label_color = if commit.refs.is_empty() { text_color } else { refs_color };
+
+		let label_entity = spawn_string(label.clone(), metrics.label_position(index), label_color, None, &mut commands);
+		make_clickable(label_entity, &label, fonts.main, &mut commands);
+
+		commands.entity(node_entity).insert(HistoryCommitNode { index });
+		commands.entity(label_entity).insert(HistoryCommitNode { index });
+
+		children.push(node_entity);
+		children.push(label_entity);
+	}
+
+	// marker is moved under selected label in update_selection
+	let marker_material = get_color_material_handle(marker_color, &mut color_materials_cache, &mut material_assets);
+	let marker_entity = commands.spawn(PbrBundle {
+		mesh		: quad_mesh,
+		material	: marker_material,
+		transform	: Transform::from_scale(Vec3::new(LABEL_COLUMNS as f32 * metrics.column_width, metrics.row_height, 1.0)),
+		..default()
+	}).id();
+
+	children.push(marker_entity);
+
+	commands.entity(root_entity).push_children(children.as_slice());
+
+	let anchor_entity = commands.spawn((
+		HistoryAnchor,
+		TransformBundle::from_transform(Transform::from_translation(metrics.anchor_position(history.selected, reader_camera.visible_rows))),
+		TextDescriptor {
+			rows			: reader_camera.visible_rows as usize,
+			columns			: VIEW_COLUMNS,
+			glyph_width		: metrics.column_width,
+			glyph_height	: metrics.row_height,
+		},
+	)).id();
+
+	reader_camera.target_entity = Some(anchor_entity);
+	reader_camera.column = VIEW_COLUMNS / 2;
+	reader_camera.set_row_offset_in(0);
+
+	history.graph			= Some(graph);
+	history.root_entity		= Some(root_entity);
+	history.anchor_entity	= Some(anchor_entity);
+	history.marker_entity	= Some(marker_entity);
+}
+
+fn travel(
+	anchor_entity	: Entity,
+	start			: Transform,
+	end				: Transform,
+	commands		: &mut Commands,
+) {
+	let distance = start.translation.distance(end.translation);
+	let travel_duration = (distance * 100.0).clamp(150.0, 500.0) as u64;
+
+	let tween = Tween::new(
+		EaseFunction::QuadraticInOut,
+		Duration::from_millis(travel_duration),
+		TransformLens {
+			start,
+			end,
+		}
+	);
+
+	commands.entity(anchor_entity).insert(Animator::new(tween));
+}
+
+// camera travels to selected commit, details and changed files of it are listed to the right of labels
+pub fn update_selection(
+	mut history				: ResMut<GitHistory>,
+		q_camera			: Query<&ReaderCamera>,
+	mut q_transform			: Query<&mut Transform, Without<ReaderCamera>>,
+		q_animator			: Query<&Animator<Transform>, With<HistoryAnchor>>,
+	mut framerate_manager	: ResMut<FramerateManager>,
+		font_assets			: Res<Assets<ABGlyphFont>>,
+		font_handles		: Res<FontAssetHandles>,
+	mut commands			: Commands,
+		app_option			: Option<NonSend<HelixApp>>,
+) {
+	let Some(app) = app_option else { return };
+
+	// graph is borrowed while the rest of history is updated
+	let history = &mut *history;
+
+	let (Some(graph), Some(root_entity), Some(anchor_entity)) = (history.graph.as_ref(), history.root_entity, history.anchor_entity) else { return };
+	let Ok(reader_camera) = q_camera.get_single() else { return };
+
+	profile_function!();
+
+	if let Ok(animator) = q_animator.get(anchor_entity) {
+		let progress = animator.tweenable().progress();
+		if progress < 1.0 {
+			framerate_manager.request_active_framerate(format!("history camera travel {:.2}%", progress * 100.));
+		}
+	}
+
+	let fonts	= ABGlyphFonts::new(&font_assets, &font_handles);
+	let metrics	= GraphMetrics::new(fonts.main, graph.lane_count);
+
+	let selected = history.selected;
+
+	if history.traveled_to != Some(selected) {
+		// anchor spawned this frame is not there until commands are applied
+		let Ok(anchor_transform) = q_transform.get(anchor_entity).map(|transform| *transform) else { return };
+
+		let end = Transform::from_translation(metrics.anchor_position(selected, reader_camera.visible_rows));
+		travel(anchor_entity, anchor_transform, end, &mut commands);
+
+		if let Some(mut marker_transform) = history.marker_entity.and_then(|entity| q_transform.get_mut(entity).ok()) {
+			let label_size = marker_transform.scale.truncate();
+			marker_transform.translation = metrics.label_position(selected) + Vec3::new(label_size.x / 2.0, label_size.y / 2.0, z_order::surface::highlight_selection() - z_order::surface::text());
+		}
+
+		history.traveled_to = Some(selected);
+	}
+
+	let cache = (selected, history.selected_file, history.files.is_some(), history.error.is_some());
+
+	if history.details_cache == Some(cache) { return }
+
+	for entity in history.details.drain(..) {
+		commands.entity(entity).despawn_recursive();
+	}
+
+	let theme			= &app.editor.theme;
+	let text_color		= color_from_helix(theme.get("ui.text").fg.unwrap_or(HelixColor::White));
+	let comment_color	= color_from_helix(theme.get("comment").fg.unwrap_or(HelixColor::Gray));
+	let error_color		= color_from_helix(theme.get("error").fg.unwrap_or(HelixColor::Red));
+	let selection_color	= color_from_helix(theme.get("ui.selection").bg.unwrap_or(HelixColor::Gray));
+
+	let mut lines : Vec<(String, Color)> = Vec::new();
+
+	if let Some(commit) = graph.commits.get(selected) {
+		lines.push((commit.hash.clone(), text_color));
+		lines.push((format!("{}, {}", commit.author, relative_time(commit.time)), comment_color));
+
+		if !commit.refs.is_empty() {
+			lines.push((commit.refs.clone(), comment_color));
+		}
+
+		lines.push((short_message(commit.subject.as_str(), FILE_COLUMNS), text_color));
+		lines.push((String::new(), text_color));
+
+		match history.files.as_ref() {
+			None => lines.push((String::from("enter: changed files, esc: back to editor"), comment_color)),
+			Some(files) if files.is_empty() => lines.push((String::from("no changed files"), comment_color)),
+			Some(_) => (),
+		}
+	} else if history.error.is_none() {
+		lines.push((String::from("No commits"), comment_color));
+	}
+
+	let mut details = Vec::new();
+
+	for (line, (string, color)) in lines.into_iter().enumerate() {
+		if string.is_empty() { continue }
+
+		details.push(spawn_string(string, metrics.details_position(selected, line), color, None, &mut commands));
+	}
+
+	let files_line = details.len() + 1;
+
+	for (index, file) in history.files.iter().flatten().enumerate() {
+		let string = short_message(format!("{} {}", file.status, file.path.display()).as_str(), FILE_COLUMNS);
+		let background = if history.selected_file == Some(index) { Some(selection_color) } else { None };
+
+		let entity = spawn_string(string.clone(), metrics.details_position(selected, files_line + index), text_color, background, &mut commands);
+		make_clickable(entity, &string, fonts.main, &mut commands);
+
+		commands.entity(entity).insert(HistoryFileEntry { index });
+
+		details.push(entity);
+	}
+
+	if let Some(error) = history.error.as_ref() {
+		let line = files_line + history.files.as_ref().map_or(0, |files| files.len()) + 1;
+		details.push(spawn_string(short_message(error.as_str(), FILE_COLUMNS), metrics.details_position(selected, line), error_color, None, &mut commands));
+	}
+
+	commands.entity(root_entity).push_children(details.as_slice());
+
+	history.details			= details;
+	history.details_cache	= Some(cache);
+}
+
+// diff is shown by diff view in code editor
+pub fn open_requested_diff(
+	mut history				: ResMut<GitHistory>,
+		diff_view			: Res<DiffView>,
+	mut input_guard			: ResMut<KeyboardInputGuard>,
+	mut context_registry	: ResMut<ContextRegistry>,
+) {
+	let Some(request) = history.diff_requested.take() else { return };
+
+	*diff_view.pending.lock().unwrap() = Some(request);
+
+	context_registry.request_switch_by_name(CODE_EDITOR_CONTEXT_NAME);
+	input_guard.consume();
+}
+
+// graph is respawned from fresh git log every time history context is entered
+pub fn on_context_switch_out(
+	mut history	: ResMut<GitHistory>,
+	mut despawn	: ResMut<DespawnResource>,
+) {
+	for entity in history.take_entities() {
+		despawn.recursive.push(entity);
+	}
+
+	history.clear();
+}
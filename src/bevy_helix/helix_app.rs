@@ -17,20 +17,23 @@ use helix_view :: {
 	current, theme, Editor,
 	graphics::Rect, input::Event, tree::Layout,
 	doc, doc_mut, DocumentId, Document,
-	view, view_mut, View,
+	view, view_mut, View, ViewId,
 	align_view, Align,
 
-	editor		:: { ConfigEvent, EditorEvent, Action },
+	editor		:: { ConfigEvent, EditorEvent, Action, Breakpoint, GutterType },
+	handlers	:: dap :: breakpoints_changed,
 	document	:: { Mode, DocumentSavedEventResult },
 };
 use helix_term :: {
 	ui, ui::PromptEvent, config::Config, job::Jobs, args::Args, keymap::Keymaps, compositor::Compositor, compositor::SurfacesMap,
 	commands, commands::apply_workspace_edit, 
+	ui::overlay::overlayed, job,
 };
 use serde_json :: json;
 
 use std	:: {
-	path	:: Path,
+	collections :: HashSet,
+	path	:: { Path, PathBuf },
 	sync	:: Arc,
 	pin		:: Pin
 };
@@ -39,6 +42,17 @@ use tokio :: time :: { sleep, Sleep, Duration };
 
 use anyhow :: { Context, Error };
 
+use super :: {
+	blame :: { self, LineCommit, SharedCommitRequest },
+	diff_view :: { self, SharedDiffRequest },
+	file_sync,
+	lsp_status :: { self, ServerAction },
+	recovery :: { RecoveredBuffer, RecoveryAction },
+	workspace_search :: { SearchField, SharedSearchInput },
+	file_explorer :: { ExplorerOperation, SharedExplorerInput },
+	outline :: SharedOutlineInput,
+};
+
 #[cfg(not(windows))]
 use {
 	signal_hook :: consts::signal,
@@ -47,6 +61,17 @@ use {
 #[cfg(windows)]
 type Signals = futures_util::stream::Empty<()>;
 
+// a single helix view (split) as seen by bevy side
+#[derive(Clone, Copy, Debug)]
+pub struct ViewDesc {
+	pub id		: ViewId,
+	pub doc_id	: DocumentId,
+	pub area	: Rect,
+	pub focused	: bool,
+	pub top_row	: usize,
+	pub cursor	: Option<helix_core::Position>, // primary cursor in editor surface coordinates, None if it's outside of view
+}
+
 pub struct HelixApp {
 	pub editor	: Editor,
 	compositor	: Compositor,
@@ -61,6 +86,11 @@ pub struct HelixApp {
 	signals		: Signals,
 	jobs		: Jobs,
 	lsp_progress: LspProgressMap,
+	lsp_crashed	: HashSet<usize>, // servers that exited while documents were still using them
+	lsp_events	: usize, // bumped on every message from language servers so that their status is not recollected every frame
+
+	pub autosave_on_idle : bool,
+	pub written_files : Vec<file_sync::WrittenFile>, // saved since file watcher has seen them last time
 
 	tokio_idle_timer : Pin<Box<Sleep>>,
 
@@ -217,6 +247,10 @@ impl HelixApp {
 			signals,
 			jobs: Jobs::new(),
 			lsp_progress: LspProgressMap::new(),
+			lsp_crashed: HashSet::new(),
+			lsp_events: 0,
+			autosave_on_idle: false,
+			written_files: Vec::new(),
 			tokio_idle_timer: Box::pin(sleep(Duration::ZERO)),
 
 			last_doc_id: doc_id,
@@ -234,6 +268,10 @@ impl HelixApp {
 		self.should_render
 	}
 
+	pub fn request_render(&mut self) {
+		self.should_render = true;
+	}
+
 	pub fn editor_area(&self) -> Rect {
 		self.editor_area
 	}
@@ -333,10 +371,60 @@ impl HelixApp {
 		self.should_render = false;
 	}
 
+	// Helix renders extra rows below viewport to fill the gap that rows hidden by folds free up on screen.
+	// Views are shrunk back right after so that scrolling and cursor movement only deal with rows of the actual viewport
+	pub fn render_with_hidden_rows(&mut self, surfaces: &mut SurfacesMap, hidden_rows: u16) {
+		if hidden_rows == 0 {
+			self.render(surfaces);
+			return;
+		}
+
+		let editor_area = self.editor_area;
+		self.editor_area.height += hidden_rows;
+
+		self.render(surfaces);
+
+		self.editor_area = editor_area;
+		self.compositor.resize(editor_area);
+
+		let mut tree_area = self.editor.tree.area();
+		tree_area.height = tree_area.height.saturating_sub(hidden_rows);
+		self.editor.resize(tree_area);
+	}
+
 	pub fn cursor(&self) -> Option<(Vec<helix_core::Position>, &str)> {
 		self.compositor.cursor_ext(&self.editor)
 	}
 
+	pub fn views(&self) -> Vec<ViewDesc> {
+		self.editor.tree.views().map(|(view, focused)| {
+			let doc = &self.editor.documents[&view.doc];
+			let text = doc.text().slice(..);
+
+			let cursor = {
+				let pos = doc.selection(view.id).primary().cursor(text);
+				let inner = view.inner_area(doc);
+
+				view.screen_coords_at_pos(doc, text, pos).map(|coords| {
+					helix_core::Position::new(inner.y as usize + coords.row, inner.x as usize + coords.col)
+				})
+			};
+
+			ViewDesc {
+				id		: view.id,
+				doc_id	: view.doc,
+				area	: view.area,
+				focused,
+				top_row	: text.char_to_line(view.offset.anchor.min(text.len_chars())),
+				cursor,
+			}
+		}).collect()
+	}
+
+	pub fn picker_active(&self) -> bool {
+		self.compositor.has_component("Picker")
+	}
+
 	pub fn editor_focused(&self) -> bool {
 		let view = view!(self.editor);
 
@@ -420,6 +508,29 @@ impl HelixApp {
 		if let Ok(row) = row_result { row } else { doc_text.char_to_line(view.offset.anchor) }
 	}
 
+	pub fn ensure_cursor_in_view(&mut self) {
+		let scrolloff = self.editor.config().scrolloff;
+		let (view, doc) = helix_view::current!(self.editor);
+
+		view.ensure_cursor_in_view(doc, scrolloff);
+	}
+
+	pub fn primary_cursor_line(&self) -> usize {
+		let (view, doc) = self.current_ref();
+		let text = doc.text().slice(..);
+
+		text.char_to_line(doc.selection(view.id).primary().cursor(text))
+	}
+
+	// the most recent jump of focused view, it changes even when jumplist is full and the oldest jump is dropped
+	pub fn jumplist_head(&self) -> Option<(DocumentId, usize)> {
+		self.current_view().jumps.iter().last().map(|(doc_id, selection)| (*doc_id, selection.primary().head))
+	}
+
+	pub fn viewport_rows(&self) -> usize {
+		self.current_view().inner_height()
+	}
+
 	pub fn enable_inlay_hints(&mut self) {
 		self.editor.display_inlay_hints = true;
 		let mut ctx = helix_term::commands::Context {
@@ -444,6 +555,249 @@ impl HelixApp {
 		self.should_render = true;
 	}
 
+	// run a static Helix command (the kind that is usually bound to a key) outside of keymap
+	pub fn run_command(&mut self, command: fn(&mut commands::Context)) {
+		let mut ctx = helix_term::commands::Context {
+			editor	: &mut self.editor,
+			jobs	: &mut self.jobs,
+
+			callback: None,
+			count	: None,
+			on_next_key_callback: None,
+			register: None,
+		};
+		command(&mut ctx);
+
+		self.should_render = true;
+	}
+
+	pub fn toggle_breakpoint(&mut self, line: usize) {
+		let Some(path) = self.current_document().path().cloned() else {
+			self.editor.set_error("Can't set breakpoint: document has no path");
+			return;
+		};
+
+		let breakpoints = self.editor.breakpoints.entry(path.clone()).or_default();
+
+		if let Some(index) = breakpoints.iter().position(|breakpoint| breakpoint.line == line) {
+			breakpoints.remove(index);
+		} else {
+			breakpoints.push(Breakpoint { line, ..Default::default() });
+		}
+
+		// running debug session has to be told about every change
+		if let Some(debugger) = self.editor.debugger.as_mut() {
+			let breakpoints = self.editor.breakpoints.get_mut(&path).unwrap();
+
+			if let Err(e) = breakpoints_changed(debugger, path, breakpoints) {
+				self.editor.set_error(format!("Failed to synchronize breakpoints: {}", e));
+			}
+		}
+
+		self.should_render = true;
+	}
+
+	pub fn lsp_progress(&self) -> &LspProgressMap {
+		&self.lsp_progress
+	}
+
+	pub fn lsp_crashed(&self, server_id: usize) -> bool {
+		self.lsp_crashed.contains(&server_id)
+	}
+
+	pub fn lsp_events(&self) -> usize {
+		self.lsp_events
+	}
+
+	pub fn open_language_servers_picker(&mut self) {
+		let entries = lsp_status::server_actions(self);
+
+		if entries.is_empty() {
+			self.editor.set_status("No language servers are running");
+			return;
+		}
+
+		let picker = ui::Picker::new(entries, (), |cx, entry: &ServerAction, _action| {
+			entry.run(cx);
+		});
+
+		self.compositor.push(Box::new(overlayed(picker)));
+		self.should_render = true;
+	}
+
+	// writes every modified document that has a path, used by autosave
+	pub fn save_modified_documents(&mut self) {
+		let doc_ids : Vec<DocumentId> = self.editor.documents()
+			.filter(|doc| doc.is_modified() && doc.path().is_some())
+			.map(|doc| doc.id())
+			.collect();
+
+		for doc_id in doc_ids {
+			if let Err(e) = self.editor.save::<PathBuf>(doc_id, None, false) {
+				self.editor.set_error(format!("Autosave failed: {}", e));
+			}
+		}
+	}
+
+	// document was changed on disk while it has unsaved changes: show what differs and let user decide which version to keep
+	pub fn prompt_external_change(&mut self, doc_id: DocumentId, disk_text: String) {
+		let Some(doc) = self.editor.documents.get(&doc_id) else { return };
+
+		let name	= doc.display_name().to_string();
+		let diff	= file_sync::diff_markdown("Your changes (-) and changes on disk (+)", &doc.text().to_string(), &disk_text);
+
+		let markdown = ui::Markdown::new(diff, self.editor.syn_loader.clone());
+		self.compositor.replace_or_push(file_sync::CONFLICT_DIFF_POPUP_ID, ui::Popup::new(file_sync::CONFLICT_DIFF_POPUP_ID, markdown));
+
+		let prompt = ui::Prompt::new(
+			format!("'{}' changed on disk, reload and discard your changes? (y/n): ", name).into(),
+			None,
+			ui::completers::none,
+			move |cx, input, event| {
+				if event == PromptEvent::Update { return }
+
+				if event == PromptEvent::Validate && input.trim().eq_ignore_ascii_case("y") {
+					file_sync::reload_document(cx.editor, doc_id, &disk_text);
+				}
+
+				cx.jobs.callback(async move {
+					let callback = job::Callback::EditorCompositor(Box::new(|_editor: &mut Editor, compositor: &mut Compositor| {
+						compositor.remove(file_sync::CONFLICT_DIFF_POPUP_ID);
+					}));
+
+					Ok(callback)
+				});
+			}
+		);
+
+		self.compositor.push(Box::new(prompt));
+		self.should_render = true;
+	}
+
+	pub fn open_recovery_picker(&mut self, buffers: Vec<RecoveredBuffer>) {
+		let picker = ui::Picker::new(RecoveryAction::from_buffers(buffers), (), |cx, entry: &RecoveryAction, _action| {
+			entry.run(cx);
+		});
+
+		self.compositor.push(Box::new(overlayed(picker)));
+		self.should_render = true;
+	}
+
+	// commits that touched given line, selected one is handed over to Bevy side which shows its changes to the file
+	pub fn open_line_history(&mut self, commits: Vec<LineCommit>, selected: SharedCommitRequest) {
+		if commits.is_empty() {
+			self.editor.set_status("Line is not committed yet");
+			return;
+		}
+
+		let picker = ui::Picker::new(commits, (), move |_cx, entry: &LineCommit, _action| {
+			*selected.lock().unwrap() = Some(entry.clone());
+		});
+
+		self.compositor.push(Box::new(overlayed(picker)));
+		self.should_render = true;
+	}
+
+	pub fn show_commit_patch(&mut self, patch: String) {
+		let contents	= format!("```diff\n{}\n```", patch);
+		let markdown	= ui::Markdown::new(contents, self.editor.syn_loader.clone());
+		let popup		= ui::Popup::new(blame::COMMIT_POPUP_ID, markdown).auto_close(true);
+
+		self.compositor.replace_or_push(blame::COMMIT_POPUP_ID, popup);
+		self.should_render = true;
+	}
+
+	// what to compare is picked in Helix prompt, diff view opens on the Bevy side once request is there
+	pub fn prompt_diff_sources(&mut self, pending: SharedDiffRequest) {
+		let doc_id = doc!(self.editor).id();
+
+		let prompt = ui::Prompt::new(
+			"diff (empty for HEAD, revision or file, or two of them): ".into(),
+			None,
+			ui::completers::filename,
+			move |cx, input, event| {
+				if event != PromptEvent::Validate { return }
+
+				match diff_view::parse_request(input, cx.editor, doc_id) {
+					Ok(request) => *pending.lock().unwrap() = Some(request),
+					Err(e) => cx.editor.set_error(e),
+				}
+			}
+		);
+
+		self.compositor.push(Box::new(prompt));
+		self.should_render = true;
+	}
+
+	// workspace search fields are edited in Helix prompt, search panel picks the input up on the Bevy side
+	pub fn prompt_search_field(&mut self, field: SearchField, pending: SharedSearchInput) {
+		let (text, history_register) = match field {
+			SearchField::Query			=> ("search workspace: ", Some('/')),
+			SearchField::Replacement	=> ("replace with (empty to stop replacing): ", None),
+			SearchField::Include		=> ("include globs (comma separated, empty for everything): ", None),
+			SearchField::Exclude		=> ("exclude globs (comma separated): ", None),
+		};
+
+		let prompt = ui::Prompt::new(
+			text.into(),
+			history_register,
+			ui::completers::none,
+			move |_cx, input, event| {
+				if event != PromptEvent::Validate { return }
+
+				*pending.lock().unwrap() = Some((field, input.to_string()));
+			}
+		);
+
+		self.compositor.push(Box::new(prompt));
+		self.should_render = true;
+	}
+
+	pub fn prompt_outline_filter(&mut self, pending: SharedOutlineInput) {
+		let prompt = ui::Prompt::new(
+			"outline filter (empty to show all symbols): ".into(),
+			None,
+			ui::completers::none,
+			move |_cx, input, event| {
+				if event != PromptEvent::Validate { return }
+
+				*pending.lock().unwrap() = Some(input.to_string());
+			}
+		);
+
+		self.compositor.push(Box::new(prompt));
+		self.should_render = true;
+	}
+
+	pub fn prompt_explorer_operation(&mut self, operation: ExplorerOperation, target: PathBuf, relative: PathBuf, pending: SharedExplorerInput) {
+		let text = match operation {
+			ExplorerOperation::NewFile		=> format!("new file in {}/: ", relative.display()),
+			ExplorerOperation::NewDirectory	=> format!("new directory in {}/: ", relative.display()),
+			ExplorerOperation::Rename		=> format!("rename {} to: ", relative.display()),
+			ExplorerOperation::Move			=> format!("move {} to: ", relative.display()),
+			ExplorerOperation::Delete		=> format!("delete {}? (y/n): ", relative.display()),
+		};
+
+		let completer = match operation {
+			ExplorerOperation::Move => ui::completers::filename,
+			_ => ui::completers::none,
+		};
+
+		let prompt = ui::Prompt::new(
+			text.into(),
+			None,
+			completer,
+			move |_cx, input, event| {
+				if event != PromptEvent::Validate { return }
+
+				*pending.lock().unwrap() = Some((operation, target.clone(), input.to_string()));
+			}
+		);
+
+		self.compositor.push(Box::new(prompt));
+		self.should_render = true;
+	}
+
 	pub fn idle_timeout_triggered(&self) -> bool {
 		self.idle_timeout_triggered
 	}
@@ -533,6 +887,33 @@ impl HelixApp {
 		gutter_len
 	}
 
+	// column of given gutter relative to the left edge of the view
+	pub fn gutter_offset(&self, gutter: GutterType) -> Option<usize> {
+		let (view, current_document) = self.current_ref();
+
+		let mut offset = 0;
+		for gutter_type in view.gutters() {
+			if *gutter_type == gutter {
+				return Some(offset);
+			}
+
+			offset += gutter_type.width(view, current_document);
+		}
+
+		None
+	}
+
+	// screen columns taken by given gutter in focused view
+	pub fn gutter_columns(&self, gutter: GutterType) -> Option<std::ops::Range<u16>> {
+		let offset = self.gutter_offset(gutter)?;
+		let (view, current_document) = self.current_ref();
+
+		let width = view.gutters().iter().find(|gutter_type| **gutter_type == gutter)?.width(view, current_document);
+		let start = view.area.x + offset as u16;
+
+		Some(start .. start + width as u16)
+	}
+
 	pub fn mode(&self) -> Mode {
 		self.editor.mode()
 	}
@@ -772,6 +1153,10 @@ impl HelixApp {
 		self.should_render |= self.compositor.handle_event(&Event::IdleTimeout, &mut cx);
 
 		self.idle_timeout_triggered = true;
+
+		if self.autosave_on_idle {
+			self.save_modified_documents();
+		}
 	}
 
 	pub fn handle_document_write(&mut self, doc_save_event: DocumentSavedEventResult) {
@@ -783,6 +1168,16 @@ impl HelixApp {
 			}
 		};
 
+		// typing right after save makes document differ from disk, file watcher has to know this write was ours
+		if let Ok(modified) = std::fs::metadata(&doc_save_event.path).and_then(|metadata| metadata.modified()) {
+			self.written_files.push(file_sync::WrittenFile {
+				doc_id	: doc_save_event.doc_id,
+				path	: doc_save_event.path.clone(),
+				modified,
+				text	: doc_save_event.text.clone(),
+			});
+		}
+
 		let doc = match self.editor.document_mut(doc_save_event.doc_id) {
 			None => {
 				warn!(
@@ -876,6 +1271,8 @@ impl HelixApp {
 	) {
 		use helix_lsp::{Call, MethodCall, Notification};
 
+		self.lsp_events += 1;
+
 		match call {
 			Call::Notification(helix_lsp::jsonrpc::Notification { method, params, .. }) => {
 				let notification = match Notification::parse(&method, params) {
@@ -1159,6 +1556,11 @@ impl HelixApp {
 					Notification::Exit => {
 						self.editor.set_status("Language server exited");
 
+						// servers stopped or restarted from Kodiki are detached from documents before they exit
+						if self.editor.documents().any(|doc| doc.language_server().map(|server| server.id()) == Some(server_id)) {
+							self.lsp_crashed.insert(server_id);
+						}
+
 						// Clear any diagnostics for documents with this server open.
 						let urls: Vec<_> = self
 							.editor
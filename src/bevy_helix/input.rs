@@ -259,6 +259,7 @@ pub fn handle_mouse_events(
 	column			: u16,
 	row				: u16,
 	pos_changed		: bool,
+	send_hover_events : bool, // components like pickers and menus highlight hovered entries
 	bevy_helix_settings	: &BevyHelixSettings,
 	tokio_runtime	: &TokioRuntime,
 	app				: &mut NonSendMut<HelixApp>,
@@ -319,18 +320,24 @@ pub fn handle_mouse_events(
 
 			send_mouse_event(helix_mouse_event_kind);
 		}
+
+		if send_hover_events && mouse_button.get_pressed().len() == 0 {
+			send_mouse_event(helix_view::input::MouseEventKind::Moved);
+		}
 	}
 }
 
 pub fn handle_scroll_events(
 	scroll_events	: &mut EventReader<MouseWheel>,
+	column			: u16,
+	row				: u16,
 	tokio_runtime	: &TokioRuntime,
 	app				: &mut NonSendMut<HelixApp>,
 ) {
 	let mut send_mouse_event = |helix_mouse_event_kind: helix_view::input::MouseEventKind| {
 		let mouse_event = helix_view::input::MouseEvent {
-			column		: 10,
-			row			: 10,
+			column,
+			row,
 			kind		: helix_mouse_event_kind,
 			modifiers	: KeyModifiers::NONE
 		};
@@ -0,0 +1,125 @@
+use bevy :: prelude :: *;
+use bevy_tweening :: *;
+
+use helix_view :: DocumentId;
+
+use std :: time :: Duration;
+
+use super :: tween_lens :: JumpRowLens;
+
+pub mod systems;
+
+// smaller offset changes are applied immediately
+pub const JUMP_MIN_ROWS				: usize	= 3;
+pub const JUMP_DURATION_MIN_MS		: f32	= 150.0;
+pub const JUMP_DURATION_MAX_MS		: f32	= 450.0;
+pub const JUMP_DURATION_PER_ROW_MS	: f32	= 1.5;
+pub const LANDING_HIGHLIGHT_MS		: u64	= 400;
+
+// Helix viewport and primary cursor of focused view
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ViewportState {
+	pub doc_id		: DocumentId,
+	pub row_offset	: usize,
+	pub cursor_line	: usize,
+	pub jump_head	: Option<(DocumentId, usize)>, // the most recent jump of focused view
+}
+
+// every Helix-side viewport jump (goto definition, jumplist, search, diagnostics navigation etc) goes through here:
+// offset change is detected, reverted and then replayed as an animation of camera travel. Camera glides between rows
+// while Helix viewport follows it row by row so that camera always has text to look at
+#[derive(Resource, Default)]
+pub struct JumpAnimation {
+	pub animator_entity	: Option<Entity>,
+	pub last_viewport	: Option<ViewportState>,
+	pub landing_pending	: bool, // landing line is highlighted once animation is finished
+}
+
+impl JumpAnimation {
+	// scrolling (page up/down, half page, mouse wheel) moves viewport first and then only pushes cursor inside of it.
+	// Anything else that moved viewport far enough within the same document is a jump, new jumplist entry is a jump no matter what
+	pub fn is_jump(&self, current: &ViewportState, viewport_rows: usize, scrolloff: usize) -> bool {
+		let Some(last) = self.last_viewport else { return false };
+
+		if last.doc_id != current.doc_id || current.row_offset.abs_diff(last.row_offset) < JUMP_MIN_ROWS {
+			return false;
+		}
+
+		if current.jump_head != last.jump_head {
+			return true;
+		}
+
+		let top		= current.row_offset + scrolloff;
+		let bottom	= (current.row_offset + viewport_rows).saturating_sub(scrolloff + 1).max(top);
+
+		let scrolled_cursor_line = last.cursor_line.clamp(top, bottom);
+
+		// one row of tolerance for folds and soft wrap
+		scrolled_cursor_line.abs_diff(current.cursor_line) > 1
+	}
+
+	// jump to another document starts from where that document was seen last time or from its top
+	pub fn is_document_jump(&self, current: &ViewportState) -> bool {
+		self.last_viewport.map_or(false, |last| last.doc_id != current.doc_id)
+	}
+
+	pub fn start(
+		&mut self,
+		start_row	: usize,
+		end_row		: usize,
+		commands	: &mut Commands
+	) {
+		let jump_length = (end_row as i32 - start_row as i32).abs();
+		let jump_duration = (jump_length as f32 * JUMP_DURATION_PER_ROW_MS).clamp(JUMP_DURATION_MIN_MS, JUMP_DURATION_MAX_MS) as u64;
+
+		let tween = Tween::new(
+			EaseFunction::QuadraticInOut,
+			Duration::from_millis(jump_duration),
+			JumpRowLens {
+				start	: start_row as f32,
+				end		: end_row as f32
+			}
+		);
+
+		let animator_entity = match self.animator_entity {
+			Some(entity) => entity,
+			None => {
+				let entity = commands.spawn_empty().id();
+				self.animator_entity = Some(entity);
+				entity
+			}
+		};
+
+		commands.entity(animator_entity)
+			.insert(Animator::new(tween))
+			.insert(JumpScrollAnimation::default())
+		;
+
+		self.landing_pending = true;
+	}
+}
+
+#[derive(Component, Default)]
+pub struct JumpScrollAnimation {
+	row			: f32,
+	row_changed : bool,
+}
+
+impl JumpScrollAnimation {
+	pub fn set_row(&mut self, new_row: f32) {
+		self.row = new_row;
+		self.row_changed = true;
+	}
+
+	pub fn row_read_to_apply(&mut self) -> Option<f32> {
+		if self.row_changed {
+			self.row_changed = false;
+			Some(self.row)
+		} else {
+			None
+		}
+	}
+}
+
+#[derive(Component)]
+pub struct JumpLandingHighlight;
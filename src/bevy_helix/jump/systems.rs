@@ -0,0 +1,212 @@
+use bevy :: prelude :: *;
+use bevy_tweening :: *;
+use bevy_reader_camera :: ReaderCamera;
+
+#[cfg(feature = "tracing")]
+use bevy_puffin :: *;
+
+use helix_term :: ui :: EditorView;
+use helix_view :: graphics :: Color as HelixColor;
+
+use std :: time :: Duration;
+
+use super :: *;
+
+use crate :: {
+	kodiki_ui :: {
+		text_cursor	:: TextCursor,
+		tween_lens	:: StandardMaterialAlphaLens,
+	},
+	bevy_ab_glyph :: { ABGlyphFont, ABGlyphFonts, FontAssetHandles },
+	bevy_framerate_manager :: FramerateManager,
+	bevy_helix :: {
+		HelixApp, HighlightKind,
+		CAMERA_ROW_CONSTANT_OFFSET,
+		canvas :: DocumentCanvas,
+		document_switch :: DocumentViewports,
+		surface :: SurfacesMapBevy,
+		utils :: color_from_helix,
+	},
+};
+
+fn viewport_state(app: &HelixApp) -> ViewportState {
+	ViewportState {
+		doc_id		: app.current_document().id(),
+		row_offset	: app.row_offset_internal(),
+		cursor_line	: app.primary_cursor_line(),
+		jump_head	: app.jumplist_head(),
+	}
+}
+
+// runs right before camera_update so every offset change since the end of previous frame came from Helix itself
+pub fn detect_jumps(
+	mut jump_animation	: ResMut<JumpAnimation>,
+		canvas			: Res<DocumentCanvas>,
+		viewports		: Res<DocumentViewports>,
+	mut commands		: Commands,
+		app_option		: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() || canvas.active { return }
+
+	profile_function!();
+
+	let current		= viewport_state(&app);
+	let row_offset	= current.row_offset;
+
+	let start_row = if jump_animation.is_document_jump(&current) {
+		// document switch restores remembered viewport, if cursor is still inside of it there is nowhere to travel
+		let start_row = viewports.get(current.doc_id, app.current_view().id).map_or(0, |viewport| viewport.row_offset);
+		if start_row.abs_diff(row_offset) < JUMP_MIN_ROWS {
+			return;
+		}
+
+		start_row
+	} else if jump_animation.is_jump(&current, app.viewport_rows(), app.editor.config().scrolloff) {
+		jump_animation.last_viewport.map_or(row_offset, |last| last.row_offset)
+	} else {
+		return;
+	};
+
+	// travel from where viewport was before the jump
+	app.set_row_offset_internal(start_row);
+
+	jump_animation.start(start_row, row_offset, &mut commands);
+}
+
+// Helix viewport takes the whole row of animated position and camera is shifted by the fraction left so it moves smoothly
+pub fn update_jump_animation(
+	mut q_jump_scroll		: Query<(&mut JumpScrollAnimation, &Animator<JumpScrollAnimation>)>,
+	mut q_reader_camera		: Query<&mut ReaderCamera>,
+	mut framerate_manager	: ResMut<FramerateManager>,
+		app_option			: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	profile_function!();
+
+	for (mut animation, animator) in q_jump_scroll.iter_mut() {
+		let progress = animator.tweenable().progress();
+
+		if let Some(row) = animation.row_read_to_apply() {
+			app.set_row_offset_internal(row.floor() as usize);
+
+			let mut reader_camera = q_reader_camera.single_mut();
+			reader_camera.set_row_offset_in(row.floor() as u32);
+			reader_camera.row_constant_offset = CAMERA_ROW_CONSTANT_OFFSET + if progress < 1.0 { row.fract() } else { 0.0 };
+		}
+
+		if progress < 1.0 {
+			framerate_manager.request_active_framerate(format!("active JumpScrollAnimation animator {:.2}%", progress * 100.));
+		}
+	}
+}
+
+// remember viewport at the end of frame so that scrolling from camera or minimap is not considered a jump
+pub fn sync_row_offset(
+	mut jump_animation	: ResMut<JumpAnimation>,
+		app_option		: Option<NonSend<HelixApp>>,
+) {
+	let Some(app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	jump_animation.last_viewport = Some(viewport_state(&app));
+}
+
+pub fn update_landing_highlight(
+		q_jump_scroll		: Query<(Entity, &Animator<JumpScrollAnimation>)>,
+		q_landing_highlight	: Query<Entity, (With<JumpLandingHighlight>, Without<AssetAnimator<StandardMaterial>>)>,
+		q_cursor			: Query<&TextCursor>,
+	mut jump_animation		: ResMut<JumpAnimation>,
+		surfaces_bevy		: Res<SurfacesMapBevy>,
+		font_assets			: Res<Assets<ABGlyphFont>>,
+		font_handles		: Res<FontAssetHandles>,
+	mut mesh_assets			: ResMut<Assets<Mesh>>,
+	mut material_assets		: ResMut<Assets<StandardMaterial>>,
+	mut commands			: Commands,
+		app_option			: Option<NonSend<HelixApp>>,
+) {
+	let Some(app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	profile_function!();
+
+	// finished material animators are removed in animations_cleanup_components so landing highlight without one has faded out
+	for entity in q_landing_highlight.iter() {
+		commands.entity(entity).despawn_recursive();
+	}
+
+	if !jump_animation.landing_pending {
+		return;
+	}
+
+	let Some(animator_entity) = jump_animation.animator_entity else { return };
+
+	if let Ok((entity, animator)) = q_jump_scroll.get(animator_entity) {
+		if animator.tweenable().progress() < 1.0 {
+			return;
+		}
+
+		commands.entity(entity)
+			.remove::<Animator<JumpScrollAnimation>>()
+			.remove::<JumpScrollAnimation>()
+		;
+	}
+
+	jump_animation.landing_pending = false;
+
+	let Some(surface_editor) = surfaces_bevy.get(EditorView::ID) else { return };
+	let Some(cursor_entity) = surface_editor.cursor_entities.first() else { return };
+	let Ok(cursor) = q_cursor.get(*cursor_entity) else { return };
+
+	let theme = &app.editor.theme;
+	let style = theme.get("ui.cursorline.primary");
+	let style = if style.bg.is_some() { style } else { theme.get("ui.selection") };
+
+	let mut base_color = color_from_helix(style.bg.unwrap_or(HelixColor::Cyan));
+	base_color.set_a(0.0);
+
+	// material is not cached because its alpha is animated
+	let material_handle = material_assets.add(
+		StandardMaterial {
+			base_color,
+			alpha_mode : AlphaMode::Blend,
+			unlit : true,
+			..default()
+		}
+	);
+
+	let fonts = ABGlyphFonts::new(&font_assets, &font_handles);
+
+	let highlight_entity = surface_editor.spawn_highlight_whole_line_untracked(
+		cursor.row,
+		HighlightKind::Cursor,
+		&material_handle,
+		app.gutter_len(),
+		&fonts,
+		&mut mesh_assets,
+		&mut commands
+	);
+
+	let tween_fade = Tween::new(
+		EaseFunction::QuadraticInOut,
+		Duration::from_millis(LANDING_HIGHLIGHT_MS),
+		StandardMaterialAlphaLens {
+			start : 0.0,
+			end : 0.35
+		}
+	)
+	.with_repeat_count(RepeatCount::Finite(2))
+	.with_repeat_strategy(RepeatStrategy::MirroredRepeat)
+	;
+
+	commands.entity(highlight_entity).insert((
+		AssetAnimator::new(material_handle, tween_fade),
+		JumpLandingHighlight,
+	));
+}
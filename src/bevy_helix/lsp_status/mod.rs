@@ -0,0 +1,446 @@
+use bevy :: prelude :: *;
+
+use helix_lsp :: { lsp, ProgressStatus };
+use helix_term :: {
+	compositor :: { self, Compositor },
+	job,
+	ui :: { self, EditorView, menu :: Item },
+};
+use helix_tui :: {
+	buffer :: { Buffer as SurfaceHelix, SurfaceFlags, SurfacePlacement },
+	widgets :: Row,
+};
+use helix_view :: { Editor, DocumentId, graphics :: Rect };
+
+use super :: {
+	helix_app :: HelixApp,
+	surface :: { SurfacesMapHelix, LSP_STATUS_SURFACE_NAME },
+};
+
+pub mod systems;
+
+pub const SPINNER_FRAMES		: &[&str] = &["◐", "◓", "◑", "◒"];
+pub const SPINNER_FRAME_MS		: u128 = 150;
+pub const MAX_PROGRESS_ROWS		: usize = 10;
+pub const MAX_TITLE_COLUMNS		: usize = 24;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum ServerState {
+	Initializing,
+	Indexing { percentage: Option<u32>, title: String },
+	Idle,
+	Crashed,
+	Stopped, // documents have a language server configured but none is attached
+}
+
+impl ServerState {
+	pub fn is_busy(&self) -> bool {
+		matches!(self, ServerState::Initializing | ServerState::Indexing { .. })
+	}
+
+	pub fn theme_scope(&self) -> &'static str {
+		match self {
+			ServerState::Initializing | ServerState::Indexing { .. } => "warning",
+			ServerState::Idle		=> "ui.text",
+			ServerState::Crashed	=> "error",
+			ServerState::Stopped	=> "ui.text.inactive",
+		}
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct ServerStatus {
+	pub id			: Option<usize>, // stopped servers have no id
+	pub name		: String,
+	pub state		: ServerState,
+	pub documents	: Vec<DocumentId>,
+}
+
+impl ServerStatus {
+	pub fn label(&self, spinner_frame: usize) -> String {
+		let spinner = SPINNER_FRAMES[spinner_frame % SPINNER_FRAMES.len()];
+
+		match &self.state {
+			ServerState::Initializing => format!("{} {} initializing", spinner, self.name),
+			ServerState::Indexing { percentage, title } => {
+				let title = short_title(title);
+
+				match percentage {
+					Some(percentage) => format!("{} {} {}% {}", spinner, self.name, percentage, title),
+					None => format!("{} {} {}", spinner, self.name, title),
+				}
+			},
+			ServerState::Idle		=> format!("● {}", self.name),
+			ServerState::Crashed	=> format!("■ {} crashed", self.name),
+			ServerState::Stopped	=> format!("○ {} stopped", self.name),
+		}
+	}
+}
+
+fn short_title(title: &str) -> String {
+	if title.chars().count() <= MAX_TITLE_COLUMNS {
+		return String::from(title);
+	}
+
+	let mut short : String = title.chars().take(MAX_TITLE_COLUMNS - 1).collect();
+	short.push('…');
+
+	short
+}
+
+// text and percentage of a single progress token
+fn progress_parts(status: &ProgressStatus) -> (String, Option<u32>) {
+	match status {
+		ProgressStatus::Created => (String::new(), None),
+		ProgressStatus::Started(lsp::WorkDoneProgress::Begin(begin)) => {
+			let text = begin.message.as_ref().map_or_else(|| begin.title.clone(), |message| format!("{} - {}", begin.title, message));
+			(text, begin.percentage)
+		},
+		ProgressStatus::Started(lsp::WorkDoneProgress::Report(report)) => (report.message.clone().unwrap_or_default(), report.percentage),
+		ProgressStatus::Started(lsp::WorkDoneProgress::End(end)) => (end.message.clone().unwrap_or_default(), None),
+	}
+}
+
+fn token_string(token: &lsp::ProgressToken) -> String {
+	match token {
+		lsp::NumberOrString::Number(number) => number.to_string(),
+		lsp::NumberOrString::String(string) => string.clone(),
+	}
+}
+
+// registry doesn't know server names so servers are collected from documents that use them
+pub fn servers_status(app: &HelixApp) -> Vec<ServerStatus> {
+	let mut servers : Vec<ServerStatus> = Vec::new();
+
+	for doc in app.editor.documents() {
+		let Some(server_config) = doc.language_config().and_then(|config| config.language_server.as_ref()) else { continue };
+
+		let id = doc.language_server().map(|server| server.id());
+
+		if let Some(server) = servers.iter_mut().find(|server| server.id == id && server.name == server_config.command) {
+			server.documents.push(doc.id());
+			continue;
+		}
+
+		let state = match id {
+			None => ServerState::Stopped,
+			Some(id) if app.lsp_crashed(id) => ServerState::Crashed,
+			Some(id) => match app.editor.language_servers.get_by_id(id) {
+				None => ServerState::Crashed,
+				Some(server) if !server.is_initialized() => ServerState::Initializing,
+				Some(_) => {
+					let mut progress : Vec<(String, Option<u32>)> = app.lsp_progress().progress_map(id)
+						.map(|progress_map| progress_map.values().map(progress_parts).collect())
+						.unwrap_or_default();
+
+					// the least advanced task is the one that keeps server busy
+					progress.sort_by_key(|(_, percentage)| percentage.unwrap_or(0));
+
+					match progress.into_iter().next() {
+						Some((title, percentage)) => ServerState::Indexing { percentage, title },
+						None => ServerState::Idle,
+					}
+				},
+			},
+		};
+
+		servers.push(ServerStatus {
+			id,
+			name		: server_config.command.clone(),
+			state,
+			documents	: vec![doc.id()],
+		});
+	}
+
+	servers.sort_by(|a, b| a.name.cmp(&b.name));
+
+	servers
+}
+
+// one row per active progress token of every running server
+pub fn progress_rows(app: &HelixApp, servers: &[ServerStatus]) -> Vec<String> {
+	let mut rows = Vec::new();
+
+	for server in servers.iter() {
+		let Some(id) = server.id else { continue };
+		let Some(progress_map) = app.lsp_progress().progress_map(id) else { continue };
+
+		let mut server_rows : Vec<String> = progress_map.iter().map(|(token, status)| {
+			let (text, percentage) = progress_parts(status);
+
+			match percentage {
+				Some(percentage) => format!("{} [{}] {}% {}", server.name, token_string(token), percentage, text),
+				None => format!("{} [{}] {}", server.name, token_string(token), text),
+			}
+		}).collect();
+
+		// progress map is a hash map, without sorting rows would jump around every frame
+		server_rows.sort();
+
+		rows.append(&mut server_rows);
+	}
+
+	rows
+}
+
+// everything servers status depends on: messages from language servers and documents with servers attached to them
+#[derive(Clone, PartialEq, Debug)]
+pub struct ServersStatusKey {
+	pub lsp_events	: usize,
+	pub documents	: Vec<(DocumentId, Option<usize>)>,
+}
+
+impl ServersStatusKey {
+	pub fn new(app: &HelixApp) -> Self {
+		Self {
+			lsp_events	: app.lsp_events(),
+			documents	: app.editor.documents().map(|doc| (doc.id(), doc.language_server().map(|server| server.id()))).collect(),
+		}
+	}
+}
+
+#[derive(Resource, Default)]
+pub struct LspStatus {
+	pub expanded		: bool, // list of active progress tokens is shown above status
+	pub spinner_frame	: usize,
+
+	pub servers			: Vec<ServerStatus>,
+	pub servers_key		: Option<ServersStatusKey>,
+}
+
+impl LspStatus {
+	pub fn toggle(&mut self) {
+		self.expanded = !self.expanded;
+	}
+
+	// servers status is recollected only after language servers sent something or documents changed
+	pub fn refresh_servers(&mut self, app: &HelixApp) -> bool {
+		let key = ServersStatusKey::new(app);
+
+		if self.servers_key.as_ref() == Some(&key) {
+			return false;
+		}
+
+		self.servers		= servers_status(app);
+		self.servers_key	= Some(key);
+
+		true
+	}
+
+	// status takes a row of its own under the editor so that Helix statusline and messages are never covered
+	pub fn reserved_rows(&self) -> usize {
+		if self.servers.is_empty() { 0 } else { 1 }
+	}
+
+	// status is right-aligned on a row right under the editor, progress list goes above it
+	pub fn update_surface(
+		&self,
+		surfaces_helix	: &mut SurfacesMapHelix,
+		app				: &HelixApp,
+	) {
+		let Some(surface_editor) = surfaces_helix.get(EditorView::ID) else { return };
+		let editor_area = surface_editor.area;
+
+		let servers = &self.servers;
+
+		if servers.is_empty() || editor_area.height < 2 {
+			surfaces_helix.remove(LSP_STATUS_SURFACE_NAME);
+			return;
+		}
+
+		let labels : Vec<(String, &ServerState)> = servers.iter().map(|server| (server.label(self.spinner_frame), &server.state)).collect();
+
+		let mut progress = if self.expanded { progress_rows(app, servers) } else { Vec::new() };
+		progress.truncate(MAX_PROGRESS_ROWS.min(editor_area.height as usize - 1));
+
+		let status_width	= labels.iter().map(|(label, _)| label.chars().count() + 2).sum::<usize>();
+		let progress_width	= progress.iter().map(|row| row.chars().count() + 1).max().unwrap_or(0);
+
+		let width	= (status_width.max(progress_width) as u16).min(editor_area.width);
+		let height	= progress.len() as u16 + 1;
+		let area	= Rect::new(editor_area.right() - width, editor_area.bottom() + 1 - height, width, height);
+
+		let surface = surfaces_helix.entry(String::from(LSP_STATUS_SURFACE_NAME)).or_insert_with(|| {
+			SurfaceHelix::empty_with_spatial(area, SurfaceFlags::default())
+		});
+
+		if surface.area != area {
+			surface.resize(area);
+		}
+
+		surface.placement = SurfacePlacement::AreaCoordinates;
+		surface.reset();
+
+		let theme		= &app.editor.theme;
+		let background	= theme.get("ui.background");
+		let popup_style	= background.patch(theme.get("ui.popup")).patch(theme.get("ui.text"));
+
+		surface.set_style(area, background);
+
+		for (index, row) in progress.iter().enumerate() {
+			let y = area.y + index as u16;
+
+			surface.set_style(Rect::new(area.x, y, area.width, 1), popup_style);
+			surface.set_stringn(area.x, y, row, area.width as usize, popup_style);
+		}
+
+		let status_y = area.bottom() - 1;
+		let mut x = area.right().saturating_sub(status_width as u16);
+
+		for (label, state) in labels.iter() {
+			let style = background.patch(theme.get(state.theme_scope()));
+			let width = area.right().saturating_sub(x) as usize;
+
+			let (next_x, _) = surface.set_stringn(x, status_y, label, width, style);
+			x = next_x + 2;
+		}
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ServerActionKind {
+	Start,
+	Restart,
+	Stop,
+	Capabilities,
+}
+
+// entry of language servers picker
+#[derive(Clone, Debug)]
+pub struct ServerAction {
+	pub kind		: ServerActionKind,
+	pub server_id	: Option<usize>,
+	pub name		: String,
+	pub documents	: Vec<DocumentId>,
+}
+
+impl Item for ServerAction {
+	type Data = ();
+
+	fn format(&self, _data: &Self::Data) -> Row {
+		let action = match self.kind {
+			ServerActionKind::Start			=> "start",
+			ServerActionKind::Restart		=> "restart",
+			ServerActionKind::Stop			=> "stop",
+			ServerActionKind::Capabilities	=> "capabilities",
+		};
+
+		format!("{}: {}", self.name, action).into()
+	}
+}
+
+impl ServerAction {
+	pub fn run(&self, cx: &mut compositor::Context) {
+		match self.kind {
+			ServerActionKind::Start => {
+				start_server(cx.editor, &self.documents);
+				cx.editor.set_status(format!("Starting {}", self.name));
+			},
+			ServerActionKind::Restart => {
+				if let Some(server_id) = self.server_id {
+					stop_server(cx.editor, server_id);
+				}
+
+				start_server(cx.editor, &self.documents);
+				cx.editor.set_status(format!("Restarting {}", self.name));
+			},
+			ServerActionKind::Stop => {
+				if let Some(server_id) = self.server_id {
+					stop_server(cx.editor, server_id);
+					cx.editor.set_status(format!("Stopped {}", self.name));
+				}
+			},
+			ServerActionKind::Capabilities => self.show_capabilities(cx),
+		}
+	}
+
+	fn show_capabilities(&self, cx: &mut compositor::Context) {
+		let capabilities = self.server_id
+			.and_then(|server_id| cx.editor.language_servers.get_by_id(server_id))
+			.filter(|server| server.is_initialized())
+			.map(|server| serde_json::to_string_pretty(server.capabilities()).unwrap_or_default());
+
+		let Some(capabilities) = capabilities else {
+			cx.editor.set_error(format!("{} is not initialized", self.name));
+			return;
+		};
+
+		let contents = format!("# {}\n```json\n{}\n```", self.name, capabilities);
+
+		cx.jobs.callback(async move {
+			let callback = job::Callback::EditorCompositor(Box::new(move |editor: &mut Editor, compositor: &mut Compositor| {
+				let markdown = ui::Markdown::new(contents, editor.syn_loader.clone());
+				let popup = ui::Popup::new("lsp-capabilities", markdown).auto_close(true);
+
+				compositor.replace_or_push("lsp-capabilities", popup);
+			}));
+
+			Ok(callback)
+		});
+	}
+}
+
+// detaches server from its documents before shutting it down so that its exit is not reported as a crash
+pub fn stop_server(editor: &mut Editor, server_id: usize) {
+	let client = editor.language_servers.iter_clients().find(|client| client.id() == server_id).cloned();
+
+	let mut urls = Vec::new();
+
+	for doc in editor.documents_mut() {
+		if doc.language_server().map(|server| server.id()) != Some(server_id) {
+			continue;
+		}
+
+		doc.set_language_server(None);
+		doc.set_diagnostics(Vec::new());
+
+		if let Some(url) = doc.url() {
+			urls.push(url);
+		}
+	}
+
+	for url in urls {
+		editor.diagnostics.remove(&url);
+	}
+
+	editor.language_servers.remove_by_id(server_id);
+
+	if let Some(client) = client {
+		tokio::spawn(async move {
+			if let Err(e) = client.shutdown_and_exit().await {
+				log::error!("failed to shut down language server: {}", e);
+			}
+		});
+	}
+}
+
+// launches a server for given documents or attaches the one that is already running for their language
+pub fn start_server(editor: &mut Editor, documents: &[DocumentId]) {
+	for doc_id in documents.iter() {
+		editor.refresh_language_server(*doc_id);
+	}
+}
+
+pub fn server_actions(app: &HelixApp) -> Vec<ServerAction> {
+	let mut actions = Vec::new();
+
+	for server in servers_status(app) {
+		let kinds : &[ServerActionKind] = match server.state {
+			ServerState::Stopped		=> &[ServerActionKind::Start],
+			ServerState::Crashed		=> &[ServerActionKind::Restart],
+			ServerState::Initializing	=> &[ServerActionKind::Restart, ServerActionKind::Stop],
+			ServerState::Idle | ServerState::Indexing { .. } => &[ServerActionKind::Restart, ServerActionKind::Stop, ServerActionKind::Capabilities],
+		};
+
+		for kind in kinds.iter() {
+			actions.push(ServerAction {
+				kind		: *kind,
+				server_id	: server.id,
+				name		: server.name.clone(),
+				documents	: server.documents.clone(),
+			});
+		}
+	}
+
+	actions
+}
@@ -0,0 +1,86 @@
+use bevy :: prelude :: *;
+
+#[cfg(feature = "tracing")]
+use bevy_puffin :: *;
+
+use super :: *;
+
+use crate :: {
+	bevy_framerate_manager :: FramerateManager,
+	kodiki_ui :: { DraggingState, raypick :: Raypick },
+	bevy_helix :: {
+		surface :: { SurfacesMapBevy, WordDescription },
+		systems_util :: hovered_surface_name,
+	},
+};
+
+// ctrl+alt+l opens language servers picker
+pub fn input_keyboard(
+		key			: Res<Input<KeyCode>>,
+		app_option	: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	let ctrl_pressed	= key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl);
+	let alt_pressed		= key.pressed(KeyCode::LAlt) || key.pressed(KeyCode::RAlt);
+
+	if !(ctrl_pressed && alt_pressed && key.just_pressed(KeyCode::L)) { return }
+
+	profile_function!();
+
+	app.open_language_servers_picker();
+}
+
+// clicking status shows or hides active progress tokens
+pub fn input_mouse(
+		mouse_button	: Res<Input<MouseButton>>,
+		raypick			: Res<Raypick>,
+		surfaces		: Res<SurfacesMapBevy>,
+		q_word			: Query<&WordDescription>,
+	mut lsp_status		: ResMut<LspStatus>,
+		dragging_state	: Res<DraggingState>,
+		app_option		: Option<NonSend<HelixApp>>,
+) {
+	let Some(app) = app_option else { return };
+
+	if app.should_close() || dragging_state.is_active() { return }
+
+	if !mouse_button.just_pressed(MouseButton::Left) { return }
+
+	let Some(hovered_entity) = raypick.last_hover else { return };
+	let Some(surface_name) = hovered_surface_name(hovered_entity, &q_word, &surfaces) else { return };
+
+	if surface_name != LSP_STATUS_SURFACE_NAME { return }
+
+	lsp_status.toggle();
+}
+
+// spinner is animated only while some server is busy, otherwise framerate is left alone
+pub fn update_spinner(
+		time				: Res<Time>,
+	mut lsp_status			: ResMut<LspStatus>,
+	mut framerate_manager	: ResMut<FramerateManager>,
+		app_option			: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	profile_function!();
+
+	if lsp_status.refresh_servers(&app) {
+		app.request_render();
+	}
+
+	if !lsp_status.servers.iter().any(|server| server.state.is_busy()) { return }
+
+	let spinner_frame = (time.elapsed().as_millis() / SPINNER_FRAME_MS) as usize % SPINNER_FRAMES.len();
+
+	if lsp_status.spinner_frame != spinner_frame {
+		lsp_status.spinner_frame = spinner_frame;
+	}
+
+	framerate_manager.request_active_framerate("language server is busy".into());
+}
@@ -21,6 +21,8 @@ use helix_core :: {
 };
 use helix_view :: {
 	Document,
+	DocumentId,
+	ViewId,
 	Theme,
 	graphics :: Color as HelixColor,
 };
@@ -41,9 +43,11 @@ use super :: {
 	tween_lens	:: *,
 	surface		:: *,
 	utils		:: *,
+	folding		:: Fold,
+	diff_hunks	:: ranges_touch,
 };
 
-use std :: time :: Duration;
+use std :: { ops :: Range, time :: Duration };
 
 pub mod systems;
 mod systems_util;
@@ -122,6 +126,12 @@ pub struct Bookmark {
 #[derive(Component)]
 pub struct BookmarkRevealed;
 
+// clickable diff hunk, remembered by its first line in the document
+#[derive(Component)]
+pub struct DiffHunkMarker {
+	pub line : usize,
+}
+
 #[derive(Component)]
 pub struct BookmarkHint {
 	pub owner			: Entity,
@@ -223,8 +233,20 @@ impl Default for MinimapScaledMode {
 	}
 }
 
+type MinimapRenderResult = (usize, Vec<ColoredStringRow>, Vec<Image>);
+
 #[derive(Component)]
-pub struct MinimapRenderTask(Task<(usize, Vec<ColoredStringRow>, Vec<Image>)>);
+pub struct MinimapRenderTask(Task<MinimapRenderResult>);
+
+// minimap of unfocused view shows its document and visible rows, all the interactive parts are left to the main one
+#[derive(Component)]
+pub struct ViewMinimap {
+	pub view_id			: ViewId,
+	pub minimap			: Minimap,
+}
+
+#[derive(Component)]
+pub struct ViewMinimapRenderTask(Task<MinimapRenderResult>);
 
 #[derive(Component)]
 pub struct Minimap {
@@ -235,6 +257,9 @@ pub struct Minimap {
 	pub selection_highlights		: Highlights<SyncDataDoc>,
 	pub search_highlights			: Highlights<VersionType>,
 	pub selection_search_highlights : Highlights<VersionType>,
+	pub fold_highlights				: Highlights<(DocumentId, VersionType, usize)>, // document, its version and folds version
+	pub breakpoint_highlights		: Highlights<(DocumentId, VersionType, Vec<usize>)>, // document, its version and lines with breakpoints
+	pub diff_gutter_cache			: Option<(DocumentId, usize, Vec<(Range<u32>, Range<u32>)>, usize)>, // document, rows total, hunks and staged hunks version
 
 	pub font_height		: f32,
 	pub size			: Vec2,
@@ -269,6 +294,9 @@ impl Default for Minimap {
 			selection_highlights		: Highlights::<_>::default(),
 			search_highlights			: Highlights::<_>::default(),
 			selection_search_highlights	: Highlights::<_>::default(),
+			fold_highlights				: Highlights::<_>::default(),
+			breakpoint_highlights		: Highlights::<_>::default(),
+			diff_gutter_cache			: None,
 
 			font_height		: MINIMAP_FONT_HEIGHT,
 			size			: Vec2::new(MINIMAP_WIDTH, MINIMAP_HEIGHT),
@@ -325,6 +353,49 @@ impl Minimap {
 		minimap_entity
 	}
 
+	pub fn spawn_for_view(
+		view_id			: ViewId,
+		mesh_assets		: &mut Assets<Mesh>,
+		material_assets	: &mut Assets<StandardMaterial>,
+		commands		: &mut Commands
+	) -> Entity {
+		let minimap_entity = commands.spawn((
+			TransformBundle::default(),
+			VisibilityBundle::default(),
+		)).id();
+
+		// plain viewport quad without collision since view minimap is not interactive
+		let viewport_mesh_handle = mesh_assets.add(shape::Quad::new(MinimapViewport::default().size).into());
+		let viewport_material_handle = material_assets.add(StandardMaterial {
+			base_color: Color::Rgba { red: 1.0, green: 1.0, blue: 1.0, alpha: VIEWPORT_ALPHA },
+			unlit : true,
+			alpha_mode : AlphaMode::Blend,
+			..default()
+		});
+
+		let viewport_entity = commands.spawn(
+			PbrBundle {
+				mesh		: viewport_mesh_handle,
+				material	: viewport_material_handle,
+				transform	: Transform::from_translation(Vec3::Z * z_order::minimap::viewport()),
+				..default()
+			}
+		).id();
+
+		let minimap = Minimap {
+			entity: minimap_entity,
+			viewport_entity,
+			..default()
+		};
+
+		commands.entity(minimap_entity)
+			.insert(ViewMinimap { view_id, minimap })
+			.add_child(viewport_entity)
+		;
+
+		minimap_entity
+	}
+
 	pub fn spawn_pointer(
 		mesh_assets		: &mut Assets<Mesh>,
 		material_assets	: &mut Assets<StandardMaterial>,
@@ -477,6 +548,20 @@ impl Minimap {
 		dark_theme			: bool,
 		commands			: &mut Commands
 	) {
+		let task = self.render_task(fonts, doc, theme, dark_theme);
+
+		commands.spawn(MinimapRenderTask(task));
+
+		self.render_task_spawned = true;
+	}
+
+	pub fn render_task(
+		&self,
+		fonts				: &ABGlyphFonts,
+		doc					: &Document,
+		theme				: Theme,
+		dark_theme			: bool,
+	) -> Task<MinimapRenderResult> {
 		let text				= doc.text().clone(); // looked like a crime at first but we really have no interest in concurrent access to file text especially if it can be altered while the background task is running
 		let rows_total			= doc.text().len_lines();
 		let tab_size			= doc.tab_width();
@@ -506,7 +591,7 @@ impl Minimap {
 
 		let thread_pool			= AsyncComputeTaskPool::get();
 
-		let task = thread_pool.spawn(async move {
+		thread_pool.spawn(async move {
 			let image_width			= Minimap::default().image_size.x as u32;
 			let image_chunk_height	= Minimap::default().image_size.y as u32;
 
@@ -636,11 +721,7 @@ impl Minimap {
 			}
 
 			(rows_total, colored_rows, minimap_chunks)
-		});
-
-		commands.spawn(MinimapRenderTask(task));
-
-		self.render_task_spawned = true;
+		})
 	}
 
 	pub fn update_viewport(
@@ -772,6 +853,7 @@ impl Minimap {
 	pub fn update_diff_gutter(
 		&mut self,
 		doc					: &Document,
+		staged_ranges		: &[Range<usize>],
 		theme				: &Theme,
 		mesh_assets			: &mut Assets<Mesh>,
 		color_materials_cache : &mut ColorMaterialsCache,
@@ -795,11 +877,12 @@ impl Minimap {
 			for hunk_i in 0 .. diff.len() {
 				let hunk = diff.nth_hunk(hunk_i);
 
-				let hunk_rows = hunk.after.end - hunk.after.start;
+				// removals take no rows but still have to be visible and clickable
+				let hunk_rows = (hunk.after.end - hunk.after.start).max(1);
 				let hunk_size = Vec2::new(DIFF_HUNK_WIDTH, self.row_height * hunk_rows as f32);
 				let hunk_mesh_handle = mesh_assets.add(shape::Quad::new(hunk_size).into());
 
-				let base_color = color_from_helix(
+				let mut base_color = color_from_helix(
 					if hunk.is_pure_insertion() {
 						added
 					} else if hunk.is_pure_removal() {
@@ -810,6 +893,12 @@ impl Minimap {
 					.fg.unwrap_or(HelixColor::Cyan)
 				);
 
+				// staged hunks are dimmed so that only what is left to commit stands out
+				let before = hunk.before.start as usize .. hunk.before.end as usize;
+				if staged_ranges.iter().any(|range| ranges_touch(range, &before)) {
+					base_color = get_color_wmodified_lightness(base_color, -0.3);
+				}
+
 				let hunk_material_handle = get_color_material_handle(
 					base_color,
 					color_materials_cache,
@@ -820,15 +909,29 @@ impl Minimap {
 				let hunk_x			= self.size.x / 2.0 + MINIMAP_PADDING / 2.0;
 				let hunk_y			= -(row as f32 * self.row_height) + self.size.y / 2.0;
 
-				let hunk_entity	= commands.spawn(
+				let hunk_visual_entity = commands.spawn(
 					PbrBundle {
 						mesh		: hunk_mesh_handle,
 						material	: hunk_material_handle,
-						transform	: Transform::from_translation(Vec3::new(hunk_x, hunk_y, z_order::minimap::diff_gutter())),
 						..default()
 					}
 				).id();
 
+				let hunk_collision_entity = commands.spawn((
+					TransformBundle	:: default(),
+					RigidBody		:: Fixed,
+					Collider		:: cuboid(DIFF_HUNK_WIDTH * 2.0, hunk_size.y / 2.0, z_order::thickness() / 2.0), // collision is intentionally wider
+				)).id();
+
+				let hunk_entity	= commands.spawn((
+					TransformBundle { local: Transform::from_translation(Vec3::new(hunk_x, hunk_y, z_order::minimap::diff_gutter())), ..default() },
+					VisibilityBundle:: default(),
+					RaypickHover	:: default(),
+					DiffHunkMarker	{ line: hunk.after.start as usize },
+				)).id();
+
+				commands.entity(hunk_entity).push_children(&[hunk_visual_entity, hunk_collision_entity]);
+
 				commands.entity(self.entity).add_child(hunk_entity);
 
 				self.diff_entities.push(hunk_entity);
@@ -847,7 +950,11 @@ impl Minimap {
 			HighlightKind::SelectionSearch =>
 				&mut self.selection_search_highlights.entities,
 			HighlightKind::Cursor		=>
-				panic!("Cursor highlights are not implemented for Minimap!")
+				panic!("Cursor highlights are not implemented for Minimap!"),
+			HighlightKind::Fold			=>
+				&mut self.fold_highlights.entities,
+			HighlightKind::Breakpoint	=>
+				&mut self.breakpoint_highlights.entities,
 		}
 	}
 
@@ -862,7 +969,11 @@ impl Minimap {
 			HighlightKind::SelectionSearch =>
 				z_order::surface::highlight_search(),
 			HighlightKind::Cursor		=>
-				panic!("Cursor highlights are not implemented for Minimap!")
+				panic!("Cursor highlights are not implemented for Minimap!"),
+			HighlightKind::Fold			=>
+				z_order::surface::highlight(),
+			HighlightKind::Breakpoint	=>
+				z_order::surface::highlight_diagnostic(),
 		}
 	}
 
@@ -1179,6 +1290,77 @@ impl Minimap {
 		}
 	}
 
+	pub fn update_fold_highlights(
+		&mut self,
+		folds				: &[Fold],
+		theme				: &Theme,
+		mesh_assets			: &mut Assets<Mesh>,
+		color_materials_cache : &mut ColorMaterialsCache,
+		material_assets		: &mut Assets<StandardMaterial>,
+		commands			: &mut Commands,
+	) {
+		self.despawn_highlights(HighlightKind::Fold, commands);
+
+		let style = theme.get("ui.virtual");
+
+		let mut base_color = color_from_helix(style.fg.or(theme.get("comment").fg).unwrap_or(HelixColor::Gray));
+		base_color.set_a(0.25);
+
+		let fold_material_handle = get_color_material_walpha_handle(
+			base_color,
+			AlphaMode::Blend,
+			color_materials_cache,
+			material_assets
+		);
+
+		// only hidden rows are highlighted, first row of each fold stays visible in editor
+		for fold in folds.iter() {
+			self.spawn_highlight_whole_line(
+				(fold.start_line + 1) as u32,
+				fold.end_line as u32,
+				HighlightKind::Fold,
+				&fold_material_handle,
+				mesh_assets,
+				commands
+			);
+		}
+	}
+
+	pub fn update_breakpoint_highlights(
+		&mut self,
+		lines				: &[usize],
+		theme				: &Theme,
+		mesh_assets			: &mut Assets<Mesh>,
+		color_materials_cache : &mut ColorMaterialsCache,
+		material_assets		: &mut Assets<StandardMaterial>,
+		commands			: &mut Commands,
+	) {
+		self.despawn_highlights(HighlightKind::Breakpoint, commands);
+
+		let style = theme.get("ui.debug.breakpoint");
+
+		let mut base_color = color_from_helix(style.fg.unwrap_or(HelixColor::Red));
+		base_color.set_a(0.6);
+
+		let breakpoint_material_handle = get_color_material_walpha_handle(
+			base_color,
+			AlphaMode::Blend,
+			color_materials_cache,
+			material_assets
+		);
+
+		for line in lines.iter() {
+			self.spawn_highlight_whole_line(
+				*line as u32,
+				*line as u32,
+				HighlightKind::Breakpoint,
+				&breakpoint_material_handle,
+				mesh_assets,
+				commands
+			);
+		}
+	}
+
 	pub fn get_search_highlights_mut(&mut self, kind: SearchKind) -> &mut Highlights<usize> {
 		match kind {
 			SearchKind::Common => &mut self.search_highlights,
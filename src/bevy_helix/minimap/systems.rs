@@ -16,7 +16,7 @@ use super :: *;
 
 use crate :: {
 	bevy_framerate_manager :: FramerateManager,
-	bevy_helix :: { SyncDataDoc, Matches, MatchesMapCache },
+	bevy_helix :: { SyncDataDoc, Matches, MatchesMapCache, folding :: Folds, diff_hunks :: { DiffHunks, hunk_at_line } },
 	bevy_ab_glyph :: {
 		glyph_mesh_generator :: generate_string_mesh_wcache,
 		{ ABGlyphFont, FontAssetHandles, ABGlyphFonts, GlyphMeshesCache, TextMeshesCache },
@@ -131,15 +131,6 @@ pub fn handle_render_tasks(
 			&mut material_assets,
 			&mut commands
 		);
-
-		minimap.update_diff_gutter(
-			current_document,
-			&app.editor.theme,
-			&mut mesh_assets,
-			&mut color_materials_cache,
-			&mut material_assets,
-			&mut commands
-		);
     }
 }
 
@@ -350,6 +341,140 @@ fn update_search_highlights_inner(
 	);
 }
 
+pub fn update_fold_highlights(
+	mut q_minimap		: Query<&mut Minimap>,
+	mut mesh_assets		: ResMut<Assets<Mesh>>,
+	mut material_assets	: ResMut<Assets<StandardMaterial>>,
+	mut color_materials_cache : ResMut<ColorMaterialsCache>,
+		folds			: Res<Folds>,
+	mut commands		: Commands,
+		app				: Option<NonSend<HelixApp>>
+) {
+	let app = if let Some(app) = app { app } else { return };
+
+	if app.should_close() { return }
+
+	profile_scope!("minimap update_fold_highlights");
+
+	let doc = app.current_document();
+
+	let mut minimap = q_minimap.single_mut();
+
+	// offsets are calculated with minimap size in mind and if there is an ongoing render task it means the size is going to change
+	if minimap.render_task_spawned { return }
+
+	let cache = (doc.id(), doc.version(), folds.version);
+
+	if minimap.fold_highlights.cache == Some(cache) {
+		return;
+	}
+
+	minimap.fold_highlights.cache = Some(cache);
+
+	minimap.update_fold_highlights(
+		folds.get(doc.id()),
+		&app.editor.theme,
+		&mut mesh_assets,
+		&mut color_materials_cache,
+		&mut material_assets,
+		&mut commands
+	);
+}
+
+// diff hunks are updated asynchronously by Helix and staging changes how they look so they are tracked separately from document changes
+pub fn update_diff_gutter(
+	mut q_minimap		: Query<&mut Minimap>,
+	mut mesh_assets		: ResMut<Assets<Mesh>>,
+	mut material_assets	: ResMut<Assets<StandardMaterial>>,
+	mut color_materials_cache : ResMut<ColorMaterialsCache>,
+		diff_hunks		: Res<DiffHunks>,
+	mut commands		: Commands,
+		app				: Option<NonSend<HelixApp>>
+) {
+	let app = if let Some(app) = app { app } else { return };
+
+	if app.should_close() { return }
+
+	profile_scope!("minimap update_diff_gutter");
+
+	let doc = app.current_document();
+
+	let mut minimap = q_minimap.single_mut();
+
+	// offsets are calculated with minimap size in mind and if there is an ongoing render task it means the size is going to change
+	if minimap.render_task_spawned { return }
+
+	let hunks : Vec<(Range<u32>, Range<u32>)> = doc.diff_handle().map_or_else(Vec::new, |diff_handle| {
+		let diff = diff_handle.load();
+
+		(0 .. diff.len()).map(|hunk_i| {
+			let hunk = diff.nth_hunk(hunk_i);
+			(hunk.before, hunk.after)
+		}).collect()
+	});
+
+	let cache = (doc.id(), minimap.rows_total, hunks, diff_hunks.staged_version);
+
+	if minimap.diff_gutter_cache.as_ref() == Some(&cache) {
+		return;
+	}
+
+	minimap.update_diff_gutter(
+		doc,
+		diff_hunks.staged_ranges(doc.id()),
+		&app.editor.theme,
+		&mut mesh_assets,
+		&mut color_materials_cache,
+		&mut material_assets,
+		&mut commands
+	);
+
+	minimap.diff_gutter_cache = Some(cache);
+}
+
+pub fn update_breakpoint_highlights(
+	mut q_minimap		: Query<&mut Minimap>,
+	mut mesh_assets		: ResMut<Assets<Mesh>>,
+	mut material_assets	: ResMut<Assets<StandardMaterial>>,
+	mut color_materials_cache : ResMut<ColorMaterialsCache>,
+	mut commands		: Commands,
+		app				: Option<NonSend<HelixApp>>
+) {
+	let app = if let Some(app) = app { app } else { return };
+
+	if app.should_close() { return }
+
+	profile_scope!("minimap update_breakpoint_highlights");
+
+	let doc = app.current_document();
+
+	let mut minimap = q_minimap.single_mut();
+
+	// offsets are calculated with minimap size in mind and if there is an ongoing render task it means the size is going to change
+	if minimap.render_task_spawned { return }
+
+	let lines : Vec<usize> = doc.path()
+		.and_then(|path| app.editor.breakpoints.get(path))
+		.map_or_else(Vec::new, |breakpoints| breakpoints.iter().map(|breakpoint| breakpoint.line).collect());
+
+	let cache = (doc.id(), doc.version(), lines);
+
+	if minimap.breakpoint_highlights.cache.as_ref() == Some(&cache) {
+		return;
+	}
+
+	minimap.update_breakpoint_highlights(
+		&cache.2,
+		&app.editor.theme,
+		&mut mesh_assets,
+		&mut color_materials_cache,
+		&mut material_assets,
+		&mut commands
+	);
+
+	minimap.breakpoint_highlights.cache = Some(cache);
+}
+
 pub fn update_selection_highlights(
 	mut q_minimap		: Query<&mut Minimap>,
 	mut mesh_assets		: ResMut<Assets<Mesh>>,
@@ -417,6 +542,7 @@ pub fn update_transform(
 	mut	q_transform_mut		: Query<&mut Transform, Without<ReaderCamera>>,
 		q_cursor			: Query<&TextCursor>,
 		time				: Res<Time>,
+		app					: Option<NonSend<HelixApp>>,
 ) {
 	profile_scope!("minimap update_position");
 
@@ -441,7 +567,12 @@ pub fn update_transform(
 		let mut minimap_transform = q_transform_mut.get_mut(minimap.entity).unwrap();
 		let mut new_translation = minimap_transform.translation.clone();
 
-		new_translation.x = (surface_editor.area.width as f32 * column_width) + minimap.width() / 2.0 + minimap.padding;
+		// when editor is split minimap sticks to the right side of focused view
+		let right_column = app.as_ref()
+			.and_then(|app| app.views().iter().find(|view| view.focused).map(|view| view.area.right()))
+			.unwrap_or(surface_editor.area.width);
+
+		new_translation.x = (right_column as f32 * column_width) + minimap.width() / 2.0 + minimap.padding;
 		new_translation.y = camera_transform.translation.y;
 
 		// here and below we calculate y_offset for minimap to either put it on top of viewport
@@ -1018,6 +1149,45 @@ pub fn input_mouse_bookmark(
 	}
 }
 
+// clicking a diff hunk scrolls to it and shows its original text
+pub fn input_mouse_diff_hunk(
+	mouse_button		: Res<Input<MouseButton>>,
+	dragging_state		: Res<DraggingState>,
+	q_minimap			: Query<&Minimap>,
+	q_diff_hunk			: Query<&DiffHunkMarker>,
+	raypick				: Res<Raypick>,
+	mut diff_hunks		: ResMut<DiffHunks>,
+
+	app_option			: Option<NonSend<HelixApp>>,
+	mut commands		: Commands
+) {
+	let app = if let Some(app) = app_option { app } else { return };
+
+	if app.should_close() { return }
+
+	if dragging_state.is_active() { return }
+
+	profile_scope!("minimap input_mouse_diff_hunk");
+
+	if mouse_button.just_pressed(MouseButton::Left) {
+		let hover_entity	= if let Some(entity)	= raypick.last_hover 				{ entity } else { return };
+		let diff_hunk		= if let Ok(diff_hunk)	= q_diff_hunk.get(hover_entity)	{ diff_hunk } else { return };
+
+		let doc = app.current_document();
+		let Some(hunk) = hunk_at_line(doc, diff_hunk.line) else { return };
+
+		let minimap = q_minimap.single();
+
+		minimap.scroll_to_row(
+			app.row_offset_internal(),
+			diff_hunk.line.max(1),
+			&mut commands
+		);
+
+		diff_hunks.toggle_preview(doc.id(), &hunk);
+	}
+}
+
 pub fn update_minimap_scroll_animation(
 	mut q_minimap_scroll	: Query<&mut MinimapScrollAnimation>,
 		app_option			: Option<NonSendMut<HelixApp>>,
@@ -1056,4 +1226,149 @@ pub fn update_click_point(
 			commands.entity(entity).despawn();
 		}
 	}
-}
\ No newline at end of file
+}
+
+// every unfocused view gets its own minimap attached to its surface
+pub fn update_view_minimaps(
+	mut	q_view_minimap		: Query<(Entity, &mut ViewMinimap, Option<&ViewMinimapRenderTask>)>,
+		surfaces_bevy		: Res<SurfacesMapBevy>,
+		font_assets			: Res<Assets<ABGlyphFont>>,
+		font_handles		: Res<FontAssetHandles>,
+	mut mesh_assets			: ResMut<Assets<Mesh>>,
+	mut material_assets		: ResMut<Assets<StandardMaterial>>,
+	mut commands			: Commands,
+		app					: Option<NonSend<HelixApp>>
+) {
+	let app = if let Some(app) = app { app } else { return };
+
+	if app.should_close() { return }
+
+	let views = app.views();
+	if views.len() < 2 { return }
+
+	profile_scope!("minimap update_view_minimaps");
+
+	// spawn minimaps for new view surfaces, they get despawned along with their surface
+	for view in views.iter().filter(|view| !view.focused) {
+		let Some(surface_bevy) = surfaces_bevy.get(&view_surface_name(view.id)) else { continue };
+
+		if q_view_minimap.iter().any(|(_, view_minimap, _)| view_minimap.view_id == view.id) {
+			continue;
+		}
+
+		let minimap_entity = Minimap::spawn_for_view(view.id, &mut mesh_assets, &mut material_assets, &mut commands);
+
+		commands.entity(surface_bevy.entity).add_child(minimap_entity);
+	}
+
+	let fonts = ABGlyphFonts::new(&font_assets, &font_handles);
+
+	for (minimap_entity, mut view_minimap, render_task) in q_view_minimap.iter_mut() {
+		// wait until previous async task is done before starting a new one
+		if render_task.is_some() { continue }
+
+		let Some(view) = views.iter().find(|view| view.id == view_minimap.view_id) else { continue };
+		let Some(document) = app.editor.document(view.doc_id) else { continue };
+
+		let document_version = document.version();
+
+		if let Some(cache) = view_minimap.minimap.document_cache.as_ref() {
+			if cache.id == document.id()
+			&& cache.theme == app.editor.theme.name()
+			&& cache.version == document_version {
+				continue;
+			}
+
+			// same as for the main minimap don't update on every version change, wait for idle timer instead
+			if cache.version != document_version && cache.id == document.id() && !app.idle_timeout_triggered() {
+				continue;
+			}
+		}
+
+		view_minimap.minimap.document_cache = Some(SyncDataDoc {
+			id			: document.id(),
+			theme		: app.editor.theme.name().into(),
+			version		: document_version,
+			..default()
+		});
+
+		let task = view_minimap.minimap.render_task(&fonts, document, app.editor.theme.clone(), app.dark_theme());
+
+		commands.entity(minimap_entity).insert(ViewMinimapRenderTask(task));
+	}
+}
+
+pub fn handle_view_minimap_render_tasks(
+	mut	q_view_minimap		: Query<(Entity, &mut ViewMinimap, &mut ViewMinimapRenderTask)>,
+	mut mesh_assets			: ResMut<Assets<Mesh>>,
+	mut image_assets		: ResMut<Assets<Image>>,
+	mut material_assets		: ResMut<Assets<StandardMaterial>>,
+	mut commands			: Commands,
+) {
+	for (minimap_entity, mut view_minimap, mut task) in q_view_minimap.iter_mut() {
+		let Some((rows_total, colored_rows, minimap_chunks)) = future::block_on(future::poll_once(&mut task.0)) else { continue };
+
+		view_minimap.minimap.apply_render_task_results(
+			rows_total,
+			colored_rows,
+			minimap_chunks,
+			&mut mesh_assets,
+			&mut image_assets,
+			&mut material_assets,
+			&mut commands
+		);
+
+		commands.entity(minimap_entity).remove::<ViewMinimapRenderTask>();
+	}
+}
+
+// view minimap is a child of view surface: it sits to the right of it with top aligned to surface top and scrolls with the view if it doesn't fit
+pub fn update_view_minimaps_transform(
+		q_view_minimap		: Query<&ViewMinimap>,
+	mut	q_transform			: Query<&mut Transform>,
+		surfaces_bevy		: Res<SurfacesMapBevy>,
+		font_assets			: Res<Assets<ABGlyphFont>>,
+		font_handles		: Res<FontAssetHandles>,
+		app					: Option<NonSend<HelixApp>>
+) {
+	let app = if let Some(app) = app { app } else { return };
+
+	if q_view_minimap.is_empty() { return }
+
+	let fonts = ABGlyphFonts::new(&font_assets, &font_handles);
+	let column_width	= fonts.main.horizontal_advance_mono();
+	let row_height		= fonts.main.vertical_advance();
+
+	let views = app.views();
+
+	for view_minimap in q_view_minimap.iter() {
+		let minimap = &view_minimap.minimap;
+
+		let Some(view) = views.iter().find(|view| view.id == view_minimap.view_id) else { continue };
+		let Some(surface_bevy) = surfaces_bevy.get(&view_surface_name(view.id)) else { continue };
+
+		let view_rows		= surface_bevy.area.height as usize;
+		let view_height		= view_rows as f32 * row_height;
+		let minimap_height	= minimap.rows_total as f32 * minimap.row_height;
+
+		let top_offset = if minimap_height > view_height {
+			let progress = view.top_row as f32 / minimap.rows_total.saturating_sub(view_rows).max(1) as f32;
+			progress.min(1.0) * (minimap_height - view_height)
+		} else {
+			0.0
+		};
+
+		if let Ok(mut minimap_transform) = q_transform.get_mut(minimap.entity) {
+			minimap_transform.translation.x = (surface_bevy.area.width as f32 * column_width) + minimap.width() / 2.0 + minimap.padding;
+			minimap_transform.translation.y = -minimap_height / 2.0 + top_offset;
+		}
+
+		if let Ok(mut viewport_transform) = q_transform.get_mut(minimap.viewport_entity) {
+			let viewport_height = view_rows as f32 * minimap.row_height;
+			viewport_transform.scale.y = viewport_height.max(0.1);
+
+			viewport_transform.translation.y = minimap_height / 2.0;
+			viewport_transform.translation.y -= view.top_row as f32 * minimap.row_height + viewport_height / 2.0;
+		}
+	}
+}
@@ -1,5 +1,6 @@
 use bevy :: prelude :: *;
 use bevy :: utils :: HashMap;
+use bevy :: transform :: TransformSystem;
 use bevy_tweening :: *;
 
 use helix_view :: { DocumentId, Document };
@@ -22,6 +23,7 @@ use surface :: *;
 
 mod input;
 pub mod utils;
+pub mod git;
 
 mod search;
 use search :: *;
@@ -29,9 +31,72 @@ use search :: *;
 mod minimap;
 use minimap :: *;
 
+pub mod canvas;
+use canvas :: DocumentCanvas;
+
+pub mod jump;
+use jump :: { JumpAnimation, JumpScrollAnimation };
+
+pub mod document_switch;
+use document_switch :: DocumentViewports;
+
+pub mod sticky_scroll;
+use sticky_scroll :: StickyScroll;
+
+pub mod folding;
+use folding :: Folds;
+
+pub mod diagnostics_lens;
+use diagnostics_lens :: DiagnosticsLens;
+
+pub mod debugger;
+use debugger :: DebugPanels;
+
+pub mod lsp_status;
+use lsp_status :: LspStatus;
+
+pub mod file_sync;
+use file_sync :: { AutosaveSettings, FileWatcher };
+
+pub mod recovery;
+use recovery :: Recovery;
+
+pub mod blame;
+use blame :: Blame;
+
+pub mod diff_hunks;
+use diff_hunks :: DiffHunks;
+
+pub mod diff_view;
+use diff_view :: DiffView;
+
+pub mod git_history;
+use git_history :: GitHistory;
+
+pub mod workspace_search;
+use workspace_search :: WorkspaceSearch;
+
+pub mod file_explorer;
+use file_explorer :: FileExplorer;
+
+pub mod outline;
+use outline :: Outline;
+
+pub mod semantic_zoom;
+use semantic_zoom :: SemanticZoom;
+
+pub mod call_graph;
+use call_graph :: CallGraph;
+
+pub mod module_graph;
+use module_graph :: ModuleGraph;
+
 mod systems_util;
 mod systems;
 
+// camera is one row above Helix viewport, a cheat for top panel which height is 1 row
+pub const CAMERA_ROW_CONSTANT_OFFSET : f32 = -1.0;
+
 #[derive(Resource)]
 pub struct BevyHelixSettings {
 	pub key_press_init_delay_seconds	: f32,
@@ -62,6 +127,23 @@ pub struct GotoDefinitionHighlight;
 #[derive(Resource, Deref, DerefMut)]
 pub struct TokioRuntime(pub tokio::runtime::Runtime);
 
+// key that opens or closes a modal view (document canvas, diff view, git history) is consumed by it. Helix input is gated off
+// while the view is shown but its EventReader still holds the key afterwards, so everything buffered until then is dropped
+#[derive(Resource, Default)]
+pub struct KeyboardInputGuard {
+	consumed : bool,
+}
+
+impl KeyboardInputGuard {
+	pub fn consume(&mut self) {
+		self.consumed = true;
+	}
+
+	pub fn take(&mut self) -> bool {
+		std::mem::take(&mut self.consumed)
+	}
+}
+
 #[derive(Clone, Copy)]
 pub struct KeyPressTiming {
 	pub init				: Instant,
@@ -81,6 +163,7 @@ pub struct MousePosState {
 	pub row : u16,
 	pub col : u16,
 	pub surface_name : String,
+	pub scroll_captured : bool, // wheel goes to Helix instead of camera while hovering a child surface
 }
 
 #[derive(Resource, Default)]
@@ -130,7 +213,9 @@ pub enum HighlightKind {
 	Search,
 	Selection,
 	SelectionSearch,
-	Cursor
+	Cursor,
+	Fold,
+	Breakpoint,
 }
 
 impl From<SearchKind> for HighlightKind {
@@ -218,12 +303,34 @@ impl Plugin for BevyHelixPlugin {
 			.insert_resource(MatchesMapCache		:: default())
 			.insert_resource(BevyHelixSettings		:: default())
 			.insert_resource(ArrowKeysState			:: default())
+			.insert_resource(KeyboardInputGuard		:: default())
 			.insert_resource(MousePosState			:: default())
 			.insert_resource(MouseButtonState		:: default())
 			.insert_resource(MouseHoverState		:: default())
 			.insert_resource(SurfacesMapHelix		:: default())
 			.insert_resource(WordsToSpawn			:: default())
 			.insert_resource(ColoringLinesToSpawn	:: default())
+			.insert_resource(DocumentCanvas			:: default())
+			.insert_resource(JumpAnimation			:: default())
+			.insert_resource(DocumentViewports		:: default())
+			.insert_resource(StickyScroll			:: default())
+			.insert_resource(Folds					:: default())
+			.insert_resource(DiagnosticsLens		:: default())
+			.insert_resource(DebugPanels			:: default())
+			.insert_resource(LspStatus				:: default())
+			.insert_resource(AutosaveSettings		:: default())
+			.insert_resource(FileWatcher			:: default())
+			.insert_resource(Recovery				:: default())
+			.insert_resource(Blame					:: default())
+			.insert_resource(DiffHunks				:: default())
+			.insert_resource(DiffView				:: default())
+			.insert_resource(GitHistory				:: default())
+			.insert_resource(WorkspaceSearch		:: default())
+			.insert_resource(FileExplorer			:: default())
+			.insert_resource(Outline				:: default())
+			.insert_resource(SemanticZoom			:: default())
+			.insert_resource(CallGraph				:: default())
+			.insert_resource(ModuleGraph			:: default())
 
 			.insert_resource(TokioRuntime {
 				0: tokio::runtime::Builder::new_multi_thread()
@@ -244,6 +351,15 @@ impl Plugin for BevyHelixPlugin {
 				HelixInput.in_base_set(CoreSet::Update)
 				.after(TweenEvents)
 				.run_if(run_condition::text_editor_context_no_fly)
+				// drop-down or docked terminal takes over input while it's focused
+				.run_if(run_condition::code_editor_focused)
+				// document canvas handles its own input while shown
+				.run_if(canvas::canvas_inactive)
+				// and so do diff view, git history, call graph and module graph
+				.run_if(diff_view::diff_view_inactive)
+				.run_if(git_history::history_inactive)
+				.run_if(call_graph::call_graph_inactive)
+				.run_if(module_graph::module_graph_inactive)
 			)
 			.configure_set(
 				HelixRender.in_base_set(CoreSet::Update)
@@ -289,26 +405,149 @@ impl Plugin for BevyHelixPlugin {
 				.chain()
 				.in_schedule(OnExit(AppMode::AssetsLoaded))
 			)
+			.add_system(
+				recovery::systems::startup
+				.in_schedule(OnExit(AppMode::AssetsLoaded))
+			)
+			.add_system(
+				file_sync::systems::load_settings
+				.after(systems::startup_app)
+				.in_schedule(OnExit(AppMode::AssetsLoaded))
+			)
 			.add_systems(
 				(
 					systems::helix_mode_tween_events,
 					minimap::systems::update_click_point
 				).in_set(TweenEvents)
 			)
+			.add_systems(
+				(
+					canvas::systems::input_keyboard,
+					canvas::systems::spawn_cards,
+					canvas::systems::despawn_cards,
+					canvas::systems::input_mouse,
+					canvas::systems::update_camera_travel,
+					canvas::systems::update_editing,
+					canvas::systems::update_card_surfaces,
+				)
+				.chain()
+				.after(TweenEvents)
+				.before(HelixInput)
+				.distributive_run_if(run_condition::text_editor_context_no_fly)
+				.distributive_run_if(run_condition::code_editor_focused)
+			)
+			.add_systems(
+				(
+					git_history::systems::update_activation,
+					git_history::systems::input_keyboard,
+					git_history::systems::input_mouse,
+					git_history::systems::spawn_graph,
+					git_history::systems::update_selection,
+					git_history::systems::open_requested_diff,
+				)
+				.chain()
+				.after(TweenEvents)
+				.before(HelixInput)
+				.distributive_run_if(run_condition::text_editor_context_no_fly)
+				.distributive_run_if(run_condition::code_editor_focused)
+			)
+			.add_systems(
+				(
+					call_graph::systems::input_keyboard,
+					call_graph::systems::input_mouse,
+					call_graph::systems::poll_requests,
+					call_graph::systems::open_requested_location,
+					call_graph::systems::spawn_graph,
+					call_graph::systems::update_selection,
+				)
+				.chain()
+				.after(TweenEvents)
+				.before(HelixInput)
+				.distributive_run_if(run_condition::text_editor_context_no_fly)
+				.distributive_run_if(run_condition::code_editor_focused)
+			)
+			.add_systems(
+				(
+					module_graph::systems::input_keyboard,
+					module_graph::systems::input_mouse,
+					module_graph::systems::poll_load,
+					module_graph::systems::open_requested_module,
+					module_graph::systems::spawn_graph,
+					module_graph::systems::update_selection,
+				)
+				.chain()
+				.after(TweenEvents)
+				.before(HelixInput)
+				.distributive_run_if(run_condition::text_editor_context_no_fly)
+				.distributive_run_if(run_condition::code_editor_focused)
+			)
 			.add_systems(
 				(
 					systems::input_mouse,
 					systems::input_keyboard,
+					systems::input_scroll_child_surfaces.after(systems::input_mouse),
+					sticky_scroll::systems::input_mouse,
+					folding::systems::input_keyboard.after(systems::input_keyboard),
+					folding::systems::input_mouse.after(systems::input_mouse),
+					diagnostics_lens::systems::input_keyboard,
 					systems::mouse_last_clicked,
 					systems::mouse_hover,
 					systems::mouse_goto_definition,
 					systems::update_editor_resizer,
+					systems::update_view_resizers.before(systems::update_editor_resizer),
 					minimap::systems::input_mouse,
 					minimap::systems::input_mouse_bookmark,
 				).in_set(HelixInput)
 			)
 			.add_systems(
 				(
+					debugger::systems::input_keyboard.after(systems::input_keyboard),
+					debugger::systems::input_mouse,
+					debugger::systems::input_scroll,
+					debugger::systems::input_mouse_gutter.after(systems::input_mouse),
+					lsp_status::systems::input_keyboard.after(systems::input_keyboard),
+					lsp_status::systems::input_mouse,
+					recovery::systems::input_keyboard,
+					blame::systems::input_keyboard.after(systems::input_keyboard),
+					diff_hunks::systems::input_mouse_gutter.after(systems::input_mouse),
+					diff_hunks::systems::input_mouse_buttons,
+					minimap::systems::input_mouse_diff_hunk,
+					workspace_search::systems::input_keyboard.after(systems::input_keyboard),
+					workspace_search::systems::input_mouse,
+					workspace_search::systems::input_scroll,
+				).in_set(HelixInput)
+			)
+			.add_systems(
+				(
+					file_explorer::systems::input_keyboard.after(systems::input_keyboard),
+					file_explorer::systems::input_mouse,
+					file_explorer::systems::input_scroll,
+					outline::systems::input_keyboard.after(systems::input_keyboard),
+					outline::systems::input_mouse,
+					outline::systems::input_scroll,
+				).in_set(HelixInput)
+			)
+			.add_systems(
+				(
+					diff_view::systems::input_keyboard,
+					diff_view::systems::update,
+				)
+				.chain()
+				// after Helix input so that keys closing diff view don't make it to Helix in the same frame
+				.after(HelixInput)
+				.before(HelixRender)
+				.distributive_run_if(run_condition::text_editor_context_no_fly)
+				.distributive_run_if(run_condition::code_editor_focused)
+			)
+			.add_systems(
+				(
+					document_switch::systems::switch_document_viewport,
+					jump::systems::detect_jumps.run_if(run_condition::text_editor_context_no_fly),
+					folding::systems::reveal_cursor,
+					debugger::systems::refresh_panels,
+					workspace_search::systems::update,
+					outline::systems::update,
+					lsp_status::systems::update_spinner,
 					systems::camera_update,
 					systems::render_helix
 				)
@@ -321,9 +560,11 @@ impl Plugin for BevyHelixPlugin {
 					systems::manage_cursors
 				).in_set(ManageSurfaces)
 			)
-			.add_system(
-				systems::update_cursor
-				.in_set(UpdateCursor)
+			.add_systems(
+				(
+					systems::update_cursor,
+					systems::update_view_cursors,
+				).in_set(UpdateCursor)
 			)
 
 			// UpdateMain START
@@ -345,6 +586,8 @@ impl Plugin for BevyHelixPlugin {
 					systems::update_selection_search_matches,
 					minimap::systems::update,
 					minimap::systems::handle_render_tasks,
+					minimap::systems::update_view_minimaps,
+					minimap::systems::handle_view_minimap_render_tasks,
 				).in_set(UpdateMain)
 			)
 			// UpdateMain END
@@ -375,20 +618,85 @@ impl Plugin for BevyHelixPlugin {
 					minimap::systems::update_selection_search_highlights,
 					minimap::systems::update_selection_highlights,
 					minimap::systems::update_transform,
+					minimap::systems::update_view_minimaps_transform,
 					minimap::systems::reveal_hovered_bookmark,
 					minimap::systems::update_minimap_scroll_animation,
+					document_switch::systems::despawn_transition_curtain,
+					semantic_zoom::systems::update,
+				).in_set(UpdateSecondary)
+			)
+			// jump animation has to be applied after minimap scroll to correctly remember the last viewport position
+			.add_systems(
+				(
+					jump::systems::update_jump_animation,
+					jump::systems::update_landing_highlight,
+					jump::systems::sync_row_offset,
+				)
+				.chain()
+				.after(minimap::systems::update_minimap_scroll_animation)
+				.in_set(UpdateSecondary)
+			)
+			.add_systems(
+				(
+					folding::systems::update_markers,
+					minimap::systems::update_fold_highlights,
+					debugger::systems::update_execution_line,
+					minimap::systems::update_breakpoint_highlights,
 				).in_set(UpdateSecondary)
 			)
+			.add_systems(
+				(
+					diagnostics_lens::systems::update_hover,
+					diagnostics_lens::systems::update_lens,
+				)
+				.chain()
+				.in_set(UpdateSecondary)
+			)
+			.add_systems(
+				(
+					blame::systems::update_blame,
+					blame::systems::update_line_history,
+					blame::systems::update_hover,
+					blame::systems::update_annotations,
+				)
+				.chain()
+				.in_set(UpdateSecondary)
+			)
+			.add_systems(
+				(
+					diff_hunks::systems::update_index,
+					diff_hunks::systems::update_preview,
+					minimap::systems::update_diff_gutter,
+				)
+				.chain()
+				.in_set(UpdateSecondary)
+			)
+			.add_systems(
+				(
+					file_explorer::systems::update,
+					file_explorer::systems::update_transform,
+					file_explorer::systems::update_text_surface,
+				)
+				.chain()
+				.in_set(UpdateSecondary)
+			)
 			// UpdateSecondary END
 
 			.add_systems(
 				(
 					component_animator_system::<MinimapScrollAnimation>,
+					component_animator_system::<JumpScrollAnimation>,
 				).in_set(AnimationSystem::AnimationUpdate)
 			)
 			.add_systems(
 				(
 					systems::on_context_switch_out,
+					file_sync::systems::autosave_on_context_switch,
+					git_history::systems::on_context_switch_out,
+					file_explorer::systems::on_context_switch_out,
+					semantic_zoom::systems::on_context_switch_out,
+					call_graph::systems::on_context_switch_out,
+					module_graph::systems::on_context_switch_out,
 				)
 				.in_set(ContextSwitch)
 				.in_schedule(OnExit(AppContext::CodeEditor))
@@ -409,10 +717,28 @@ impl Plugin for BevyHelixPlugin {
 				)
 				.in_base_set(CoreSet::PostUpdate)
 			)
+			// words spawned during Update are only available after commands are applied so rows are offset right before transform propagation
+			.add_system(
+				folding::systems::update_rows_offset
+				.in_base_set(CoreSet::PostUpdate)
+				.before(TransformSystem::TransformPropagate)
+				.run_if(run_condition::text_editor_context_no_fly)
+			)
 			// tokio events processing is kept alive even when Helix is not in focus to keep Helix updated
 			.add_system(
 				systems::tokio_events.in_set(OnUpdate(AppMode::Main))
 			)
+			// documents are kept in sync with disk no matter which context is active
+			.add_systems(
+				(
+					file_sync::systems::sync_settings,
+					file_sync::systems::autosave_on_focus_lost,
+					file_sync::systems::watch_files,
+					recovery::systems::offer_recovery,
+					recovery::systems::update_backups,
+				)
+				.in_set(OnUpdate(AppMode::Main))
+			)
 
 			.add_system(systems::on_window_close_requested)
 			.add_system(systems::exit_app)
@@ -0,0 +1,577 @@
+use bevy :: prelude :: *;
+use bevy :: tasks :: Task;
+use bevy :: utils :: { HashMap, HashSet };
+
+use std :: path :: { Path, PathBuf };
+
+pub mod systems;
+
+pub const LEVEL_COLUMNS			: f32	= 40.0;	// horizontal distance between module tree levels
+pub const LEVEL_DEPTH_COLUMNS	: f32	= 8.0;	// every next level is pushed further away from camera
+pub const NODE_ROWS				: f32	= 3.0;	// name, size and a gap
+pub const BOX_COLUMNS			: f32	= 6.0;	// room for module box in front of the name
+pub const LABEL_COLUMNS			: usize	= 32;
+pub const DETAILS_COLUMNS		: usize	= 80;
+pub const VIEW_COLUMNS			: usize	= 160;	// what camera is centered on
+pub const EDGE_THICKNESS		: f32	= 0.08;	// fraction of column width
+pub const ARC_HEIGHT			: f32	= 0.25;	// dependency edges bulge towards camera by this fraction of their length
+pub const MAX_MODULES			: usize	= 5000;
+pub const MAX_SCAN_DEPTH		: usize	= 6;	// how deep crates are looked for in workspace
+
+#[derive(Clone, Debug)]
+pub struct ModuleNode {
+	pub name		: String,
+	pub file		: PathBuf,
+	pub lines		: usize,
+	pub parent		: Option<usize>,
+	pub depth		: usize,
+	pub uses		: Vec<Vec<String>>, // paths of use statements, resolved into edges once every module is known
+}
+
+// modules of every crate in workspace in depth first order, so every module is followed by its submodules
+#[derive(Default)]
+pub struct ModuleTree {
+	pub root		: PathBuf,
+	pub modules		: Vec<ModuleNode>,
+	pub edges		: Vec<(usize, usize)>, // module and the one it uses
+}
+
+impl ModuleTree {
+	pub fn module_path(&self, index: usize) -> String {
+		let mut segments = Vec::new();
+		let mut current = Some(index);
+
+		while let Some(index) = current {
+			segments.push(self.modules[index].name.as_str());
+			current = self.modules[index].parent;
+		}
+
+		segments.reverse();
+		segments.join("::")
+	}
+
+	fn crate_root(&self, mut index: usize) -> usize {
+		while let Some(parent) = self.modules[index].parent {
+			index = parent;
+		}
+
+		index
+	}
+
+	// column is tree depth, slot is row in units of NODE_ROWS. Submodules start next to their parent and go down, crates are stacked
+	pub fn layout(&self) -> Vec<(f32, f32)> {
+		let mut slots		= vec![(0.0, 0.0); self.modules.len()];
+		let mut next_free	: Vec<f32> = Vec::new();
+		let mut last_slot	= -2.0;
+
+		for (index, module) in self.modules.iter().enumerate() {
+			if next_free.len() <= module.depth {
+				next_free.resize(module.depth + 1, 0.0);
+			}
+
+			let slot = match module.parent {
+				Some(parent) => slots[parent].1.max(next_free[module.depth]),
+				// gap between crates
+				None => last_slot + 2.0,
+			};
+
+			next_free[module.depth] = slot + 1.0;
+			last_slot = last_slot.max(slot);
+
+			slots[index] = (module.depth as f32, slot);
+		}
+
+		slots
+	}
+}
+
+// crates are directories with Cargo.toml, build output and hidden directories are skipped
+fn find_manifests(dir: &Path, depth: usize, manifests: &mut Vec<PathBuf>) {
+	if dir.join("Cargo.toml").is_file() {
+		manifests.push(dir.to_path_buf());
+	}
+
+	if depth >= MAX_SCAN_DEPTH { return }
+
+	let Ok(entries) = std::fs::read_dir(dir) else { return };
+
+	let mut dirs : Vec<PathBuf> = entries
+		.filter_map(|entry| entry.ok())
+		.filter(|entry| entry.file_type().map_or(false, |file_type| file_type.is_dir()))
+		.filter(|entry| {
+			let name = entry.file_name();
+			let name = name.to_string_lossy();
+			!name.starts_with('.') && name != "target" && name != "node_modules"
+		})
+		.map(|entry| entry.path())
+		.collect();
+
+	dirs.sort();
+
+	for dir in dirs {
+		find_manifests(&dir, depth + 1, manifests);
+	}
+}
+
+// name from [package] section with dashes replaced the way rustc does it
+fn crate_name(manifest_dir: &Path) -> String {
+	let fallback = manifest_dir.file_name().map_or(String::from("crate"), |name| name.to_string_lossy().to_string());
+
+	let Ok(manifest) = std::fs::read_to_string(manifest_dir.join("Cargo.toml")) else { return fallback.replace('-', "_") };
+
+	let mut in_package = false;
+
+	for line in manifest.lines().map(str::trim) {
+		if line.starts_with('[') {
+			in_package = line == "[package]";
+			continue;
+		}
+
+		if !in_package { continue }
+
+		let Some((key, value)) = line.split_once('=') else { continue };
+
+		if key.trim() == "name" {
+			return value.trim().trim_matches('"').replace('-', "_");
+		}
+	}
+
+	fallback.replace('-', "_")
+}
+
+// module and use items found in a file, module bodies declared inline are treated as part of the file
+struct FileItems {
+	lines	: usize,
+	modules	: Vec<(String, Option<String>)>, // name and #[path] attribute
+	uses	: Vec<Vec<String>>,
+}
+
+fn strip_visibility(line: &str) -> &str {
+	let Some(rest) = line.strip_prefix("pub") else { return line };
+
+	let rest = rest.trim_start();
+
+	match rest.strip_prefix('(') {
+		Some(rest) => rest.split_once(')').map_or(rest, |(_, rest)| rest).trim_start(),
+		None => rest,
+	}
+}
+
+fn scan_file(file: &Path) -> Option<FileItems> {
+	let text = std::fs::read_to_string(file).ok()?;
+
+	let mut items = FileItems { lines: text.lines().count(), modules: Vec::new(), uses: Vec::new() };
+
+	let mut in_block_comment	= false;
+	let mut path_attribute		: Option<String> = None;
+	let mut use_statement		: Option<String> = None;
+
+	for line in text.lines() {
+		let mut line = line.trim();
+
+		if in_block_comment {
+			let Some((_, rest)) = line.split_once("*/") else { continue };
+			in_block_comment = false;
+			line = rest.trim();
+		}
+
+		if line.starts_with("/*") && !line.contains("*/") {
+			in_block_comment = true;
+			continue;
+		}
+
+		let line = line.split("//").next().unwrap_or_default().trim();
+
+		// use statements can span several lines
+		if let Some(statement) = use_statement.as_mut() {
+			statement.push(' ');
+			statement.push_str(line);
+		} else if let Some(rest) = strip_visibility(line).strip_prefix("use ") {
+			use_statement = Some(String::from(rest));
+		}
+
+		if let Some(statement) = use_statement.take() {
+			match statement.split_once(';') {
+				Some((tree, _)) => expand_use_tree(&[], tree, &mut items.uses),
+				None => use_statement = Some(statement),
+			}
+
+			continue;
+		}
+
+		if let Some(attribute) = line.strip_prefix("#[path") {
+			path_attribute = attribute.split('"').nth(1).map(String::from);
+			continue;
+		}
+
+		if line.starts_with("#[") { continue }
+
+		// only modules in their own files, inline ones end with a body
+		if let Some(rest) = strip_visibility(line).strip_prefix("mod ") {
+			if let Some(name) = rest.trim().strip_suffix(';') {
+				items.modules.push((String::from(name.trim()), path_attribute.take()));
+			}
+		}
+
+		path_attribute = None;
+	}
+
+	Some(items)
+}
+
+// a::{b, c::{d, e as f}, *} turns into a::b, a::c::d, a::c::e and a
+pub fn expand_use_tree(prefix: &[String], tree: &str, paths: &mut Vec<Vec<String>>) {
+	let tree = tree.trim();
+
+	let segments = |head: &str| -> Vec<String> {
+		head.split("::").map(str::trim).filter(|segment| !segment.is_empty()).map(String::from).collect()
+	};
+
+	if let (Some(open), Some(close)) = (tree.find('{'), tree.rfind('}')) {
+		let mut path = prefix.to_vec();
+		path.extend(segments(&tree[.. open]));
+
+		let mut depth = 0;
+		let mut start = open + 1;
+
+		for (offset, c) in tree[open + 1 .. close].char_indices() {
+			let position = open + 1 + offset;
+
+			match c {
+				'{' => depth += 1,
+				'}' => depth -= 1,
+				',' if depth == 0 => {
+					expand_use_tree(&path, &tree[start .. position], paths);
+					start = position + 1;
+				},
+				_ => (),
+			}
+		}
+
+		expand_use_tree(&path, &tree[start .. close], paths);
+
+		return;
+	}
+
+	let leaf = tree.split(" as ").next().unwrap_or_default();
+
+	let mut path = prefix.to_vec();
+	path.extend(segments(leaf));
+
+	if matches!(path.last().map(String::as_str), Some("*") | Some("self")) {
+		path.pop();
+	}
+
+	if !path.is_empty() {
+		paths.push(path);
+	}
+}
+
+// mod.rs, lib.rs and main.rs own the directory they are in, other files own the directory named after them
+fn submodule_file(file: &Path, name: &str, path_attribute: Option<&String>) -> Option<PathBuf> {
+	let file_dir = file.parent()?;
+
+	if let Some(path) = path_attribute {
+		return Some(file_dir.join(path));
+	}
+
+	let owns_directory = matches!(file.file_name().and_then(|name| name.to_str()), Some("mod.rs") | Some("lib.rs") | Some("main.rs"));
+
+	let dir = if owns_directory { file_dir.to_path_buf() } else { file_dir.join(file.file_stem()?) };
+
+	[dir.join(format!("{}.rs", name)), dir.join(name).join("mod.rs")].into_iter().find(|candidate| candidate.is_file())
+}
+
+fn scan_module(
+	name	: String,
+	file	: PathBuf,
+	parent	: Option<usize>,
+	depth	: usize,
+	visited	: &mut HashSet<PathBuf>,
+	modules	: &mut Vec<ModuleNode>,
+) {
+	if modules.len() >= MAX_MODULES || !visited.insert(file.clone()) { return }
+
+	let Some(items) = scan_file(&file) else { return };
+
+	let index = modules.len();
+
+	modules.push(ModuleNode {
+		name,
+		file	: file.clone(),
+		lines	: items.lines,
+		parent,
+		depth,
+		uses	: items.uses,
+	});
+
+	for (name, path_attribute) in items.modules {
+		let Some(submodule_file) = submodule_file(&file, &name, path_attribute.as_ref()) else { continue };
+
+		scan_module(name, submodule_file, Some(index), depth + 1, visited, modules);
+	}
+}
+
+// use paths are followed through modules as far as they go: crate, self, super, other crates of workspace and submodules
+fn resolve_edges(tree: &ModuleTree, crates: &HashMap<String, usize>) -> Vec<(usize, usize)> {
+	let children : HashMap<(usize, &str), usize> = tree.modules.iter().enumerate()
+		.filter_map(|(index, module)| Some(((module.parent?, module.name.as_str()), index)))
+		.collect();
+
+	let mut edges = HashSet::new();
+
+	for (index, module) in tree.modules.iter().enumerate() {
+		for path in module.uses.iter() {
+			let mut segments = path.iter().map(String::as_str).peekable();
+
+			let mut target = match segments.peek().copied() {
+				Some("crate")	=> { segments.next(); tree.crate_root(index) },
+				Some("self")	=> { segments.next(); index },
+				Some("super")	=> index,
+				Some(first) if children.contains_key(&(index, first)) => index,
+				Some(first) => match crates.get(first) {
+					Some(crate_root) => { segments.next(); *crate_root },
+					None => continue,
+				},
+				None => continue,
+			};
+
+			while segments.peek() == Some(&"super") {
+				segments.next();
+				target = tree.modules[target].parent.unwrap_or(target);
+			}
+
+			for segment in segments {
+				let Some(child) = children.get(&(target, segment)) else { break };
+				target = *child;
+			}
+
+			// parent is already connected by the tree
+			if target == index || module.parent == Some(target) { continue }
+
+			edges.insert((index, target));
+		}
+	}
+
+	let mut edges : Vec<(usize, usize)> = edges.into_iter().collect();
+	edges.sort();
+
+	edges
+}
+
+// every crate of workspace starting from lib.rs or main.rs
+pub fn load_tree(workspace: &Path) -> Result<ModuleTree, String> {
+	let mut manifests = Vec::new();
+	find_manifests(workspace, 0, &mut manifests);
+
+	if manifests.is_empty() {
+		return Err(format!("no Cargo.toml found in {}", workspace.display()));
+	}
+
+	let mut modules = Vec::new();
+	let mut visited = HashSet::new();
+	let mut crates = HashMap::new();
+
+	for manifest_dir in manifests {
+		let name = crate_name(&manifest_dir);
+
+		for root_file in ["lib.rs", "main.rs"] {
+			let file = manifest_dir.join("src").join(root_file);
+			if !file.is_file() { continue }
+
+			// other crates refer to the library when there are both
+			crates.entry(name.clone()).or_insert(modules.len());
+
+			scan_module(name.clone(), file, None, 0, &mut visited, &mut modules);
+		}
+	}
+
+	let mut tree = ModuleTree { root: workspace.to_path_buf(), modules, edges: Vec::new() };
+	tree.edges = resolve_edges(&tree, &crates);
+
+	Ok(tree)
+}
+
+#[derive(Component)]
+pub struct ModuleGraphNode {
+	pub index : usize,
+}
+
+#[derive(Component)]
+pub struct ModuleGraphAnchor;
+
+// modules of workspace crates and their use dependencies, shown over hidden editor while active
+#[derive(Resource, Default)]
+pub struct ModuleGraph {
+	pub active			: bool,
+	pub tree			: Option<ModuleTree>,
+	pub load_task		: Option<Task<Result<ModuleTree, String>>>,
+	pub error			: Option<String>,
+	pub selected		: usize,
+	pub open_requested	: Option<usize>,
+
+	pub version			: usize, // bumped when modules or their diagnostics change so graph is respawned
+	pub spawned_version	: Option<usize>,
+	pub diagnostics_key	: (usize, usize),
+	pub root_entity		: Option<Entity>,
+	pub anchor_entity	: Option<Entity>,
+	pub marker_entity	: Option<Entity>, // highlights selected module
+	pub traveled_to		: Option<usize>, // module camera anchor was last sent to
+	pub details			: Vec<Entity>, // edges and description of selected module
+	pub details_cache	: Option<usize>,
+}
+
+pub fn module_graph_inactive(module_graph: Res<ModuleGraph>) -> bool {
+	!module_graph.active
+}
+
+impl ModuleGraph {
+	pub fn open(&mut self, load_task: Task<Result<ModuleTree, String>>) {
+		self.clear();
+
+		self.active		= true;
+		self.load_task	= Some(load_task);
+	}
+
+	pub fn close(&mut self) {
+		self.active = false;
+	}
+
+	pub fn finish_load(&mut self, result: Result<ModuleTree, String>) {
+		match result {
+			Ok(tree) => self.tree = Some(tree),
+			Err(e) => self.error = Some(e),
+		}
+
+		self.version += 1;
+	}
+
+	// up and down walk modules in tree order, left goes to parent, right to the first submodule
+	pub fn select_relative(&mut self, delta: i64) {
+		let Some(tree) = self.tree.as_ref() else { return };
+		if tree.modules.is_empty() { return }
+
+		self.selected = (self.selected as i64 + delta).clamp(0, tree.modules.len() as i64 - 1) as usize;
+	}
+
+	pub fn select_parent(&mut self) {
+		let Some(parent) = self.tree.as_ref().and_then(|tree| tree.modules.get(self.selected)?.parent) else { return };
+		self.selected = parent;
+	}
+
+	pub fn select_child(&mut self) {
+		let Some(tree) = self.tree.as_ref() else { return };
+
+		let child = self.selected + 1;
+
+		if tree.modules.get(child).map_or(false, |module| module.parent == Some(self.selected)) {
+			self.selected = child;
+		}
+	}
+
+	pub fn selected_module(&self) -> Option<&ModuleNode> {
+		self.tree.as_ref()?.modules.get(self.selected)
+	}
+
+	// everything spawned for graph except the camera anchor is a child of the root
+	pub fn take_graph_entities(&mut self) -> Option<Entity> {
+		self.marker_entity		= None;
+		self.traveled_to		= None;
+		self.spawned_version	= None;
+		self.details_cache		= None;
+		self.details.clear();
+
+		self.root_entity.take()
+	}
+
+	pub fn take_entities(&mut self) -> Vec<Entity> {
+		self.take_graph_entities().into_iter().chain(self.anchor_entity.take()).collect()
+	}
+
+	pub fn clear(&mut self) {
+		self.active			= false;
+		self.tree			= None;
+		self.load_task		= None;
+		self.error			= None;
+		self.selected		= 0;
+		self.open_requested	= None;
+		self.version		+= 1;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super :: *;
+
+	fn paths(tree: &str) -> Vec<Vec<String>> {
+		let mut paths = Vec::new();
+		expand_use_tree(&[], tree, &mut paths);
+		paths
+	}
+
+	fn path(segments: &[&str]) -> Vec<String> {
+		segments.iter().map(|segment| String::from(*segment)).collect()
+	}
+
+	fn module(name: &str, parent: Option<usize>, depth: usize, uses: &[&[&str]]) -> ModuleNode {
+		ModuleNode {
+			name	: String::from(name),
+			file	: PathBuf::new(),
+			lines	: 0,
+			parent,
+			depth,
+			uses	: uses.iter().map(|segments| path(segments)).collect(),
+		}
+	}
+
+	#[test]
+	fn expand_nested_use_tree() {
+		assert_eq!(paths("a::{b, c::{d, e as f}, *}"), vec![
+			path(&["a", "b"]),
+			path(&["a", "c", "d"]),
+			path(&["a", "c", "e"]),
+			path(&["a"]),
+		]);
+	}
+
+	#[test]
+	fn expand_use_tree_with_self_and_spaces() {
+		assert_eq!(paths("super :: { self, Foo }"), vec![path(&["super"]), path(&["super", "Foo"])]);
+		assert_eq!(paths("crate::x as y"), vec![path(&["crate", "x"])]);
+	}
+
+	#[test]
+	fn resolve_edges_through_modules_and_crates() {
+		let tree = ModuleTree {
+			root	: PathBuf::new(),
+			modules	: vec![
+				module("kodiki", None, 0, &[&["crate", "b", "c"], &["a", "Foo"]]),
+				module("a", Some(0), 1, &[&["super", "b"]]),
+				module("b", Some(0), 1, &[&["std", "fs"]]),
+				module("c", Some(2), 2, &[&["crate", "a"], &["other", "m", "Bar"], &["super", "d"]]),
+				module("other", None, 0, &[]),
+				module("m", Some(4), 1, &[&["self"]]),
+			],
+			edges	: Vec::new(),
+		};
+
+		let crates : HashMap<String, usize> = [(String::from("kodiki"), 0), (String::from("other"), 4)].into_iter().collect();
+
+		// other crates are left out and so are parents and modules using themselves
+		assert_eq!(resolve_edges(&tree, &crates), vec![(0, 1), (0, 3), (1, 2), (3, 1), (3, 5)]);
+	}
+
+	#[test]
+	fn module_path_joins_ancestors() {
+		let tree = ModuleTree {
+			modules	: vec![
+				module("kodiki", None, 0, &[]),
+				module("bevy_helix", Some(0), 1, &[]),
+				module("folding", Some(1), 2, &[]),
+			],
+			..default()
+		};
+
+		assert_eq!(tree.module_path(2), "kodiki::bevy_helix::folding");
+	}
+}
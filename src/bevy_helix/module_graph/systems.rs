@@ -0,0 +1,613 @@
+use bevy :: prelude :: *;
+use bevy :: tasks :: AsyncComputeTaskPool;
+use bevy_tweening :: *;
+use bevy_reader_camera :: { ReaderCamera, TextDescriptor };
+
+#[cfg(feature = "tracing")]
+use bevy_puffin :: *;
+
+use futures_lite :: future;
+
+use helix_lsp :: lsp;
+use helix_term :: ui :: EditorView;
+use helix_view :: { Editor, graphics :: Color as HelixColor };
+
+use std :: time :: Duration;
+
+use super :: *;
+
+use crate :: {
+	z_order,
+	kodiki :: DespawnResource,
+	kodiki_ui :: {
+		String3dSpawnRequest, CommonString3dSpawnParams, ColorMaterialsCache,
+		color :: { get_color_material_handle, get_color_material_walpha_handle },
+		raypick :: RaypickHover,
+		spawn :: string_mesh_collision,
+		tween_lens :: TransformLens,
+	},
+	bevy_ab_glyph :: { ABGlyphFont, ABGlyphFonts, FontAssetHandles },
+	bevy_framerate_manager :: FramerateManager,
+	bevy_helix :: {
+		HelixApp, TokioRuntime,
+		diagnostics_lens :: short_message,
+		surface :: SurfacesMapBevy,
+		utils :: color_from_helix,
+	},
+};
+
+// where modules go in the graph, deeper modules are further to the right and further away from camera
+struct GraphMetrics {
+	column_width	: f32,
+	row_height		: f32,
+}
+
+impl GraphMetrics {
+	fn new(font: &ABGlyphFont) -> Self {
+		Self {
+			column_width	: font.horizontal_advance_mono(),
+			row_height		: font.vertical_advance(),
+		}
+	}
+
+	fn node_position(&self, (column, slot): (f32, f32)) -> Vec3 {
+		Vec3::new(
+			column * LEVEL_COLUMNS * self.column_width,
+			-slot * NODE_ROWS * self.row_height,
+			-column * LEVEL_DEPTH_COLUMNS * self.column_width + z_order::surface::text()
+		)
+	}
+
+	// edges connect module boxes
+	fn box_center(&self, node: Vec3) -> Vec3 {
+		node + Vec3::new(BOX_COLUMNS / 2.0 * self.column_width, self.row_height / 2.0, 0.0)
+	}
+
+	// bigger modules get bigger boxes, square root keeps huge files from taking over
+	fn box_size(&self, lines: usize) -> f32 {
+		((lines as f32).sqrt() / 10.0).clamp(0.5, 2.5) * self.row_height
+	}
+
+	fn label_position(&self, node: Vec3) -> Vec3 {
+		node + Vec3::X * BOX_COLUMNS * self.column_width
+	}
+
+	fn details_position(&self, node: Vec3, line: usize) -> Vec3 {
+		self.label_position(node) + Vec3::new((LABEL_COLUMNS + 2) as f32 * self.column_width, -(line as f32) * self.row_height, 0.0)
+	}
+
+	// selected module stays in the middle of the screen
+	fn anchor_position(&self, node: Vec3, visible_rows: f32) -> Vec3 {
+		Vec3::new(
+			node.x + BOX_COLUMNS * self.column_width - (VIEW_COLUMNS / 4) as f32 * self.column_width,
+			node.y + (visible_rows / 2.0).floor() * self.row_height,
+			0.0
+		)
+	}
+}
+
+fn spawn_string(
+	string				: String,
+	position			: Vec3,
+	color				: Color,
+	commands			: &mut Commands,
+) -> Entity {
+	commands.spawn((
+		TransformBundle::from_transform(Transform::from_translation(position)),
+		VisibilityBundle::default(),
+		String3dSpawnRequest {
+			common : CommonString3dSpawnParams {
+				string,
+				color,
+				..default()
+			},
+			..default()
+		},
+	)).id()
+}
+
+fn make_clickable(entity: Entity, string: &String, font: &ABGlyphFont, commands: &mut Commands) {
+	let collision_entity = string_mesh_collision(string, font, commands);
+
+	commands.entity(entity)
+		.insert(RaypickHover::default())
+		.add_child(collision_entity);
+}
+
+// unit quad stretched between two points
+fn spawn_segment(
+	from		: Vec3,
+	to			: Vec3,
+	thickness	: f32,
+	mesh		: &Handle<Mesh>,
+	material	: &Handle<StandardMaterial>,
+	commands	: &mut Commands,
+) -> Option<Entity> {
+	let delta	= to - from;
+	let length	= delta.length();
+
+	if length < f32::EPSILON { return None }
+
+	Some(commands.spawn(PbrBundle {
+		mesh		: mesh.clone(),
+		material	: material.clone(),
+		transform	: Transform {
+			translation	: (from + to) / 2.0,
+			rotation	: Quat::from_rotation_arc(Vec3::Y, delta / length),
+			scale		: Vec3::new(thickness, length, 1.0),
+		},
+		..default()
+	}).id())
+}
+
+// dependency edges bulge towards camera so they don't run through the tree
+fn spawn_arc(
+	from		: Vec3,
+	to			: Vec3,
+	thickness	: f32,
+	mesh		: &Handle<Mesh>,
+	material	: &Handle<StandardMaterial>,
+	commands	: &mut Commands,
+) -> Vec<Entity> {
+	let middle = (from + to) / 2.0 + Vec3::Z * from.distance(to) * ARC_HEIGHT;
+
+	[(from, middle), (middle, to)].into_iter()
+		.filter_map(|(from, to)| spawn_segment(from, to, thickness, mesh, material, commands))
+		.collect()
+}
+
+// errors and warnings per file
+fn diagnostics_counts(editor: &Editor) -> HashMap<PathBuf, (usize, usize)> {
+	let mut counts : HashMap<PathBuf, (usize, usize)> = HashMap::default();
+
+	for (url, diagnostics) in editor.diagnostics.iter() {
+		let Ok(path) = url.to_file_path() else { continue };
+
+		let errors		= diagnostics.iter().filter(|diagnostic| diagnostic.severity == Some(lsp::DiagnosticSeverity::ERROR)).count();
+		let warnings	= diagnostics.iter().filter(|diagnostic| diagnostic.severity == Some(lsp::DiagnosticSeverity::WARNING)).count();
+
+		if errors + warnings == 0 { continue }
+
+		counts.insert(path, (errors, warnings));
+	}
+
+	counts
+}
+
+fn diagnostics_key(editor: &Editor) -> (usize, usize) {
+	(editor.diagnostics.len(), editor.diagnostics.values().map(|diagnostics| diagnostics.len()).sum())
+}
+
+// ctrl+alt+m shows module graph of workspace. While graph is shown: esc closes it, up/down (j/k) walk modules,
+// left/right (h/l) go to parent and submodule, enter opens module file
+pub fn input_keyboard(
+		key				: Res<Input<KeyCode>>,
+	mut module_graph	: ResMut<ModuleGraph>,
+		app_option		: Option<NonSend<HelixApp>>,
+) {
+	let Some(app) = app_option else { return };
+
+	let ctrl_pressed	= key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl);
+	let alt_pressed		= key.pressed(KeyCode::LAlt) || key.pressed(KeyCode::RAlt);
+
+	if ctrl_pressed && alt_pressed && key.just_pressed(KeyCode::M) {
+		if module_graph.active {
+			module_graph.close();
+		} else if app.editor_focused() {
+			let workspace = std::env::current_dir().unwrap_or_default();
+
+			module_graph.open(AsyncComputeTaskPool::get().spawn(async move {
+				load_tree(&workspace)
+			}));
+		}
+
+		return;
+	}
+
+	if !module_graph.active { return }
+
+	if key.just_pressed(KeyCode::Escape) {
+		module_graph.close();
+	} else if key.just_pressed(KeyCode::Up) || key.just_pressed(KeyCode::K) {
+		module_graph.select_relative(-1);
+	} else if key.just_pressed(KeyCode::Down) || key.just_pressed(KeyCode::J) {
+		module_graph.select_relative(1);
+	} else if key.just_pressed(KeyCode::Left) || key.just_pressed(KeyCode::H) {
+		module_graph.select_parent();
+	} else if key.just_pressed(KeyCode::Right) || key.just_pressed(KeyCode::L) {
+		module_graph.select_child();
+	} else if key.just_pressed(KeyCode::Return) {
+		module_graph.open_requested = Some(module_graph.selected);
+	}
+}
+
+// clicking a module selects it, clicking selected module opens its file
+pub fn input_mouse(
+		mouse_button	: Res<Input<MouseButton>>,
+		q_node			: Query<(&ModuleGraphNode, &RaypickHover)>,
+	mut module_graph	: ResMut<ModuleGraph>,
+) {
+	if !module_graph.active || !mouse_button.just_pressed(MouseButton::Left) { return }
+
+	let Some((node, _)) = q_node.iter().find(|(_, hover)| hover.hovered()) else { return };
+
+	if node.index != module_graph.selected {
+		module_graph.selected = node.index;
+	} else {
+		module_graph.open_requested = Some(node.index);
+	}
+}
+
+pub fn poll_load(
+	mut module_graph	: ResMut<ModuleGraph>,
+) {
+	let Some(mut load_task) = module_graph.load_task.take() else { return };
+
+	match future::block_on(future::poll_once(&mut load_task)) {
+		Some(result) => module_graph.finish_load(result),
+		None => module_graph.load_task = Some(load_task),
+	}
+}
+
+// file of module is opened in editor and graph is closed
+pub fn open_requested_module(
+	mut module_graph	: ResMut<ModuleGraph>,
+		tokio_runtime	: Res<TokioRuntime>,
+		app_option		: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(index) = module_graph.open_requested.take() else { return };
+	let Some(mut app) = app_option else { return };
+	let Some(file) = module_graph.tree.as_ref().and_then(|tree| tree.modules.get(index)).map(|module| module.file.clone()) else { return };
+
+	tokio_runtime.block_on(app.jump_to_path(&file, None, None));
+	app.request_render();
+
+	module_graph.close();
+}
+
+fn travel(
+	anchor_entity	: Entity,
+	start			: Transform,
+	end				: Transform,
+	commands		: &mut Commands,
+) {
+	let distance = start.translation.distance(end.translation);
+	let travel_duration = (distance * 100.0).clamp(150.0, 500.0) as u64;
+
+	let tween = Tween::new(
+		EaseFunction::QuadraticInOut,
+		Duration::from_millis(travel_duration),
+		TransformLens {
+			start,
+			end,
+		}
+	);
+
+	commands.entity(anchor_entity).insert(Animator::new(tween));
+}
+
+// editor is hidden while graph is shown, graph is respawned once modules are loaded and when diagnostics change
+pub fn spawn_graph(
+	mut module_graph	: ResMut<ModuleGraph>,
+		surfaces_bevy	: Res<SurfacesMapBevy>,
+	mut q_camera		: Query<(&mut ReaderCamera, &Transform)>,
+	mut q_visibility	: Query<&mut Visibility>,
+		font_assets		: Res<Assets<ABGlyphFont>>,
+		font_handles	: Res<FontAssetHandles>,
+	mut color_materials_cache : ResMut<ColorMaterialsCache>,
+	mut mesh_assets		: ResMut<Assets<Mesh>>,
+	mut material_assets	: ResMut<Assets<StandardMaterial>>,
+	mut despawn			: ResMut<DespawnResource>,
+	mut commands		: Commands,
+		app_option		: Option<NonSend<HelixApp>>,
+) {
+	let Some(app) = app_option else { return };
+	let Ok((mut reader_camera, camera_transform)) = q_camera.get_single_mut() else { return };
+
+	let fonts	= ABGlyphFonts::new(&font_assets, &font_handles);
+	let metrics	= GraphMetrics::new(fonts.main);
+
+	// closing
+
+	if !module_graph.active {
+		if module_graph.anchor_entity.is_none() { return }
+
+		for entity in module_graph.take_entities() {
+			despawn.recursive.push(entity);
+		}
+
+		module_graph.clear();
+
+		for (_surface_name, surface_bevy) in surfaces_bevy.iter() {
+			let Ok(mut visibility) = q_visibility.get_mut(surface_bevy.entity) else { continue };
+			*visibility.as_mut() = Visibility::Visible;
+
+			if surface_bevy.name == EditorView::ID {
+				reader_camera.target_entity = Some(surface_bevy.entity);
+				reader_camera.column = (surface_bevy.area.width / 2) as usize;
+			}
+		}
+
+		return;
+	}
+
+	// opening
+
+	if module_graph.anchor_entity.is_none() {
+		for (_surface_name, surface_bevy) in surfaces_bevy.iter() {
+			let Ok(mut visibility) = q_visibility.get_mut(surface_bevy.entity) else { continue };
+			*visibility.as_mut() = Visibility::Hidden;
+		}
+
+		// camera starts from where it's looking now and travels to the first crate once modules are loaded
+		let anchor_entity = commands.spawn((
+			ModuleGraphAnchor,
+			TransformBundle::from_transform(Transform::from_translation(Vec3::new(
+				camera_transform.translation.x - (VIEW_COLUMNS / 2) as f32 * metrics.column_width,
+				camera_transform.translation.y + (reader_camera.visible_rows / 2.0).floor() * metrics.row_height,
+				0.0
+			))),
+			TextDescriptor {
+				rows			: reader_camera.visible_rows as usize,
+				columns			: VIEW_COLUMNS,
+				glyph_width		: metrics.column_width,
+				glyph_height	: metrics.row_height,
+			},
+		)).id();
+
+		reader_camera.target_entity = Some(anchor_entity);
+		reader_camera.column = VIEW_COLUMNS / 2;
+		reader_camera.set_row_offset_in(0);
+
+		module_graph.anchor_entity = Some(anchor_entity);
+	}
+
+	let diagnostics_key = diagnostics_key(&app.editor);
+
+	if module_graph.diagnostics_key != diagnostics_key {
+		module_graph.diagnostics_key = diagnostics_key;
+		module_graph.version += 1;
+	}
+
+	if module_graph.spawned_version == Some(module_graph.version) { return }
+
+	profile_function!();
+
+	if let Some(root_entity) = module_graph.take_graph_entities() {
+		despawn.recursive.push(root_entity);
+	}
+
+	let theme			= &app.editor.theme;
+	let text_color		= color_from_helix(theme.get("ui.text").fg.unwrap_or(HelixColor::White));
+	let comment_color	= color_from_helix(theme.get("comment").fg.unwrap_or(HelixColor::Gray));
+	let clean_color		= color_from_helix(theme.get("diff.plus").fg.unwrap_or(HelixColor::Green));
+	let warning_color	= color_from_helix(theme.get("warning").fg.unwrap_or(HelixColor::Yellow));
+	let error_color		= color_from_helix(theme.get("error").fg.unwrap_or(HelixColor::Red));
+	let crate_color		= color_from_helix(theme.get("ui.statusline").bg.unwrap_or(HelixColor::Cyan));
+	let marker_color	= color_from_helix(theme.get("ui.selection").bg.unwrap_or(HelixColor::Gray));
+
+	let root_entity = commands.spawn((
+		TransformBundle::default(),
+		VisibilityBundle::default(),
+	)).id();
+
+	let mut children = Vec::new();
+
+	let quad_mesh		= mesh_assets.add(Mesh::from(shape::Quad::new(Vec2::ONE)));
+	let cube_mesh		= mesh_assets.add(Mesh::from(shape::Cube { size: 1.0 }));
+	let edge_thickness	= EDGE_THICKNESS * metrics.column_width;
+
+	let status = match (module_graph.error.as_ref(), module_graph.load_task.is_some()) {
+		(Some(error), _)	=> (short_message(error.as_str(), DETAILS_COLUMNS), error_color),
+		(None, true)		=> (String::from("scanning workspace..."), comment_color),
+		(None, false)		=> (String::from("enter: open, arrows: walk modules, esc: back to editor"), text_color),
+	};
+
+	children.push(spawn_string(status.0, metrics.node_position((0.0, -1.0)), status.1, &mut commands));
+
+	let counts = diagnostics_counts(&app.editor);
+
+	if let Some(tree) = module_graph.tree.as_ref() {
+		let positions : Vec<Vec3> = tree.layout().into_iter().map(|slot| metrics.node_position(slot)).collect();
+
+		// tree edges run from parent box along the level and then to the submodule
+		let tree_material = get_color_material_handle(comment_color, &mut color_materials_cache, &mut material_assets);
+
+		for (index, module) in tree.modules.iter().enumerate() {
+			let Some(parent) = module.parent else { continue };
+
+			let from	= metrics.box_center(positions[parent]);
+			let to		= metrics.box_center(positions[index]);
+			let bend	= Vec3::new(from.x, to.y, from.z);
+
+			for (from, to) in [(from, bend), (bend, to)] {
+				children.extend(spawn_segment(from, to, edge_thickness, &quad_mesh, &tree_material, &mut commands));
+			}
+		}
+
+		// all dependencies are dimmed, the ones of selected module are highlighted in update_selection
+		let mut dependency_color = text_color;
+		dependency_color.set_a(0.15);
+
+		let dependency_material = get_color_material_walpha_handle(dependency_color, AlphaMode::Blend, &mut color_materials_cache, &mut material_assets);
+
+		for (from, to) in tree.edges.iter().copied() {
+			children.extend(spawn_arc(metrics.box_center(positions[from]), metrics.box_center(positions[to]), edge_thickness, &quad_mesh, &dependency_material, &mut commands));
+		}
+
+		for (index, module) in tree.modules.iter().enumerate() {
+			let position = positions[index];
+
+			let (errors, warnings) = counts.get(&module.file).copied().unwrap_or_default();
+
+			let box_color = if errors > 0 { error_color } else if warnings > 0 { warning_color } else { clean_color };
+			let box_material = get_color_material_handle(box_color, &mut color_materials_cache, &mut material_assets);
+
+			let box_entity = commands.spawn(PbrBundle {
+				mesh		: cube_mesh.clone(),
+				material	: box_material,
+				transform	: Transform::from_translation(metrics.box_center(position)).with_scale(Vec3::splat(metrics.box_size(module.lines))),
+				..default()
+			}).id();
+
+			children.push(box_entity);
+
+			let label_color = if module.parent.is_none() { crate_color } else { text_color };
+
+			let label = short_message(module.name.as_str(), LABEL_COLUMNS);
+			let label_entity = spawn_string(label.clone(), metrics.label_position(position), label_color, &mut commands);
+			make_clickable(label_entity, &label, fonts.main, &mut commands);
+
+			commands.entity(label_entity).insert(ModuleGraphNode { index });
+			children.push(label_entity);
+
+			let mut summary = format!("{} lines", module.lines);
+
+			if errors > 0 { summary.push_str(format!(", {} errors", errors).as_str()) }
+			if warnings > 0 { summary.push_str(format!(", {} warnings", warnings).as_str()) }
+
+			children.push(spawn_string(summary, metrics.label_position(position) - Vec3::Y * metrics.row_height, comment_color, &mut commands));
+		}
+	}
+
+	// marker is moved under selected label in update_selection
+	let marker_material = get_color_material_handle(marker_color, &mut color_materials_cache, &mut material_assets);
+	let marker_entity = commands.spawn(PbrBundle {
+		mesh		: quad_mesh,
+		material	: marker_material,
+		transform	: Transform::from_scale(Vec3::new(LABEL_COLUMNS as f32 * metrics.column_width, metrics.row_height, 1.0)),
+		visibility	: if module_graph.tree.is_none() { Visibility::Hidden } else { Visibility::Inherited },
+		..default()
+	}).id();
+
+	children.push(marker_entity);
+
+	commands.entity(root_entity).push_children(children.as_slice());
+
+	module_graph.root_entity		= Some(root_entity);
+	module_graph.marker_entity		= Some(marker_entity);
+	module_graph.spawned_version	= Some(module_graph.version);
+}
+
+// camera travels to selected module, its dependencies are highlighted and listed next to it
+pub fn update_selection(
+	mut module_graph		: ResMut<ModuleGraph>,
+		q_camera			: Query<&ReaderCamera>,
+	mut q_transform			: Query<&mut Transform, Without<ReaderCamera>>,
+		q_animator			: Query<&Animator<Transform>, With<ModuleGraphAnchor>>,
+	mut framerate_manager	: ResMut<FramerateManager>,
+		font_assets			: Res<Assets<ABGlyphFont>>,
+		font_handles		: Res<FontAssetHandles>,
+	mut color_materials_cache : ResMut<ColorMaterialsCache>,
+	mut mesh_assets			: ResMut<Assets<Mesh>>,
+	mut material_assets		: ResMut<Assets<StandardMaterial>>,
+	mut commands			: Commands,
+		app_option			: Option<NonSend<HelixApp>>,
+) {
+	let Some(app) = app_option else { return };
+
+	// tree is borrowed while the rest of graph is updated
+	let module_graph = &mut *module_graph;
+
+	let (Some(tree), Some(root_entity), Some(anchor_entity)) = (module_graph.tree.as_ref(), module_graph.root_entity, module_graph.anchor_entity) else { return };
+	let Ok(reader_camera) = q_camera.get_single() else { return };
+
+	if tree.modules.is_empty() { return }
+
+	if let Ok(animator) = q_animator.get(anchor_entity) {
+		let progress = animator.tweenable().progress();
+		if progress < 1.0 {
+			framerate_manager.request_active_framerate(format!("module graph camera travel {:.2}%", progress * 100.));
+		}
+	}
+
+	let selected = module_graph.selected.min(tree.modules.len() - 1);
+
+	let fonts		= ABGlyphFonts::new(&font_assets, &font_handles);
+	let metrics		= GraphMetrics::new(fonts.main);
+	let positions	: Vec<Vec3> = tree.layout().into_iter().map(|slot| metrics.node_position(slot)).collect();
+
+	if module_graph.traveled_to != Some(selected) {
+		// anchor spawned this frame is not there until commands are applied
+		let Ok(anchor_transform) = q_transform.get(anchor_entity).map(|transform| *transform) else { return };
+
+		profile_function!();
+
+		let end = Transform::from_translation(metrics.anchor_position(positions[selected], reader_camera.visible_rows));
+		travel(anchor_entity, anchor_transform, end, &mut commands);
+
+		if let Some(mut marker_transform) = module_graph.marker_entity.and_then(|entity| q_transform.get_mut(entity).ok()) {
+			let label_size = marker_transform.scale.truncate();
+			marker_transform.translation = metrics.label_position(positions[selected]) + Vec3::new(label_size.x / 2.0, label_size.y / 2.0, z_order::surface::highlight_selection() - z_order::surface::text());
+		}
+
+		module_graph.traveled_to = Some(selected);
+	}
+
+	if module_graph.details_cache == Some(selected) { return }
+
+	for entity in module_graph.details.drain(..) {
+		commands.entity(entity).despawn_recursive();
+	}
+
+	let theme			= &app.editor.theme;
+	let text_color		= color_from_helix(theme.get("ui.text").fg.unwrap_or(HelixColor::White));
+	let comment_color	= color_from_helix(theme.get("comment").fg.unwrap_or(HelixColor::Gray));
+	let uses_color		= color_from_helix(theme.get("function").fg.unwrap_or(HelixColor::Cyan));
+	let used_by_color	= color_from_helix(theme.get("type").fg.unwrap_or(HelixColor::Yellow));
+
+	let quad_mesh		= mesh_assets.add(Mesh::from(shape::Quad::new(Vec2::ONE)));
+	let edge_thickness	= 2.0 * EDGE_THICKNESS * metrics.column_width;
+
+	let uses_material		= get_color_material_handle(uses_color, &mut color_materials_cache, &mut material_assets);
+	let used_by_material	= get_color_material_handle(used_by_color, &mut color_materials_cache, &mut material_assets);
+
+	let uses	: Vec<usize> = tree.edges.iter().filter(|(from, _)| *from == selected).map(|(_, to)| *to).collect();
+	let used_by	: Vec<usize> = tree.edges.iter().filter(|(_, to)| *to == selected).map(|(from, _)| *from).collect();
+
+	let mut details = Vec::new();
+
+	for target in uses.iter() {
+		details.extend(spawn_arc(metrics.box_center(positions[selected]), metrics.box_center(positions[*target]), edge_thickness, &quad_mesh, &uses_material, &mut commands));
+	}
+
+	for source in used_by.iter() {
+		details.extend(spawn_arc(metrics.box_center(positions[*source]), metrics.box_center(positions[selected]), edge_thickness, &quad_mesh, &used_by_material, &mut commands));
+	}
+
+	let module	= &tree.modules[selected];
+	let file	= module.file.strip_prefix(&tree.root).unwrap_or(&module.file);
+
+	let mut lines : Vec<(String, Color)> = vec![
+		(tree.module_path(selected), text_color),
+		(file.display().to_string(), comment_color),
+	];
+
+	for (title, modules, color) in [("uses", &uses, uses_color), ("used by", &used_by, used_by_color)] {
+		if modules.is_empty() { continue }
+
+		let names : Vec<String> = modules.iter().map(|index| tree.module_path(*index)).collect();
+		lines.push((short_message(format!("{}: {}", title, names.join(", ")).as_str(), DETAILS_COLUMNS), color));
+	}
+
+	for (line, (string, color)) in lines.into_iter().enumerate() {
+		details.push(spawn_string(string, metrics.details_position(positions[selected], line), color, &mut commands));
+	}
+
+	commands.entity(root_entity).push_children(details.as_slice());
+
+	module_graph.details		= details;
+	module_graph.details_cache	= Some(selected);
+}
+
+// graph is rescanned every time it's opened
+pub fn on_context_switch_out(
+	mut module_graph	: ResMut<ModuleGraph>,
+	mut despawn			: ResMut<DespawnResource>,
+) {
+	for entity in module_graph.take_entities() {
+		despawn.recursive.push(entity);
+	}
+
+	module_graph.clear();
+}
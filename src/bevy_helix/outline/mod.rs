@@ -0,0 +1,569 @@
+use bevy :: prelude :: *;
+use bevy :: utils :: HashSet;
+
+use helix_lsp :: lsp :: { self, SymbolKind };
+use helix_term :: ui :: EditorView;
+use helix_tui :: buffer :: { Buffer as SurfaceHelix, SurfaceFlags, SurfacePlacement };
+use helix_view :: {
+	DocumentId, Theme,
+	graphics :: { Rect, Style },
+};
+
+use std :: sync :: { Arc, Mutex };
+
+use super :: {
+	HelixApp,
+	surface :: { SurfacesMapHelix, OUTLINE_SURFACE_NAME, SEARCH_PANEL_SURFACE_NAME },
+};
+
+pub mod systems;
+
+pub const PANEL_COLUMNS	: u16	= 44;
+pub const HEADER_ROWS	: usize	= 2; // title and filters stay on top while symbols scroll
+pub const INDENT_COLUMNS: usize	= 2;
+pub const WHEEL_ROWS	: i32	= 3;
+
+// name filter entered in Helix prompt, picked up by the panel on the Bevy side
+pub type SharedOutlineInput = Arc<Mutex<Option<String>>>;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum OutlineKind {
+	Function,
+	Type,
+	Constant,
+	Module,
+	Field,
+	Other,
+}
+
+impl OutlineKind {
+	pub const ALL : [OutlineKind; 6] = [
+		OutlineKind::Function,
+		OutlineKind::Type,
+		OutlineKind::Constant,
+		OutlineKind::Module,
+		OutlineKind::Field,
+		OutlineKind::Other,
+	];
+
+	pub fn from_symbol(kind: SymbolKind) -> Self {
+		match kind {
+			SymbolKind::FUNCTION
+		|	SymbolKind::METHOD
+		|	SymbolKind::CONSTRUCTOR
+		|	SymbolKind::OPERATOR		=> OutlineKind::Function,
+			SymbolKind::CLASS
+		|	SymbolKind::STRUCT
+		|	SymbolKind::INTERFACE
+		|	SymbolKind::OBJECT // impl blocks in rust-analyzer
+		|	SymbolKind::ENUM
+		|	SymbolKind::TYPE_PARAMETER	=> OutlineKind::Type,
+			SymbolKind::CONSTANT
+		|	SymbolKind::ENUM_MEMBER		=> OutlineKind::Constant,
+			SymbolKind::MODULE
+		|	SymbolKind::NAMESPACE
+		|	SymbolKind::PACKAGE
+		|	SymbolKind::FILE			=> OutlineKind::Module,
+			SymbolKind::FIELD
+		|	SymbolKind::PROPERTY
+		|	SymbolKind::VARIABLE		=> OutlineKind::Field,
+			_							=> OutlineKind::Other,
+		}
+	}
+
+	fn label(self) -> &'static str {
+		match self {
+			OutlineKind::Function	=> "fn",
+			OutlineKind::Type		=> "type",
+			OutlineKind::Constant	=> "const",
+			OutlineKind::Module		=> "mod",
+			OutlineKind::Field		=> "field",
+			OutlineKind::Other		=> "other",
+		}
+	}
+
+	// single letter in front of symbol name
+	fn tag(self) -> &'static str {
+		match self {
+			OutlineKind::Function	=> "f",
+			OutlineKind::Type		=> "t",
+			OutlineKind::Constant	=> "c",
+			OutlineKind::Module		=> "m",
+			OutlineKind::Field		=> "v",
+			OutlineKind::Other		=> "·",
+		}
+	}
+
+	pub fn theme_scope(self) -> &'static str {
+		match self {
+			OutlineKind::Function	=> "function",
+			OutlineKind::Type		=> "type",
+			OutlineKind::Constant	=> "constant",
+			OutlineKind::Module		=> "namespace",
+			OutlineKind::Field		=> "variable.other.member",
+			OutlineKind::Other		=> "ui.text",
+		}
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct OutlineSymbol {
+	pub name	: String,
+	pub kind	: OutlineKind,
+	pub range	: lsp::Range,
+	pub depth	: usize,
+	pub parent	: Option<usize>,
+}
+
+// language servers report a flat list, symbols are nested by their ranges
+pub fn build_tree<'a>(symbols: impl Iterator<Item = &'a lsp::SymbolInformation>) -> Vec<OutlineSymbol> {
+	let mut sorted : Vec<&lsp::SymbolInformation> = symbols.collect();
+
+	// outer symbol goes first when both start at the same place
+	sorted.sort_by(|a, b| {
+		let (a, b) = (a.location.range, b.location.range);
+		a.start.cmp(&b.start).then_with(|| b.end.cmp(&a.end))
+	});
+
+	let mut tree : Vec<OutlineSymbol> = Vec::with_capacity(sorted.len());
+	let mut stack : Vec<usize> = Vec::new();
+
+	for symbol in sorted {
+		let range = symbol.location.range;
+
+		while let Some(&top) = stack.last() {
+			if tree[top].range.end >= range.end { break }
+			stack.pop();
+		}
+
+		tree.push(OutlineSymbol {
+			name	: symbol.name.clone(),
+			kind	: OutlineKind::from_symbol(symbol.kind),
+			range,
+			depth	: stack.len(),
+			parent	: stack.last().copied(),
+		});
+
+		stack.push(tree.len() - 1);
+	}
+
+	tree
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SegmentStyle {
+	Text,
+	Dim,
+	Header,
+	Button,
+	ButtonOn,
+	Current,
+	Kind(OutlineKind),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OutlineAction {
+	ToggleKind(OutlineKind),
+	EditFilter,
+	Close,
+	Jump(usize), // symbol index in tree
+}
+
+#[derive(Clone, Debug)]
+pub struct Segment {
+	pub text	: String,
+	pub style	: SegmentStyle,
+	pub action	: Option<OutlineAction>,
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct OutlineRow {
+	pub segments : Vec<Segment>,
+	pub current	: bool, // row of the symbol containing cursor is highlighted as a whole
+}
+
+impl OutlineRow {
+	fn push(&mut self, text: impl Into<String>, style: SegmentStyle, action: Option<OutlineAction>) -> &mut Self {
+		self.segments.push(Segment { text: text.into(), style, action });
+		self
+	}
+
+	fn columns(&self) -> usize {
+		self.segments.iter().map(|segment| segment.text.chars().count()).sum()
+	}
+
+	pub fn action_at(&self, column: usize) -> Option<OutlineAction> {
+		let mut start = 0;
+
+		for segment in self.segments.iter() {
+			let end = start + segment.text.chars().count();
+
+			if (start .. end).contains(&column) {
+				return segment.action;
+			}
+
+			start = end;
+		}
+
+		// the whole symbol row is clickable, not only its name
+		self.segments.last().and_then(|segment| segment.action).filter(|action| matches!(action, OutlineAction::Jump(_)))
+	}
+}
+
+#[derive(Clone, PartialEq, Default)]
+struct RowsKey {
+	document		: Option<DocumentId>,
+	symbols_version	: usize,
+	current			: Option<usize>,
+	filter			: String,
+	hidden_kinds	: Vec<OutlineKind>,
+}
+
+#[derive(Resource, Default)]
+pub struct Outline {
+	pub visible			: bool,
+	pub filter			: String, // case insensitive part of symbol name
+	pub hidden_kinds	: HashSet<OutlineKind>,
+	pub pending_input	: SharedOutlineInput,
+
+	pub document		: Option<DocumentId>,
+	pub document_name	: String,
+	pub symbols_version	: Option<usize>,
+	pub symbols			: Vec<OutlineSymbol>,
+	pub current			: Option<usize>, // innermost symbol containing cursor
+
+	pub rows			: Vec<OutlineRow>, // header rows come first
+	pub scroll			: usize, // first visible symbol row
+	pub visible_rows	: usize,
+
+		rows_key		: Option<RowsKey>,
+}
+
+impl Outline {
+	pub fn toggle(&mut self) {
+		self.visible = !self.visible;
+		self.rows_key = None;
+	}
+
+	pub fn close(&mut self) {
+		self.visible = false;
+		self.rows.clear();
+		self.rows_key = None;
+	}
+
+	pub fn toggle_kind(&mut self, kind: OutlineKind) {
+		if !self.hidden_kinds.remove(&kind) {
+			self.hidden_kinds.insert(kind);
+		}
+	}
+
+	pub fn set_filter(&mut self, filter: String) {
+		self.filter = filter.trim().to_string();
+		self.scroll = 0;
+	}
+
+	// returns true if symbol tree was rebuilt
+	pub fn update_symbols(&mut self, document: DocumentId, document_name: String, symbols_version: usize, symbols: &[lsp::SymbolInformation]) -> bool {
+		if self.document == Some(document) && self.symbols_version == Some(symbols_version) {
+			return false;
+		}
+
+		if self.document != Some(document) {
+			self.scroll = 0;
+		}
+
+		self.document			= Some(document);
+		self.document_name		= document_name;
+		self.symbols_version	= Some(symbols_version);
+		self.symbols			= build_tree(symbols.iter());
+
+		true
+	}
+
+	// symbols are sorted by start so the last one containing cursor is the innermost
+	pub fn update_current(&mut self, cursor: lsp::Position) {
+		self.current = self.symbols.iter()
+			.rposition(|symbol| symbol.range.start <= cursor && cursor <= symbol.range.end);
+	}
+
+	pub fn scroll_by(&mut self, delta: i32) {
+		let max_scroll = self.rows.len().saturating_sub(HEADER_ROWS + self.visible_rows);
+
+		self.scroll = (self.scroll as i64 + delta as i64).clamp(0, max_scroll as i64) as usize;
+	}
+
+	// row on screen to panel row, header rows don't scroll
+	pub fn row_at(&self, row: usize) -> Option<&OutlineRow> {
+		if row < HEADER_ROWS {
+			self.rows.get(row)
+		} else {
+			self.rows.get(row + self.scroll)
+		}
+	}
+
+	fn matches_filter(&self, symbol: &OutlineSymbol) -> bool {
+		!self.hidden_kinds.contains(&symbol.kind)
+		&& (self.filter.is_empty() || symbol.name.to_lowercase().contains(&self.filter.to_lowercase()))
+	}
+
+	pub fn rebuild_rows(&mut self) {
+		let mut hidden_kinds : Vec<OutlineKind> = self.hidden_kinds.iter().copied().collect();
+		hidden_kinds.sort_by_key(|kind| OutlineKind::ALL.iter().position(|all| all == kind));
+
+		let key = RowsKey {
+			document		: self.document,
+			symbols_version	: self.symbols_version.unwrap_or_default(),
+			current			: self.current,
+			filter			: self.filter.clone(),
+			hidden_kinds,
+		};
+
+		if self.rows_key.as_ref() == Some(&key) {
+			return;
+		}
+
+		let current_changed = self.rows_key.as_ref().map_or(true, |old_key| old_key.current != key.current || old_key.document != key.document);
+
+		self.rows_key = Some(key);
+		self.rows.clear();
+
+		// title
+		let mut title = OutlineRow::default();
+		title.push(" OUTLINE ", SegmentStyle::Header, None);
+		title.push(self.document_name.clone(), SegmentStyle::Dim, None);
+
+		let padding = (PANEL_COLUMNS as usize).saturating_sub(title.columns() + 4);
+		title.push(" ".repeat(padding), SegmentStyle::Text, None);
+		title.push("[x]", SegmentStyle::Button, Some(OutlineAction::Close));
+		self.rows.push(title);
+
+		// kind toggles and name filter
+		let mut filters = OutlineRow::default();
+		for kind in OutlineKind::ALL {
+			let style = if self.hidden_kinds.contains(&kind) { SegmentStyle::Button } else { SegmentStyle::ButtonOn };
+
+			filters.push(" ", SegmentStyle::Text, None);
+			filters.push(kind.label(), style, Some(OutlineAction::ToggleKind(kind)));
+		}
+
+		filters.push(" ", SegmentStyle::Text, None);
+		filters.push(format!("[/{}]", self.filter), if self.filter.is_empty() { SegmentStyle::Button } else { SegmentStyle::ButtonOn }, Some(OutlineAction::EditFilter));
+		self.rows.push(filters);
+
+		// with name filter on parents of matching symbols are kept to show where they are
+		let mut shown = vec![false; self.symbols.len()];
+		let mut matching = vec![false; self.symbols.len()];
+
+		for (index, symbol) in self.symbols.iter().enumerate() {
+			if !self.matches_filter(symbol) { continue }
+
+			matching[index] = true;
+
+			let mut parent = Some(index);
+			while let Some(parent_index) = parent {
+				if shown[parent_index] && parent_index != index { break }
+
+				shown[parent_index] = true;
+				parent = self.symbols[parent_index].parent;
+			}
+		}
+
+		let mut current_row = None;
+
+		for (index, symbol) in self.symbols.iter().enumerate() {
+			if !shown[index] { continue }
+
+			let current = self.current == Some(index);
+			if current {
+				current_row = Some(self.rows.len() - HEADER_ROWS);
+			}
+
+			let action = Some(OutlineAction::Jump(index));
+			let name_style = if current { SegmentStyle::Current } else if matching[index] { SegmentStyle::Text } else { SegmentStyle::Dim };
+
+			let mut row = OutlineRow { current, ..default() };
+			row.push(" ".repeat(1 + symbol.depth * INDENT_COLUMNS), SegmentStyle::Text, action);
+			row.push(symbol.kind.tag(), SegmentStyle::Kind(symbol.kind), action);
+			row.push(" ", SegmentStyle::Text, action);
+			row.push(symbol.name.clone(), name_style, action);
+
+			self.rows.push(row);
+		}
+
+		if self.symbols.is_empty() {
+			let mut row = OutlineRow::default();
+			row.push(" no symbols, language server may still be busy", SegmentStyle::Dim, None);
+			self.rows.push(row);
+		}
+
+		// keep symbol under cursor in sight when cursor moves to another one
+		if let Some(current_row) = current_row.filter(|_| current_changed) {
+			if current_row < self.scroll || current_row >= self.scroll + self.visible_rows {
+				self.scroll = current_row.saturating_sub(self.visible_rows / 2);
+			}
+		}
+
+		self.scroll_by(0);
+	}
+
+	pub fn update_surface(
+		&mut self,
+		surfaces_helix	: &mut SurfacesMapHelix,
+		app				: &HelixApp,
+	) {
+		let Some(surface_editor) = surfaces_helix.get(EditorView::ID) else { return };
+		let view_area = app.views().iter().find(|view_desc| view_desc.focused).map_or(surface_editor.area, |view_desc| view_desc.area);
+
+		if !self.visible || self.rows.is_empty() || view_area.height as usize <= HEADER_ROWS {
+			surfaces_helix.remove(OUTLINE_SURFACE_NAME);
+			return;
+		}
+
+		// workspace search panel keeps the right edge, outline goes next to it
+		let right = surfaces_helix.get(SEARCH_PANEL_SURFACE_NAME)
+			.map(|search_panel| search_panel.area.x)
+			.filter(|search_x| *search_x >= view_area.x + PANEL_COLUMNS)
+			.unwrap_or(view_area.right());
+
+		let width	= PANEL_COLUMNS.min(right - view_area.x);
+		let area	= Rect::new(right - width, view_area.y, width, view_area.height);
+
+		self.visible_rows = area.height as usize - HEADER_ROWS;
+		self.scroll_by(0);
+
+		let surface = surfaces_helix.entry(String::from(OUTLINE_SURFACE_NAME)).or_insert_with(|| {
+			SurfaceHelix::empty_with_spatial(area, SurfaceFlags::default())
+		});
+
+		if surface.area != area {
+			surface.resize(area);
+		}
+
+		surface.placement = SurfacePlacement::AreaCoordinates;
+		surface.reset();
+
+		let styles = OutlineStyles::new(&app.editor.theme);
+
+		surface.set_style(area, styles.background);
+
+		let rows = self.rows.iter().take(HEADER_ROWS).chain(self.rows.iter().skip(HEADER_ROWS + self.scroll));
+
+		for (index, row) in rows.take(area.height as usize).enumerate() {
+			let y = area.y + index as u16;
+			let mut x = area.x;
+
+			if row.current {
+				surface.set_style(Rect::new(area.x, y, area.width, 1), styles.current);
+			}
+
+			for segment in row.segments.iter() {
+				let left = area.right().saturating_sub(x) as usize;
+				if left == 0 { break }
+
+				let mut style = styles.get(segment.style);
+				if row.current {
+					style = style.patch(styles.current);
+				}
+
+				(x, _) = surface.set_stringn(x, y, segment.text.as_str(), left, style);
+			}
+		}
+	}
+}
+
+struct OutlineStyles {
+	background	: Style,
+	text		: Style,
+	dim			: Style,
+	header		: Style,
+	button		: Style,
+	button_on	: Style,
+	current		: Style,
+	kinds		: [Style; OutlineKind::ALL.len()],
+}
+
+impl OutlineStyles {
+	fn new(theme: &Theme) -> Self {
+		let background	= theme.get("ui.background").patch(theme.get("ui.popup"));
+		let text		= background.patch(theme.get("ui.text"));
+
+		Self {
+			background,
+			text,
+			dim			: background.patch(theme.get("ui.linenr")),
+			header		: background.patch(theme.get("ui.text.focus")),
+			button		: text.patch(theme.get("ui.menu")),
+			button_on	: text.patch(theme.get("ui.menu.selected")),
+			current		: theme.get("ui.cursorline.primary").patch(theme.get("ui.selection")),
+			kinds		: OutlineKind::ALL.map(|kind| text.patch(theme.get(kind.theme_scope()))),
+		}
+	}
+
+	fn get(&self, style: SegmentStyle) -> Style {
+		match style {
+			SegmentStyle::Text		=> self.text,
+			SegmentStyle::Dim		=> self.dim,
+			SegmentStyle::Header	=> self.header,
+			SegmentStyle::Button	=> self.button,
+			SegmentStyle::ButtonOn	=> self.button_on,
+			SegmentStyle::Current	=> self.text,
+			SegmentStyle::Kind(kind) => self.kinds[kind as usize],
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super :: *;
+
+	#[allow(deprecated)] // SymbolInformation::deprecated has to be set to build one
+	fn symbol(name: &str, kind: SymbolKind, start: (u32, u32), end: (u32, u32)) -> lsp::SymbolInformation {
+		lsp::SymbolInformation {
+			name			: String::from(name),
+			kind,
+			tags			: None,
+			deprecated		: None,
+			location		: lsp::Location {
+				uri		: lsp::Url::parse("file:///main.rs").unwrap(),
+				range	: lsp::Range::new(lsp::Position::new(start.0, start.1), lsp::Position::new(end.0, end.1)),
+			},
+			container_name	: None,
+		}
+	}
+
+	#[test]
+	fn build_tree_nests_by_range() {
+		// language servers don't have to report symbols in order
+		let symbols = vec![
+			symbol("new", SymbolKind::METHOD, (2, 1), (4, 2)),
+			symbol("main", SymbolKind::FUNCTION, (7, 0), (9, 1)),
+			symbol("Foo", SymbolKind::OBJECT, (1, 0), (5, 1)),
+			symbol("x", SymbolKind::VARIABLE, (3, 2), (3, 10)),
+			symbol("VALUE", SymbolKind::CONSTANT, (0, 0), (0, 20)),
+		];
+
+		let tree = build_tree(symbols.iter());
+
+		let names : Vec<&str> = tree.iter().map(|symbol| symbol.name.as_str()).collect();
+		assert_eq!(names, vec!["VALUE", "Foo", "new", "x", "main"]);
+
+		let structure : Vec<(usize, Option<usize>)> = tree.iter().map(|symbol| (symbol.depth, symbol.parent)).collect();
+		assert_eq!(structure, vec![(0, None), (0, None), (1, Some(1)), (2, Some(2)), (0, None)]);
+
+		assert_eq!(tree[1].kind, OutlineKind::Type);
+		assert_eq!(tree[3].kind, OutlineKind::Field);
+	}
+
+	#[test]
+	fn build_tree_outer_symbol_first_on_same_start() {
+		let symbols = vec![
+			symbol("inner", SymbolKind::FUNCTION, (1, 0), (2, 0)),
+			symbol("outer", SymbolKind::MODULE, (1, 0), (8, 0)),
+		];
+
+		let tree = build_tree(symbols.iter());
+
+		assert_eq!(tree[0].name, "outer");
+		assert_eq!(tree[1].parent, Some(0));
+	}
+}
@@ -0,0 +1,186 @@
+use bevy :: prelude :: *;
+use bevy :: input :: mouse :: { MouseWheel, MouseScrollUnit };
+
+#[cfg(feature = "tracing")]
+use bevy_puffin :: *;
+
+use super :: *;
+
+use crate :: {
+	kodiki_ui :: { DraggingState, raypick :: Raypick },
+	bevy_ab_glyph :: { ABGlyphFont, FontAssetHandles },
+	bevy_helix :: {
+		minimap :: Minimap,
+		surface :: { SurfacesMapBevy, WordDescription },
+		systems_util :: hovered_surface_name,
+	},
+};
+
+// ctrl+alt+o shows or hides outline of current document
+pub fn input_keyboard(
+		key			: Res<Input<KeyCode>>,
+	mut outline		: ResMut<Outline>,
+		app_option	: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() || !app.editor_focused() { return }
+
+	let ctrl_pressed	= key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl);
+	let alt_pressed		= key.pressed(KeyCode::LAlt) || key.pressed(KeyCode::RAlt);
+
+	if !ctrl_pressed || !alt_pressed || !key.just_pressed(KeyCode::O) { return }
+
+	outline.toggle();
+
+	app.request_render();
+}
+
+// header rows filter symbols by kind and name, symbol rows scroll editor to them the same way minimap bookmarks do
+pub fn input_mouse(
+		mouse_button	: Res<Input<MouseButton>>,
+		raypick			: Res<Raypick>,
+		surfaces		: Res<SurfacesMapBevy>,
+		q_word			: Query<&WordDescription>,
+		q_transform		: Query<&GlobalTransform>,
+		q_minimap		: Query<&Minimap>,
+		font_assets		: Res<Assets<ABGlyphFont>>,
+		font_handles	: Res<FontAssetHandles>,
+	mut outline			: ResMut<Outline>,
+		dragging_state	: Res<DraggingState>,
+	mut commands		: Commands,
+		app_option		: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() || dragging_state.is_active() || !outline.visible { return }
+
+	if !mouse_button.just_pressed(MouseButton::Left) { return }
+
+	let Some(hovered_entity) = raypick.last_hover else { return };
+	let Some(surface_name) = hovered_surface_name(hovered_entity, &q_word, &surfaces) else { return };
+
+	if surface_name != OUTLINE_SURFACE_NAME { return }
+
+	profile_function!();
+
+	let Some(surface_panel) = surfaces.get(surface_name) else { return };
+	let Ok(surface_transform) = q_transform.get(surface_panel.entity) else { return };
+
+	let font = font_assets.get(&font_handles.main).unwrap();
+	let row_height		= font.vertical_advance();
+	let column_width	= font.horizontal_advance_mono();
+
+	// world space to surface space
+	let cursor_position_world	= raypick.ray_pos + raypick.ray_dir * raypick.ray_dist;
+	let cursor_position_surface	= surface_transform.compute_matrix().inverse().transform_point3(cursor_position_world);
+
+	let row = (cursor_position_surface.y.abs() / row_height) - surface_panel.scroll_info.offset as f32;
+	if row < 0.0 || cursor_position_surface.x < 0.0 {
+		return;
+	}
+
+	let column = (cursor_position_surface.x / column_width) as usize;
+
+	let Some(action) = outline.row_at(row as usize).and_then(|outline_row| outline_row.action_at(column)) else { return };
+
+	match action {
+		OutlineAction::ToggleKind(kind) => outline.toggle_kind(kind),
+		OutlineAction::EditFilter => app.prompt_outline_filter(outline.pending_input.clone()),
+		OutlineAction::Close => outline.close(),
+		OutlineAction::Jump(index) => {
+			let Some(symbol) = outline.symbols.get(index) else { return };
+			let Ok(minimap) = q_minimap.get_single() else { return };
+
+			let target_row = (symbol.range.start.line as usize).max(1);
+
+			minimap.scroll_to_row(
+				app.row_offset_internal(),
+				target_row,
+				&mut commands
+			);
+		},
+	}
+
+	app.request_render();
+}
+
+// wheel over panel scrolls symbols, Helix doesn't get those events
+pub fn input_scroll(
+	mut scroll_events	: EventReader<MouseWheel>,
+		raypick			: Res<Raypick>,
+		surfaces		: Res<SurfacesMapBevy>,
+		q_word			: Query<&WordDescription>,
+	mut outline			: ResMut<Outline>,
+		app_option		: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	let hovered = raypick.last_hover
+		.and_then(|entity| hovered_surface_name(entity, &q_word, &surfaces))
+		.map_or(false, |name| name == OUTLINE_SURFACE_NAME);
+
+	if !outline.visible || !hovered {
+		scroll_events.clear();
+		return;
+	}
+
+	let mut delta = 0;
+
+	for scroll_event in scroll_events.iter() {
+		let steps = match scroll_event.unit {
+			MouseScrollUnit::Line	=> scroll_event.y.signum() as i32,
+			MouseScrollUnit::Pixel	=> (scroll_event.y / 50.0) as i32,
+		};
+
+		delta -= steps * WHEEL_ROWS;
+	}
+
+	if delta == 0 { return }
+
+	outline.scroll_by(delta);
+
+	app.request_render();
+}
+
+// follows current document symbols and cursor, applies name filter entered in prompt
+pub fn update(
+	mut outline		: ResMut<Outline>,
+		app_option	: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	let input = outline.pending_input.lock().unwrap().take();
+
+	if let Some(filter) = input {
+		outline.set_filter(filter);
+		app.request_render();
+	}
+
+	if !outline.visible { return }
+
+	profile_function!();
+
+	let doc = app.current_document();
+	let view = app.current_view();
+
+	let text	= doc.text().slice(..);
+	let cursor	= doc.selection(view.id).primary().cursor(text);
+	let line	= text.char_to_line(cursor);
+
+	let cursor_position = lsp::Position::new(line as u32, (cursor - text.line_to_char(line)) as u32);
+
+	let document_name = doc.display_name().to_string();
+	let rebuilt = outline.update_symbols(doc.id(), document_name, doc.symbols_version(), doc.symbols());
+
+	let current = outline.current;
+	outline.update_current(cursor_position);
+
+	outline.rebuild_rows();
+
+	if rebuilt || current != outline.current {
+		app.request_render();
+	}
+}
@@ -0,0 +1,260 @@
+use bevy :: prelude :: *;
+use bevy :: utils :: { HashMap, HashSet };
+
+use helix_core :: { Rope, path :: get_relative_path };
+use helix_term :: {
+	compositor :: { self, Compositor },
+	job,
+	ui :: { self, menu :: Item },
+};
+use helix_tui :: widgets :: Row;
+use helix_view :: { Editor, DocumentId, editor :: Action };
+
+use std :: {
+	collections :: hash_map :: DefaultHasher,
+	hash :: { Hash, Hasher },
+	path :: PathBuf,
+	sync :: { Arc, Mutex, TryLockError },
+	time :: { SystemTime, UNIX_EPOCH },
+};
+
+use super :: file_sync :: { self, replace_document_text };
+
+pub mod systems;
+
+pub const BACKUP_INTERVAL_SECONDS	: f32 = 5.0;
+pub const BACKUP_EXTENSION			: &str = "kodiki_backup";
+pub const RECOVERY_DIFF_POPUP_ID	: &str = "kodiki-recovery-diff";
+
+pub fn recovery_dir() -> PathBuf {
+	helix_loader::cache_dir().join("kodiki_recovery")
+}
+
+// process id and start time, backups of different sessions never share names even with several instances running
+pub fn session_id() -> String {
+	let started = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_millis());
+
+	format!("{}-{}", std::process::id(), started)
+}
+
+// within a session backups are named after document path so that the same file always ends up in the same backup
+pub fn backup_name(session_id: &str, path: Option<&PathBuf>, doc_id: DocumentId) -> String {
+	let mut hasher = DefaultHasher::new();
+
+	match path {
+		Some(path) => path.hash(&mut hasher),
+		None => format!("scratch-{}", doc_id).hash(&mut hasher),
+	}
+
+	format!("{}-{:016x}.{}", session_id, hasher.finish(), BACKUP_EXTENSION)
+}
+
+// first line is the path of original file (empty for scratch buffers), the rest is buffer contents
+fn write_backup(name: &str, path: Option<&PathBuf>, text: &Rope) -> std::io::Result<()> {
+	let dir = recovery_dir();
+	std::fs::create_dir_all(&dir)?;
+
+	let path_line = path.map_or_else(String::new, |path| path.to_string_lossy().to_string());
+
+	std::fs::write(dir.join(name), format!("{}\n{}", path_line, text))
+}
+
+fn remove_backup(name: &str) {
+	let _ = std::fs::remove_file(recovery_dir().join(name));
+}
+
+#[derive(Clone)]
+pub struct BufferSnapshot {
+	pub path		: Option<PathBuf>,
+	pub text		: Rope, // ropes are cheap to clone so snapshots are taken on every change
+	pub version		: usize,
+	pub flushed		: bool, // written to recovery directory
+}
+
+pub type SharedSnapshots = Arc<Mutex<HashMap<String, BufferSnapshot>>>;
+
+// writes every snapshot that is not on disk yet
+pub fn flush_snapshots(snapshots: &mut HashMap<String, BufferSnapshot>) {
+	for (name, snapshot) in snapshots.iter_mut().filter(|(_, snapshot)| !snapshot.flushed) {
+		match write_backup(name, snapshot.path.as_ref(), &snapshot.text) {
+			Ok(_) => snapshot.flushed = true,
+			Err(e) => log::error!("failed to write backup {}: {}", name, e),
+		}
+	}
+}
+
+// panic can happen anywhere, including the middle of backup so poisoned lock is still used and a held one is skipped
+pub fn install_panic_hook(snapshots: SharedSnapshots) {
+	let default_hook = std::panic::take_hook();
+
+	std::panic::set_hook(Box::new(move |info| {
+		match snapshots.try_lock() {
+			Ok(mut snapshots) => flush_snapshots(&mut snapshots),
+			Err(TryLockError::Poisoned(poisoned)) => flush_snapshots(&mut poisoned.into_inner()),
+			Err(TryLockError::WouldBlock) => (),
+		}
+
+		default_hook(info);
+	}));
+}
+
+#[derive(Resource)]
+pub struct Recovery {
+	pub session_id		: String,
+	pub snapshots		: SharedSnapshots,
+	// backups left from previous sessions are listed in recovery picker and are only removed by restoring or discarding them there.
+	// This session writes and removes only backups of its own
+	pub own_backups		: HashSet<String>,
+	pub since_last_flush: f32,
+}
+
+impl Default for Recovery {
+	fn default() -> Self {
+		Self {
+			session_id		: session_id(),
+			snapshots		: SharedSnapshots::default(),
+			own_backups		: HashSet::default(),
+			since_last_flush: 0.0,
+		}
+	}
+}
+
+impl Recovery {
+	fn is_own_backup(&self, file_name: &str) -> bool {
+		file_name.starts_with(format!("{}-", self.session_id).as_str())
+	}
+
+	pub fn backup_name(&self, path: Option<&PathBuf>, doc_id: DocumentId) -> String {
+		backup_name(self.session_id.as_str(), path, doc_id)
+	}
+
+	pub fn pending_backups(&self) -> Vec<RecoveredBuffer> {
+		let Ok(entries) = std::fs::read_dir(recovery_dir()) else { return Vec::new() };
+
+		let mut buffers : Vec<RecoveredBuffer> = entries
+			.filter_map(|entry| entry.ok())
+			.map(|entry| entry.path())
+			.filter(|path| path.extension().map_or(false, |extension| extension == BACKUP_EXTENSION))
+			.filter(|path| path.file_name().map_or(false, |name| !self.is_own_backup(name.to_string_lossy().as_ref())))
+			.filter_map(|backup_path| RecoveredBuffer::load(backup_path))
+			.collect();
+
+		buffers.sort_by(|a, b| a.path.cmp(&b.path));
+
+		buffers
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct RecoveredBuffer {
+	pub backup_path	: PathBuf,
+	pub path		: Option<PathBuf>,
+	pub text		: String,
+}
+
+impl RecoveredBuffer {
+	pub fn load(backup_path: PathBuf) -> Option<Self> {
+		let contents = std::fs::read_to_string(&backup_path).ok()?;
+		let (path_line, text) = contents.split_once('\n')?;
+
+		Some(Self {
+			path	: (!path_line.is_empty()).then(|| PathBuf::from(path_line)),
+			text	: String::from(text),
+			backup_path,
+		})
+	}
+
+	pub fn display_path(&self) -> String {
+		self.path.as_ref().map_or_else(|| String::from("[scratch]"), |path| get_relative_path(path).to_string_lossy().to_string())
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RecoveryActionKind {
+	Restore,
+	Discard,
+	Diff,
+}
+
+// entry of recovery picker
+#[derive(Clone, Debug)]
+pub struct RecoveryAction {
+	pub kind	: RecoveryActionKind,
+	pub buffer	: RecoveredBuffer,
+}
+
+impl Item for RecoveryAction {
+	type Data = ();
+
+	fn format(&self, _data: &Self::Data) -> Row {
+		let action = match self.kind {
+			RecoveryActionKind::Restore	=> "restore",
+			RecoveryActionKind::Discard	=> "discard",
+			RecoveryActionKind::Diff	=> "diff",
+		};
+
+		format!("{}: {}", action, self.buffer.display_path()).into()
+	}
+}
+
+impl RecoveryAction {
+	pub fn from_buffers(buffers: Vec<RecoveredBuffer>) -> Vec<Self> {
+		buffers.into_iter().flat_map(|buffer| {
+			[RecoveryActionKind::Restore, RecoveryActionKind::Diff, RecoveryActionKind::Discard]
+				.into_iter()
+				.map(move |kind| RecoveryAction { kind, buffer: buffer.clone() })
+		}).collect()
+	}
+
+	pub fn run(&self, cx: &mut compositor::Context) {
+		match self.kind {
+			RecoveryActionKind::Restore => self.restore(cx.editor),
+			RecoveryActionKind::Discard => {
+				let _ = std::fs::remove_file(&self.buffer.backup_path);
+				cx.editor.set_status(format!("Discarded backup of {}", self.buffer.display_path()));
+			},
+			RecoveryActionKind::Diff => self.show_diff(cx),
+		}
+	}
+
+	// restored text is applied as an edit so document stays modified until user saves it
+	fn restore(&self, editor: &mut Editor) {
+		let doc_id = match self.buffer.path.as_ref() {
+			Some(path) => match editor.open(path, Action::Replace) {
+				Ok(doc_id) => doc_id,
+				Err(e) => {
+					editor.set_error(format!("Failed to open {}: {}", path.display(), e));
+					return;
+				}
+			},
+			None => editor.new_file(Action::Replace),
+		};
+
+		if !replace_document_text(editor, doc_id, &self.buffer.text) {
+			editor.set_error(format!("Failed to restore {}", self.buffer.display_path()));
+			return;
+		}
+
+		// document is modified now so this session backs it up again under its own name
+		let _ = std::fs::remove_file(&self.buffer.backup_path);
+
+		editor.set_status(format!("Restored {}", self.buffer.display_path()));
+	}
+
+	fn show_diff(&self, cx: &mut compositor::Context) {
+		let disk_text = self.buffer.path.as_ref().and_then(|path| std::fs::read_to_string(path).ok()).unwrap_or_default();
+
+		let diff = file_sync::diff_markdown("File on disk (-) and recovered buffer (+)", &disk_text, &self.buffer.text);
+
+		cx.jobs.callback(async move {
+			let callback = job::Callback::EditorCompositor(Box::new(move |editor: &mut Editor, compositor: &mut Compositor| {
+				let markdown = ui::Markdown::new(diff, editor.syn_loader.clone());
+				let popup = ui::Popup::new(RECOVERY_DIFF_POPUP_ID, markdown).auto_close(true);
+
+				compositor.replace_or_push(RECOVERY_DIFF_POPUP_ID, popup);
+			}));
+
+			Ok(callback)
+		});
+	}
+}
@@ -0,0 +1,115 @@
+use bevy :: prelude :: *;
+
+#[cfg(feature = "tracing")]
+use bevy_puffin :: *;
+
+use super :: *;
+
+use crate :: bevy_helix :: HelixApp;
+
+pub fn startup(
+	recovery : Res<Recovery>,
+) {
+	install_panic_hook(recovery.snapshots.clone());
+}
+
+// recovery picker is shown once Helix is up if previous session left any backups behind
+pub fn offer_recovery(
+		recovery	: Res<Recovery>,
+	mut offered		: Local<bool>,
+		app_option	: Option<NonSendMut<HelixApp>>,
+) {
+	if *offered { return }
+
+	let Some(mut app) = app_option else { return };
+
+	*offered = true;
+
+	let buffers = recovery.pending_backups();
+
+	if !buffers.is_empty() {
+		app.open_recovery_picker(buffers);
+	}
+}
+
+// ctrl+alt+r reopens recovery picker
+pub fn input_keyboard(
+		key			: Res<Input<KeyCode>>,
+		recovery	: Res<Recovery>,
+		app_option	: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	let ctrl_pressed	= key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl);
+	let alt_pressed		= key.pressed(KeyCode::LAlt) || key.pressed(KeyCode::RAlt);
+
+	if !(ctrl_pressed && alt_pressed && key.just_pressed(KeyCode::R)) { return }
+
+	let buffers = recovery.pending_backups();
+
+	if buffers.is_empty() {
+		app.editor.set_status("No buffers to recover");
+	} else {
+		app.open_recovery_picker(buffers);
+	}
+}
+
+// snapshots of modified documents are taken on every change and written to disk periodically,
+// backups this session wrote for saved, closed or discarded documents are removed
+pub fn update_backups(
+		time		: Res<Time>,
+	mut recovery	: ResMut<Recovery>,
+		app_option	: Option<NonSend<HelixApp>>,
+) {
+	let Some(app) = app_option else { return };
+
+	profile_function!();
+
+	let recovery = &mut *recovery;
+	let mut snapshots = recovery.snapshots.lock().unwrap();
+
+	// quitting with unsaved changes requires forcing it so they are meant to be discarded
+	if app.should_close() {
+		for name in recovery.own_backups.drain() {
+			remove_backup(name.as_str());
+		}
+
+		snapshots.clear();
+		return;
+	}
+
+	let mut alive = HashSet::new();
+
+	for doc in app.editor.documents().filter(|doc| doc.is_modified()) {
+		let name = recovery.backup_name(doc.path(), doc.id());
+
+		if snapshots.get(&name).map_or(true, |snapshot| snapshot.version != doc.version()) {
+			snapshots.insert(name.clone(), BufferSnapshot {
+				path	: doc.path().cloned(),
+				text	: doc.text().clone(),
+				version	: doc.version(),
+				flushed	: false,
+			});
+		}
+
+		alive.insert(name);
+	}
+
+	snapshots.retain(|name, _| alive.contains(name));
+
+	for name in recovery.own_backups.iter().filter(|name| !alive.contains(*name)) {
+		remove_backup(name.as_str());
+	}
+
+	recovery.own_backups = alive;
+
+	recovery.since_last_flush += time.delta_seconds();
+
+	if recovery.since_last_flush >= BACKUP_INTERVAL_SECONDS {
+		recovery.since_last_flush = 0.0;
+
+		flush_snapshots(&mut snapshots);
+	}
+}
@@ -0,0 +1,199 @@
+use bevy :: prelude :: *;
+
+use helix_view :: DocumentId;
+
+use super :: outline :: { OutlineKind, OutlineSymbol };
+
+pub mod systems;
+
+// camera visible rows at which code starts giving way to symbol labels and where it's fully replaced
+pub const BLEND_START_ROWS	: f32 = 110.0;
+pub const BLEND_FULL_ROWS	: f32 = 170.0;
+
+pub const LINES_PER_ROW		: f32 = 6.0; // label height grows by one row for this many lines of symbol span
+pub const MAX_SCALE_TOP		: f32 = 24.0;
+pub const MAX_SCALE_NESTED	: f32 = 8.0;
+pub const GAP_COLUMNS		: f32 = 2.0;
+
+// 0.0 shows code, 1.0 shows only symbol labels
+pub fn blend_factor(visible_rows: f32) -> f32 {
+	let t = ((visible_rows - BLEND_START_ROWS) / (BLEND_FULL_ROWS - BLEND_START_ROWS)).clamp(0.0, 1.0);
+
+	t * t * (3.0 - 2.0 * t)
+}
+
+// symbol name as a large label on editor surface, scale is in rows
+#[derive(Clone, Debug)]
+pub struct SymbolLabel {
+	pub name	: String,
+	pub kind	: OutlineKind,
+	pub line	: usize,
+	pub column	: f32,
+	pub scale	: f32,
+}
+
+fn is_labeled(kind: OutlineKind) -> bool {
+	matches!(kind, OutlineKind::Function | OutlineKind::Type | OutlineKind::Module)
+}
+
+// top level items go in the first column, their members to the right of the parent label.
+// Each label starts at the first line of its symbol and is capped so it doesn't reach the next label of the same or higher level
+pub fn layout_labels(symbols: &[OutlineSymbol]) -> Vec<SymbolLabel> {
+	let mut labels : Vec<(SymbolLabel, usize, Option<usize>)> = Vec::new(); // label, depth, parent label
+	let mut label_of_symbol : Vec<Option<usize>> = vec![None; symbols.len()];
+
+	for (index, symbol) in symbols.iter().enumerate() {
+		if symbol.depth > 1 || !is_labeled(symbol.kind) { continue }
+
+		let parent_label = match symbol.parent {
+			Some(parent) => {
+				let Some(parent_label) = label_of_symbol[parent] else { continue };
+				Some(parent_label)
+			},
+			None => None,
+		};
+
+		let span		= (symbol.range.end.line - symbol.range.start.line + 1) as f32;
+		let max_scale	= if parent_label.is_some() { MAX_SCALE_NESTED } else { MAX_SCALE_TOP };
+
+		label_of_symbol[index] = Some(labels.len());
+
+		labels.push((
+			SymbolLabel {
+				name	: symbol.name.clone(),
+				kind	: symbol.kind,
+				line	: symbol.range.start.line as usize,
+				column	: 0.0,
+				scale	: (span / LINES_PER_ROW).clamp(1.0, max_scale),
+			},
+			symbol.depth,
+			parent_label,
+		));
+	}
+
+	for index in 0 .. labels.len() {
+		let (label, depth, _) = &labels[index];
+
+		let next_line = labels[index + 1 ..].iter()
+			.find(|(_, next_depth, _)| next_depth <= depth)
+			.map(|(next, _, _)| next.line);
+
+		if let Some(next_line) = next_line {
+			let gap = next_line.saturating_sub(label.line) as f32;
+			labels[index].0.scale = labels[index].0.scale.min(gap).max(1.0);
+		}
+	}
+
+	for index in 0 .. labels.len() {
+		let Some(parent) = labels[index].2 else { continue };
+
+		let parent_label = &labels[parent].0;
+		let column = parent_label.column + parent_label.name.chars().count() as f32 * parent_label.scale + GAP_COLUMNS;
+
+		labels[index].0.column = column;
+	}
+
+	labels.into_iter().map(|(label, _, _)| label).collect()
+}
+
+#[derive(Component)]
+pub struct SemanticZoomLabel {
+	pub column	: f32,
+	pub top		: f32,
+	pub scale	: f32,
+}
+
+// quad of editor background color between code and labels
+#[derive(Component)]
+pub struct SemanticZoomCurtain;
+
+#[derive(Resource, Default)]
+pub struct SemanticZoom {
+	pub blend			: f32,
+	pub curtain_entity	: Option<Entity>,
+	pub curtain_material: Option<Handle<StandardMaterial>>,
+	pub label_entities	: Vec<Entity>,
+	pub labels_key		: Option<(DocumentId, usize)>, // document and its symbols version labels were spawned for
+}
+
+impl SemanticZoom {
+	pub fn is_active(&self) -> bool {
+		self.curtain_entity.is_some() || !self.label_entities.is_empty()
+	}
+
+	pub fn take_labels(&mut self) -> Vec<Entity> {
+		self.labels_key = None;
+		std::mem::take(&mut self.label_entities)
+	}
+
+	pub fn take_entities(&mut self) -> Vec<Entity> {
+		let mut entities = self.take_labels();
+
+		entities.extend(self.curtain_entity.take());
+		self.curtain_material = None;
+		self.blend = 0.0;
+
+		entities
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super :: *;
+
+	use helix_lsp :: lsp;
+
+	fn symbol(name: &str, kind: OutlineKind, lines: (u32, u32), depth: usize, parent: Option<usize>) -> OutlineSymbol {
+		OutlineSymbol {
+			name	: String::from(name),
+			kind,
+			range	: lsp::Range::new(lsp::Position::new(lines.0, 0), lsp::Position::new(lines.1, 0)),
+			depth,
+			parent,
+		}
+	}
+
+	#[test]
+	fn layout_labels_places_members_next_to_parent() {
+		let symbols = vec![
+			symbol("a", OutlineKind::Module, (0, 119), 0, None),
+			symbol("b", OutlineKind::Function, (2, 61), 1, Some(0)),
+			symbol("x", OutlineKind::Field, (62, 62), 1, Some(0)),
+			symbol("c", OutlineKind::Function, (64, 69), 1, Some(0)),
+			symbol("d", OutlineKind::Function, (65, 68), 2, Some(3)),
+			symbol("T", OutlineKind::Type, (130, 135), 0, None),
+		];
+
+		let labels = layout_labels(&symbols);
+
+		let placed : Vec<(&str, usize, f32, f32)> = labels.iter().map(|label| (label.name.as_str(), label.line, label.column, label.scale)).collect();
+
+		// fields and symbols nested deeper than members get no labels, nested labels are capped lower
+		assert_eq!(placed, vec![
+			("a", 0, 0.0, 20.0),
+			("b", 2, 22.0, MAX_SCALE_NESTED),
+			("c", 64, 22.0, 1.0),
+			("T", 130, 0.0, 1.0),
+		]);
+	}
+
+	#[test]
+	fn layout_labels_capped_by_next_label() {
+		let symbols = vec![
+			symbol("e", OutlineKind::Function, (200, 399), 0, None),
+			symbol("f", OutlineKind::Function, (210, 221), 0, None),
+		];
+
+		let labels = layout_labels(&symbols);
+
+		assert_eq!(labels[0].scale, 10.0);
+		assert_eq!(labels[1].scale, 2.0);
+	}
+
+	#[test]
+	fn blend_factor_range() {
+		assert_eq!(blend_factor(BLEND_START_ROWS - 10.0), 0.0);
+		assert_eq!(blend_factor((BLEND_START_ROWS + BLEND_FULL_ROWS) / 2.0), 0.5);
+		assert_eq!(blend_factor(BLEND_FULL_ROWS + 10.0), 1.0);
+	}
+}
@@ -0,0 +1,180 @@
+use bevy :: prelude :: *;
+use bevy_reader_camera :: ReaderCamera;
+
+#[cfg(feature = "tracing")]
+use bevy_puffin :: *;
+
+use helix_term :: ui :: EditorView;
+use helix_view :: graphics :: Color as HelixColor;
+
+use super :: *;
+
+use crate :: {
+	z_order,
+	kodiki :: DespawnResource,
+	kodiki_ui :: { String3dSpawnRequest, CommonString3dSpawnParams },
+	bevy_ab_glyph :: { ABGlyphFont, ABGlyphFonts, FontAssetHandles },
+	bevy_helix :: {
+		HelixApp,
+		outline :: build_tree,
+		surface :: SurfacesMapBevy,
+		utils :: color_from_helix,
+	},
+};
+
+// past zoom threshold code fades behind a curtain and symbol labels grow in, zooming back in reverses it
+pub fn update(
+		q_camera			: Query<&ReaderCamera>,
+	mut q_label				: Query<(&mut Transform, &SemanticZoomLabel)>,
+	mut q_curtain			: Query<&mut Transform, (With<SemanticZoomCurtain>, Without<SemanticZoomLabel>)>,
+		surfaces_bevy		: Res<SurfacesMapBevy>,
+		font_assets			: Res<Assets<ABGlyphFont>>,
+		font_handles		: Res<FontAssetHandles>,
+	mut mesh_assets			: ResMut<Assets<Mesh>>,
+	mut material_assets		: ResMut<Assets<StandardMaterial>>,
+	mut semantic_zoom		: ResMut<SemanticZoom>,
+	mut commands			: Commands,
+		app_option			: Option<NonSend<HelixApp>>,
+) {
+	let Some(app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	let Ok(reader_camera) = q_camera.get_single() else { return };
+
+	let blend = blend_factor(reader_camera.visible_rows);
+
+	if blend <= 0.0 {
+		for entity in semantic_zoom.take_entities() {
+			commands.entity(entity).despawn_recursive();
+		}
+
+		return;
+	}
+
+	profile_function!();
+
+	let Some(surface_editor) = surfaces_bevy.get(EditorView::ID) else { return };
+
+	let fonts			= ABGlyphFonts::new(&font_assets, &font_handles);
+	let row_height		= fonts.main.vertical_advance();
+	let column_width	= fonts.main.horizontal_advance_mono();
+
+	// curtain
+
+	if semantic_zoom.curtain_entity.is_none() {
+		let background_style = app.editor.theme.get("ui.background");
+		let background_color = color_from_helix(background_style.bg.unwrap_or(HelixColor::Black));
+
+		// material is not cached because its alpha follows zoom
+		let material_handle = material_assets.add(
+			StandardMaterial {
+				base_color	: background_color.with_a(0.0),
+				alpha_mode	: AlphaMode::Blend,
+				unlit		: true,
+				..default()
+			}
+		);
+
+		let curtain_entity = commands.spawn((
+			PbrBundle {
+				mesh		: mesh_assets.add(shape::Quad::new(Vec2::ONE).into()),
+				material	: material_handle.clone(),
+				..default()
+			},
+			SemanticZoomCurtain,
+		)).id();
+
+		commands.entity(surface_editor.entity).add_child(curtain_entity);
+
+		semantic_zoom.curtain_entity	= Some(curtain_entity);
+		semantic_zoom.curtain_material	= Some(material_handle);
+	}
+
+	// curtain follows editor area which grows while zooming out
+	if let Some(mut transform) = semantic_zoom.curtain_entity.and_then(|entity| q_curtain.get_mut(entity).ok()) {
+		let curtain_size = Vec2::new(
+			surface_editor.area.width as f32 * column_width,
+			surface_editor.area.height as f32 * row_height
+		);
+
+		transform.translation = Vec3::new(
+			curtain_size.x / 2.0,
+			-((app.row_offset_internal() as f32 * row_height) + curtain_size.y / 2.0),
+			z_order::surface::transition()
+		);
+
+		transform.scale = curtain_size.extend(1.0);
+	}
+
+	if semantic_zoom.blend != blend {
+		if let Some(material) = semantic_zoom.curtain_material.as_ref().and_then(|handle| material_assets.get_mut(handle)) {
+			material.base_color.set_a(blend);
+		}
+	}
+
+	semantic_zoom.blend = blend;
+
+	// labels
+
+	let (doc_id, symbols_version) = {
+		let doc = app.current_document();
+		(doc.id(), doc.symbols_version())
+	};
+
+	if semantic_zoom.labels_key != Some((doc_id, symbols_version)) {
+		for entity in semantic_zoom.take_labels() {
+			commands.entity(entity).despawn_recursive();
+		}
+
+		let theme = &app.editor.theme;
+		let tree = build_tree(app.current_document().symbols().iter());
+
+		for label in layout_labels(&tree) {
+			let color = color_from_helix(theme.get(label.kind.theme_scope()).fg.unwrap_or(HelixColor::White));
+
+			let top = -(label.line as f32) * row_height;
+
+			let label_entity = commands.spawn((
+				TransformBundle::default(),
+				VisibilityBundle::default(),
+				String3dSpawnRequest {
+					common : CommonString3dSpawnParams {
+						string : label.name,
+						color,
+						..default()
+					},
+					..default()
+				},
+				SemanticZoomLabel {
+					column	: label.column * column_width,
+					top,
+					scale	: label.scale,
+				},
+			)).id();
+
+			commands.entity(surface_editor.entity).add_child(label_entity);
+
+			semantic_zoom.label_entities.push(label_entity);
+		}
+
+		semantic_zoom.labels_key = Some((doc_id, symbols_version));
+	}
+
+	// strings grow right and up from their position so label top stays on the first line of symbol while it grows
+	for (mut transform, label) in q_label.iter_mut() {
+		let scale = label.scale * blend;
+
+		transform.translation	= Vec3::new(label.column, label.top - scale * row_height, z_order::surface::child_surface());
+		transform.scale			= Vec3::splat(scale);
+	}
+}
+
+pub fn on_context_switch_out(
+	mut semantic_zoom	: ResMut<SemanticZoom>,
+	mut despawn			: ResMut<DespawnResource>,
+) {
+	for entity in semantic_zoom.take_entities() {
+		despawn.recursive.push(entity);
+	}
+}
@@ -0,0 +1,179 @@
+use bevy :: prelude :: *;
+
+use helix_core :: syntax :: Syntax;
+use helix_term :: ui :: EditorView;
+use helix_tui :: buffer :: { Buffer as SurfaceHelix, SurfaceFlags, SurfacePlacement };
+use helix_view :: { Document, DocumentId, graphics :: Rect };
+
+use super :: {
+	VersionType,
+	helix_app :: HelixApp,
+	surface :: { SurfacesMapHelix, STICKY_SCROLL_SURFACE_NAME },
+};
+
+pub mod systems;
+
+pub const MAX_STICKY_ROWS : usize = 5;
+
+// tree-sitter node kinds considered scopes worth pinning
+const SCOPE_KINDS : &[&str] = &[
+	// rust
+	"mod_item",
+	"impl_item",
+	"trait_item",
+	"function_item",
+	"struct_item",
+	"enum_item",
+	"match_arm",
+	// common across other grammars
+	"function_definition",
+	"function_declaration",
+	"method_definition",
+	"method_declaration",
+	"class_definition",
+	"class_declaration",
+];
+
+// everything pinned headers were drawn from, surface is left untouched while it stays the same
+#[derive(Clone, PartialEq)]
+struct RenderedHeaders {
+	doc_id				: DocumentId,
+	doc_version			: VersionType,
+	headers				: Vec<usize>,
+	area				: Rect,
+	horizontal_offset	: usize,
+}
+
+#[derive(Resource)]
+pub struct StickyScroll {
+	pub enabled		: bool,
+	pub headers		: Vec<usize>, // start lines of pinned scopes, outermost first
+	rendered		: Option<RenderedHeaders>,
+}
+
+impl Default for StickyScroll {
+	fn default() -> Self {
+		Self {
+			enabled		: true,
+			headers		: Vec::new(),
+			rendered	: None,
+		}
+	}
+}
+
+impl StickyScroll {
+	// start lines of scopes that enclose top_row and whose header is already scrolled out of view
+	pub fn enclosing_scopes(syntax: &Syntax, doc: &Document, top_row: usize) -> Vec<usize> {
+		let text = doc.text().slice(..);
+
+		if top_row >= text.len_lines() {
+			return Vec::new();
+		}
+
+		let byte = text.line_to_byte(top_row);
+
+		let mut scopes = Vec::new();
+		let mut node = syntax.tree().root_node().descendant_for_byte_range(byte, byte);
+
+		while let Some(scope_node) = node {
+			if SCOPE_KINDS.contains(&scope_node.kind()) {
+				let start_line = text.byte_to_line(scope_node.start_byte());
+
+				if start_line < top_row && scopes.last() != Some(&start_line) {
+					scopes.push(start_line);
+				}
+			}
+
+			node = scope_node.parent();
+		}
+
+		// innermost scopes are the most relevant so outer ones are dropped first
+		scopes.truncate(MAX_STICKY_ROWS);
+		scopes.reverse();
+
+		scopes
+	}
+
+	// renders pinned headers into a surface of their own on top of focused view
+	pub fn update_surface(
+		&mut self,
+		surfaces_helix	: &mut SurfacesMapHelix,
+		app				: &HelixApp,
+	) {
+		self.headers.clear();
+
+		let (view, doc) = app.current_ref();
+
+		if self.enabled {
+			if let Some(syntax) = doc.syntax() {
+				self.headers = Self::enclosing_scopes(syntax, doc, app.row_offset_external());
+			}
+		}
+
+		let Some(surface_editor) = surfaces_helix.get(EditorView::ID) else { return };
+		let view_area = app.views().iter().find(|view_desc| view_desc.focused).map_or(surface_editor.area, |view_desc| view_desc.area);
+
+		if self.headers.is_empty() || view_area.width == 0 {
+			surfaces_helix.remove(STICKY_SCROLL_SURFACE_NAME);
+			self.rendered = None;
+			return;
+		}
+
+		let area = Rect::new(view_area.x, view_area.y, view_area.width, self.headers.len() as u16);
+
+		let rendered = RenderedHeaders {
+			doc_id				: doc.id(),
+			doc_version			: doc.version(),
+			headers				: self.headers.clone(),
+			area,
+			horizontal_offset	: view.offset.horizontal_offset,
+		};
+
+		let surface = surfaces_helix.entry(String::from(STICKY_SCROLL_SURFACE_NAME)).or_insert_with(|| {
+			SurfaceHelix::empty_with_spatial(area, SurfaceFlags::default())
+		});
+
+		// scope stack and document are the same as last time so words spawned from previous frame are kept as is
+		if self.rendered.as_ref() == Some(&rendered) {
+			surface.mark_frozen();
+			return;
+		}
+
+		self.rendered = Some(rendered);
+
+		if surface.area != area {
+			surface.resize(area);
+		}
+
+		surface.placement = SurfacePlacement::AreaCoordinates;
+		surface.reset();
+
+		let theme			= &app.editor.theme;
+		let background		= theme.get("ui.background").patch(theme.get("ui.popup"));
+		let text_style		= background.patch(theme.get("ui.text"));
+		let linenr_style	= background.patch(theme.get("ui.linenr"));
+
+		surface.set_style(area, background);
+
+		let text		= doc.text().slice(..);
+		let gutter_len	= app.gutter_len() as u16;
+		let tab			= " ".repeat(doc.tab_width());
+		let horizontal_offset = view.offset.horizontal_offset;
+
+		for (index, line) in self.headers.iter().enumerate() {
+			let y = area.y + index as u16;
+
+			let line_number = format!("{:>width$} ", line + 1, width = (gutter_len as usize).saturating_sub(2));
+			surface.set_stringn(area.x, y, line_number, gutter_len as usize, linenr_style);
+
+			let header : String = text.line(*line).to_string().replace('\t', tab.as_str());
+			let header : String = header.trim_end().chars().skip(horizontal_offset).collect();
+
+			surface.set_stringn(area.x + gutter_len, y, header, area.width.saturating_sub(gutter_len) as usize, text_style);
+		}
+	}
+
+	pub fn header_at(&self, row: usize) -> Option<usize> {
+		self.headers.get(row).copied()
+	}
+}
@@ -0,0 +1,62 @@
+use bevy :: prelude :: *;
+
+#[cfg(feature = "tracing")]
+use bevy_puffin :: *;
+
+use super :: *;
+
+use crate :: {
+	kodiki_ui :: { DraggingState, raypick :: Raypick },
+	bevy_ab_glyph :: { ABGlyphFont, FontAssetHandles },
+	bevy_helix :: {
+		surface :: { SurfacesMapBevy, WordDescription },
+		systems_util :: hovered_surface_name,
+	},
+};
+
+// clicking a pinned header moves cursor to the start of its scope, camera travel is animated by jump detection
+pub fn input_mouse(
+		mouse_button	: Res<Input<MouseButton>>,
+		raypick			: Res<Raypick>,
+		surfaces		: Res<SurfacesMapBevy>,
+		q_word			: Query<&WordDescription>,
+		q_transform		: Query<&GlobalTransform>,
+		font_assets		: Res<Assets<ABGlyphFont>>,
+		font_handles	: Res<FontAssetHandles>,
+		sticky_scroll	: Res<StickyScroll>,
+		dragging_state	: Res<DraggingState>,
+		app_option		: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() || dragging_state.is_active() { return }
+
+	if !mouse_button.just_pressed(MouseButton::Left) { return }
+
+	let Some(hovered_entity) = raypick.last_hover else { return };
+	let Some(surface_name) = hovered_surface_name(hovered_entity, &q_word, &surfaces) else { return };
+
+	if surface_name != STICKY_SCROLL_SURFACE_NAME { return }
+
+	profile_function!();
+
+	let Some(surface_sticky) = surfaces.get(surface_name) else { return };
+	let Ok(surface_transform) = q_transform.get(surface_sticky.entity) else { return };
+
+	let font = font_assets.get(&font_handles.main).unwrap();
+	let row_height = font.vertical_advance();
+
+	// world space to surface space
+	let cursor_position_world	= raypick.ray_pos + raypick.ray_dir * raypick.ray_dist;
+	let cursor_position_surface	= surface_transform.compute_matrix().inverse().transform_point3(cursor_position_world);
+
+	let row = (cursor_position_surface.y.abs() / row_height) - surface_sticky.scroll_info.offset as f32;
+	if row < 0.0 {
+		return;
+	}
+
+	let Some(line) = sticky_scroll.header_at(row as usize) else { return };
+
+	app.set_cursor(line, 0);
+	app.set_row_offset_internal(line);
+}
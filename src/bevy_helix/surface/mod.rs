@@ -27,6 +27,7 @@ use helix_tui	:: buffer :: { Buffer as SurfaceHelix, SurfaceAnchor, SurfacePlace
 use helix_view :: {
 	Document,
 	View,
+	ViewId,
 	Theme,
 	graphics :: {
 		Color as HelixColor,
@@ -226,6 +227,8 @@ pub struct SurfaceBevy {
 	pub search_highlights	: Highlights<VersionType>,
 	pub selection_search_highlights : Highlights<VersionType>,
 	pub cursor_highlights	: Highlights<helix_core::Position>,
+	pub fold_highlights		: Highlights<usize>, // folds version
+	pub breakpoint_highlights : Highlights<Vec<usize>>, // lines with breakpoints
 
 	pub cursor_entities		: Vec<Entity>,
 	pub resizer_entity		: Option<Entity>,
@@ -252,6 +255,8 @@ impl Default for SurfaceBevy {
 			search_highlights			: Highlights::<_>::default(),
 			selection_search_highlights : Highlights::<_>::default(),
 			cursor_highlights			: Highlights::<_>::default(),
+			fold_highlights				: Highlights::<_>::default(),
+			breakpoint_highlights		: Highlights::<_>::default(),
 
 			cursor_entities		: Vec::new(),
 			resizer_entity		: None,
@@ -262,6 +267,62 @@ impl Default for SurfaceBevy {
 	}
 }
 
+// unfocused helix views (splits) are carved out of editor surface into surfaces of their own
+pub const VIEW_SURFACE_PREFIX : &str = "helix_view_";
+
+pub fn view_surface_name(view_id: ViewId) -> String {
+	format!("{}{:?}", VIEW_SURFACE_PREFIX, view_id)
+}
+
+pub fn is_view_surface_name(name: &str) -> bool {
+	name.starts_with(VIEW_SURFACE_PREFIX)
+}
+
+// headers of enclosing scopes pinned to the top of editor surface
+pub const STICKY_SCROLL_SURFACE_NAME : &str = "kodiki_sticky_scroll";
+
+// call stack, threads, variables and watch expressions of a running debug session
+pub const DEBUG_PANEL_SURFACE_NAME : &str = "kodiki_debug_panel";
+
+// language servers state in the status row at the bottom of editor
+pub const LSP_STATUS_SURFACE_NAME : &str = "kodiki_lsp_status";
+
+// two panes of side by side diff view, both cover focused view while diff is shown
+pub const DIFF_LEFT_SURFACE_NAME : &str = "kodiki_diff_left";
+pub const DIFF_RIGHT_SURFACE_NAME : &str = "kodiki_diff_right";
+
+// results of workspace wide search and replace over the right side of focused view
+pub const SEARCH_PANEL_SURFACE_NAME : &str = "kodiki_search_panel";
+
+// symbol tree of current document over the right side of focused view, left of search panel when both are shown
+pub const OUTLINE_SURFACE_NAME : &str = "kodiki_outline";
+
+// workspace tree to the left of editor. Unlike the rest it's a kodiki_ui text surface that Helix never renders into
+pub const FILE_EXPLORER_SURFACE_NAME : &str = "kodiki_file_explorer";
+
+// documents shown as cards on canvas, placed in world space by canvas itself
+pub const CANVAS_CARD_SURFACE_PREFIX : &str = "kodiki_canvas_card_";
+
+pub fn canvas_card_surface_name(index: usize) -> String {
+	format!("{}{}", CANVAS_CARD_SURFACE_PREFIX, index)
+}
+
+pub fn is_canvas_card_surface_name(name: &str) -> bool {
+	name.starts_with(CANVAS_CARD_SURFACE_PREFIX)
+}
+
+// surfaces that are filled after Helix render and have to follow editor surface when it scrolls
+pub fn is_editor_attached_surface_name(name: &str) -> bool {
+	is_view_surface_name(name) || is_kodiki_surface_name(name)
+}
+
+// surfaces that Helix doesn't know about and that handle mouse input on their own
+pub fn is_kodiki_surface_name(name: &str) -> bool {
+	name == STICKY_SCROLL_SURFACE_NAME || name == DEBUG_PANEL_SURFACE_NAME || name == LSP_STATUS_SURFACE_NAME
+	|| name == DIFF_LEFT_SURFACE_NAME || name == DIFF_RIGHT_SURFACE_NAME || name == SEARCH_PANEL_SURFACE_NAME
+	|| name == OUTLINE_SURFACE_NAME || name == FILE_EXPLORER_SURFACE_NAME || is_canvas_card_surface_name(name)
+}
+
 pub type SurfacesMapBevyInner = HashMap<String, SurfaceBevy>;
 
 #[derive(Resource, Deref, DerefMut, Default)]
@@ -945,6 +1006,10 @@ impl SurfaceBevy {
 				&mut self.selection_search_highlights.entities,
 			HighlightKind::Cursor		=>
 				&mut self.cursor_highlights.entities,
+			HighlightKind::Fold			=>
+				&mut self.fold_highlights.entities,
+			HighlightKind::Breakpoint	=>
+				&mut self.breakpoint_highlights.entities,
 		}
 	}
 
@@ -960,6 +1025,10 @@ impl SurfaceBevy {
 				z_order::surface::highlight_search(),
 			HighlightKind::Cursor		=>
 				z_order::surface::cursor(),
+			HighlightKind::Fold			=>
+				z_order::surface::highlight(),
+			HighlightKind::Breakpoint	=>
+				z_order::surface::highlight_diagnostic(),
 		}
 	}
 
@@ -1064,6 +1133,30 @@ impl SurfaceBevy {
 		mesh_assets			: &mut Assets<Mesh>,
 		commands			: &mut Commands,
 	) {
+		let highlight_entity = self.spawn_highlight_whole_line_untracked(
+			highlight_line,
+			highlight_kind,
+			highlight_material_handle,
+			gutter_len,
+			fonts,
+			mesh_assets,
+			commands
+		);
+
+		self.highlight_entities_mut(highlight_kind).push(highlight_entity);
+	}
+
+	// caller is responsible for despawning returned entity, e.g. after a short animation
+	pub fn spawn_highlight_whole_line_untracked(
+		&self,
+		highlight_line		: usize,
+		highlight_kind		: HighlightKind,
+		highlight_material_handle : &Handle<StandardMaterial>,
+		gutter_len			: usize,
+		fonts				: &ABGlyphFonts,
+		mesh_assets			: &mut Assets<Mesh>,
+		commands			: &mut Commands,
+	) -> Entity {
 		let row_height		= fonts.main.vertical_advance();
 		let column_width	= fonts.main.horizontal_advance_mono();
 
@@ -1087,7 +1180,7 @@ impl SurfaceBevy {
 
 		commands.entity(self.entity).add_child(highlight_entity);
 
-		self.highlight_entities_mut(highlight_kind).push(highlight_entity);
+		highlight_entity
 	}
 
 	pub fn despawn_highlights(
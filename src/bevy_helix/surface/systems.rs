@@ -657,7 +657,8 @@ pub fn update_transform(
 	let	camera_transform = q_transform.get(camera_entity).unwrap().clone();
 
 	for (surface_name, surface_helix) in surfaces_helix.iter() {
-		if surface_name == EditorView::ID {
+		// canvas cards are placed by canvas
+		if surface_name == EditorView::ID || is_canvas_card_surface_name(surface_name) {
 			continue;
 		}
 
@@ -669,7 +670,8 @@ pub fn update_transform(
 
 		let target_pos =
 		if surface_helix.placement == SurfacePlacement::AreaCoordinates {
-			if area_bevy.same_position(area_helix) {
+			// view and sticky scroll surfaces have to follow editor surface when it scrolls
+			if area_bevy.same_position(area_helix) && !is_editor_attached_surface_name(surface_name) {
 				continue
 			} else {
 				area_bevy.assign_position(area_helix);
@@ -23,6 +23,7 @@ use helix_term :: {
 
 use helix_view :: {
 	document :: Mode,
+	editor :: GutterType,
 	graphics :: { Rect, Color as HelixColor },
 };
 
@@ -39,10 +40,21 @@ use super :: {
 	TokioRuntime,
 	BevyHelixSettings,
 	GotoDefinitionHighlight,
+	CAMERA_ROW_CONSTANT_OFFSET,
 
 	{ MatchesMapCache, SearchKind },
-	{ ArrowKeysState, KeyPressTiming, MousePosState, MouseButtonState, MouseHoverState },
+	{ ArrowKeysState, KeyPressTiming, KeyboardInputGuard, MousePosState, MouseButtonState, MouseHoverState },
 	{ Minimap, MinimapViewport },
+	canvas :: DocumentCanvas,
+	sticky_scroll :: StickyScroll,
+	folding :: { Folds, FoldedView },
+	debugger :: { DebugPanels, is_debugger_hotkey },
+	lsp_status :: LspStatus,
+	diff_view :: DiffView,
+	git_history :: GitHistory,
+	workspace_search :: WorkspaceSearch,
+	file_explorer :: FileExplorer,
+	outline :: Outline,
 
 	systems_util	:: *,
 	surface			:: *,
@@ -52,7 +64,7 @@ use super :: {
 
 use crate :: {
 	z_order,
-	kodiki :: DespawnResource,
+	kodiki :: { DespawnResource, DockLayout, DockSide, AppCameraMode },
 	kodiki_ui :: {
 		*,
 		text_cursor	:: *,
@@ -178,7 +190,7 @@ pub fn startup_spawn(
 
 	let mut camera		= q_reader_camera.single_mut();
 
-	camera.row_constant_offset = -1.0; // a cheat for top panel which height is 1 row
+	camera.row_constant_offset = CAMERA_ROW_CONSTANT_OFFSET;
 
 	camera.target_entity = Some(surface_bevy_editor.entity);
 	camera.column		= (surface_bevy_editor.area.width / 2) as usize;
@@ -188,16 +200,36 @@ pub fn startup_spawn(
 
 pub fn camera_update(
 	mut q_camera		: Query<&mut ReaderCamera>,
+		dock_layout		: Res<DockLayout>,
+		canvas			: Res<DocumentCanvas>,
+		history			: Res<GitHistory>,
+	mut diff_view		: ResMut<DiffView>,
+	mut views_cnt_prev	: Local<usize>,
 		app_option		: Option<NonSendMut<HelixApp>>,
 ) {
 	let mut app = if let Some(app) = app_option { app } else { return };
 
 	if app.should_close() { return }
 
+	// camera is driven by canvas or history anchor while any of them is shown, card edited on canvas is driven by Helix as usual
+	if canvas.overview() || history.active { return }
+
 	profile_function!();
 
 	let mut reader_camera = q_camera.single_mut();
 
+	// both diff panes scroll together with camera, Helix keeps its own offset untouched meanwhile
+	if diff_view.active {
+		if reader_camera.row_offset_delta() != 0 {
+			diff_view.scroll(reader_camera.row_offset_delta_apply());
+		}
+
+		reader_camera.set_row_offset_in(diff_view.scroll_row as u32);
+		diff_view.top_row = reader_camera.row_offset_out() as usize;
+
+		return;
+	}
+
 	// scrolling offset coming from camera to Helix
 	if reader_camera.row_offset_delta() != 0 {
 		app.scroll(reader_camera.row_offset_delta_apply());
@@ -208,12 +240,45 @@ pub fn camera_update(
 
 	// make Helix aware of row offset in camera to render viewport accordingly
 	app.set_row_offset_external(reader_camera.row_offset_out() as usize);
+
+	// camera follows focused view when editor is split, views are placed side by side so editor is as wide as all of them.
+	// Terminal docked to the right takes care of camera column on its own
+	let views = app.views();
+	let views_cnt = views.len();
+
+	if dock_layout.side != DockSide::Right {
+		let column = if views_cnt > 1 {
+			views.iter().find(|view| view.focused).map(|view| (view.area.x + view.area.width / 2) as usize)
+		} else if *views_cnt_prev > 1 {
+			// the last split got closed so get back to the middle of editor
+			Some((app.editor_area().width / 2) as usize)
+		} else {
+			None
+		};
+
+		if let Some(column) = column {
+			if reader_camera.column != column {
+				reader_camera.column = column;
+			}
+		}
+	}
+
+	*views_cnt_prev = views_cnt;
 }
 
 pub fn render_helix(
 		q_camera			: Query<&ReaderCamera>,
 	mut surfaces_helix		: ResMut<SurfacesMapHelix>,
 	mut framerate_manager	: ResMut<FramerateManager>,
+		dock_layout			: Res<DockLayout>,
+	mut sticky_scroll		: ResMut<StickyScroll>,
+	mut debug_panels		: ResMut<DebugPanels>,
+		lsp_status			: Res<LspStatus>,
+	mut diff_view			: ResMut<DiffView>,
+	mut workspace_search	: ResMut<WorkspaceSearch>,
+		file_explorer		: Res<FileExplorer>,
+	mut outline				: ResMut<Outline>,
+		folds				: Res<Folds>,
 		app_option			: Option<NonSendMut<HelixApp>>,
 ) {
 	let Some(mut app) = app_option else { return };
@@ -229,10 +294,39 @@ pub fn render_helix(
 	let Ok(reader_camera) = q_camera.get_single() else { return };
 
 	// surface area depends on camera frustum
-	app.resize_editor_height(reader_camera.visible_rows.ceil() as u16 + 1); // + 1 to make sure we don't show empty rows on camera when scrolling
-	app.resize_screen_width	(reader_camera.visible_columns.floor() as u16);
+	let mut editor_rows = reader_camera.visible_rows.ceil() as u16 + 1; // + 1 to make sure we don't show empty rows on camera when scrolling
+
+	// panels docked at the bottom take their share of rows and language servers status takes a row under the editor
+	editor_rows = editor_rows.saturating_sub(dock_layout.reserved_rows(reader_camera.visible_rows) as u16).max(1);
+	editor_rows = editor_rows.saturating_sub(lsp_status.reserved_rows() as u16).max(1);
+
+	let folded_view = FoldedView::new(&app);
+	let hidden_rows = folds.hidden_rows(folded_view.doc_id, folded_view.top_row, editor_rows as usize) as u16;
+
+	// file explorer takes its columns on the left and panels docked to the right take theirs
+	let reserved_columns = file_explorer.reserved_columns() + dock_layout.reserved_columns(reader_camera.visible_columns);
+	let screen_columns = (reader_camera.visible_columns.floor() as u16).saturating_sub(reserved_columns as u16).max(1);
+
+	app.resize_editor_height(editor_rows);
+	app.resize_screen_width	(screen_columns);
+
+	// rows hidden by folds are pulled up in folding::update_rows_offset, viewport stays as is
+	app.render_with_hidden_rows(&mut surfaces_helix, hidden_rows);
+
+	split_editor_views(&mut surfaces_helix, &app.views());
+
+	sticky_scroll.update_surface(&mut surfaces_helix, &app);
+
+	debug_panels.update_surface(&mut surfaces_helix, &app);
 
-	app.render(&mut surfaces_helix);
+	lsp_status.update_surface(&mut surfaces_helix, &app);
+
+	diff_view.update_surface(&mut surfaces_helix, &app);
+
+	workspace_search.update_surface(&mut surfaces_helix, &app);
+
+	// goes after search panel to take place next to it
+	outline.update_surface(&mut surfaces_helix, &app);
 }
 
 #[cfg(feature = "stats")]
@@ -339,6 +433,7 @@ pub fn manage_cursors(
 pub fn update_cursor(
 		surfaces_bevy		: Res<SurfacesMapBevy>,
 	mut	q_cursor			: Query<&mut TextCursor>,
+		folds				: Res<Folds>,
 		app_option			: Option<NonSend<HelixApp>>,
 ) {
 	let app = if let Some(app) = app_option { app } else { return };
@@ -368,11 +463,21 @@ pub fn update_cursor(
 	};
 	let row_offset_sign = row_offset_dir.sign();
 
+	let folded_view = FoldedView::new(&app);
+	let on_editor = cursor_surface_name == EditorView::ID;
+
 	for (cursor_index, cursor_entity) in surface_bevy.cursor_entities.iter().enumerate() {
 		let mut cursor = if let Ok(cu) = q_cursor.get_mut(*cursor_entity) { cu } else { continue };
 
 		cursor.color = cursor_color;
 		cursor.row = cursor_positions[cursor_index].row;
+
+		// rows below folded ranges are moved up
+		if on_editor {
+			if let Some(doc_row) = folded_view.document_row(cursor.row) {
+				cursor.row = folded_view.surface_row(folds.visual_row(folded_view.doc_id, doc_row, folded_view.top_row));
+			}
+		}
 		cursor.col = cursor_positions[cursor_index].col;
 		cursor.row_offset_sign = row_offset_sign;
 
@@ -380,11 +485,89 @@ pub fn update_cursor(
 	}
 }
 
+// unfocused views show their primary cursor on their own surfaces
+pub fn update_view_cursors(
+	mut surfaces_bevy	: ResMut<SurfacesMapBevy>,
+	mut	q_cursor		: Query<&mut TextCursor>,
+		font_assets		: Res<Assets<ABGlyphFont>>,
+		font_handles	: Res<FontAssetHandles>,
+
+	(mut gltf_assets, mut material_assets, mut cursor_asset)
+						:
+	(ResMut<Assets<Gltf>>, ResMut<Assets<StandardMaterial>>, ResMut<CursorVisualAsset>),
+
+	mut despawn			: ResMut<DespawnResource>,
+	mut commands		: Commands,
+		app_option		: Option<NonSend<HelixApp>>,
+) {
+	let app = if let Some(app) = app_option { app } else { return };
+
+	if app.should_close() { return }
+
+	let views = app.views();
+	if views.len() < 2 { return }
+
+	profile_function!();
+
+	let fonts = ABGlyphFonts::new(&font_assets, &font_handles);
+
+	let cursor_theme = app.editor.theme.get("ui.cursor");
+	let cursor_color = if let Some(bg_color) = cursor_theme.bg {
+		color_from_helix(bg_color)
+	} else {
+		Color::WHITE
+	};
+
+	for view in views.iter().filter(|view| !view.focused) {
+		let Some(surface_bevy) = surfaces_bevy.get_mut(&view_surface_name(view.id)) else { continue };
+
+		// cursor scrolled out of view
+		let Some(cursor_position) = view.cursor else {
+			while let Some(cursor_entity) = surface_bevy.cursor_entities.pop() {
+				despawn.recursive.push(cursor_entity);
+			}
+
+			continue;
+		};
+
+		let Some(cursor_entity) = surface_bevy.cursor_entities.first().copied() else {
+			let cursor_entity = TextCursor::spawn(
+				&surface_bevy.name,
+				z_order::surface::cursor(),
+				fonts.main,
+				&mut gltf_assets,
+				&mut material_assets,
+				&mut cursor_asset,
+				&mut commands
+			);
+
+			surface_bevy.cursor_entities.push(cursor_entity);
+
+			commands.entity(surface_bevy.entity).add_child(cursor_entity);
+
+			continue;
+		};
+
+		let Ok(mut cursor) = q_cursor.get_mut(cursor_entity) else { continue };
+
+		// view surface is a part of editor surface so cursor position is relative to its area
+		cursor.color = cursor_color;
+		cursor.row = cursor_position.row.saturating_sub(surface_bevy.area.y as usize);
+		cursor.col = cursor_position.col.saturating_sub(surface_bevy.area.x as usize);
+		cursor.row_offset_sign = RowOffsetDirection::Down.sign();
+
+		cursor.blink_alpha = if app.dark_theme() { 0.2 } else { 0.8 };
+	}
+}
+
 pub fn update_editor_resizer(
 	mut q_resizer	: Query<(&mut Resizer, &mut Transform), Without<ReaderCamera>>,
-		q_camera	: Query<&Transform, With<ReaderCamera>>,
+		q_camera	: Query<(&Transform, &ReaderCamera)>,
 
 	surfaces_bevy	: Res<SurfacesMapBevy>,
+	dock_layout		: Res<DockLayout>,
+	font_assets		: Res<Assets<ABGlyphFont>>,
+	font_handles	: Res<FontAssetHandles>,
 	app_option		: Option<NonSendMut<HelixApp>>,
 ) {
 	let Some(mut app) = app_option else { return };
@@ -396,9 +579,20 @@ pub fn update_editor_resizer(
 
 	let Ok((mut resizer, mut resizer_transform)) = q_resizer.get_mut(resizer_entity) else { return };
 
-	// resizer -> helix : editor area is taken from resizer
+	let Ok((camera_transform, reader_camera)) = q_camera.get_single() else { return };
+
+	// resizer -> helix : resizer defines width of a single view, views split vertically are placed side by side so editor gets as wide as all of them.
+	// Panels docked to the right leave the rest of the screen to editor
+
+	let views = app.views();
+
+	let mut editor_width = resizer.area.x as u16 * vsplit_columns(&views);
+	if dock_layout.side == DockSide::Right && dock_layout.is_docked() {
+		let available_columns = (reader_camera.visible_columns.floor() as usize).saturating_sub(dock_layout.reserved_columns(reader_camera.visible_columns));
+		editor_width = editor_width.min(available_columns.max(1) as u16);
+	}
 
-	app.resize_editor_width(resizer.area.x as u16);
+	app.resize_editor_width(editor_width);
 
 	// helix -> resizer : all colors are take from helix themes
 	
@@ -406,12 +600,81 @@ pub fn update_editor_resizer(
 
 	resizer.quad_color	= color_from_helix(style.bg.unwrap_or_else(|| { HelixColor::Cyan }));
 
-	let Ok(camera_transform) = q_camera.get_single() else { return };
+	// editor resizer sticks to the left side of focused view
+	let fonts = ABGlyphFonts::new(&font_assets, &font_handles);
+	let focused_column = views.iter().find(|view| view.focused).map_or(0, |view| view.area.x);
 
-	resizer_transform.translation.x = -resizer.width / 2.0 - resizer.margin;
+	resizer_transform.translation.x = focused_column as f32 * fonts.main.horizontal_advance_mono() - resizer.width / 2.0 - resizer.margin;
 	resizer_transform.translation.y = camera_transform.translation.y;
 }
 
+// every unfocused view gets a resizer of its own. Helix splits editor evenly so all of them drive the same view width as editor resizer
+pub fn update_view_resizers(
+	mut q_resizer		: Query<(&mut Resizer, &mut Transform)>,
+		q_transform		: Query<&Transform, Without<Resizer>>,
+		q_camera		: Query<Entity, With<ReaderCamera>>,
+	mut surfaces_bevy	: ResMut<SurfacesMapBevy>,
+	mut mesh_assets		: ResMut<Assets<Mesh>>,
+	mut material_assets	: ResMut<Assets<StandardMaterial>>,
+	mut commands		: Commands,
+) {
+	let Some(editor_surface) = surfaces_bevy.get(&String::from(EditorView::ID)) else { return };
+	let Some(editor_resizer_entity) = editor_surface.resizer_entity else { return };
+
+	let Ok((editor_resizer, _)) = q_resizer.get(editor_resizer_entity) else { return };
+	let editor_area		= editor_resizer.area;
+	let quad_color		= editor_resizer.quad_color;
+
+	let Ok(camera_entity) = q_camera.get_single() else { return };
+	let Ok(camera_transform) = q_transform.get(camera_entity) else { return };
+	let camera_y		= camera_transform.translation.y;
+
+	let mut dragged_area = None;
+
+	for (surface_name, surface_bevy) in surfaces_bevy.iter_mut() {
+		if !is_view_surface_name(surface_name) {
+			continue;
+		}
+
+		let Some(resizer_entity) = surface_bevy.resizer_entity else {
+			let resizer_entity = Resizer::spawn(
+				surface_name,
+				editor_area,
+				&mut mesh_assets,
+				&mut material_assets,
+				&mut commands
+			);
+
+			commands.entity(surface_bevy.entity).add_child(resizer_entity);
+			surface_bevy.resizer_entity = Some(resizer_entity);
+
+			continue;
+		};
+
+		let Ok((mut resizer, mut resizer_transform)) = q_resizer.get_mut(resizer_entity) else { continue };
+
+		if resizer.dragging_active() {
+			dragged_area = Some(resizer.area);
+		} else if resizer.area != editor_area {
+			resizer.area = editor_area;
+		}
+
+		resizer.quad_color = quad_color;
+
+		// view surfaces are not attached to anything so their transform is in world space
+		let surface_y = q_transform.get(surface_bevy.entity).map_or(0.0, |transform| transform.translation.y);
+
+		resizer_transform.translation.x = -resizer.width / 2.0 - resizer.margin;
+		resizer_transform.translation.y = camera_y - surface_y;
+	}
+
+	if let Some(area) = dragged_area {
+		if let Ok((mut editor_resizer, _)) = q_resizer.get_mut(editor_resizer_entity) {
+			editor_resizer.area = area;
+		}
+	}
+}
+
 pub fn update_search_matches(
 	mut matches_cache	: ResMut<MatchesMapCache>,
 		key				: Res<Input<KeyCode>>,
@@ -926,6 +1189,7 @@ pub fn input_mouse(
 	q_word				: Query<&WordDescription>,
 	raypick				: Res<Raypick>,
 
+	folds				: Res<Folds>,
 	bevy_helix_settings	: Res<BevyHelixSettings>,
 	dragging_state		: Res<DraggingState>,
 	tokio_runtime		: Res<TokioRuntime>,
@@ -941,23 +1205,10 @@ pub fn input_mouse(
 
 	profile_function!();
 
-	let surface_name = if let Ok(word) = q_word.get(hovered_entity) {
-		&word.surface_name
-	} else {
-		// TODO: refactor, if it's a surface we already have the entity to get the transform
-		let mut found_surface_name = None;
-		for surface in surfaces.iter() {
-			if surface.1.entity == hovered_entity {
-				found_surface_name = Some(surface.0); // name
-			}
-		}
+	let Some(surface_name) = hovered_surface_name(hovered_entity, &q_word, &surfaces) else { return };
 
-		if let Some(name) = found_surface_name {
-			name
-		} else {
-			return
-		}
-	};
+	// pinned scope headers and debug panel are not part of Helix layout and handle clicks on their own
+	if is_kodiki_surface_name(surface_name) { return }
 
 	// getting editor surface first
 	let hovered_surface = surfaces.get(surface_name).unwrap();
@@ -990,13 +1241,48 @@ pub fn input_mouse(
 
 	let modifiers_helix = input::key_code_to_helix_modifiers(&key);
 
-	let column			= column as u16;
-	let row				= row as u16;
+	let mut column		= column as u16;
+	let mut row			= row as u16;
 	let mouse_moved		= !cursor_events.is_empty();
 
+	// rows below folded ranges are displayed higher than Helix expects them to be
+	if surface_name == EditorView::ID {
+		let folded_view = FoldedView::new(&app);
+
+		if let Some(doc_row) = folded_view.document_row(folded_view.top_row + row as usize) {
+			let doc_row = folds.document_row(folded_view.doc_id, doc_row, folded_view.top_row);
+			row = (folded_view.surface_row(doc_row) - folded_view.top_row) as u16;
+		}
+	}
+
+	let child_surface	= is_child_surface(surface_name, hovered_surface);
+
+	// view surfaces are parts of editor surface for Helix so convert to editor coordinates,
+	// pickers, prompts and menus expect screen coordinates so that compositor can route events to them
+	let surface_name = if is_view_surface_name(surface_name) || child_surface {
+		column	+= area.x;
+		row		+= area.y;
+		if child_surface { surface_name.clone() } else { String::from(EditorView::ID) }
+	} else {
+		surface_name.clone()
+	};
+
 	mouse_pos_state.row = row;
 	mouse_pos_state.col = column;
-	mouse_pos_state.surface_name = surface_name.clone();
+	mouse_pos_state.surface_name = surface_name;
+
+	// clicks on line numbers toggle breakpoints, see debugger::systems::input_mouse_gutter
+	if mouse_button.just_pressed(MouseButton::Left) && mouse_pos_state.surface_name == EditorView::ID {
+		let (view, _) = app.current_ref();
+		let breakpoint_columns = app.gutter_columns(GutterType::LineNumbers);
+
+		if breakpoint_columns.map_or(false, |columns| columns.contains(&column)) && row >= view.area.y && row < view.area.bottom() {
+			return;
+		}
+	}
+
+	let double_click = mouse_button.just_pressed(MouseButton::Left)
+		&& mouse_button_state.is_double_click(&MouseButton::Left, bevy_helix_settings.double_click_delay_seconds);
 
 	input::handle_mouse_events(
 		&mouse_button,
@@ -1005,10 +1291,16 @@ pub fn input_mouse(
 		column,
 		row,
 		mouse_moved,
+		child_surface, /* send_hover_events */
 		&bevy_helix_settings,
 		&tokio_runtime,
 		&mut app
 	);
+
+	// first click selected the entry under cursor already, second one accepts it
+	if child_surface && double_click && app.picker_active() {
+		input::send_keyboard_event(&helix_view::keyboard::KeyCode::Enter, &helix_view::keyboard::KeyModifiers::NONE, &tokio_runtime, &mut app);
+	}
 }
 
 // currently not used since scrolling is handled by camera. Keeping it in case we need to pass wheel events to Helix for other reasons in the future
@@ -1021,6 +1313,7 @@ pub fn input_scroll_deprecated(
 	q_minimap		: Query<&Minimap>,
 	q_minimap_viewport : Query<&MinimapViewport>,
 
+	mouse_pos		: Res<MousePosState>,
 	tokio_runtime	: Res<TokioRuntime>,
 	app_option		: Option<NonSendMut<HelixApp>>,
 ) {
@@ -1044,6 +1337,73 @@ pub fn input_scroll_deprecated(
 
 	input::handle_scroll_events(
 		&mut scroll_events,
+		mouse_pos.col,
+		mouse_pos.row,
+		&tokio_runtime,
+		&mut app
+	);
+}
+
+// editor is scrolled by camera, but pickers, prompts and menus have their own scrolling in Helix
+pub fn input_scroll_child_surfaces(
+	mut scroll_events	: EventReader<MouseWheel>,
+	mut q_camera		: Query<&mut ReaderCamera>,
+	mut mouse_pos		: ResMut<MousePosState>,
+		cam_mode		: Res<State<AppCameraMode>>,
+		raypick			: Res<Raypick>,
+		surfaces		: Res<SurfacesMapBevy>,
+		q_word			: Query<&WordDescription>,
+		file_explorer	: Res<FileExplorer>,
+		tokio_runtime	: Res<TokioRuntime>,
+		app_option		: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	profile_function!();
+
+	let hovered_name = raypick.last_hover.and_then(|entity| hovered_surface_name(entity, &q_word, &surfaces));
+
+	// file explorer background is not a word so it's checked by entity
+	let hovered_explorer = hovered_name.map_or(false, |name| name == FILE_EXPLORER_SURFACE_NAME)
+		|| raypick.last_hover.map_or(false, |entity| file_explorer.owns(entity))
+	;
+
+	// workspace search panel, outline, debug panel and file explorer scroll on their own
+	let hovered_scrollable_panel = hovered_explorer
+		|| hovered_name.map_or(false, |name| name == SEARCH_PANEL_SURFACE_NAME || name == OUTLINE_SURFACE_NAME || name == DEBUG_PANEL_SURFACE_NAME)
+	;
+
+	let hovered_child_surface = hovered_name
+		.and_then(|name| surfaces.get(name).map(|surface| (name, surface)))
+		.map_or(false, |(name, surface)| is_child_surface(name, surface))
+	;
+
+	let scroll_captured = hovered_child_surface || hovered_scrollable_panel;
+
+	// camera should not scroll editor while wheel is used on a child surface or a panel. Zoom mode manages restrictions on its own
+	if cam_mode.0 == AppCameraMode::Main && scroll_captured != mouse_pos.scroll_captured {
+		let Ok(mut reader_camera) = q_camera.get_single_mut() else { return };
+
+		if scroll_captured {
+			reader_camera.set_restrictions(false, false, false, false);
+		} else {
+			reader_camera.apply_default_restrictions();
+		}
+
+		mouse_pos.scroll_captured = scroll_captured;
+	}
+
+	if !hovered_child_surface {
+		scroll_events.clear();
+		return;
+	}
+
+	input::handle_scroll_events(
+		&mut scroll_events,
+		mouse_pos.col,
+		mouse_pos.row,
 		&tokio_runtime,
 		&mut app
 	);
@@ -1052,7 +1412,9 @@ pub fn input_scroll_deprecated(
 pub fn input_keyboard(
 	mut arrow_keys		: ResMut<ArrowKeysState>,
 	mut keyboard_events : EventReader<KeyboardInput>,
+	mut input_guard		: ResMut<KeyboardInputGuard>,
 		key				: Res<Input<KeyCode>>,
+		dock_layout		: Res<DockLayout>,
 
 		bevy_helix_settings : Res<BevyHelixSettings>,
 		tokio_runtime	: Res<TokioRuntime>,
@@ -1061,13 +1423,20 @@ pub fn input_keyboard(
 	let Some(mut app) = app_option else { return };
 
 	if app.should_close() { return }
-	
+
+	// key that closed a modal view is still buffered, it belongs to that view
+	if input_guard.take() {
+		keyboard_events.clear();
+		return;
+	}
 
 	profile_function!();
 
 	let modifiers_helix = input::key_code_to_helix_modifiers(&key);
 	let shift = modifiers_helix.contains(helix_view::keyboard::KeyModifiers::SHIFT);
 
+	let debug_session_active = app.editor.debugger.is_some();
+
 	let now = Instant::now();
 
 	// EventReader<KeyboardInput> gets updated irregularly and if arrow key is being held we want to send updates with more consistent delays between them
@@ -1121,6 +1490,62 @@ pub fn input_keyboard(
 				KeyCode::Key1 | KeyCode::Key2 | KeyCode::Key3 | KeyCode::Key4 | KeyCode::Key5 |
 				KeyCode::Key6 | KeyCode::Key7 | KeyCode::Key8 | KeyCode::Key9 | KeyCode::Key0
 				if key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl) => continue,
+
+				// ignore ctrl+` as it toggles drop-down terminal
+				KeyCode::Grave
+				if key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl) => continue,
+
+				// ignore ctrl+\ and ctrl+tab while panels are docked as those manage docking then
+				KeyCode::Backslash | KeyCode::Tab
+				if (key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl)) && dock_layout.is_docked() => continue,
+
+				// ignore ctrl+alt+pageup/pagedown as those reorder contexts
+				KeyCode::PageUp | KeyCode::PageDown
+				if (key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl)) && (key.pressed(KeyCode::LAlt) || key.pressed(KeyCode::RAlt)) => continue,
+
+				// ignore ctrl+shift+home as it opens document canvas
+				KeyCode::Home
+				if (key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl)) && (key.pressed(KeyCode::LShift) || key.pressed(KeyCode::RShift)) => continue,
+
+				// ignore ctrl+alt+d/h as those toggle diagnostics lens and cfg-inactive hints in it
+				KeyCode::D | KeyCode::H
+				if (key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl)) && (key.pressed(KeyCode::LAlt) || key.pressed(KeyCode::RAlt)) => continue,
+
+				// ignore debugger hotkeys: f9 breakpoint and f5 continue/stop, f6 pause, f10/f11 stepping during debug session
+				KeyCode::F5 | KeyCode::F6 | KeyCode::F9 | KeyCode::F10 | KeyCode::F11
+				if is_debugger_hotkey(keycode_bevy, &key, debug_session_active) => continue,
+
+				// ignore ctrl+alt+w as it adds selection to debugger watch expressions
+				KeyCode::W
+				if (key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl)) && (key.pressed(KeyCode::LAlt) || key.pressed(KeyCode::RAlt)) => continue,
+
+				// ignore ctrl+alt+l and ctrl+alt+r as those open language servers and recovery pickers
+				KeyCode::L | KeyCode::R
+				if (key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl)) && (key.pressed(KeyCode::LAlt) || key.pressed(KeyCode::RAlt)) => continue,
+
+				// ignore ctrl+alt+b/i/g as those toggle blame column, inline blame and open line history
+				KeyCode::B | KeyCode::I | KeyCode::G
+				if (key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl)) && (key.pressed(KeyCode::LAlt) || key.pressed(KeyCode::RAlt)) => continue,
+
+				// ignore ctrl+alt+v as it opens diff view
+				KeyCode::V
+				if (key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl)) && (key.pressed(KeyCode::LAlt) || key.pressed(KeyCode::RAlt)) => continue,
+
+				// ignore ctrl+shift+f and ctrl+shift+h as those open workspace search and replace prompts
+				KeyCode::F | KeyCode::H
+				if (key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl)) && (key.pressed(KeyCode::LShift) || key.pressed(KeyCode::RShift)) => continue,
+
+				// ignore ctrl+alt+o as it toggles document outline and ctrl+alt+c and ctrl+alt+m as they open call and module graphs
+				KeyCode::O | KeyCode::C | KeyCode::M
+				if (key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl)) && (key.pressed(KeyCode::LAlt) || key.pressed(KeyCode::RAlt)) => continue,
+
+				// ignore ctrl+shift+e as it toggles file explorer
+				KeyCode::E
+				if (key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl)) && (key.pressed(KeyCode::LShift) || key.pressed(KeyCode::RShift)) => continue,
+
+				// ignore ctrl+shift+[ as it toggles code folding
+				KeyCode::BracketLeft
+				if (key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl)) && (key.pressed(KeyCode::LShift) || key.pressed(KeyCode::RShift)) => continue,
 				_ => (),
 			}
 		}
@@ -8,7 +8,7 @@ use bevy_reader_camera :: ReaderCamera;
 
 use super :: {
 	TweenPoint,
-	helix_app :: HelixApp,
+	helix_app :: { HelixApp, ViewDesc },
 	surface	:: *,
 };
 
@@ -19,6 +19,7 @@ use crate :: {
 };
 
 use helix_term	:: ui			:: EditorView;
+use helix_tui	:: buffer		:: { Buffer as SurfaceHelix, SurfaceFlags, SurfacePlacement };
 use helix_view	:: graphics		:: Color as HelixColor;
 
 use std :: time :: Duration;
@@ -112,6 +113,11 @@ pub fn despawn_unused_surfaces(
 			continue;
 		}
 
+		// view and sticky scroll surfaces are filled after render and their lifetime is managed in split_editor_views and sticky_scroll
+		if is_editor_attached_surface_name(surface_name) {
+			continue;
+		}
+
 		to_remove.push(surface_name.clone());
 	}
     for layer in to_remove.iter() {
@@ -133,6 +139,90 @@ pub fn despawn_unused_surfaces(
 	}
 }
 
+// move every unfocused view from editor surface into a surface of its own so that it's rendered separately in 3d
+pub fn split_editor_views(
+	surfaces_helix	: &mut SurfacesMapHelix,
+	views			: &Vec<ViewDesc>,
+) {
+	let editor_name = String::from(EditorView::ID);
+
+	// with a single view everything stays on editor surface
+	let unfocused_views : Vec<&ViewDesc> = if views.len() > 1 {
+		views.iter().filter(|view| !view.focused).collect()
+	} else {
+		Vec::new()
+	};
+
+	// forget views that were closed or got focused, their bevy counterparts are despawned in despawn_unused_surfaces
+	surfaces_helix.retain(|surface_name, _| {
+		!is_view_surface_name(surface_name) || unfocused_views.iter().any(|view| view_surface_name(view.id) == *surface_name)
+	});
+
+	let Some(mut surface_editor) = surfaces_helix.remove(&editor_name) else { return };
+
+	for view in unfocused_views.iter() {
+		let area = surface_editor.area.intersection(view.area);
+		if area.width == 0 || area.height == 0 {
+			continue;
+		}
+
+		let surface_view = surfaces_helix.entry(view_surface_name(view.id)).or_insert_with(|| {
+			SurfaceHelix::empty_with_spatial(area, SurfaceFlags::default())
+		});
+
+		if surface_view.area != area {
+			surface_view.resize(area);
+		}
+
+		surface_view.placement = SurfacePlacement::AreaCoordinates;
+
+		for y in area.top()..area.bottom() {
+			for x in area.left()..area.right() {
+				let cell = surface_editor.get_mut(x, y);
+				*surface_view.get_mut(x, y) = cell.clone();
+				cell.reset();
+			}
+		}
+	}
+
+	surfaces_helix.insert(editor_name, surface_editor);
+}
+
+// amount of views placed side by side in the widest row of the layout, editor gets that many view widths
+pub fn vsplit_columns(views: &Vec<ViewDesc>) -> u16 {
+	views.iter().map(|view| {
+		views.iter().filter(|other| other.area.top() <= view.area.top() && view.area.top() < other.area.bottom()).count()
+	}).max().unwrap_or(1).max(1) as u16
+}
+
+// surface hit by raypick either directly or through one of its words
+pub fn hovered_surface_name<'a>(
+	hovered_entity	: Entity,
+	q_word			: &'a Query<&WordDescription>,
+	surfaces		: &'a SurfacesMapBevy,
+) -> Option<&'a String> {
+	if let Ok(word) = q_word.get(hovered_entity) {
+		return Some(&word.surface_name);
+	}
+
+	surfaces.iter().find(|(_, surface)| surface.entity == hovered_entity).map(|(name, _)| name)
+}
+
+// first row of a popup attached to rows start_row..=end_row of the view: right under them covering code below
+// or above them when the popup doesn't fit between them and the bottom of the view
+pub fn popup_first_row(start_row: usize, end_row: usize, popup_rows: usize, top_row: usize, view_height: usize) -> usize {
+	if end_row + 1 + popup_rows <= top_row + view_height {
+		end_row + 1
+	} else {
+		start_row.saturating_sub(popup_rows).max(top_row)
+	}
+}
+
+// pickers, prompts, menus and popups as opposed to editor surface, its views and surfaces Helix doesn't know about
+pub fn is_child_surface(surface_name: &str, surface_bevy: &SurfaceBevy) -> bool {
+	!surface_bevy.is_editor && surface_name != EditorView::ID && !is_view_surface_name(surface_name) && !is_kodiki_surface_name(surface_name)
+}
+
 pub fn benchmark_surface_render(surfaces_bevy: &mut SurfacesMapBevy, surfaces_helix: &mut SurfacesMapHelix, app: &HelixApp) {
     let surface_bevy_editor = surfaces_bevy.get_mut(&String::from(EditorView::ID)).unwrap();
     if surface_bevy_editor.update {
@@ -1,6 +1,9 @@
 use bevy_tweening :: *;
 
-use super :: minimap :: MinimapScrollAnimation;
+use super :: {
+	minimap	:: MinimapScrollAnimation,
+	jump	:: JumpScrollAnimation,
+};
 
 /// A lens to manipulate current_row field of [`Minimap`] component.
 ///
@@ -22,3 +25,20 @@ impl Lens<MinimapScrollAnimation> for MinimapRowLens {
 		target.set_row(value);
 	}
 }
+
+/// A lens to manipulate row field of [`JumpScrollAnimation`] component.
+///
+/// [`JumpScrollAnimation`]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct JumpRowLens {
+	/// Start row.
+	pub start: f32,
+	/// End row.
+	pub end: f32,
+}
+
+impl Lens<JumpScrollAnimation> for JumpRowLens {
+	fn lerp(&mut self, target: &mut JumpScrollAnimation, ratio: f32) {
+		target.set_row(self.start.lerp(&self.end, &ratio));
+	}
+}
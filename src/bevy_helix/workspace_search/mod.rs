@@ -0,0 +1,932 @@
+use bevy :: prelude :: *;
+use bevy :: tasks :: { AsyncComputeTaskPool, Task };
+use bevy :: utils :: { HashMap, HashSet };
+
+use helix_core :: regex :: { self, Regex, RegexBuilder };
+use helix_term :: ui :: EditorView;
+use helix_tui :: buffer :: { Buffer as SurfaceHelix, SurfaceFlags, SurfacePlacement };
+use helix_view :: {
+	Editor, DocumentId, Theme,
+	editor :: Action,
+	graphics :: { Rect, Style },
+};
+
+use std :: {
+	collections :: BTreeMap,
+	ops :: Range,
+	path :: { Path, PathBuf },
+	process :: Command,
+	sync :: { Arc, Mutex },
+	time :: SystemTime,
+};
+
+use super :: {
+	HelixApp,
+	diff_view :: tinted_style,
+	file_sync :: replace_document_text,
+	surface :: { SurfacesMapHelix, SEARCH_PANEL_SURFACE_NAME },
+};
+
+pub mod systems;
+
+pub const PANEL_COLUMNS		: u16	= 80;
+pub const HEADER_ROWS		: usize	= 5; // query, options, globs, replacement and summary stay on top while results scroll
+pub const CONTEXT_LINES		: usize	= 1; // lines shown above and below every match
+pub const MAX_MATCHES		: usize	= 5000;
+pub const MAX_FILE_BYTES	: u64	= 2 * 1024 * 1024;
+pub const BINARY_PROBE_BYTES: usize	= 8000; // files with zero bytes in the beginning are not searched
+pub const WHEEL_ROWS		: i32	= 3;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SearchField {
+	Query,
+	Replacement,
+	Include,
+	Exclude,
+}
+
+// text entered in Helix prompt, picked up by the panel on the Bevy side
+pub type SharedSearchInput = Arc<Mutex<Option<(SearchField, String)>>>;
+
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct SearchOptions {
+	pub regex			: bool,
+	pub whole_word		: bool,
+	pub case_sensitive	: bool,
+	pub include			: Vec<String>,
+	pub exclude			: Vec<String>,
+}
+
+impl SearchOptions {
+	// path is relative to workspace root with forward slashes
+	pub fn accepts(&self, path: &str) -> bool {
+		(self.include.is_empty() || self.include.iter().any(|pattern| glob_matches_path(pattern, path)))
+		&& !self.exclude.iter().any(|pattern| glob_matches_path(pattern, path))
+	}
+}
+
+pub fn build_regex(query: &str, options: &SearchOptions) -> Result<Regex, String> {
+	let pattern = if options.regex { query.to_string() } else { regex::escape(query) };
+	let pattern = if options.whole_word { format!(r"\b(?:{})\b", pattern) } else { pattern };
+
+	RegexBuilder::new(pattern.as_str())
+		.case_insensitive(!options.case_sensitive)
+		.multi_line(false)
+		.build()
+		.map_err(|e| e.to_string())
+}
+
+// comma separated list as typed in prompt
+pub fn parse_globs(input: &str) -> Vec<String> {
+	input.split(',').map(|pattern| pattern.trim()).filter(|pattern| !pattern.is_empty()).map(String::from).collect()
+}
+
+// `*` and `?` stay within one path component, `**` spans any number of them
+fn glob_matches(pattern: &[char], text: &[char]) -> bool {
+	match pattern.first() {
+		None => text.is_empty(),
+		Some('*') if pattern.get(1) == Some(&'*') => {
+			let rest = &pattern[2 ..];
+			if rest.is_empty() { return true }
+
+			let rest = rest.strip_prefix(&['/']).unwrap_or(rest);
+
+			(0 ..= text.len()).any(|skip| (skip == 0 || text[skip - 1] == '/') && glob_matches(rest, &text[skip ..]))
+		},
+		Some('*') => (0 ..= text.len())
+			.take_while(|skip| *skip == 0 || text[skip - 1] != '/')
+			.any(|skip| glob_matches(&pattern[1 ..], &text[skip ..])),
+		Some('?') => text.first().map_or(false, |c| *c != '/') && glob_matches(&pattern[1 ..], &text[1 ..]),
+		Some(c) => text.first() == Some(c) && glob_matches(&pattern[1 ..], &text[1 ..]),
+	}
+}
+
+// patterns without a slash are matched against every path component so that `target` or `*.rs` work anywhere in the tree,
+// the rest is matched against the whole path or a directory it is in
+pub fn glob_matches_path(pattern: &str, path: &str) -> bool {
+	let pattern : Vec<char> = pattern.trim_matches('/').chars().collect();
+	let chars = |text: &str| -> Vec<char> { text.chars().collect() };
+
+	if !pattern.contains(&'/') {
+		return path.split('/').any(|component| glob_matches(&pattern, &chars(component)));
+	}
+
+	glob_matches(&pattern, &chars(path))
+	|| path.match_indices('/').any(|(index, _)| glob_matches(&pattern, &chars(&path[.. index])))
+}
+
+// files git would show in status, ignored ones are left out. Outside of repository everything but hidden directories and target is searched
+fn workspace_files(root: &Path) -> Vec<String> {
+	let output = Command::new("git")
+		.current_dir(root)
+		.args(["ls-files", "--cached", "--others", "--exclude-standard", "-z"])
+		.output();
+
+	match output {
+		Ok(output) if output.status.success() => {
+			let mut files : Vec<String> = String::from_utf8_lossy(&output.stdout)
+				.split('\0')
+				.filter(|path| !path.is_empty())
+				.map(String::from)
+				.collect();
+
+			// files with unmerged changes are listed once per stage
+			files.dedup();
+			files
+		},
+		_ => {
+			let mut files = Vec::new();
+			walk_directory(root, "", &mut files);
+			files
+		}
+	}
+}
+
+fn walk_directory(dir: &Path, prefix: &str, files: &mut Vec<String>) {
+	let Ok(entries) = std::fs::read_dir(dir) else { return };
+
+	let mut entries : Vec<_> = entries.filter_map(|entry| entry.ok()).collect();
+	entries.sort_by_key(|entry| entry.file_name());
+
+	for entry in entries {
+		let name = entry.file_name().to_string_lossy().to_string();
+		let Ok(file_type) = entry.file_type() else { continue };
+
+		let relative = format!("{}{}", prefix, name);
+
+		if file_type.is_dir() {
+			if name.starts_with('.') || name == "target" { continue }
+
+			walk_directory(&entry.path(), format!("{}/", relative).as_str(), files);
+		} else if file_type.is_file() {
+			files.push(relative);
+		}
+	}
+}
+
+fn file_modified(path: &Path) -> Option<SystemTime> {
+	std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+fn read_text_file(path: &Path) -> Option<String> {
+	if std::fs::metadata(path).ok()?.len() > MAX_FILE_BYTES {
+		return None;
+	}
+
+	let bytes = std::fs::read(path).ok()?;
+
+	if bytes.iter().take(BINARY_PROBE_BYTES).any(|byte| *byte == 0) {
+		return None;
+	}
+
+	String::from_utf8(bytes).ok()
+}
+
+#[derive(Clone, Debug)]
+pub struct LineMatch {
+	pub line	: usize,
+	pub ranges	: Vec<Range<usize>>, // byte ranges in line
+}
+
+#[derive(Clone, Debug)]
+pub struct FileMatches {
+	pub path		: PathBuf,
+	pub relative	: String,
+	pub modified	: Option<SystemTime>, // of the file searched on disk, None if unsaved document text was searched
+	pub lines		: BTreeMap<usize, String>, // lines with matches and their context
+	pub matches		: Vec<LineMatch>,
+}
+
+impl FileMatches {
+	fn find(path: PathBuf, relative: String, modified: Option<SystemTime>, text: &str, regex: &Regex, limit: usize) -> Option<Self> {
+		let lines : Vec<&str> = text.lines().collect();
+
+		let mut matches = Vec::new();
+
+		for (index, line) in lines.iter().enumerate() {
+			if matches.len() >= limit { break }
+
+			let ranges : Vec<Range<usize>> = regex.find_iter(line).map(|found| found.range()).filter(|range| !range.is_empty()).collect();
+
+			if !ranges.is_empty() {
+				matches.push(LineMatch { line: index, ranges });
+			}
+		}
+
+		if matches.is_empty() {
+			return None;
+		}
+
+		let mut context = BTreeMap::new();
+
+		for line_match in matches.iter() {
+			let first	= line_match.line.saturating_sub(CONTEXT_LINES);
+			let last	= (line_match.line + CONTEXT_LINES).min(lines.len() - 1);
+
+			for index in first ..= last {
+				context.entry(index).or_insert_with(|| lines[index].to_string());
+			}
+		}
+
+		Some(Self { path, relative, modified, lines: context, matches })
+	}
+
+	pub fn match_at(&self, line: usize) -> Option<&LineMatch> {
+		// matches are sorted by line
+		self.matches.binary_search_by_key(&line, |line_match| line_match.line).ok().map(|index| &self.matches[index])
+	}
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct SearchResults {
+	pub files		: Vec<FileMatches>,
+	pub match_count	: usize, // lines with matches
+	pub truncated	: bool,
+}
+
+// runs on a background thread. Unsaved text of modified documents is searched instead of what is on disk
+pub fn search_workspace(
+	root			: PathBuf,
+	query			: String,
+	options			: SearchOptions,
+	open_documents	: HashMap<PathBuf, String>,
+) -> Result<SearchResults, String> {
+	let regex = build_regex(&query, &options)?;
+
+	let root = root.canonicalize().unwrap_or(root);
+
+	let mut results = SearchResults::default();
+
+	for relative in workspace_files(&root) {
+		if !options.accepts(&relative) { continue }
+
+		if results.match_count >= MAX_MATCHES {
+			results.truncated = true;
+			break;
+		}
+
+		let path = root.join(&relative);
+
+		let disk_text;
+		let (text, modified) = match open_documents.get(&path) {
+			Some(text) => (text.as_str(), None),
+			None => {
+				// looked at before reading so that a write in between makes results outdated
+				let modified = file_modified(&path);
+
+				let Some(text) = read_text_file(&path) else { continue };
+				disk_text = text;
+				(disk_text.as_str(), modified)
+			}
+		};
+
+		let Some(file) = FileMatches::find(path, relative, modified, text, &regex, MAX_MATCHES - results.match_count) else { continue };
+
+		results.match_count += file.matches.len();
+		results.files.push(file);
+	}
+
+	Ok(results)
+}
+
+// line split into unchanged parts and replacements, capture groups are expanded in regex mode.
+// Matches are counted the same way as in search results, the ones that are not included stay as they are
+pub fn replacement_parts(regex: &Regex, line: &str, replacement: &str, expand: bool, included: impl Fn(usize) -> bool) -> Vec<(String, bool)> {
+	let mut parts = Vec::new();
+	let mut last = 0;
+
+	let non_empty = regex.captures_iter(line).filter(|captures| captures.get(0).map_or(false, |found| !found.range().is_empty()));
+
+	for (match_index, captures) in non_empty.enumerate() {
+		let Some(found) = captures.get(0) else { continue };
+		if !included(match_index) { continue }
+
+		parts.push((line[last .. found.start()].to_string(), false));
+
+		let mut replaced = String::new();
+
+		if expand {
+			captures.expand(replacement, &mut replaced);
+		} else {
+			replaced.push_str(replacement);
+		}
+
+		parts.push((replaced, true));
+
+		last = found.end();
+	}
+
+	parts.push((line[last ..].to_string(), false));
+
+	parts
+}
+
+pub fn replace_line(regex: &Regex, line: &str, replacement: &str, expand: bool, included: impl Fn(usize) -> bool) -> String {
+	replacement_parts(regex, line, replacement, expand, included).into_iter().map(|(part, _)| part).collect()
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SegmentStyle {
+	Text,
+	Dim,
+	Header,
+	Button,
+	ButtonOn,
+	Match,
+	Removed,
+	Added,
+	Error,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SearchAction {
+	EditQuery,
+	ToggleRegex,
+	ToggleWholeWord,
+	ToggleCaseSensitive,
+	EditInclude,
+	EditExclude,
+	EditReplacement,
+	ReplaceAll,
+	Undo,
+	Close,
+	OpenFile(usize), // file index in results
+	OpenLine(usize, usize), // file index and line
+	ToggleMatch(usize, usize, usize), // file index, line and match index in line. Includes match into replace all or leaves it out
+}
+
+#[derive(Clone, Debug)]
+pub struct Segment {
+	pub text	: String,
+	pub style	: SegmentStyle,
+	pub action	: Option<SearchAction>,
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct PanelRow {
+	pub segments : Vec<Segment>,
+}
+
+impl PanelRow {
+	fn push(&mut self, text: impl Into<String>, style: SegmentStyle, action: Option<SearchAction>) -> &mut Self {
+		self.segments.push(Segment { text: text.into(), style, action });
+		self
+	}
+
+	pub fn action_at(&self, column: usize) -> Option<SearchAction> {
+		let mut start = 0;
+
+		for segment in self.segments.iter() {
+			let end = start + segment.text.chars().count();
+
+			if (start .. end).contains(&column) {
+				return segment.action;
+			}
+
+			start = end;
+		}
+
+		None
+	}
+}
+
+fn button(label: &str, on: bool) -> (String, SegmentStyle) {
+	(format!("[{}]", label), if on { SegmentStyle::ButtonOn } else { SegmentStyle::Button })
+}
+
+#[derive(Resource, Default)]
+pub struct WorkspaceSearch {
+	pub visible			: bool,
+	pub query			: String,
+	pub replacement		: Option<String>,
+	pub options			: SearchOptions,
+	pub pending_input	: SharedSearchInput,
+
+	pub task			: Option<Task<Result<SearchResults, String>>>,
+	pub results			: Option<SearchResults>,
+	pub error			: Option<String>,
+	pub excluded		: HashSet<(usize, usize, usize)>, // file index, line and match index in line of matches left out of replace all
+	pub undo			: Vec<(DocumentId, usize)>, // documents changed by last replace all and their versions right after it
+	pub searched_versions : HashMap<PathBuf, usize>, // versions of open documents when search was started
+
+	pub rows			: Vec<PanelRow>, // header rows come first
+	pub scroll			: usize, // first visible result row
+	pub visible_rows	: usize,
+}
+
+impl WorkspaceSearch {
+	// returns true if search has to be started again
+	pub fn apply_input(&mut self, field: SearchField, input: String) -> bool {
+		match field {
+			SearchField::Query => {
+				self.query		= input;
+				self.visible	= true;
+			},
+			SearchField::Replacement => {
+				self.replacement = (!input.is_empty()).then_some(input);
+				self.rebuild_rows();
+				return false;
+			},
+			SearchField::Include => self.options.include = parse_globs(&input),
+			SearchField::Exclude => self.options.exclude = parse_globs(&input),
+		}
+
+		true
+	}
+
+	pub fn start_search(&mut self, root: PathBuf, open_documents: HashMap<PathBuf, String>, versions: HashMap<PathBuf, usize>) {
+		self.searched_versions = versions;
+
+		self.results	= None;
+		self.error		= None;
+		self.task		= None;
+		self.scroll		= 0;
+		self.excluded.clear();
+
+		if !self.query.is_empty() {
+			let query	= self.query.clone();
+			let options	= self.options.clone();
+
+			self.task = Some(AsyncComputeTaskPool::get().spawn(async move {
+				search_workspace(root, query, options, open_documents)
+			}));
+		}
+
+		self.rebuild_rows();
+	}
+
+	pub fn finish_search(&mut self, result: Result<SearchResults, String>) {
+		match result {
+			Ok(results) => self.results = Some(results),
+			Err(e) => self.error = Some(e),
+		}
+
+		self.rebuild_rows();
+	}
+
+	pub fn toggle_option(&mut self, action: SearchAction) {
+		match action {
+			SearchAction::ToggleRegex			=> self.options.regex = !self.options.regex,
+			SearchAction::ToggleWholeWord		=> self.options.whole_word = !self.options.whole_word,
+			SearchAction::ToggleCaseSensitive	=> self.options.case_sensitive = !self.options.case_sensitive,
+			_ => (),
+		}
+	}
+
+	pub fn toggle_match(&mut self, file_index: usize, line: usize, match_index: usize) {
+		let key = (file_index, line, match_index);
+
+		if !self.excluded.remove(&key) {
+			self.excluded.insert(key);
+		}
+
+		self.rebuild_rows();
+	}
+
+	pub fn close(&mut self) {
+		self.visible	= false;
+		self.task		= None;
+		self.results	= None;
+		self.error		= None;
+		self.rows.clear();
+	}
+
+	pub fn scroll_by(&mut self, delta: i32) {
+		let max_scroll = self.rows.len().saturating_sub(HEADER_ROWS + self.visible_rows);
+
+		self.scroll = (self.scroll as i64 + delta as i64).clamp(0, max_scroll as i64) as usize;
+	}
+
+	// row on screen to panel row, header rows don't scroll
+	pub fn row_at(&self, row: usize) -> Option<&PanelRow> {
+		if row < HEADER_ROWS {
+			self.rows.get(row)
+		} else {
+			self.rows.get(row + self.scroll)
+		}
+	}
+
+	pub fn rebuild_rows(&mut self) {
+		self.rows.clear();
+
+		let replacing = self.replacement.is_some();
+
+		let mut query_row = PanelRow::default();
+		query_row.push(" SEARCH ", SegmentStyle::Header, None);
+
+		if self.query.is_empty() {
+			query_row.push("<click to enter>", SegmentStyle::Dim, Some(SearchAction::EditQuery));
+		} else {
+			query_row.push(self.query.clone(), SegmentStyle::Text, Some(SearchAction::EditQuery));
+		}
+
+		query_row.push("  ", SegmentStyle::Text, None);
+		query_row.push("[close]", SegmentStyle::Button, Some(SearchAction::Close));
+		self.rows.push(query_row);
+
+		let mut options_row = PanelRow::default();
+		options_row.push(" ", SegmentStyle::Text, None);
+
+		for (label, on, action) in [
+			("regex", self.options.regex, SearchAction::ToggleRegex),
+			("word", self.options.whole_word, SearchAction::ToggleWholeWord),
+			("case", self.options.case_sensitive, SearchAction::ToggleCaseSensitive),
+		] {
+			let (text, style) = button(label, on);
+			options_row.push(text, style, Some(action));
+			options_row.push(" ", SegmentStyle::Text, None);
+		}
+		self.rows.push(options_row);
+
+		let globs_text = |globs: &Vec<String>, empty: &str| if globs.is_empty() { (String::from(empty), SegmentStyle::Dim) } else { (globs.join(", "), SegmentStyle::Text) };
+
+		let (include_text, include_style) = globs_text(&self.options.include, "everything");
+		let (exclude_text, exclude_style) = globs_text(&self.options.exclude, "nothing");
+
+		let mut globs_row = PanelRow::default();
+		globs_row.push(" include: ", SegmentStyle::Dim, None);
+		globs_row.push(include_text, include_style, Some(SearchAction::EditInclude));
+		globs_row.push("  exclude: ", SegmentStyle::Dim, None);
+		globs_row.push(exclude_text, exclude_style, Some(SearchAction::EditExclude));
+		self.rows.push(globs_row);
+
+		let mut replace_row = PanelRow::default();
+		replace_row.push(" replace: ", SegmentStyle::Dim, None);
+
+		match self.replacement.as_ref() {
+			Some(replacement) => replace_row.push(replacement.clone(), SegmentStyle::Text, Some(SearchAction::EditReplacement)),
+			None => replace_row.push("<click to enter>", SegmentStyle::Dim, Some(SearchAction::EditReplacement)),
+		};
+
+		if replacing && self.results.as_ref().map_or(false, |results| results.match_count > 0) {
+			replace_row.push("  ", SegmentStyle::Text, None);
+			replace_row.push("[replace all]", SegmentStyle::Button, Some(SearchAction::ReplaceAll));
+		}
+
+		if !self.undo.is_empty() {
+			replace_row.push("  ", SegmentStyle::Text, None);
+			replace_row.push("[undo]", SegmentStyle::Button, Some(SearchAction::Undo));
+		}
+		self.rows.push(replace_row);
+
+		let mut summary_row = PanelRow::default();
+
+		match (&self.results, &self.error) {
+			(_, Some(error)) => { summary_row.push(format!(" {}", error), SegmentStyle::Error, None); },
+			(Some(results), _) => {
+				let excluded = if replacing && !self.excluded.is_empty() { format!(", {} excluded from replace", self.excluded.len()) } else { String::new() };
+				let truncated = if results.truncated { format!(" (stopped at {})", MAX_MATCHES) } else { String::new() };
+
+				summary_row.push(format!(" {} matching lines in {} files{}{}", results.match_count, results.files.len(), truncated, excluded), SegmentStyle::Dim, None);
+			},
+			_ if self.task.is_some() => { summary_row.push(" searching...", SegmentStyle::Dim, None); },
+			_ => (),
+		}
+		self.rows.push(summary_row);
+
+		let Some(results) = self.results.as_ref() else { return };
+
+		// regex was built successfully for this query already
+		let Ok(regex) = build_regex(&self.query, &self.options) else { return };
+
+		let number_width = results.files.iter()
+			.filter_map(|file| file.lines.keys().last())
+			.max()
+			.map_or(1, |line| (line + 1).to_string().len());
+
+		for (file_index, file) in results.files.iter().enumerate() {
+			let mut file_row = PanelRow::default();
+			file_row.push(format!(" {}", file.relative), SegmentStyle::Header, Some(SearchAction::OpenFile(file_index)));
+			file_row.push(format!(" ({})", file.matches.len()), SegmentStyle::Dim, Some(SearchAction::OpenFile(file_index)));
+			self.rows.push(file_row);
+
+			let mut previous_line = None;
+
+			for (line, text) in file.lines.iter() {
+				// gap between groups of context lines
+				if previous_line.map_or(false, |previous| previous + 1 != *line) {
+					let mut gap_row = PanelRow::default();
+					gap_row.push(format!("{:>width$}", "⋯", width = number_width + 3), SegmentStyle::Dim, None);
+					self.rows.push(gap_row);
+				}
+
+				previous_line = Some(*line);
+
+				let action = Some(SearchAction::OpenLine(file_index, *line));
+				let number = format!("  {:>width$} ", line + 1, width = number_width);
+
+				let mut row = PanelRow::default();
+
+				let Some(line_match) = file.match_at(*line) else {
+					row.push(number, SegmentStyle::Dim, action);
+					row.push(expand_tabs(text), SegmentStyle::Dim, action);
+					self.rows.push(row);
+					continue;
+				};
+
+				let included = |match_index: usize| !self.excluded.contains(&(file_index, *line, match_index));
+
+				row.push(number, SegmentStyle::Dim, action);
+
+				let mut last = 0;
+
+				// while replacing clicking a match leaves it out of replace all or brings it back
+				for (match_index, range) in line_match.ranges.iter().enumerate() {
+					let (match_style, match_action) = match (replacing, included(match_index)) {
+						(true, true)	=> (SegmentStyle::Removed, Some(SearchAction::ToggleMatch(file_index, *line, match_index))),
+						(true, false)	=> (SegmentStyle::Match, Some(SearchAction::ToggleMatch(file_index, *line, match_index))),
+						_				=> (SegmentStyle::Match, action),
+					};
+
+					row.push(expand_tabs(&text[last .. range.start]), SegmentStyle::Text, action);
+					row.push(expand_tabs(&text[range.clone()]), match_style, match_action);
+					last = range.end;
+				}
+
+				row.push(expand_tabs(&text[last ..]), SegmentStyle::Text, action);
+				self.rows.push(row);
+
+				// what the line turns into after replace all
+				let Some(replacement) = self.replacement.as_ref() else { continue };
+
+				if !(0 .. line_match.ranges.len()).any(included) { continue }
+
+				let mut preview_row = PanelRow::default();
+				preview_row.push(format!("{:>width$}", "→ ", width = number_width + 7), SegmentStyle::Dim, action);
+
+				for (part, replaced) in replacement_parts(&regex, text, replacement, self.options.regex, included) {
+					preview_row.push(expand_tabs(&part), if replaced { SegmentStyle::Added } else { SegmentStyle::Text }, action);
+				}
+
+				self.rows.push(preview_row);
+			}
+		}
+	}
+
+	// results are outdated if a document was edited since search started or a file searched on disk was written since.
+	// Files are not read again here, only their modification time is looked at
+	fn is_outdated(&self, file: &FileMatches, editor: &Editor) -> bool {
+		let disk_changed = || file.modified.is_none() || file_modified(&file.path) != file.modified;
+
+		match editor.document_by_path(&file.path) {
+			Some(doc) => match self.searched_versions.get(&file.path) {
+				Some(version) => *version != doc.version(),
+				None => doc.is_modified() || disk_changed(), // opened after search was started, searched text came from disk
+			},
+			// unsaved text of a document that is closed now can't be replaced
+			None => disk_changed(),
+		}
+	}
+
+	// included matches are replaced line by line in document text, files that are not open yet are opened in the editor first.
+	// Nothing is replaced if any of the results are outdated. Documents are left modified, saving them is up to user
+	pub fn replace_all(&mut self, editor: &mut Editor) -> Result<usize, String> {
+		let Some(replacement) = self.replacement.clone() else { return Ok(0) };
+		let Some(results) = self.results.as_ref() else { return Ok(0) };
+
+		let regex = build_regex(&self.query, &self.options)?;
+
+		let included = |file_index: usize, line: usize, match_index: usize| !self.excluded.contains(&(file_index, line, match_index));
+
+		let included_count = |file_index: usize, line_match: &LineMatch| -> usize {
+			(0 .. line_match.ranges.len()).filter(|match_index| included(file_index, line_match.line, *match_index)).count()
+		};
+
+		// lines with at least one included match
+		let included_lines = |file_index: usize, file: &FileMatches| -> HashSet<usize> {
+			file.matches.iter()
+				.filter(|line_match| included_count(file_index, line_match) > 0)
+				.map(|line_match| line_match.line)
+				.collect()
+		};
+
+		let mut undo = Vec::new();
+		let mut replaced = 0;
+
+		let files : Vec<_> = results.files.iter().enumerate().filter(|(file_index, file)| !included_lines(*file_index, file).is_empty()).collect();
+
+		if files.iter().any(|(_, file)| self.is_outdated(file, editor)) {
+			return Err(String::from("files changed since search, results are updated, review them and replace again"));
+		}
+
+		for (file_index, file) in files {
+			let lines = included_lines(file_index, file);
+
+			replaced += file.matches.iter().map(|line_match| included_count(file_index, line_match)).sum::<usize>();
+
+			let doc_id = match editor.document_by_path(&file.path) {
+				Some(doc) => doc.id(),
+				None => editor.open(&file.path, Action::Load).map_err(|e| format!("{}: {}", file.relative, e))?,
+			};
+
+			let Some(doc) = editor.documents.get(&doc_id) else { continue };
+
+			let text = doc.text().to_string();
+			let mut new_text = String::with_capacity(text.len());
+
+			for (index, line) in text.split_inclusive('\n').enumerate() {
+				if !lines.contains(&index) {
+					new_text.push_str(line);
+					continue;
+				}
+
+				let content = line.trim_end_matches(&['\r', '\n']);
+
+				let new_line = replace_line(&regex, content, &replacement, self.options.regex, |match_index| included(file_index, index, match_index));
+
+				new_text.push_str(&new_line);
+				new_text.push_str(&line[content.len() ..]);
+			}
+
+			if new_text == text { continue }
+
+			if replace_document_text(editor, doc_id, &new_text) {
+				let Some(doc) = editor.documents.get(&doc_id) else { continue };
+				undo.push((doc_id, doc.version()));
+			}
+		}
+
+		self.undo = undo;
+
+		Ok(replaced)
+	}
+
+	// documents edited after replace all are skipped, undoing them would revert those edits instead
+	pub fn undo_replace(&mut self, editor: &mut Editor) -> (usize, usize) {
+		let mut undone	= 0;
+		let mut skipped	= 0;
+
+		for (doc_id, version) in std::mem::take(&mut self.undo) {
+			let view_id = editor.tree.views().find(|(view, _)| view.doc == doc_id).map_or(editor.tree.focus, |(view, _)| view.id);
+
+			let Some(doc) = editor.documents.get_mut(&doc_id) else { continue };
+
+			if doc.version() != version {
+				skipped += 1;
+				continue;
+			}
+
+			if doc.undo(editor.tree.get_mut(view_id)) {
+				undone += 1;
+			}
+		}
+
+		(undone, skipped)
+	}
+
+	// panel covers the right side of focused view, whole view if it is too narrow
+	pub fn update_surface(
+		&mut self,
+		surfaces_helix	: &mut SurfacesMapHelix,
+		app				: &HelixApp,
+	) {
+		let Some(surface_editor) = surfaces_helix.get(EditorView::ID) else { return };
+		let view_area = app.views().iter().find(|view_desc| view_desc.focused).map_or(surface_editor.area, |view_desc| view_desc.area);
+
+		if !self.visible || self.rows.is_empty() || view_area.height as usize <= HEADER_ROWS {
+			surfaces_helix.remove(SEARCH_PANEL_SURFACE_NAME);
+			return;
+		}
+
+		let width	= if view_area.width >= PANEL_COLUMNS * 2 { PANEL_COLUMNS } else { view_area.width };
+		let area	= Rect::new(view_area.right() - width, view_area.y, width, view_area.height);
+
+		self.visible_rows = area.height as usize - HEADER_ROWS;
+		self.scroll_by(0);
+
+		let surface = surfaces_helix.entry(String::from(SEARCH_PANEL_SURFACE_NAME)).or_insert_with(|| {
+			SurfaceHelix::empty_with_spatial(area, SurfaceFlags::default())
+		});
+
+		if surface.area != area {
+			surface.resize(area);
+		}
+
+		surface.placement = SurfacePlacement::AreaCoordinates;
+		surface.reset();
+
+		let styles = PanelStyles::new(&app.editor.theme);
+
+		surface.set_style(area, styles.background);
+
+		let rows = self.rows.iter().take(HEADER_ROWS).chain(self.rows.iter().skip(HEADER_ROWS + self.scroll));
+
+		for (index, row) in rows.take(area.height as usize).enumerate() {
+			let y = area.y + index as u16;
+			let mut x = area.x;
+
+			for segment in row.segments.iter() {
+				let left = area.right().saturating_sub(x) as usize;
+				if left == 0 { break }
+
+				(x, _) = surface.set_stringn(x, y, segment.text.as_str(), left, styles.get(segment.style));
+			}
+		}
+	}
+}
+
+fn expand_tabs(text: &str) -> String {
+	text.replace('\t', "    ")
+}
+
+struct PanelStyles {
+	background	: Style,
+	text		: Style,
+	dim			: Style,
+	header		: Style,
+	button		: Style,
+	button_on	: Style,
+	found		: Style,
+	removed		: Style,
+	added		: Style,
+	error		: Style,
+}
+
+impl PanelStyles {
+	fn new(theme: &Theme) -> Self {
+		let background	= theme.get("ui.background").patch(theme.get("ui.popup"));
+		let text		= background.patch(theme.get("ui.text"));
+
+		Self {
+			background,
+			text,
+			dim			: background.patch(theme.get("ui.linenr")),
+			header		: background.patch(theme.get("ui.text.focus")),
+			button		: text.patch(theme.get("ui.menu")),
+			button_on	: text.patch(theme.get("ui.menu.selected")),
+			found		: text.patch(theme.get("ui.selection")),
+			removed		: text.patch(tinted_style(theme, "diff.minus", 0.5)),
+			added		: text.patch(tinted_style(theme, "diff.plus", 0.5)),
+			error		: background.patch(theme.get("error")),
+		}
+	}
+
+	fn get(&self, style: SegmentStyle) -> Style {
+		match style {
+			SegmentStyle::Text		=> self.text,
+			SegmentStyle::Dim		=> self.dim,
+			SegmentStyle::Header	=> self.header,
+			SegmentStyle::Button	=> self.button,
+			SegmentStyle::ButtonOn	=> self.button_on,
+			SegmentStyle::Match		=> self.found,
+			SegmentStyle::Removed	=> self.removed,
+			SegmentStyle::Added		=> self.added,
+			SegmentStyle::Error		=> self.error,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super :: *;
+
+	#[test]
+	fn glob_without_slash_matches_any_component() {
+		assert!(glob_matches_path("*.rs", "src/bevy_helix/mod.rs"));
+		assert!(glob_matches_path("target", "target/debug/kodiki"));
+		assert!(glob_matches_path("mod.?s", "src/mod.rs"));
+		assert!(!glob_matches_path("*.rs", "src/main.rsx"));
+	}
+
+	#[test]
+	fn glob_with_slash_matches_path_or_its_directory() {
+		assert!(glob_matches_path("src/*.rs", "src/main.rs"));
+		assert!(!glob_matches_path("src/*.rs", "src/bevy_helix/mod.rs"));
+		assert!(glob_matches_path("src/**/*.rs", "src/bevy_helix/mod.rs"));
+		assert!(glob_matches_path("src/**/*.rs", "src/main.rs"));
+		assert!(glob_matches_path("assets/fonts/", "assets/fonts/FiraCode.ttf"));
+		assert!(!glob_matches_path("assets/fonts", "assets/fontsextra/a.ttf"));
+	}
+
+	#[test]
+	fn search_options_accept() {
+		let options = SearchOptions {
+			include : parse_globs("*.rs, *.toml"),
+			exclude : parse_globs("target,"),
+			..default()
+		};
+
+		assert!(options.accepts("src/main.rs"));
+		assert!(options.accepts("Cargo.toml"));
+		assert!(!options.accepts("README.md"));
+		assert!(!options.accepts("target/build/out.rs"));
+	}
+
+	#[test]
+	fn replacement_of_included_matches_only() {
+		let regex = build_regex("foo", &SearchOptions::default()).unwrap();
+
+		assert_eq!(replace_line(&regex, "foo bar foo", "baz", false, |_| true), "baz bar baz");
+		assert_eq!(replace_line(&regex, "foo bar foo", "baz", false, |match_index| match_index == 1), "foo bar baz");
+		assert_eq!(replace_line(&regex, "foo bar foo", "baz", false, |_| false), "foo bar foo");
+	}
+
+	#[test]
+	fn replacement_expands_captures_in_regex_mode() {
+		let options = SearchOptions { regex: true, case_sensitive: true, ..default() };
+		let regex = build_regex(r"(\w+)\.unwrap\(\)", &options).unwrap();
+
+		assert_eq!(replace_line(&regex, "let a = b.unwrap();", "$1?", true, |_| true), "let a = b?;");
+		assert_eq!(replace_line(&regex, "let a = b.unwrap();", "$1?", false, |_| true), "let a = $1?;");
+	}
+}
@@ -0,0 +1,228 @@
+use bevy :: prelude :: *;
+use bevy :: input :: mouse :: { MouseWheel, MouseScrollUnit };
+
+#[cfg(feature = "tracing")]
+use bevy_puffin :: *;
+
+use futures_lite :: future;
+
+use super :: *;
+
+use crate :: {
+	kodiki_ui :: { DraggingState, raypick :: Raypick },
+	bevy_ab_glyph :: { ABGlyphFont, FontAssetHandles },
+	bevy_helix :: {
+		TokioRuntime,
+		surface :: { SurfacesMapBevy, WordDescription },
+		systems_util :: hovered_surface_name,
+	},
+};
+
+// unsaved text of modified documents is what gets searched for them
+fn restart_search(search: &mut WorkspaceSearch, editor: &Editor) {
+	let open_documents = editor.documents()
+		.filter(|doc| doc.is_modified())
+		.filter_map(|doc| Some((doc.path()?.clone(), doc.text().to_string())))
+		.collect();
+
+	// replace all refuses to touch documents edited after this
+	let versions = editor.documents()
+		.filter_map(|doc| Some((doc.path()?.clone(), doc.version())))
+		.collect();
+
+	let root = std::env::current_dir().unwrap_or_default();
+
+	search.start_search(root, open_documents, versions);
+}
+
+// ctrl+shift+f asks what to search for in the whole workspace, ctrl+shift+h asks for replacement
+pub fn input_keyboard(
+		key			: Res<Input<KeyCode>>,
+		search		: Res<WorkspaceSearch>,
+		app_option	: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() || !app.editor_focused() { return }
+
+	let ctrl_pressed	= key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl);
+	let shift_pressed	= key.pressed(KeyCode::LShift) || key.pressed(KeyCode::RShift);
+
+	if !ctrl_pressed || !shift_pressed { return }
+
+	if key.just_pressed(KeyCode::F) {
+		app.prompt_search_field(SearchField::Query, search.pending_input.clone());
+	} else if key.just_pressed(KeyCode::H) {
+		app.prompt_search_field(SearchField::Replacement, search.pending_input.clone());
+	}
+}
+
+// header rows edit query, options and globs, result rows open files. With replacement set clicking a match leaves it out of replace all
+pub fn input_mouse(
+		mouse_button	: Res<Input<MouseButton>>,
+		raypick			: Res<Raypick>,
+		surfaces		: Res<SurfacesMapBevy>,
+		q_word			: Query<&WordDescription>,
+		q_transform		: Query<&GlobalTransform>,
+		font_assets		: Res<Assets<ABGlyphFont>>,
+		font_handles	: Res<FontAssetHandles>,
+	mut search			: ResMut<WorkspaceSearch>,
+		dragging_state	: Res<DraggingState>,
+		tokio_runtime	: Res<TokioRuntime>,
+		app_option		: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() || dragging_state.is_active() || !search.visible { return }
+
+	if !mouse_button.just_pressed(MouseButton::Left) { return }
+
+	let Some(hovered_entity) = raypick.last_hover else { return };
+	let Some(surface_name) = hovered_surface_name(hovered_entity, &q_word, &surfaces) else { return };
+
+	if surface_name != SEARCH_PANEL_SURFACE_NAME { return }
+
+	profile_function!();
+
+	let Some(surface_panel) = surfaces.get(surface_name) else { return };
+	let Ok(surface_transform) = q_transform.get(surface_panel.entity) else { return };
+
+	let font = font_assets.get(&font_handles.main).unwrap();
+	let row_height		= font.vertical_advance();
+	let column_width	= font.horizontal_advance_mono();
+
+	// world space to surface space
+	let cursor_position_world	= raypick.ray_pos + raypick.ray_dir * raypick.ray_dist;
+	let cursor_position_surface	= surface_transform.compute_matrix().inverse().transform_point3(cursor_position_world);
+
+	let row = (cursor_position_surface.y.abs() / row_height) - surface_panel.scroll_info.offset as f32;
+	if row < 0.0 || cursor_position_surface.x < 0.0 {
+		return;
+	}
+
+	let column = (cursor_position_surface.x / column_width) as usize;
+
+	let Some(action) = search.row_at(row as usize).and_then(|panel_row| panel_row.action_at(column)) else { return };
+
+	let pending = search.pending_input.clone();
+
+	match action {
+		SearchAction::EditQuery			=> app.prompt_search_field(SearchField::Query, pending),
+		SearchAction::EditReplacement	=> app.prompt_search_field(SearchField::Replacement, pending),
+		SearchAction::EditInclude		=> app.prompt_search_field(SearchField::Include, pending),
+		SearchAction::EditExclude		=> app.prompt_search_field(SearchField::Exclude, pending),
+		SearchAction::ToggleRegex | SearchAction::ToggleWholeWord | SearchAction::ToggleCaseSensitive => {
+			search.toggle_option(action);
+			restart_search(&mut search, &app.editor);
+		},
+		SearchAction::ReplaceAll => {
+			match search.replace_all(&mut app.editor) {
+				Ok(replaced) => {
+					let files = search.undo.len();
+					app.editor.set_status(format!("Replaced {} matches in {} files, documents are left unsaved", replaced, files));
+				},
+				Err(e) => app.editor.set_error(format!("Replace failed: {}", e)),
+			}
+
+			restart_search(&mut search, &app.editor);
+		},
+		SearchAction::Undo => {
+			let (undone, skipped) = search.undo_replace(&mut app.editor);
+
+			if skipped > 0 {
+				app.editor.set_error(format!("Undid replace in {} files, {} were edited after it and left as they are", undone, skipped));
+			} else {
+				app.editor.set_status(format!("Undid replace in {} files", undone));
+			}
+
+			restart_search(&mut search, &app.editor);
+		},
+		SearchAction::Close => search.close(),
+		SearchAction::OpenFile(file_index) => {
+			let Some(file) = search.results.as_ref().and_then(|results| results.files.get(file_index)) else { return };
+			let line = file.matches.first().map(|line_match| line_match.line);
+
+			tokio_runtime.block_on(app.jump_to_path(&file.path, line, None));
+		},
+		SearchAction::OpenLine(file_index, line) => {
+			let Some(file) = search.results.as_ref().and_then(|results| results.files.get(file_index)) else { return };
+			let column = file.match_at(line).and_then(|line_match| line_match.ranges.first()).map(|range| range.start);
+
+			tokio_runtime.block_on(app.jump_to_path(&file.path, Some(line), column));
+		},
+		SearchAction::ToggleMatch(file_index, line, match_index) => search.toggle_match(file_index, line, match_index),
+	}
+
+	app.request_render();
+}
+
+// wheel over panel scrolls results, Helix doesn't get those events
+pub fn input_scroll(
+	mut scroll_events	: EventReader<MouseWheel>,
+		raypick			: Res<Raypick>,
+		surfaces		: Res<SurfacesMapBevy>,
+		q_word			: Query<&WordDescription>,
+	mut search			: ResMut<WorkspaceSearch>,
+		app_option		: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	let hovered = raypick.last_hover
+		.and_then(|entity| hovered_surface_name(entity, &q_word, &surfaces))
+		.map_or(false, |name| name == SEARCH_PANEL_SURFACE_NAME);
+
+	if !search.visible || !hovered {
+		scroll_events.clear();
+		return;
+	}
+
+	let mut delta = 0;
+
+	for scroll_event in scroll_events.iter() {
+		let steps = match scroll_event.unit {
+			MouseScrollUnit::Line	=> scroll_event.y.signum() as i32,
+			MouseScrollUnit::Pixel	=> (scroll_event.y / 50.0) as i32,
+		};
+
+		delta -= steps * WHEEL_ROWS;
+	}
+
+	if delta == 0 { return }
+
+	search.scroll_by(delta);
+
+	app.request_render();
+}
+
+// applies what was entered in prompts and picks up finished search
+pub fn update(
+	mut search		: ResMut<WorkspaceSearch>,
+		app_option	: Option<NonSendMut<HelixApp>>,
+) {
+	let Some(mut app) = app_option else { return };
+
+	if app.should_close() { return }
+
+	let input = search.pending_input.lock().unwrap().take();
+
+	if let Some((field, input)) = input {
+		if search.apply_input(field, input) {
+			restart_search(&mut search, &app.editor);
+		}
+
+		app.request_render();
+	}
+
+	let Some(mut task) = search.task.take() else { return };
+
+	let Some(result) = future::block_on(future::poll_once(&mut task)) else {
+		search.task = Some(task);
+		return;
+	};
+
+	profile_function!();
+
+	search.finish_search(result);
+
+	app.request_render();
+}
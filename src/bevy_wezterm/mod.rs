@@ -3,10 +3,11 @@ use bevy :: input :: keyboard::KeyboardInput;
 use bevy :: gltf :: Gltf;
 
 use bevy_reader_camera :: TextDescriptor;
+use bevy_tweening :: { component_animator_system, AnimationSystem };
 
 use crate :: {
 	z_order,
-	kodiki :: { AppContext, AppMode },
+	kodiki :: { AppContext, AppMode, DockPanel },
 	bevy_ab_glyph :: { ABGlyphFont, ABGlyphFonts, FontAssetHandles },
 	kodiki_ui :: {
 		KodikiUISystems,
@@ -30,6 +31,8 @@ mod systems;
 
 mod key_code;
 
+mod tween_lens;
+
 use wezterm_portable :: {
 	terminalstate :: { TerminalState as WezTermState, TerminalSize },
 	color :: ColorPalette,
@@ -49,6 +52,13 @@ impl wezterm_portable::config::TerminalConfiguration for WezTermLiteConfiguratio
 #[derive(Component)]
 pub struct GotoPathHighlight;
 
+// 0.0 - terminal is hidden above the top of the screen, 1.0 - terminal is fully slid down
+#[derive(Component, Default)]
+pub struct TerminalOverlaySlide {
+	pub progress	: f32,
+	pub target		: bool,
+}
+
 #[derive(Component)]
 pub struct BevyWezTerm {
 	wez_state			: WezTermState,
@@ -133,7 +143,8 @@ impl BevyWezTerm {
 				Transform::from_translation(translation.unwrap_or(Vec3::ZERO))
 			),
 			VisibilityBundle::default(),
-			RaypickHover::default()
+			RaypickHover::default(),
+			DockPanel::default(),
 		)).id();
 
 		commands.entity(terminal_entity).push_children(&[cursor_entity, background_entity, resizer_entity]);
@@ -227,7 +238,8 @@ impl BevyWezTerm {
 		&mut self,
 		keyboard_input: &KeyboardInput,
 		input_key: &Input<KeyCodeBevy>,
-		is_down: bool
+		is_down: bool,
+		docked: bool,
 	) -> anyhow::Result<()> {
 		if let Some(key_code_bevy) = keyboard_input.key_code {
 			// ignore modifier only key codes
@@ -243,6 +255,13 @@ impl BevyWezTerm {
 				match key_code_bevy {
 					KeyCode::Key1 | KeyCode::Key2 | KeyCode::Key3 | KeyCode::Key4 | KeyCode::Key5 |
 					KeyCode::Key6 | KeyCode::Key7 | KeyCode::Key8 | KeyCode::Key9 | KeyCode::Key0 => return Ok(()),
+					// ctrl+` toggles drop-down terminal
+					KeyCode::Grave => return Ok(()),
+					// ctrl+\ and ctrl+tab manage docking while terminal is docked
+					KeyCode::Backslash | KeyCode::Tab if docked => return Ok(()),
+					// ctrl+alt+pageup/pagedown reorder contexts
+					KeyCode::PageUp | KeyCode::PageDown
+					if input_key.pressed(KeyCode::LAlt) || input_key.pressed(KeyCode::RAlt) => return Ok(()),
 					_ => (),
 				}
 			}
@@ -275,6 +294,10 @@ impl BevyWezTerm {
 		self.active
 	}
 
+	pub fn set_active(&mut self, active: bool) {
+		self.active = active;
+	}
+
 	pub fn state_changed(&self) -> bool {
 		self.state_changed
 	}
@@ -312,7 +335,7 @@ impl Plugin for BevyWezTermPlugin {
 					systems::update_resizer,
 					systems::update_background_color,
 					systems::update_cursor,
-					systems::keyboard,
+					systems::keyboard.run_if(run_condition::terminal_focused),
 					systems::mouse,
 					systems::mouse_goto_path,
 
@@ -320,7 +343,22 @@ impl Plugin for BevyWezTermPlugin {
 				)
 				.chain()
 				.before(KodikiUISystems)
-				.in_set(OnUpdate(AppContext::Terminal))
+				.distributive_run_if(run_condition::terminal_visible)
+			)
+
+			// drop-down or docked terminal over code editor
+			.add_systems(
+				(
+					systems::update_overlay_slide,
+					systems::update_overlay_transform,
+				)
+				.chain()
+				.before(KodikiUISystems)
+				.distributive_run_if(run_condition::text_editor_context)
+			)
+
+			.add_system(
+				component_animator_system::<TerminalOverlaySlide>.in_set(AnimationSystem::AnimationUpdate)
 			)
 
 			.add_system(
@@ -22,8 +22,9 @@ use wezterm_portable :: {
 };
 
 use super :: *;
+use super :: tween_lens :: TerminalOverlaySlideLens;
 
-use crate :: kodiki :: DespawnResource;
+use crate :: kodiki :: { DespawnResource, TerminalOverlay, DockLayout, DockSide, DockFocus, DockPanel };
 use crate :: kodiki_ui :: {
 	ColorMaterialsCache,
 	WordSubEntities,
@@ -201,10 +202,12 @@ pub fn update_text_surface(
 }
 
 pub fn update_resizer(
-	mut	q_terminal_surface	: Query<(Entity, &mut BevyWezTerm, &mut TextSurface)>,
-	mut q_resizer			: Query<(&mut Resizer, &mut Transform)>,
+	mut	q_terminal_surface	: Query<(Entity, &mut BevyWezTerm, &mut TextSurface, &DockPanel)>,
+	mut q_resizer			: Query<(&mut Resizer, &mut Transform, &mut Visibility)>,
 	mut q_bg_quad			: Query<&mut TextBackgroundQuad>,
 		q_reader_camera		: Query<&ReaderCamera>,
+		terminal_overlay	: Res<TerminalOverlay>,
+		dock_layout			: Res<DockLayout>,
 		font_assets			: Res<Assets<ABGlyphFont>>,
 		font_handles		: Res<FontAssetHandles>,
 	mut entities_to_despawn	: ResMut<DespawnResource>,
@@ -219,11 +222,34 @@ pub fn update_resizer(
 	let column_width	= fonts.main.horizontal_advance_mono();
 	let row_height		= fonts.main.vertical_advance();
 
-	for (terminal_entity, mut terminal, mut text_surface) in q_terminal_surface.iter_mut() {
-		let Ok((mut resizer, mut resizer_transform)) = q_resizer.get_mut(terminal.resizer_entity) else { continue };
+	for (terminal_entity, mut terminal, mut text_surface, dock_panel) in q_terminal_surface.iter_mut() {
+		let Ok((mut resizer, mut resizer_transform, mut resizer_visibility)) = q_resizer.get_mut(terminal.resizer_entity) else { continue };
 
-		let rows = camera.visible_rows.floor() as usize;
-		let cols = resizer.area.x as usize;
+		let docked = dock_layout.is_docked_panel(terminal_entity);
+
+		// drop-down terminal covers only a part of the screen, docked one takes the area given by dock layout
+		let (rows, cols) = if terminal_overlay.active {
+			(((camera.visible_rows * terminal_overlay.height_ratio).floor() as usize).max(1), resizer.area.x as usize)
+		} else if docked && dock_panel.columns > 0 {
+			(dock_panel.rows, dock_panel.columns)
+		} else {
+			(camera.visible_rows.floor() as usize, resizer.area.x as usize)
+		};
+
+		// docked terminal is resized with the split resizer of dock layout
+		let resizer_visibility_new = if docked { Visibility::Hidden } else { Visibility::Inherited };
+		if *resizer_visibility != resizer_visibility_new {
+			*resizer_visibility = resizer_visibility_new;
+		}
+
+		if let Some(bg_entity) = text_surface.background_entity {
+			if let Ok(mut bg_quad) = q_bg_quad.get_mut(bg_entity) {
+				let fill_vertically = !terminal_overlay.active && !(docked && (dock_layout.side == DockSide::Bottom || dock_layout.panels.len() > 1));
+				if bg_quad.fill_vertically != fill_vertically {
+					bg_quad.fill_vertically = fill_vertically;
+				}
+			}
+		}
 
 		if terminal.resize(rows, cols) {
 			if let Some(bg_entity) = text_surface.background_entity {
@@ -254,20 +280,27 @@ pub fn update_resizer(
 }
 
 pub fn update_background_color(
-		q_terminal_surface	: Query<(&TextSurface, &BevyWezTerm)>,
+		q_terminal_surface	: Query<(&TextSurface, &BevyWezTerm, Option<&TerminalOverlaySlide>)>,
 	mut q_bg_quad			: Query<&mut TextBackgroundQuad>,
+		terminal_overlay	: Res<TerminalOverlay>,
 ) {
 	profile_function!();
 
-	for (text_surface, terminal) in q_terminal_surface.iter() {
+	for (text_surface, terminal, overlay_slide) in q_terminal_surface.iter() {
 		let srgba = terminal.wez_state.get_config().color_palette().background;
-		let background_color = Color::Rgba { red: srgba.0, green: srgba.1, blue: srgba.2, alpha: srgba.3 };
+		// code editor stays slightly visible through drop-down terminal
+		let (alpha, alpha_mode) = if overlay_slide.is_some() { (terminal_overlay.background_alpha, AlphaMode::Blend) } else { (srgba.3, AlphaMode::Opaque) };
+		let background_color = Color::Rgba { red: srgba.0, green: srgba.1, blue: srgba.2, alpha };
 
 		if let Some(bg_entity) = text_surface.background_entity {
 			if let Ok(mut bg_quad) = q_bg_quad.get_mut(bg_entity) {
 				if bg_quad.color != Some(background_color) {
 					bg_quad.color = Some(background_color);
 				}
+
+				if bg_quad.alpha_mode != alpha_mode {
+					bg_quad.alpha_mode = alpha_mode;
+				}
 			}
 		}
 	}
@@ -316,6 +349,7 @@ pub fn on_context_switch_in(
 	mut q_window_primary: Query<&mut Window, With<PrimaryWindow>>,
 		font_assets		: Res<Assets<ABGlyphFont>>,
 		font_handles	: Res<FontAssetHandles>,
+	mut commands		: Commands,
 ) {
 	let fonts = ABGlyphFonts::new(&font_assets, &font_handles);
 
@@ -339,6 +373,9 @@ pub fn on_context_switch_in(
 
 		reader_camera.target_entity = Some(terminal_entity);
 
+		// terminal could be in drop-down mode before switching
+		commands.entity(terminal_entity).remove::<(TerminalOverlaySlide, Animator<TerminalOverlaySlide>)>();
+
 		*visibility.as_mut() = Visibility::Visible;
 
 		let x = camera_transform.translation.x + (-column_width * (terminal.wez_state.screen().physical_cols as f32 / 2.0));
@@ -354,18 +391,113 @@ pub fn on_context_switch_in(
 	}
 }
 
+pub fn update_overlay_slide(
+	mut q_terminal		: Query<(Entity, &BevyWezTerm, &mut Visibility, Option<&TerminalOverlaySlide>)>,
+		terminal_overlay: Res<TerminalOverlay>,
+	mut commands		: Commands,
+) {
+	for (terminal_entity, terminal, mut visibility, overlay_slide) in q_terminal.iter_mut() {
+		if !terminal.active {
+			continue;
+		}
+
+		let (progress, target) = match overlay_slide {
+			Some(slide) => (slide.progress, slide.target),
+			None if terminal_overlay.active => (0.0, false),
+			None => continue,
+		};
+
+		if target == terminal_overlay.active {
+			continue;
+		}
+
+		if terminal_overlay.active {
+			*visibility.as_mut() = Visibility::Visible;
+		}
+
+		let end = if terminal_overlay.active { 1.0 } else { 0.0 };
+		let slide_duration = ((end - progress).abs() * 250.0).max(1.0) as u64;
+
+		let tween = Tween::new(
+			EaseFunction::QuadraticInOut,
+			Duration::from_millis(slide_duration),
+			TerminalOverlaySlideLens {
+				start	: progress,
+				end
+			}
+		);
+
+		commands.entity(terminal_entity).insert((
+			TerminalOverlaySlide { progress, target: terminal_overlay.active },
+			Animator::new(tween),
+		));
+	}
+}
+
+pub fn update_overlay_transform(
+	mut q_terminal		: Query<(Entity, &BevyWezTerm, &TerminalOverlaySlide, &Animator<TerminalOverlaySlide>, &mut Visibility, &mut Transform), Without<ReaderCamera>>,
+		q_camera		: Query<(&ReaderCamera, &Transform), Without<BevyWezTerm>>,
+		font_assets		: Res<Assets<ABGlyphFont>>,
+		font_handles	: Res<FontAssetHandles>,
+	mut framerate_manager : ResMut<FramerateManager>,
+	mut commands		: Commands,
+) {
+	profile_function!();
+
+	let fonts = ABGlyphFonts::new(&font_assets, &font_handles);
+
+	let column_width	= fonts.main.horizontal_advance_mono();
+	let row_height		= fonts.main.vertical_advance();
+
+	let Ok((reader_camera, camera_transform)) = q_camera.get_single() else { return };
+
+	for (terminal_entity, terminal, overlay_slide, animator, mut visibility, mut transform) in q_terminal.iter_mut() {
+		let animation_finished = animator.tweenable().progress() >= 1.0;
+
+		if !animation_finished {
+			framerate_manager.request_active_framerate(format!("active TerminalOverlaySlide animator {:.2}%", animator.tweenable().progress() * 100.));
+		}
+
+		// slide out is over, terminal is fully hidden
+		if animation_finished && !overlay_slide.target {
+			*visibility.as_mut() = Visibility::Hidden;
+			commands.entity(terminal_entity).remove::<(TerminalOverlaySlide, Animator<TerminalOverlaySlide>)>();
+			continue;
+		}
+
+		let screen		= terminal.wez_state.screen();
+		let height		= screen.physical_rows as f32 * row_height;
+
+		let x = camera_transform.translation.x + (-column_width * (screen.physical_cols as f32 / 2.0));
+		let y = camera_transform.translation.y + reader_camera.y_top + (1.0 - overlay_slide.progress) * height; // NOTE: surface anchor is not accounted for
+		let z = z_order::terminal_overlay();
+
+		transform.translation = Vec3::new(x, y, z);
+	}
+}
+
 pub fn keyboard(
-	mut q_terminal		: Query<&mut BevyWezTerm>,
+	mut q_terminal		: Query<(Entity, &mut BevyWezTerm)>,
 	mut keyboard_events : EventReader<KeyboardInput>,
 		input_key		: Res<Input<KeyCode>>,
+		dock_layout		: Res<DockLayout>,
 ) {
-	let Ok(mut terminal) = q_terminal.get_single_mut() else { return };
+	// docked terminal gets input only when focused, otherwise it goes to the active one
+	let focused_entity = match dock_layout.focus {
+		DockFocus::Panel(panel_entity) if dock_layout.is_docked() => Some(panel_entity),
+		_ => None,
+	};
+
+	let Some((_, mut terminal)) = q_terminal.iter_mut().find(|(terminal_entity, terminal)| {
+		focused_entity.map_or(terminal.active, |focused_entity| focused_entity == *terminal_entity)
+	}) else { return };
 
 	for keyboard_event in keyboard_events.iter() {
 		let _res = terminal.key_up_down(
 			keyboard_event,
 			&input_key,
-			keyboard_event.state.is_pressed()
+			keyboard_event.state.is_pressed(),
+			dock_layout.is_docked()
 		);
 
 		// if res.is_err() {
@@ -0,0 +1,20 @@
+use bevy_tweening :: *;
+
+use super :: TerminalOverlaySlide;
+
+/// A lens to manipulate progress field of [`TerminalOverlaySlide`] component.
+///
+/// [`TerminalOverlaySlide`]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TerminalOverlaySlideLens {
+	/// Start progress.
+	pub start: f32,
+	/// End progress.
+	pub end: f32,
+}
+
+impl Lens<TerminalOverlaySlide> for TerminalOverlaySlideLens {
+	fn lerp(&mut self, target: &mut TerminalOverlaySlide, ratio: f32) {
+		target.progress = self.start + (self.end - self.start) * ratio;
+	}
+}
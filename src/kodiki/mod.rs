@@ -37,9 +37,356 @@ pub enum AppContext {
 	Terminal,
 }
 
+pub const CODE_EDITOR_CONTEXT_NAME	: &str = "code_editor";
+pub const TERMINAL_CONTEXT_NAME		: &str = "terminal";
+
+// marks ContextSwitcher entry that represents a context from ContextRegistry
 #[derive(Component)]
-pub enum AppContextSwitcher {
-	Entry(AppContext),
+pub struct AppContextSwitcher {
+	pub context_name : String,
+}
+
+// a single user-visible context. Several of them can share the same AppContext (e.g. a few terminals)
+#[derive(Clone, Debug)]
+pub struct ContextDesc {
+	pub name			: String, // unique among registered contexts
+	pub glyph			: String,
+	pub hint			: String,
+	pub app_context		: AppContext,
+	pub target_entity	: Option<Entity>, // specific terminal/surface to focus, None means whatever is active in given AppContext
+	pub own_terminal	: bool, // terminal context that gets a terminal of its own on first switch instead of sharing the default one
+}
+
+// user-defined context as it's described in kodiki_contexts.json in helix config directory:
+// [{ "name": "notes", "glyph": "", "hint": "Notes", "context": "terminal" }]
+// every terminal context from config gets its own terminal
+#[derive(serde::Deserialize)]
+struct ContextConfig {
+	name	: String,
+	glyph	: String,
+	hint	: String,
+	context	: String, // "code_editor" or "terminal"
+}
+
+// ordered list of contexts shown in ContextSwitcher, ctrl+N hotkeys are generated from this order
+#[derive(Resource, Default)]
+pub struct ContextRegistry {
+	contexts			: Vec<ContextDesc>,
+	active				: usize,
+	requested			: Option<usize>,
+	layout_changed		: bool, // ContextSwitcher has to be respawned
+}
+
+impl ContextRegistry {
+	const HOTKEYS : [KeyCode; 10] = [
+		KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5,
+		KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9, KeyCode::Key0,
+	];
+
+	// registering a context with existing name replaces it in place
+	pub fn register(&mut self, desc: ContextDesc) -> usize {
+		self.layout_changed = true;
+
+		if let Some(index) = self.index_of(&desc.name) {
+			self.contexts[index] = desc;
+			return index;
+		}
+
+		self.contexts.push(desc);
+		self.contexts.len() - 1
+	}
+
+	pub fn unregister(&mut self, name: &str) {
+		let Some(index) = self.index_of(name) else { return };
+
+		self.contexts.remove(index);
+
+		if self.active >= index && self.active > 0 {
+			self.active -= 1;
+		}
+
+		self.requested		= None;
+		self.layout_changed	= true;
+	}
+
+	// reorder contexts, active context stays active
+	pub fn move_entry(&mut self, from: usize, to: usize) {
+		if from >= self.contexts.len() || to >= self.contexts.len() || from == to {
+			return;
+		}
+
+		let active_name = self.contexts[self.active].name.clone();
+
+		let desc = self.contexts.remove(from);
+		self.contexts.insert(to, desc);
+
+		self.active			= self.index_of(&active_name).unwrap_or(0);
+		self.layout_changed	= true;
+	}
+
+	pub fn move_active_entry(&mut self, up: bool) {
+		let to = if up { self.active.checked_sub(1) } else { Some(self.active + 1) };
+		let Some(to) = to else { return };
+		self.move_entry(self.active, to);
+	}
+
+	pub fn request_switch(&mut self, index: usize) {
+		if index < self.contexts.len() {
+			self.requested = Some(index);
+		}
+	}
+
+	pub fn request_switch_by_name(&mut self, name: &str) {
+		if let Some(index) = self.index_of(name) {
+			self.requested = Some(index);
+		}
+	}
+
+	pub fn set_target_entity(&mut self, name: &str, entity: Entity) {
+		if let Some(index) = self.index_of(name) {
+			self.contexts[index].target_entity = Some(entity);
+		}
+	}
+
+	pub fn take_requested(&mut self) -> Option<&ContextDesc> {
+		let index = self.requested.take()?;
+		self.active = index;
+		self.contexts.get(index)
+	}
+
+	// keep active entry in sync when AppContext is changed directly bypassing registry
+	pub fn sync_active(&mut self, app_context: AppContext) {
+		if self.active().map_or(false, |desc| desc.app_context == app_context) {
+			return;
+		}
+
+		if let Some(index) = self.contexts.iter().position(|desc| desc.app_context == app_context) {
+			self.active = index;
+		}
+	}
+
+	pub fn take_layout_changed(&mut self) -> bool {
+		std::mem::take(&mut self.layout_changed)
+	}
+
+	pub fn index_of(&self, name: &str) -> Option<usize> {
+		self.contexts.iter().position(|desc| desc.name == name)
+	}
+
+	pub fn active(&self) -> Option<&ContextDesc> {
+		self.contexts.get(self.active)
+	}
+
+	pub fn contexts(&self) -> &Vec<ContextDesc> {
+		&self.contexts
+	}
+
+	// contexts from config are appended after default ones, a context with the name of a default one replaces it
+	pub fn load_user_contexts(&mut self) {
+		let file_path = helix_loader::config_dir().join("kodiki_contexts.json");
+		let Ok(contents) = std::fs::read_to_string(&file_path) else { return };
+
+		let configs = match serde_json::from_str::<Vec<ContextConfig>>(&contents) {
+			Ok(configs) => configs,
+			Err(err) => {
+				warn!("failed to parse contexts from {:?}: {:?}", file_path, err);
+				return
+			}
+		};
+
+		for config in configs {
+			let app_context = match config.context.as_str() {
+				"code_editor"	=> AppContext::CodeEditor,
+				"terminal"		=> AppContext::Terminal,
+				_ => {
+					warn!("unknown context kind {:?} of {:?} in {:?}", config.context, config.name, file_path);
+					continue
+				}
+			};
+
+			self.register(ContextDesc {
+				name			: config.name,
+				glyph			: config.glyph,
+				hint			: config.hint,
+				own_terminal	: app_context == AppContext::Terminal,
+				app_context,
+				target_entity	: None,
+			});
+		}
+	}
+
+	// ctrl + 1..9, 0 for the first 10 contexts
+	pub fn hotkey(index: usize) -> Option<KeyCode> {
+		Self::HOTKEYS.get(index).copied()
+	}
+
+	pub fn hotkey_hint(index: usize) -> String {
+		match index {
+			0..=8 => format!("Ctrl + {}", index + 1),
+			9 => String::from("Ctrl + 0"),
+			_ => String::new(),
+		}
+	}
+}
+
+// drop-down terminal that slides over code editor without leaving AppContext::CodeEditor
+#[derive(Resource)]
+pub struct TerminalOverlay {
+	pub enabled				: bool,
+	pub active				: bool,
+	pub height_ratio		: f32, // fraction of visible rows occupied by terminal
+	pub background_alpha	: f32,
+}
+
+impl Default for TerminalOverlay {
+	fn default() -> Self {
+		Self {
+			enabled			: true,
+			active			: false,
+			height_ratio	: 0.5,
+			background_alpha: 0.85,
+		}
+	}
+}
+
+impl TerminalOverlay {
+	pub fn toggle(&mut self) {
+		self.active = self.enabled && !self.active;
+	}
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum DockSide {
+	#[default]
+	None,
+	Right,	// panels to the right of code editor
+	Bottom,	// panels below code editor
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum DockFocus {
+	#[default]
+	CodeEditor,
+	Panel(Entity),
+}
+
+// any surface that can be docked next to code editor. Dock layout assigns the area, panel resizes itself to fit it
+#[derive(Component, Default)]
+pub struct DockPanel {
+	pub columns	: usize,
+	pub rows	: usize,
+}
+
+// docking layout that shows code editor and panels (terminals for now) at the same time without leaving AppContext::CodeEditor
+#[derive(Resource)]
+pub struct DockLayout {
+	pub side				: DockSide,
+	pub focus				: DockFocus, // keyboard input is routed to focused panel
+	pub panels				: Vec<Entity>, // docked panels in the order they are laid out
+	pub right_ratio			: f32, // fraction of visible columns occupied by panels docked to the right
+	pub bottom_ratio		: f32, // fraction of visible rows occupied by panels docked at the bottom
+	pub gap_columns			: f32, // space between code editor and panels docked to the right and between panels at the bottom
+	pub resizer_entity		: Option<Entity>, // splits code editor and docked panels
+}
+
+impl Default for DockLayout {
+	fn default() -> Self {
+		Self {
+			side			: DockSide::None,
+			focus			: DockFocus::CodeEditor,
+			panels			: Vec::new(),
+			right_ratio		: 0.4,
+			bottom_ratio	: 0.35,
+			gap_columns		: 3.0,
+			resizer_entity	: None,
+		}
+	}
+}
+
+impl DockLayout {
+	pub const MIN_RATIO : f32 = 0.15;
+	pub const MAX_RATIO : f32 = 0.85;
+
+	pub fn is_docked(&self) -> bool {
+		self.side != DockSide::None && !self.panels.is_empty()
+	}
+
+	pub fn is_docked_panel(&self, entity: Entity) -> bool {
+		self.is_docked() && self.panels.contains(&entity)
+	}
+
+	// None -> Right -> Bottom -> None. Given panels are docked when layout goes from None to Right
+	pub fn cycle_side(&mut self, panels: impl Iterator<Item = Entity>) {
+		self.side = match self.side {
+			DockSide::None		=> DockSide::Right,
+			DockSide::Right		=> DockSide::Bottom,
+			DockSide::Bottom	=> DockSide::None,
+		};
+
+		match self.side {
+			DockSide::None => self.reset(),
+			DockSide::Right => self.panels = panels.collect(),
+			DockSide::Bottom => (),
+		}
+	}
+
+	// code editor -> first panel -> ... -> last panel -> code editor
+	pub fn toggle_focus(&mut self) {
+		if !self.is_docked() {
+			return;
+		}
+
+		let next_panel = match self.focus {
+			DockFocus::CodeEditor => self.panels.first(),
+			DockFocus::Panel(entity) => self.panels.iter().position(|panel| *panel == entity).and_then(|index| self.panels.get(index + 1)),
+		};
+
+		self.focus = next_panel.map_or(DockFocus::CodeEditor, |entity| DockFocus::Panel(*entity));
+	}
+
+	pub fn panel_focused(&self) -> bool {
+		self.is_docked() && self.focus != DockFocus::CodeEditor
+	}
+
+	pub fn reset(&mut self) {
+		self.side	= DockSide::None;
+		self.focus	= DockFocus::CodeEditor;
+		self.panels.clear();
+	}
+
+	// columns taken from code editor by panels docked to the right, gap included
+	pub fn reserved_columns(&self, visible_columns: f32) -> usize {
+		match self.side {
+			DockSide::Right if self.is_docked() => (visible_columns * self.right_ratio).floor() as usize + self.gap_columns as usize,
+			_ => 0,
+		}
+	}
+
+	// rows taken from code editor by panels docked at the bottom
+	pub fn reserved_rows(&self, visible_rows: f32) -> usize {
+		match self.side {
+			DockSide::Bottom if self.is_docked() => ((visible_rows * self.bottom_ratio).floor() as usize).max(1),
+			_ => 0,
+		}
+	}
+
+	// dragging split resizer changes the share of docked panels
+	pub fn set_ratio(&mut self, ratio: f32) {
+		let ratio = ratio.clamp(Self::MIN_RATIO, Self::MAX_RATIO);
+
+		match self.side {
+			DockSide::Right		=> self.right_ratio = ratio,
+			DockSide::Bottom	=> self.bottom_ratio = ratio,
+			DockSide::None		=> (),
+		}
+	}
+
+	pub fn ratio(&self) -> f32 {
+		match self.side {
+			DockSide::Bottom	=> self.bottom_ratio,
+			_					=> self.right_ratio,
+		}
+	}
 }
 
 #[derive(Default, Resource)]
@@ -77,6 +424,9 @@ impl Plugin for KodikiPlugin {
 
 			.insert_resource(AppState::default())
 			.insert_resource(MouseCursorState::default())
+			.insert_resource(TerminalOverlay::default())
+			.insert_resource(DockLayout::default())
+			.insert_resource(ContextRegistry::default())
 
 			.insert_resource(clear_color)
 			.insert_resource(Msaa::default())
@@ -115,8 +465,24 @@ impl Plugin for KodikiPlugin {
 			.add_systems(
 				(
 					systems::spawn_first_terminal,
+					systems::spawn_context_terminal,
 				).in_schedule(OnEnter(AppContext::Terminal))
 			)
+			.add_system(
+				systems::spawn_first_terminal
+				.run_if(run_condition::terminal_overlay_or_docked)
+				.in_set(OnUpdate(AppMode::Main))
+			)
+			.add_system(
+				systems::update_dock_focus
+				.run_if(run_condition::terminal_docked)
+				.in_set(OnUpdate(AppMode::Main))
+			)
+			.add_system(
+				systems::update_dock_layout
+				.before(KodikiUISystems)
+				.run_if(run_condition::text_editor_context)
+			)
 
 			// generic app systems
 			.add_systems(
@@ -131,7 +497,9 @@ impl Plugin for KodikiPlugin {
 			// context switching
 			.add_systems(
 				(
+					systems::update_context_switcher,
 					systems::apply_context_switcher_state,
+					systems::apply_context_registry,
 					systems::highlight_active_context_switcher,
 				)
 				.chain()
@@ -149,5 +517,8 @@ impl Plugin for KodikiPlugin {
 			// unified despawning through a resource
 			.add_system(systems::despawn.in_base_set(CoreSet::PostUpdate))
  		;
+
+		#[cfg(feature = "debug")]
+		app	.add_system(systems::setup_debug_world.in_schedule(OnEnter(AppMode::AssetsLoaded)));
 	}
 }
@@ -1,6 +1,6 @@
 use bevy :: prelude :: *;
 
-use super :: { AppMode, AppCameraMode, AppContext };
+use super :: { AppMode, AppCameraMode, AppContext, TerminalOverlay, DockLayout, DockFocus, DockSide };
 
 pub fn main_app_mode(app_mode: Res<State<AppMode>>) -> bool {
 	app_mode.0 == AppMode::Main
@@ -21,3 +21,25 @@ pub fn text_editor_context_no_fly(app_mode: Res<State<AppMode>>, app_ctx: Res<St
 pub fn terminal_context(app_mode: Res<State<AppMode>>, app_ctx: Res<State<AppContext>>) -> bool {
 	app_mode.0 == AppMode::Main && app_ctx.0 == AppContext::Terminal
 }
+
+pub fn terminal_docked(app_mode: Res<State<AppMode>>, app_ctx: Res<State<AppContext>>, dock: Res<DockLayout>) -> bool {
+	app_mode.0 == AppMode::Main && app_ctx.0 == AppContext::CodeEditor && dock.is_docked()
+}
+
+// docking side is chosen before there are any panels to dock so first terminal gets spawned for it
+pub fn terminal_overlay_or_docked(app_mode: Res<State<AppMode>>, app_ctx: Res<State<AppContext>>, overlay: Res<TerminalOverlay>, dock: Res<DockLayout>) -> bool {
+	app_mode.0 == AppMode::Main && app_ctx.0 == AppContext::CodeEditor && (overlay.active || dock.side != DockSide::None)
+}
+
+pub fn terminal_visible(app_mode: Res<State<AppMode>>, app_ctx: Res<State<AppContext>>, overlay: Res<TerminalOverlay>, dock: Res<DockLayout>) -> bool {
+	app_mode.0 == AppMode::Main && (app_ctx.0 == AppContext::Terminal || overlay.active || dock.is_docked())
+}
+
+// keyboard input goes either to code editor or to terminal, never both
+pub fn terminal_focused(app_ctx: Res<State<AppContext>>, overlay: Res<TerminalOverlay>, dock: Res<DockLayout>) -> bool {
+	app_ctx.0 == AppContext::Terminal || overlay.active || dock.panel_focused()
+}
+
+pub fn code_editor_focused(overlay: Res<TerminalOverlay>, dock: Res<DockLayout>) -> bool {
+	!overlay.active && (!dock.is_docked() || dock.focus == DockFocus::CodeEditor)
+}
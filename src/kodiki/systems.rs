@@ -18,99 +18,176 @@ use super :: spawn :: AxisDesc;
 use crate :: {
 	z_order,
 	bevy_wezterm	:: BevyWezTerm,
-	bevy_helix		:: { HelixApp, TokioRuntime, utils :: * },
+	bevy_helix		:: { HelixApp, TokioRuntime, utils :: *, git_history :: HISTORY_CONTEXT_NAME },
 	bevy_ab_glyph	:: { ABGlyphFont, glyph_mesh_generator :: generate_string_mesh },
 	kodiki_ui :: {
 		text_cursor		:: CursorVisualAsset,
 		text_surface	:: PathRowCol,
-		raypick			:: Clicked,
+		raypick			:: { Raypick, Clicked },
+		resizer			:: Resizer,
 		context_switcher:: { ContextSwitcher, ContextSwitcherEntry },
 	},
 };
 
+#[cfg(feature = "debug")]
+pub fn setup_debug_world(
+	mut mesh_assets		: ResMut<Assets<Mesh>>,
+	mut material_assets : ResMut<Assets<StandardMaterial>>,
+	mut commands		: Commands,
+) {
+	spawn::axis	(Transform::default(), AxisDesc::default(), &mut mesh_assets, &mut material_assets, &mut commands);
+	spawn::fixed_sphere	(Transform::default(), 0.02, Color::SEA_GREEN, &mut mesh_assets, &mut material_assets, &mut commands);
+}
+
 pub fn setup_world(
 	mut camera_ids		: ResMut<CameraIDs>,
 	mut rapier_debug	: ResMut<DebugRenderContext>,
 	mut next_state		: ResMut<NextState<AppMode>>,
+	mut context_registry: ResMut<ContextRegistry>,
+	mut commands		: Commands,
+) {
+	spawn::camera(
+		None,
+		&mut camera_ids,
+		&mut commands
+	);
+
+	// default contexts, Context Switcher gets spawned from registry in update_context_switcher
+
+	context_registry.register(ContextDesc {
+		name			: CODE_EDITOR_CONTEXT_NAME.into(),
+		glyph			: "󱃖".into(),
+		hint			: "Code Editor".into(),
+		app_context		: AppContext::CodeEditor,
+		target_entity	: None,
+		own_terminal	: false,
+	});
+
+	context_registry.register(ContextDesc {
+		name			: TERMINAL_CONTEXT_NAME.into(),
+		glyph			: "".into(),
+		hint			: "Terminal".into(),
+		app_context		: AppContext::Terminal,
+		target_entity	: None, // set once the shared terminal is spawned
+		own_terminal	: false,
+	});
+
+	// commit graph is drawn on the code editor side with editor itself hidden
+	context_registry.register(ContextDesc {
+		name			: HISTORY_CONTEXT_NAME.into(),
+		glyph			: "".into(),
+		hint			: "Git History".into(),
+		app_context		: AppContext::CodeEditor,
+		target_entity	: None,
+		own_terminal	: false,
+	});
+
+	context_registry.load_user_contexts();
+
+	//
+
+	rapier_debug.enabled = false;
+	rapier_debug.pipeline.style.rigid_body_axes_length = 0.1;
+
+	next_state.set(AppMode::Main);
+}
+
+pub fn update_context_switcher(
+		q_switcher		: Query<Entity, With<ContextSwitcher>>,
+	mut context_registry: ResMut<ContextRegistry>,
+	mut despawn			: ResMut<DespawnResource>,
 		font_assets		: Res<Assets<ABGlyphFont>>,
 		font_handles	: Res<FontAssetHandles>,
 	mut mesh_assets		: ResMut<Assets<Mesh>>,
 	mut material_assets : ResMut<Assets<StandardMaterial>>,
 	mut commands		: Commands,
 ) {
-	#[cfg(feature = "debug")] {
-		spawn::axis	(Transform::default(), AxisDesc::default(), &mut mesh_assets, &mut material_assets, &mut commands);
-		spawn::fixed_sphere	(Transform::default(), 0.02, Color::SEA_GREEN, &mut mesh_assets, &mut material_assets, &mut commands);
+	if !context_registry.take_layout_changed() {
+		return;
 	}
 
-	let fonts = ABGlyphFonts::new(&font_assets, &font_handles);
-
-	spawn::camera(
-		None,
-		&mut camera_ids,
-		&mut commands
-	);
+	// entries are respawned from scratch on every registry change to keep order and hotkey hints consistent
+	for switcher_entity in q_switcher.iter() {
+		despawn.recursive.push(switcher_entity);
+	}
 
-	// Context Switcher between terminal and code/text editor
+	if context_registry.contexts().is_empty() {
+		return;
+	}
 
-	let entry0 = ContextSwitcher::new_entry(
-		"󱃖".into(),
-		"Code Editor".into(),
-		"Ctrl + 1".into(),
-	);
+	let fonts = ABGlyphFonts::new(&font_assets, &font_handles);
 
-	let entry1 = ContextSwitcher::new_entry(
-		"".into(),
-		"Terminal".into(),
-		"Ctrl + 2".into(),
-	);
+	let entries = context_registry.contexts().iter().enumerate().map(|(index, desc)| {
+		ContextSwitcher::new_entry(
+			desc.glyph.clone(),
+			desc.hint.clone(),
+			ContextRegistry::hotkey_hint(index),
+		)
+	}).collect();
 
 	let spawned_entries = ContextSwitcher::spawn(
 		0.2,
-		[entry0, entry1].into(),
+		entries,
 		&mut mesh_assets,
 		&mut material_assets,
 		&fonts,
 		&mut commands
 	);
 
-	commands.entity(spawned_entries[0]).insert(AppContextSwitcher::Entry(AppContext::CodeEditor));
-	commands.entity(spawned_entries[1]).insert(AppContextSwitcher::Entry(AppContext::Terminal));
-
-	//
-
-	rapier_debug.enabled = false;
-	rapier_debug.pipeline.style.rigid_body_axes_length = 0.1;
-
-	next_state.set(AppMode::Main);
+	for (entry_entity, desc) in spawned_entries.iter().zip(context_registry.contexts().iter()) {
+		commands.entity(*entry_entity).insert(AppContextSwitcher { context_name: desc.name.clone() });
+	}
 }
 
 pub fn apply_context_switcher_state(
 	mut	q_app_context_switcher	: Query<(&AppContextSwitcher, &mut ContextSwitcherEntry)>,
-	mut next_context			: ResMut<NextState<AppContext>>,
+	mut context_registry		: ResMut<ContextRegistry>,
 ) {
 	for (marker, mut switcher_entry) in q_app_context_switcher.iter_mut() {
 		if !switcher_entry.is_triggered {
 			continue;
 		}
 
-		match marker {
-			AppContextSwitcher::Entry(AppContext::CodeEditor) => {
-				next_context.set(AppContext::CodeEditor);
-			},
-			AppContextSwitcher::Entry(AppContext::Terminal) => {
-				next_context.set(AppContext::Terminal);
-			}
-		}
+		context_registry.request_switch_by_name(&marker.context_name);
 
 		// trigger is processed now
 		switcher_entry.is_triggered = false;
 	}
 }
 
+pub fn apply_context_registry(
+	mut q_terminal				: Query<(Entity, &mut BevyWezTerm)>,
+	mut context_registry		: ResMut<ContextRegistry>,
+		app_context				: Res<State<AppContext>>,
+	mut next_context			: ResMut<NextState<AppContext>>,
+	mut terminal_overlay		: ResMut<TerminalOverlay>,
+	mut dock_layout				: ResMut<DockLayout>,
+) {
+	let Some(desc) = context_registry.take_requested().cloned() else {
+		if next_context.0.is_none() {
+			context_registry.sync_active(app_context.0);
+		}
+		return
+	};
+
+	terminal_overlay.active = false;
+	dock_layout.reset();
+
+	// context may point to a specific terminal among several. Context without its terminal yet gets it spawned
+	// in spawn_context_terminal so none of existing ones stays active
+	if desc.app_context == AppContext::Terminal && (desc.target_entity.is_some() || desc.own_terminal) {
+		for (terminal_entity, mut terminal) in q_terminal.iter_mut() {
+			terminal.set_active(Some(terminal_entity) == desc.target_entity);
+		}
+	}
+
+	// setting the same state reruns OnExit/OnEnter so switching between contexts of the same kind works too
+	next_context.set(desc.app_context);
+}
+
 pub fn highlight_active_context_switcher(
 	mut	q_switcher_entry: Query<(&AppContextSwitcher, &mut ContextSwitcherEntry, &Transform, Entity)>,
-		app_context		: Res<State<AppContext>>,
+		context_registry: Res<ContextRegistry>,
 		next_context	: Res<NextState<AppContext>>,
 	mut color_materials_cache : ResMut<ColorMaterialsCache>,
 	mut material_assets	: ResMut<Assets<StandardMaterial>>,
@@ -120,29 +197,29 @@ pub fn highlight_active_context_switcher(
 		return;
 	}
 
+	let Some(active_desc) = context_registry.active() else { return };
+
 	for (app_context_switcher, mut entry, transform, entity) in q_switcher_entry.iter_mut() {
-		match app_context_switcher {
-			AppContextSwitcher::Entry(entry_app_context) => {
-				if *entry_app_context == app_context.0 && !entry.is_active {
-					entry.highlight(
-						entity,
-						transform,
-						&mut color_materials_cache,
-						&mut material_assets,
-						&mut commands
-					);
-					entry.is_active = true;
-				} else if *entry_app_context != app_context.0 && entry.is_active {
-					entry.unhighlight(
-						entity,
-						transform,
-						&mut color_materials_cache,
-						&mut material_assets,
-						&mut commands
-					);
-					entry.is_active = false;
-				}
-			},
+		let is_active_context = app_context_switcher.context_name == active_desc.name;
+
+		if is_active_context && !entry.is_active {
+			entry.highlight(
+				entity,
+				transform,
+				&mut color_materials_cache,
+				&mut material_assets,
+				&mut commands
+			);
+			entry.is_active = true;
+		} else if !is_active_context && entry.is_active {
+			entry.unhighlight(
+				entity,
+				transform,
+				&mut color_materials_cache,
+				&mut material_assets,
+				&mut commands
+			);
+			entry.is_active = false;
 		}
 	}
 }
@@ -197,9 +274,14 @@ pub fn keyboard_input(
 	mut q_window_primary	: Query<&mut Window, With<PrimaryWindow>>,
 
 	mut next_camera_mode	: ResMut<NextState<AppCameraMode>>,
-	mut next_context		: ResMut<NextState<AppContext>>,
+		app_context			: Res<State<AppContext>>,
+	mut context_registry	: ResMut<ContextRegistry>,
+	mut terminal_overlay	: ResMut<TerminalOverlay>,
+	mut dock_layout			: ResMut<DockLayout>,
+		q_terminal			: Query<Entity, With<BevyWezTerm>>,
 ) {
-	if key.pressed(KeyCode::LControl) && key.just_pressed(KeyCode::Key9) {
+	// ctrl + digits are taken by context hotkeys
+	if key.pressed(KeyCode::LControl) && key.just_pressed(KeyCode::F12) {
 		rapier_debug.enabled = !rapier_debug.enabled;
 	}
 
@@ -212,14 +294,39 @@ pub fn keyboard_input(
 	let ctrl_pressed = key.pressed(KeyCode::LControl) || key.pressed(KeyCode::RControl);
 
 	if ctrl_pressed {
-		// switch to Helix
-		if key.just_pressed(KeyCode::Key1) {
-			next_context.set(AppContext::CodeEditor);
+		// ctrl + N switches to N-th context in registry
+		for index in 0..context_registry.contexts().len() {
+			let Some(hotkey) = ContextRegistry::hotkey(index) else { break };
+			if key.just_pressed(hotkey) {
+				context_registry.request_switch(index);
+			}
+		}
+
+		// reorder active context in switcher
+		let alt_pressed = key.pressed(KeyCode::LAlt) || key.pressed(KeyCode::RAlt);
+		if alt_pressed && key.just_pressed(KeyCode::PageUp) {
+			context_registry.move_active_entry(true);
+		}
+
+		if alt_pressed && key.just_pressed(KeyCode::PageDown) {
+			context_registry.move_active_entry(false);
+		}
+
+		let code_editor_context = app_context.0 == AppContext::CodeEditor;
+
+		// drop-down terminal over code editor
+		if key.just_pressed(KeyCode::Grave) && code_editor_context && !dock_layout.is_docked() {
+			terminal_overlay.toggle();
+		}
+
+		// dock terminals next to code editor: none -> right -> bottom -> none
+		if key.just_pressed(KeyCode::Backslash) && code_editor_context && !terminal_overlay.active {
+			dock_layout.cycle_side(q_terminal.iter());
 		}
 
-		// switch to WezTerm
-		if key.just_pressed(KeyCode::Key2) {
-			next_context.set(AppContext::Terminal);
+		// move keyboard focus between code editor and docked panels
+		if key.just_pressed(KeyCode::Tab) && code_editor_context && dock_layout.is_docked() {
+			dock_layout.toggle_focus();
 		}
 	}
 }
@@ -482,9 +589,53 @@ pub fn asset_loading_tracking(
 	}
 }
 
+fn spawn_terminal_at_camera(
+	name				: &str,
+	camera_transform	: &Transform,
+	reader_camera		: &ReaderCamera,
+	font				: &ABGlyphFont,
+	gltf_assets			: &mut Assets<Gltf>,
+	cursor_asset		: &mut CursorVisualAsset,
+	mesh_assets			: &mut Assets<Mesh>,
+	material_assets		: &mut Assets<StandardMaterial>,
+	commands			: &mut Commands,
+) -> Option<Entity> {
+	// this is fragile but will work as long as code editor (helix in our case currently) uses std::env::set_current_dir/current_dir
+	let Ok(cwd) = std::env::current_dir() else { return None };
+
+	let rows = 24;
+	let cols = 150;
+
+	let column_width = font.horizontal_advance_mono();
+
+	// putting terminal where the camera is currently 
+	let x = camera_transform.translation.x + (-column_width * (cols as f32 / 2.0));
+	let y = camera_transform.translation.y + reader_camera.y_top; // NOTE: surface anchor is not accounted for
+	let z = z_order::surface::base();
+
+	let translation = Vec3::new(x, y, z);
+
+	Some(BevyWezTerm::spawn(
+		name,
+		Some(cwd),
+		font,
+		rows,
+		cols,
+		Some(translation),
+		gltf_assets,
+		cursor_asset,
+		mesh_assets,
+		material_assets,
+		commands
+	))
+}
+
 pub fn spawn_first_terminal(
 		q_terminal		: Query<&BevyWezTerm>,
 	mut q_camera		: Query<(&mut ReaderCamera, &Transform)>,
+		app_context		: Res<State<AppContext>>,
+	mut dock_layout		: ResMut<DockLayout>,
+	mut context_registry: ResMut<ContextRegistry>,
 	mut commands		: Commands,
 
 	(mut gltf_assets, mut cursor_asset)		: (ResMut<Assets<Gltf>>, ResMut<CursorVisualAsset>),
@@ -493,45 +644,285 @@ pub fn spawn_first_terminal(
 ) {
 	if !q_terminal.is_empty() { return }
 
-	// this is fragile but will work as long as code editor (helix in our case currently) uses std::env::set_current_dir/current_dir
-    let Ok(cwd) = std::env::current_dir() else { return };
+	// context with a terminal of its own spawns it in spawn_context_terminal
+	if app_context.0 == AppContext::Terminal && context_registry.active().map_or(false, |desc| desc.own_terminal) { return }
 
 	let font = font_assets.get(&font_handles.main).unwrap();
 
-	let rows = 24;
-	let cols = 150;
-
 	let Ok((mut reader_camera, camera_transform)) = q_camera.get_single_mut() else { return };
 
-	let column_width = font.horizontal_advance_mono();
+	let Some(terminal_entity) = spawn_terminal_at_camera(
+		"Bevy Terminal",
+		camera_transform,
+		&reader_camera,
+		font,
+		&mut gltf_assets,
+		&mut cursor_asset,
+		&mut mesh_assets,
+		&mut material_assets,
+		&mut commands
+	) else { return };
 
-	// putting terminal where the camera is currently 
-	let x = camera_transform.translation.x + (-column_width * (cols as f32 / 2.0));
-	let y = camera_transform.translation.y + reader_camera.y_top; // NOTE: surface anchor is not accounted for
-	let z = z_order::surface::base();
+	context_registry.set_target_entity(TERMINAL_CONTEXT_NAME, terminal_entity);
 
-	let translation = Vec3::new(x, y, z);
+	// docking was requested before there was any terminal to dock
+	if dock_layout.side != DockSide::None && dock_layout.panels.is_empty() {
+		dock_layout.panels.push(terminal_entity);
+	}
 
-	let terminal_entity = BevyWezTerm::spawn(
-		"Bevy Terminal",
-		Some(cwd),
+	// in overlay mode camera keeps following code editor underneath the terminal
+	if app_context.0 == AppContext::Terminal {
+		reader_camera.target_entity = Some(terminal_entity);
+	}
+}
+
+// terminal contexts from config get their terminal on first switch to them and keep it afterwards
+pub fn spawn_context_terminal(
+	mut q_camera		: Query<(&mut ReaderCamera, &Transform)>,
+	mut context_registry: ResMut<ContextRegistry>,
+	mut commands		: Commands,
+
+	(mut gltf_assets, mut cursor_asset)		: (ResMut<Assets<Gltf>>, ResMut<CursorVisualAsset>),
+	(mut mesh_assets, mut material_assets)	: (ResMut<Assets<Mesh>>, ResMut<Assets<StandardMaterial>>),
+	(font_assets, font_handles)				: (Res<Assets<ABGlyphFont>>, Res<FontAssetHandles>),
+) {
+	let Some(desc) = context_registry.active().cloned() else { return };
+
+	if !desc.own_terminal || desc.target_entity.is_some() { return }
+
+	let font = font_assets.get(&font_handles.main).unwrap();
+
+	let Ok((mut reader_camera, camera_transform)) = q_camera.get_single_mut() else { return };
+
+	let Some(terminal_entity) = spawn_terminal_at_camera(
+		desc.hint.as_str(),
+		camera_transform,
+		&reader_camera,
 		font,
-		rows,
-		cols,
-		Some(translation),
 		&mut gltf_assets,
 		&mut cursor_asset,
 		&mut mesh_assets,
 		&mut material_assets,
 		&mut commands
-	);
+	) else { return };
+
+	context_registry.set_target_entity(&desc.name, terminal_entity);
 
 	reader_camera.target_entity = Some(terminal_entity);
 }
 
+pub fn update_dock_focus(
+		mouse_button	: Res<Input<MouseButton>>,
+		raypick			: Res<Raypick>,
+		q_parent		: Query<&Parent>,
+	mut dock_layout		: ResMut<DockLayout>,
+) {
+	if !mouse_button.just_pressed(MouseButton::Left) {
+		return;
+	}
+
+	let Some(hovered_entity) = raypick.last_hover else { return };
+
+	// split resizer is not a part of any panel and dragging it shouldn't move focus
+	if dock_layout.resizer_entity == Some(hovered_entity) {
+		return;
+	}
+
+	// anything that belongs to a docked panel hierarchy gives focus to that panel, everything else is considered code editor
+	let mut clicked_panel = None;
+	let mut entity = hovered_entity;
+	loop {
+		if dock_layout.panels.contains(&entity) {
+			clicked_panel = Some(entity);
+			break;
+		}
+
+		let Ok(parent) = q_parent.get(entity) else { break };
+		entity = parent.get();
+	}
+
+	let new_focus = clicked_panel.map_or(DockFocus::CodeEditor, |entity| DockFocus::Panel(entity));
+
+	if dock_layout.focus != new_focus {
+		dock_layout.focus = new_focus;
+	}
+}
+
+// docked panels are placed next to code editor that camera follows and get their share of the screen through DockPanel.
+// Split between code editor and panels is moved by dragging its resizer
+pub fn update_dock_layout(
+	mut q_panel			: Query<(Entity, &mut DockPanel, &mut Visibility, &mut Transform), (Without<ReaderCamera>, Without<Resizer>)>,
+	mut q_camera		: Query<(&mut ReaderCamera, &Transform), (Without<DockPanel>, Without<Resizer>)>,
+		q_editor		: Query<(&Transform, &TextDescriptor), (Without<ReaderCamera>, Without<DockPanel>, Without<Resizer>)>,
+	mut q_resizer		: Query<(&mut Resizer, &mut Transform, &mut Visibility), (Without<ReaderCamera>, Without<DockPanel>)>,
+		raypick			: Res<Raypick>,
+		kodiki_ui		: Res<KodikiUI>,
+	mut dock_layout		: ResMut<DockLayout>,
+		font_assets		: Res<Assets<ABGlyphFont>>,
+		font_handles	: Res<FontAssetHandles>,
+	mut mesh_assets		: ResMut<Assets<Mesh>>,
+	mut material_assets	: ResMut<Assets<StandardMaterial>>,
+	mut drag_init_ratio	: Local<Option<f32>>,
+	mut commands		: Commands,
+) {
+	let Ok((mut reader_camera, camera_transform)) = q_camera.get_single_mut() else { return };
+
+	// undocked panels hide once, they can still be shown by drop-down overlay or their own context afterwards
+	if dock_layout.is_changed() {
+		for (panel_entity, _, mut visibility, _) in q_panel.iter_mut() {
+			if !dock_layout.is_docked_panel(panel_entity) && *visibility != Visibility::Hidden {
+				*visibility.as_mut() = Visibility::Hidden;
+			}
+		}
+	}
+
+	if let Some((_, _, mut visibility)) = dock_layout.resizer_entity.and_then(|entity| q_resizer.get_mut(entity).ok()) {
+		let resizer_visibility = if dock_layout.is_docked() { Visibility::Visible } else { Visibility::Hidden };
+		if *visibility != resizer_visibility {
+			*visibility.as_mut() = resizer_visibility;
+		}
+	}
+
+	if !dock_layout.is_docked() {
+		if dock_layout.is_changed() {
+			if let Some(editor_columns) = reader_camera.target_entity.and_then(|entity| q_editor.get(entity).ok()).map(|(_, descriptor)| descriptor.columns) {
+				reader_camera.column = editor_columns / 2;
+			}
+		}
+
+		return;
+	}
+
+	profile_function!();
+
+	// camera follows code editor in AppContext::CodeEditor so its target is where panels are docked to
+	let Some(editor_entity) = reader_camera.target_entity else { return };
+	let Ok((editor_transform, editor_descriptor)) = q_editor.get(editor_entity) else { return };
+
+	let fonts = ABGlyphFonts::new(&font_assets, &font_handles);
+
+	let column_width	= fonts.main.horizontal_advance_mono();
+	let row_height		= fonts.main.vertical_advance();
+
+	let editor_columns	= editor_descriptor.columns;
+	let gap_columns		= dock_layout.gap_columns as usize;
+	let panels_count	= dock_layout.panels.len();
+
+	let dock_columns	= dock_layout.reserved_columns(reader_camera.visible_columns).saturating_sub(gap_columns).max(1);
+	let dock_rows		= dock_layout.reserved_rows(reader_camera.visible_rows);
+
+	let top_y			= camera_transform.translation.y + reader_camera.y_top; // NOTE: surface anchor is not accounted for
+	let bottom_y		= camera_transform.translation.y + reader_camera.y_bottom;
+
+	// split resizer
+
+	let resizer_entity = match dock_layout.resizer_entity {
+		Some(resizer_entity) => resizer_entity,
+		None => {
+			let resizer_entity = Resizer::spawn(
+				"dock_split",
+				UVec2::ZERO,
+				&mut mesh_assets,
+				&mut material_assets,
+				&mut commands
+			);
+
+			dock_layout.resizer_entity = Some(resizer_entity);
+			resizer_entity
+		}
+	};
+
+	if let Ok((mut resizer, mut resizer_transform, _)) = q_resizer.get_mut(resizer_entity) {
+		// resizer keeps track of dragging, normalized mouse offset from where dragging started is turned into a new ratio
+		match (resizer.init_mouse_pos, *drag_init_ratio) {
+			(Some(init_mouse_pos), Some(init_ratio)) => {
+				let mouse_pos_diff = raypick.mouse_pos - init_mouse_pos;
+
+				let ratio = match dock_layout.side {
+					DockSide::Bottom	=> init_ratio + mouse_pos_diff.y / 2.0,
+					_					=> init_ratio - mouse_pos_diff.x / 2.0,
+				};
+
+				if (ratio - dock_layout.ratio()).abs() > f32::EPSILON {
+					dock_layout.set_ratio(ratio);
+				}
+			},
+			(Some(_), None) => *drag_init_ratio = Some(dock_layout.ratio()),
+			(None, _) => *drag_init_ratio = None,
+		}
+
+		resizer.area		= UVec2::new(dock_columns as u32, dock_rows as u32);
+		resizer.quad_color	= kodiki_ui.context_switch_color;
+
+		*resizer_transform = match dock_layout.side {
+			DockSide::Bottom => Transform {
+				translation	: Vec3::new(editor_transform.translation.x + editor_columns as f32 * column_width / 2.0, bottom_y + dock_rows as f32 * row_height, z_order::surface::child_surface()),
+				rotation	: Quat::from_rotation_z(std::f32::consts::FRAC_PI_2),
+				..default()
+			},
+			_ => Transform::from_translation(Vec3::new(
+				editor_transform.translation.x + (editor_columns as f32 + dock_layout.gap_columns / 2.0) * column_width,
+				top_y - reader_camera.visible_rows * row_height / 2.0,
+				z_order::surface::child_surface()
+			)),
+		};
+	}
+
+	// panels docked to the right are stacked from top to bottom, panels at the bottom go from left to right
+
+	for (index, panel_entity) in dock_layout.panels.iter().enumerate() {
+		let Ok((_, mut dock_panel, mut visibility, mut transform)) = q_panel.get_mut(*panel_entity) else { continue };
+
+		let (columns, rows, x, y) = match dock_layout.side {
+			DockSide::Right => {
+				let rows = (reader_camera.visible_rows.floor() as usize / panels_count).max(1);
+
+				let x = editor_transform.translation.x + (editor_columns + gap_columns) as f32 * column_width;
+				let y = top_y - (index * rows) as f32 * row_height;
+
+				(dock_columns, rows, x, y)
+			},
+			DockSide::Bottom => {
+				let columns = (editor_columns.saturating_sub(gap_columns * (panels_count - 1)) / panels_count).max(1);
+
+				let x = editor_transform.translation.x + (index * (columns + gap_columns)) as f32 * column_width;
+				let y = bottom_y + dock_rows as f32 * row_height;
+
+				(columns, dock_rows, x, y)
+			},
+			// layout is undocked above, nothing to place
+			DockSide::None => continue,
+		};
+
+		if dock_panel.columns != columns || dock_panel.rows != rows {
+			dock_panel.columns	= columns;
+			dock_panel.rows		= rows;
+		}
+
+		if *visibility != Visibility::Visible {
+			*visibility.as_mut() = Visibility::Visible;
+		}
+
+		// panels are drawn on top of code editor background quad that fills the whole screen
+		transform.translation = Vec3::new(x, y, z_order::surface::child_surface());
+	}
+
+	// camera looks at the middle of code editor and panels docked to the right
+	let camera_column = match dock_layout.side {
+		DockSide::Right => (editor_columns + gap_columns + dock_columns) / 2,
+		_ => editor_columns / 2,
+	};
+
+	if reader_camera.column != camera_column {
+		reader_camera.column = camera_column;
+	}
+}
+
 pub fn process_clicked_terminal_path(
 		q_clicked		: Query<(Entity, &PathRowCol), With<Clicked>>,
 	mut next_context	: ResMut<NextState<AppContext>>,
+	mut terminal_overlay: ResMut<TerminalOverlay>,
+	mut dock_layout		: ResMut<DockLayout>,
 		tokio_runtime	: Res<TokioRuntime>,
 		helix_app_option: Option<NonSendMut<HelixApp>>,
 	mut commands		: Commands,
@@ -541,6 +932,9 @@ pub fn process_clicked_terminal_path(
 	let Ok((clicked_entity, path)) = q_clicked.get_single() else { return };
 	
 	next_context.set(AppContext::CodeEditor);
+	terminal_overlay.active = false;
+	// docked terminal stays on screen, only focus goes to code editor
+	dock_layout.focus = DockFocus::CodeEditor;
 
 	let row = path.row.saturating_sub(1); // helix indexing
 	let col = path.col.saturating_sub(1);
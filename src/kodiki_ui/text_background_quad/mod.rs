@@ -10,6 +10,8 @@ pub mod systems;
 pub struct TextBackgroundQuad {
 	pub color			: Option<Color>,
 		color_internal	: Option<Color>,
+	pub alpha_mode		: AlphaMode, // opaque unless surface wants what's behind it to show through
+		alpha_mode_internal : AlphaMode,
 	pub in_camera_space	: bool,
 	pub fill_vertically	: bool,
 	pub top_anchor		: bool,
@@ -23,6 +25,8 @@ impl Default for TextBackgroundQuad {
         Self {
 			color 			: None,
 			color_internal	: None,
+			alpha_mode		: AlphaMode::Opaque,
+			alpha_mode_internal : AlphaMode::Opaque,
 			in_camera_space	: false,
 			fill_vertically	: false,
 			top_anchor		: false,
@@ -76,7 +76,7 @@ pub fn update_color(
 	profile_function!();
 
 	for (bg_quad_entity, mut bg_quad) in q_bg_quad.iter_mut() {
-		if bg_quad.color.is_none() || bg_quad.color_internal == bg_quad.color {
+		if bg_quad.color.is_none() || (bg_quad.color_internal == bg_quad.color && bg_quad.alpha_mode_internal == bg_quad.alpha_mode) {
 			continue;
 		}
 
@@ -87,8 +87,9 @@ pub fn update_color(
 			));
 		}
 				
-		let background_material_handle = get_color_material_handle(
+		let background_material_handle = get_color_material_walpha_handle(
 			bg_quad.color.unwrap(),
+			bg_quad.alpha_mode,
 			&mut color_materials_cache,
 			&mut material_assets
 		);
@@ -97,5 +98,6 @@ pub fn update_color(
 		commands.entity(bg_quad_entity).insert(background_material_handle.clone_weak());
 
 		bg_quad.color_internal = bg_quad.color;
+		bg_quad.alpha_mode_internal = bg_quad.alpha_mode;
 	}
 }
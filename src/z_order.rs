@@ -13,6 +13,7 @@ mod SURFACE {
 	}
 	pub const CURSOR			: f32 = HIGHLIGHT::SELECTION + 1.;
 	pub const TEXT				: f32 = CURSOR + 1.;
+	pub const TRANSITION		: f32 = TEXT + 1.;
 	pub const CHILD_SURFACE		: f32 = TEXT + 2.;
 	pub const CENTER_SURFACE	: f32 = CHILD_SURFACE + TEXT + 2.;
 	pub const LAST				: f32 = CENTER_SURFACE;
@@ -45,7 +46,8 @@ mod MINIMAP {
 }
 
 const RESIZER					: f32 = 0.; // same as main surface
-const CONTEXT_SWITCHER			: f32 = SURFACE::LAST + 1.;
+const TERMINAL_OVERLAY			: f32 = SURFACE::LAST + 1.;
+const CONTEXT_SWITCHER			: f32 = TERMINAL_OVERLAY + SURFACE::LAST + 1.;
 
 pub fn thickness() -> f32 {
 	THICKNESS
@@ -96,6 +98,10 @@ pub mod surface {
 		offset() * SURFACE::HIGHLIGHT::SEARCH
 	}
 
+	pub fn transition() -> f32 {
+		offset() * SURFACE::TRANSITION
+	}
+
 	pub fn child_surface() -> f32 {
 		offset() * SURFACE::CHILD_SURFACE
 	}
@@ -169,6 +175,12 @@ pub fn resizer() -> f32 {
 	offset() * RESIZER
 }
 
+// Terminal Overlay
+
+pub fn terminal_overlay() -> f32 {
+	offset() * TERMINAL_OVERLAY
+}
+
 // Context Switcher
 
 pub fn context_switcher() -> f32 {